@@ -8,8 +8,40 @@
 //! - 使用启发式规则进行识别，不依赖AI
 
 use crate::core::models::{DirectoryType, FileDescriptor};
-use std::collections::HashSet;
-use std::path::Path;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// 版本库目录名：包含其中任一子目录的目录是版本库，整体视为原子目录
+pub const VCS_DIR_NAMES: [&str; 3] = [".git", ".hg", ".svn"];
+
+/// 查找可执行文件和项目标志时向下检查的层数（1 为直接子项）
+const DESCENDANT_DEPTH: usize = 2;
+/// 向下检查时最多查看的条目数，避免在大目录树上逐个比较
+const MAX_INSPECTED_DESCENDANTS: usize = 500;
+/// 程序常把 exe/dll 放在这些子目录里（App/bin/app.exe），只在这些子目录中才向下识别
+const PROGRAM_SUBDIR_NAMES: [&str; 6] = ["bin", "bin64", "x64", "x86", "win32", "win64"];
+
+/// 用户边界规则文件名（与 config.json 放在同一目录）
+pub const BOUNDARY_CONFIG_FILE: &str = "boundary.json";
+
+/// 用户自定义的边界规则，合并到内置默认值之上（只能追加，不能删除内置项）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BoundaryConfig {
+    /// 追加的程序文件扩展名（如 ".pak"）
+    #[serde(default)]
+    pub program_extensions: Vec<String>,
+    /// 追加的开发项目标志文件（如 "build.gradle.kts"）
+    #[serde(default)]
+    pub dev_project_markers: Vec<String>,
+    /// 追加的虚拟环境/依赖目录名（如 ".gradle"、".terraform"）
+    #[serde(default)]
+    pub venv_dir_names: Vec<String>,
+    /// 追加的系统路径前缀，含 `\` 或盘符的按 Windows 路径处理
+    #[serde(default)]
+    pub system_path_prefixes: Vec<String>,
+}
 
 /// 目录边界分析器
 pub struct BoundaryAnalyzer {
@@ -25,6 +57,21 @@ pub struct BoundaryAnalyzer {
     system_path_prefixes_windows: Vec<String>,
     /// 系统路径前缀（Unix）
     system_path_prefixes_unix: Vec<String>,
+    /// 目录内文件总数超过此值视为原子目录（0 表示不限制）
+    large_dir_max_files: usize,
+    /// 目录内文件总大小超过此值视为原子目录（0 表示不限制）
+    large_dir_max_bytes: u64,
+    /// 用户追加的规则（已规范化），快速检查扫描根时使用
+    user_rules: BoundaryConfig,
+}
+
+/// 目录统计（递归包含所有子目录）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DirStats {
+    /// 文件数
+    pub file_count: usize,
+    /// 文件总大小（字节）
+    pub total_bytes: u64,
 }
 
 impl Default for BoundaryAnalyzer {
@@ -138,11 +185,94 @@ impl BoundaryAnalyzer {
                 "/var".to_string(),
                 "/Applications".to_string(),
             ],
+
+            large_dir_max_files: 0,
+            large_dir_max_bytes: 0,
+            user_rules: BoundaryConfig::default(),
+        }
+    }
+
+    /// 在内置默认值之上合并用户边界规则文件（JSON，格式见 [`BoundaryConfig`]）
+    pub fn with_config(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let config: BoundaryConfig = serde_json::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("边界规则文件格式错误 {}: {}", path.display(), e))?;
+        Ok(Self::new().merge_config(config))
+    }
+
+    /// 把用户边界规则追加到当前规则上
+    pub fn merge_config(mut self, config: BoundaryConfig) -> Self {
+        for ext in config.program_extensions {
+            let ext = ext.trim().trim_start_matches('.').to_lowercase();
+            if !ext.is_empty() {
+                self.program_extensions.insert(format!(".{}", ext));
+                self.user_rules.program_extensions.push(format!(".{}", ext));
+            }
+        }
+        for marker in config.dev_project_markers {
+            let marker = marker.trim().to_string();
+            if !marker.is_empty() {
+                self.user_rules.dev_project_markers.push(marker.to_lowercase());
+                self.dev_project_markers.insert(marker);
+            }
+        }
+        for name in config.venv_dir_names {
+            let name = name.trim().to_lowercase();
+            if !name.is_empty() {
+                self.user_rules.venv_dir_names.push(name.clone());
+                self.venv_dir_names.insert(name);
+            }
+        }
+        for prefix in config.system_path_prefixes {
+            let prefix = prefix.trim().to_string();
+            if prefix.is_empty() {
+                continue;
+            }
+            let windows = prefix.contains('\\') || prefix.get(1..2) == Some(":");
+            let prefixes = if windows {
+                &mut self.system_path_prefixes_windows
+            } else {
+                &mut self.system_path_prefixes_unix
+            };
+            if !prefixes.contains(&prefix) {
+                prefixes.push(prefix);
+            }
         }
+        self
+    }
+
+    /// 设置大目录阈值：文件数或总大小超过阈值的目录整体视为原子目录（0 表示不限制）
+    pub fn large_dir_thresholds(mut self, max_files: usize, max_bytes: u64) -> Self {
+        self.large_dir_max_files = max_files;
+        self.large_dir_max_bytes = max_bytes;
+        self
+    }
+
+    /// 扫描根本身是原子目录时，列出可以单独整理的一级子目录
+    ///
+    /// 排除隐藏目录、虚拟环境/依赖目录以及自身看起来就是原子目录的子目录。
+    pub fn safe_subdirs(&self, root: &Path) -> Vec<PathBuf> {
+        let entries = match std::fs::read_dir(root) {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new(),
+        };
+
+        let mut dirs: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
+            .filter(|e| {
+                let name = e.file_name().to_string_lossy().to_string();
+                !name.starts_with('.') && !self.venv_dir_names.contains(&name)
+            })
+            .map(|e| e.path())
+            .filter(|path| !quick_check_atomic(path, self))
+            .collect();
+        dirs.sort();
+        dirs
     }
 
     /// 分析文件列表，标记原子目录
-    pub fn analyze(&self, files: &mut Vec<FileDescriptor>) {
+    pub fn analyze(&self, files: &mut [FileDescriptor]) {
         // 首先收集需要分析的目录路径
         let dir_paths: Vec<(usize, std::path::PathBuf)> = files
             .iter()
@@ -151,11 +281,23 @@ impl BoundaryAnalyzer {
             .map(|(i, f)| (i, f.full_path.clone()))
             .collect();
 
+        let dir_stats = if self.large_dir_max_files > 0 || self.large_dir_max_bytes > 0 {
+            aggregate_dir_stats(files)
+        } else {
+            HashMap::new()
+        };
+
         // 分析每个目录
         let results: Vec<(usize, DirectoryType, bool)> = dir_paths
             .iter()
             .map(|(i, path)| {
-                let (dir_type, atomic) = self.analyze_directory(path, files);
+                let (dir_type, atomic) = match self.analyze_directory(path, files) {
+                    (DirectoryType::Normal, false) if self.exceeds_large_dir(dir_stats.get(path)) => {
+                        tracing::info!("目录过大，整体视为原子目录: {}", path.display());
+                        (DirectoryType::ProgramRoot, true)
+                    }
+                    other => other,
+                };
                 (*i, dir_type, atomic)
             })
             .collect();
@@ -199,13 +341,22 @@ impl BoundaryAnalyzer {
             return (DirectoryType::System, true);
         }
 
-        // 2. 获取目录下的直接子项
+        // 2. 获取目录下的直接子项，以及有限层数内的后代（数量有上限）
         let children: Vec<_> = all_files
             .iter()
             .filter(|f| {
                 f.parent_dir == path
             })
             .collect();
+        let descendants: Vec<_> = all_files
+            .iter()
+            .filter(|f| {
+                f.full_path
+                    .strip_prefix(path)
+                    .is_ok_and(|rel| (1..=DESCENDANT_DEPTH).contains(&rel.components().count()))
+            })
+            .take(MAX_INSPECTED_DESCENDANTS)
+            .collect();
 
         // 3. 检查是否为虚拟环境目录
         let dir_name = path
@@ -217,7 +368,16 @@ impl BoundaryAnalyzer {
             return (DirectoryType::VirtualEnv, true);
         }
 
-        // 4. 检查是否包含程序文件标志
+        // 4. 检查是否为版本库（扫描默认跳过隐藏目录，因此同时检查磁盘）
+        let has_vcs_child = children
+            .iter()
+            .any(|f| f.is_directory && VCS_DIR_NAMES.contains(&f.name.as_str()))
+            || VCS_DIR_NAMES.iter().any(|name| path.join(name).is_dir());
+        if has_vcs_child {
+            return (DirectoryType::ProgramRoot, true);
+        }
+
+        // 5. 检查是否包含程序文件标志
         let has_program_markers = children.iter().any(|f| {
             // 检查可执行文件
             if self.program_extensions.contains(&f.extension.to_lowercase()) {
@@ -230,18 +390,18 @@ impl BoundaryAnalyzer {
             false
         });
 
-        // 5. 检查是否同时有exe和dll（强信号）
-        let has_exe = children.iter().any(|f| f.extension.to_lowercase() == ".exe");
-        let has_dll = children.iter().any(|f| f.extension.to_lowercase() == ".dll");
-
-        if has_exe && has_dll {
+        // 6. 检查同一目录中是否同时有exe和dll（强信号），包括 bin 等程序子目录
+        if has_program_binaries(path, &descendants) {
             return (DirectoryType::ProgramRoot, true);
         }
 
-        // 6. 检查是否为开发项目目录
-        let has_dev_markers = children.iter().any(|f| {
-            self.dev_project_markers.contains(&f.name.to_lowercase())
-                || self.dev_project_markers.iter().any(|m| f.name.ends_with(m))
+        // 7. 检查是否为开发项目目录（不区分大小写，包括有限层数内的后代）
+        let has_dev_markers = descendants.iter().any(|f| {
+            let name = f.name.to_lowercase();
+            self.dev_project_markers.iter().any(|m| {
+                let marker = m.to_lowercase();
+                name == marker || name.ends_with(&marker)
+            })
         });
 
         if has_dev_markers {
@@ -256,7 +416,7 @@ impl BoundaryAnalyzer {
             }
         }
 
-        // 7. 检查标准目录结构 (bin + lib)
+        // 8. 检查标准目录结构 (bin + lib)
         let has_bin = children.iter().any(|f| f.is_directory && f.name.to_lowercase() == "bin");
         let has_lib = children.iter().any(|f| f.is_directory && f.name.to_lowercase() == "lib");
 
@@ -267,6 +427,16 @@ impl BoundaryAnalyzer {
         (DirectoryType::Normal, false)
     }
 
+    /// 目录统计是否超过大目录阈值
+    fn exceeds_large_dir(&self, stats: Option<&DirStats>) -> bool {
+        let stats = match stats {
+            Some(s) => s,
+            None => return false,
+        };
+        (self.large_dir_max_files > 0 && stats.file_count > self.large_dir_max_files)
+            || (self.large_dir_max_bytes > 0 && stats.total_bytes > self.large_dir_max_bytes)
+    }
+
     /// 检查是否为系统路径
     fn is_system_path(&self, path: &str) -> bool {
         let path_lower = path.to_lowercase();
@@ -307,8 +477,67 @@ impl BoundaryAnalyzer {
     }
 }
 
+/// 汇总每个已扫描目录（递归）的文件数与总大小
+pub fn aggregate_dir_stats(files: &[FileDescriptor]) -> HashMap<PathBuf, DirStats> {
+    let mut stats: HashMap<PathBuf, DirStats> = files
+        .iter()
+        .filter(|f| f.is_directory)
+        .map(|f| (f.full_path.clone(), DirStats::default()))
+        .collect();
+
+    for file in files.iter().filter(|f| !f.is_directory) {
+        // 只累加到已扫描的祖先目录，扫描根之外的路径不计入
+        for ancestor in file.full_path.ancestors().skip(1) {
+            match stats.get_mut(ancestor) {
+                Some(entry) => {
+                    entry.file_count += 1;
+                    entry.total_bytes += file.size;
+                }
+                None => break,
+            }
+        }
+    }
+
+    stats
+}
+
+/// 目录本身或其程序子目录（bin 等）中是否同时有 exe 和 dll
+fn has_program_binaries(path: &Path, descendants: &[&FileDescriptor]) -> bool {
+    let mut binaries: HashMap<&Path, (bool, bool)> = HashMap::new();
+    for f in descendants.iter().filter(|f| !f.is_directory) {
+        let dir = f.parent_dir.as_path();
+        let eligible = dir == path
+            || (dir.parent() == Some(path)
+                && dir.file_name().is_some_and(|n| {
+                    PROGRAM_SUBDIR_NAMES.contains(&n.to_string_lossy().to_lowercase().as_str())
+                }));
+        if !eligible {
+            continue;
+        }
+        let entry = binaries.entry(dir).or_default();
+        match f.extension.to_lowercase().as_str() {
+            ".exe" => entry.0 = true,
+            ".dll" => entry.1 = true,
+            _ => {}
+        }
+    }
+    binaries.values().any(|&(exe, dll)| exe && dll)
+}
+
 /// 快速检查目录是否可能是原子目录（不需要完整扫描）
-pub fn quick_check_atomic(path: &Path) -> bool {
+///
+/// 除内置标志外，还按 analyzer 中用户追加的规则（boundary.json）检查：
+/// 目录自身或子目录是追加的依赖目录名，或同时含有追加的项目标志和依赖目录/程序文件。
+pub fn quick_check_atomic(path: &Path, analyzer: &BoundaryAnalyzer) -> bool {
+    let user = &analyzer.user_rules;
+    let own_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if user.venv_dir_names.contains(&own_name) {
+        return true;
+    }
+
     let entries: Vec<_> = match std::fs::read_dir(path) {
         Ok(entries) => entries.filter_map(|e| e.ok()).collect(),
         Err(_) => return false,
@@ -317,20 +546,40 @@ pub fn quick_check_atomic(path: &Path) -> bool {
     // 快速检查标志
     let mut has_exe = false;
     let mut has_dll = false;
-    let mut has_package_json = false;
     let mut has_cargo_toml = false;
     let mut has_node_modules = false;
     let mut has_venv = false;
+    let mut has_vcs = false;
+    let mut has_user_marker = false;
+    let mut has_dependency_dir = false;
+    let mut has_program_file = false;
 
     for entry in entries {
         let name = entry.file_name().to_string_lossy().to_lowercase();
-        
+        let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+
+        if VCS_DIR_NAMES.contains(&name.as_str()) && is_dir {
+            has_vcs = true;
+            continue;
+        }
+        if is_dir && user.venv_dir_names.contains(&name) {
+            has_venv = true;
+        }
+        if is_dir && analyzer.venv_dir_names.contains(&name) {
+            has_dependency_dir = true;
+        }
+        if user.dev_project_markers.iter().any(|m| name == *m || name.ends_with(m.as_str())) {
+            has_user_marker = true;
+        }
+        if user.program_extensions.iter().any(|ext| name.ends_with(ext.as_str())) {
+            has_program_file = true;
+        }
+
+
         if name.ends_with(".exe") {
             has_exe = true;
         } else if name.ends_with(".dll") {
             has_dll = true;
-        } else if name == "package.json" {
-            has_package_json = true;
         } else if name == "cargo.toml" {
             has_cargo_toml = true;
         } else if name == "node_modules" {
@@ -344,7 +593,8 @@ pub fn quick_check_atomic(path: &Path) -> bool {
     (has_exe && has_dll) // Windows程序
         || has_node_modules // Node.js项目
         || has_venv // Python项目
-        || (has_package_json && has_node_modules)
+        || has_vcs // 版本库
+        || (has_user_marker && (has_dependency_dir || has_program_file)) // 用户追加的项目标志
         || (has_cargo_toml && path.join("target").exists())
 }
 
@@ -360,4 +610,175 @@ mod tests {
         assert!(analyzer.is_system_path("C:\\Program Files\\SomeApp"));
         assert!(!analyzer.is_system_path("D:\\MyDocuments"));
     }
+
+    fn entry(path: &str, size: u64, is_directory: bool) -> FileDescriptor {
+        let path = PathBuf::from(path);
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        let ext = path
+            .extension()
+            .map(|e| format!(".{}", e.to_string_lossy()))
+            .unwrap_or_default();
+        FileDescriptor::new(path, name, ext, size, chrono::Utc::now(), is_directory)
+    }
+
+    #[test]
+    fn test_large_directory_is_atomic() {
+        let mut files = vec![
+            entry("/data/Game", 0, true),
+            entry("/data/Game/assets", 0, true),
+            entry("/data/Game/assets/level1.pak", 600, false),
+            entry("/data/Game/assets/level2.pak", 600, false),
+            entry("/data/Small", 0, true),
+            entry("/data/Small/note.txt", 10, false),
+        ];
+
+        let stats = aggregate_dir_stats(&files);
+        assert_eq!(stats[&PathBuf::from("/data/Game")], DirStats { file_count: 2, total_bytes: 1200 });
+
+        let analyzer = BoundaryAnalyzer::new().large_dir_thresholds(0, 1000);
+        analyzer.analyze(&mut files);
+
+        assert!(files[0].atomic);
+        assert_eq!(files[0].directory_type, DirectoryType::ProgramRoot);
+        assert!(files[2].atomic, "大目录内的文件应随目录锁定");
+        assert!(!files[4].atomic);
+        assert!(!files[5].atomic);
+
+        // 默认不启用大小阈值
+        for file in files.iter_mut() {
+            file.atomic = false;
+        }
+        BoundaryAnalyzer::new().analyze(&mut files);
+        assert!(!files[0].atomic);
+    }
+
+    #[test]
+    fn test_program_binaries_in_bin_subdir() {
+        let mut files = vec![
+            entry("/data/program", 0, true),
+            entry("/data/program/bin", 0, true),
+            entry("/data/program/bin/app.exe", 10, false),
+            entry("/data/program/bin/app.DLL", 10, false),
+            entry("/data/program/readme.txt", 10, false),
+            // 普通子目录里的程序不会把上层的文档目录锁住
+            entry("/data/Documents", 0, true),
+            entry("/data/Documents/Tools", 0, true),
+            entry("/data/Documents/Tools/tool.exe", 10, false),
+            entry("/data/Documents/Tools/tool.dll", 10, false),
+            entry("/data/Documents/report.docx", 10, false),
+            // 标志文件不区分大小写
+            entry("/data/web", 0, true),
+            entry("/data/web/PACKAGE.JSON", 10, false),
+            entry("/data/web/node_modules", 0, true),
+        ];
+        BoundaryAnalyzer::new().analyze(&mut files);
+
+        assert!(files[0].atomic, "exe/dll 在 bin 子目录的程序目录应为原子目录");
+        assert_eq!(files[0].directory_type, DirectoryType::ProgramRoot);
+        assert!(files[4].atomic);
+        assert!(!files[5].atomic);
+        assert!(files[6].atomic);
+        assert!(!files[9].atomic);
+        assert!(files[10].atomic);
+    }
+
+    #[test]
+    fn test_vcs_repository_is_atomic() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        std::fs::create_dir_all(repo.join(".git/objects")).unwrap();
+        std::fs::write(repo.join("README.md"), "# repo").unwrap();
+        let plain = dir.path().join("plain");
+        std::fs::create_dir_all(&plain).unwrap();
+        // 名为 .git 的普通文件（如 worktree 的指针文件）不算
+        std::fs::write(plain.join(".git"), "gitdir: ../elsewhere").unwrap();
+
+        let analyzer = BoundaryAnalyzer::new();
+        assert!(quick_check_atomic(&repo, &analyzer));
+        assert!(!quick_check_atomic(&plain, &analyzer));
+
+        // 扫描默认不包含隐藏的 .git 目录，分析时仍能识别
+        let mut files = vec![
+            entry(&repo.to_string_lossy(), 0, true),
+            entry(&repo.join("README.md").to_string_lossy(), 6, false),
+            entry(&plain.to_string_lossy(), 0, true),
+        ];
+        BoundaryAnalyzer::new().analyze(&mut files);
+        assert!(files[0].atomic);
+        assert_eq!(files[0].directory_type, DirectoryType::ProgramRoot);
+        assert!(files[1].atomic, "版本库内的文件不能单独移动");
+        assert!(!files[2].atomic);
+    }
+
+    #[test]
+    fn test_with_config_merges_onto_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(BOUNDARY_CONFIG_FILE);
+        std::fs::write(
+            &path,
+            r#"{
+                "dev_project_markers": ["build.gradle.kts"],
+                "venv_dir_names": [".Gradle", ".terraform"],
+                "system_path_prefixes": ["D:\\Games", "/srv"]
+            }"#,
+        )
+        .unwrap();
+
+        let analyzer = BoundaryAnalyzer::with_config(&path).unwrap();
+        // 内置规则仍然生效
+        assert!(analyzer.is_system_path("C:\\Windows\\System32"));
+        assert!(analyzer.is_system_path("D:\\Games\\Steam"));
+        assert!(analyzer.is_system_path("/srv/www"));
+
+        let mut files = vec![
+            entry("/home/app", 0, true),
+            entry("/home/app/build.gradle.kts", 10, false),
+            entry("/home/app/.gradle", 0, true),
+            entry("/home/app/.gradle/cache.bin", 10, false),
+            entry("/home/infra/.terraform", 0, true),
+        ];
+        analyzer.analyze(&mut files);
+        assert!(files[0].atomic, "带 .gradle 的 Gradle 项目应为原子目录");
+        assert_eq!(files[4].directory_type, DirectoryType::VirtualEnv);
+
+        // 默认规则不认识这些布局
+        for file in files.iter_mut() {
+            file.atomic = false;
+        }
+        BoundaryAnalyzer::new().analyze(&mut files);
+        assert!(!files[0].atomic);
+        assert!(!files[4].atomic);
+
+        std::fs::write(&path, "not json").unwrap();
+        assert!(BoundaryAnalyzer::with_config(&path).is_err());
+    }
+
+    #[test]
+    fn test_quick_check_uses_user_boundary_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let game = dir.path().join("game");
+        std::fs::create_dir_all(&game).unwrap();
+        std::fs::write(game.join("game.manifest"), "{}").unwrap();
+        std::fs::write(game.join("assets.pak"), "pak").unwrap();
+        let terraform = dir.path().join(".terraform");
+        std::fs::create_dir_all(&terraform).unwrap();
+        let home = dir.path().join("home");
+        std::fs::create_dir_all(home.join("Documents")).unwrap();
+        std::fs::create_dir_all(home.join("bin")).unwrap();
+
+        let defaults = BoundaryAnalyzer::new();
+        assert!(!quick_check_atomic(&game, &defaults));
+        assert!(!quick_check_atomic(&terraform, &defaults));
+
+        let analyzer = BoundaryAnalyzer::new().merge_config(BoundaryConfig {
+            program_extensions: vec!["pak".to_string()],
+            dev_project_markers: vec!["game.manifest".to_string()],
+            venv_dir_names: vec![".terraform".to_string()],
+            ..Default::default()
+        });
+        assert!(quick_check_atomic(&game, &analyzer), "带追加标志和程序文件的目录应为原子目录");
+        assert!(quick_check_atomic(&terraform, &analyzer));
+        // 内置依赖目录名（bin）单独出现不会锁定普通目录
+        assert!(!quick_check_atomic(&home, &analyzer));
+    }
 }
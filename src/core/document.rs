@@ -0,0 +1,172 @@
+//! 文档内容摘要模块
+//!
+//! 从 PDF 和 Word（.docx）中抽取开头的一段正文，作为AI分析的内容摘要。
+//! 解析在独立线程中进行并限时等待：损坏或异常复杂的文件不会拖住分析流程，
+//! 解析失败、超时或解析库崩溃时都返回 None，只是少了摘要。
+
+use anyhow::Result;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// 文档摘要的最大字符数
+pub const DOCUMENT_SUMMARY_CHARS: usize = 500;
+
+/// 单个文档的最长解析时间
+pub const EXTRACT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 超过此大小的文档不解析（字节）
+const MAX_DOCUMENT_BYTES: u64 = 50 * 1024 * 1024;
+
+/// docx 中 document.xml 最多读取的字节数
+const MAX_DOCX_XML_BYTES: u64 = 8 * 1024 * 1024;
+
+/// 是否为可抽取正文的文档
+pub fn is_document_file(extension: &str) -> bool {
+    matches!(extension.to_lowercase().as_str(), ".pdf" | ".docx")
+}
+
+/// 抽取文档开头最多 max_chars 个字符的正文（空白已合并）
+///
+/// 超时后不再等待，解析线程在后台自行结束。
+pub fn document_summary(path: &Path, max_chars: usize) -> Option<String> {
+    let size = std::fs::metadata(path).ok()?.len();
+    if size > MAX_DOCUMENT_BYTES {
+        tracing::debug!("文档过大，不抽取摘要: {}", path.display());
+        return None;
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let owned: PathBuf = path.to_path_buf();
+    thread::spawn(move || {
+        let _ = tx.send(extract_text(&owned, max_chars));
+    });
+
+    let text = match rx.recv_timeout(EXTRACT_TIMEOUT) {
+        Ok(Ok(text)) => text,
+        Ok(Err(e)) => {
+            tracing::debug!("抽取文档正文失败 {}: {}", path.display(), e);
+            return None;
+        }
+        Err(mpsc::RecvTimeoutError::Timeout) => {
+            tracing::warn!("抽取文档正文超时: {}", path.display());
+            return None;
+        }
+        // 解析线程崩溃（解析库对畸形文件 panic）
+        Err(mpsc::RecvTimeoutError::Disconnected) => {
+            tracing::warn!("抽取文档正文时解析器异常退出: {}", path.display());
+            return None;
+        }
+    };
+
+    let summary = collapse_whitespace(&text, max_chars);
+    (!summary.is_empty()).then_some(summary)
+}
+
+/// 按扩展名抽取正文
+fn extract_text(path: &Path, max_chars: usize) -> Result<String> {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "pdf" => Ok(pdf_extract::extract_text(path)?),
+        "docx" => extract_docx(path, max_chars),
+        _ => Err(anyhow::anyhow!("不支持的文档类型: {}", path.display())),
+    }
+}
+
+/// 读取 docx 中 word/document.xml 的文字（w:t），段落之间换行
+fn extract_docx(path: &Path, max_chars: usize) -> Result<String> {
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(path)?)?;
+    let mut xml = String::new();
+    archive
+        .by_name("word/document.xml")?
+        .take(MAX_DOCX_XML_BYTES)
+        .read_to_string(&mut xml)?;
+
+    let mut reader = Reader::from_str(&xml);
+    let mut text = String::new();
+    let mut in_text = false;
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) if e.name().as_ref() == b"w:t" => in_text = true,
+            Event::End(e) if e.name().as_ref() == b"w:t" => in_text = false,
+            Event::End(e) if e.name().as_ref() == b"w:p" => {
+                text.push('\n');
+                // 已经够用，不必解析整篇文档
+                if text.chars().count() >= max_chars {
+                    break;
+                }
+            }
+            Event::Text(t) if in_text => text.push_str(&t.unescape()?),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(text)
+}
+
+/// 合并连续空白为单个空格，并截取前 max_chars 个字符
+fn collapse_whitespace(text: &str, max_chars: usize) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(max_chars)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    fn write_docx(path: &Path, paragraphs: &[&str]) {
+        let body: String = paragraphs
+            .iter()
+            .map(|p| format!("<w:p><w:r><w:t xml:space=\"preserve\">{}</w:t></w:r></w:p>", p))
+            .collect();
+        let xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?><w:document \
+             xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\">\
+             <w:body>{}</w:body></w:document>",
+            body
+        );
+
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+        zip.start_file("word/document.xml", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(xml.as_bytes()).unwrap();
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_docx_summary_and_corrupt_fallback() {
+        let dir = tempdir().unwrap();
+
+        let docx = dir.path().join("合同.docx");
+        write_docx(&docx, &["租赁合同", "甲方：某某公司 &amp; 合伙人", "   租期  一年 "]);
+        assert_eq!(
+            document_summary(&docx, DOCUMENT_SUMMARY_CHARS).as_deref(),
+            Some("租赁合同 甲方：某某公司 & 合伙人 租期 一年")
+        );
+        assert_eq!(document_summary(&docx, 4).as_deref(), Some("租赁合同"));
+
+        // 损坏的文件不报错，只是没有摘要
+        let pdf = dir.path().join("broken.pdf");
+        std::fs::write(&pdf, b"%PDF-1.4 not really a pdf").unwrap();
+        assert_eq!(document_summary(&pdf, DOCUMENT_SUMMARY_CHARS), None);
+        let fake_docx = dir.path().join("broken.docx");
+        std::fs::write(&fake_docx, b"PK garbage").unwrap();
+        assert_eq!(document_summary(&fake_docx, DOCUMENT_SUMMARY_CHARS), None);
+
+        assert!(is_document_file(".PDF"));
+        assert!(!is_document_file(".doc"));
+    }
+}
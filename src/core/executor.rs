@@ -7,50 +7,201 @@
 //! - 所有操作可回滚
 //! - 详细记录每一步操作
 
-use crate::core::models::{HistoryEntry, MoveOperation, MovePlan, OperationStatus};
+use crate::core::models::{
+    ConflictStrategy, ExecutionMode, HistoryEntry, MoveOperation, MovePlan, OperationStatus,
+};
+use crate::core::planner::{
+    available_space, format_bytes, partial_content_hash, same_volume, PlanStats,
+};
+use crate::core::scanner::hash_file_content;
 use anyhow::Result;
 use chrono::Utc;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// 分类目录说明文件名（扫描时会被忽略）
+pub const FOLDER_README_NAME: &str = "_orderly.txt";
+
+/// 跨设备复制时临时文件的后缀（扫描时会被忽略）
+pub const TEMP_SUFFIX: &str = ".orderly-tmp";
+
+/// 目标原有文件被移到回收站时的说明（日志和移动失败时的错误信息中使用）
+pub const TRASHED_TARGET_NOTE: &str = "原有文件已移到回收站";
+
+/// 把文件移到回收站的函数，见 [`Executor::set_trash`]
+pub type TrashFn = fn(&Path) -> std::io::Result<()>;
+
+/// 默认的回收站实现：移到系统回收站
+pub fn system_trash(path: &Path) -> std::io::Result<()> {
+    trash::delete(path).map_err(std::io::Error::other)
+}
+
+/// 执行过程中每完成多少个操作保存一次断点
+const CHECKPOINT_INTERVAL: usize = 10;
+
+/// 历史记录文件名（未使用数据库时的存储，启用数据库后会被导入）
+pub const HISTORY_FILE_NAME: &str = "history.json";
+
+/// 历史记录存储
+///
+/// 执行器在内存中维护完整历史，每次变化后保存，保证存储内容与内存一致。
+pub trait HistoryStore: Send {
+    /// 加载全部历史记录（旧的在前）
+    fn load_history(&self) -> Result<Vec<HistoryEntry>>;
+
+    /// 用给定的历史记录替换已保存的全部记录
+    fn save_history(&self, history: &[HistoryEntry]) -> Result<()>;
+
+    /// 保存新增或变化的一条记录；history 是变化后的完整历史，默认整体保存
+    fn save_entry(&self, history: &[HistoryEntry], entry: &HistoryEntry) -> Result<()> {
+        let _ = entry;
+        self.save_history(history)
+    }
+}
+
+/// 基于 JSON 文件的历史记录存储
+pub struct JsonHistoryStore {
+    path: PathBuf,
+}
+
+impl JsonHistoryStore {
+    /// 使用指定的历史文件
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+impl HistoryStore for JsonHistoryStore {
+    fn load_history(&self) -> Result<Vec<HistoryEntry>> {
+        if self.path.exists() {
+            let content = fs::read_to_string(&self.path)?;
+            Ok(serde_json::from_str(&content)?)
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    fn save_history(&self, history: &[HistoryEntry]) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(history)?;
+        fs::write(&self.path, content)?;
+        Ok(())
+    }
+}
 
 /// 执行器
 pub struct Executor {
     /// 历史记录
     history: Vec<HistoryEntry>,
-    /// 历史文件路径
-    history_file: PathBuf,
+    /// 历史记录存储
+    history_store: Box<dyn HistoryStore>,
+    /// 未完成批次的断点文件路径
+    pending_file: PathBuf,
+    /// 新建目标目录时是否写入说明文件
+    write_folder_readme: bool,
+    /// 目标已存在时的处理策略
+    conflict_strategy: ConflictStrategy,
+    /// 本应覆盖或报错的冲突改为先把原有文件移到回收站
+    use_trash_on_conflict: bool,
+    /// 按内容去重跳过时，把源文件移到回收站（仅移动模式）
+    trash_duplicate_sources: bool,
+    /// 把文件移到回收站的实现
+    trash: TrashFn,
+}
+
+/// 单个操作的执行结果
+enum OperationOutcome {
+    /// 已移动（同卷重命名）
+    Moved,
+    /// 跨设备复制后删除源文件（附复制字节数与耗时，用于测速）
+    Copied { bytes: u64, elapsed: Duration },
+    /// 已跳过（附原因）
+    Skipped(String),
+}
+
+/// 目标已存在时按冲突策略得出的处理方式
+enum ConflictResolution {
+    /// 移动到该路径（未冲突时为原目标，自动改名时为新名称）
+    MoveTo(PathBuf),
+    /// 覆盖已有文件
+    Overwrite,
+    /// 先把已有文件移到回收站再移动
+    Trash,
+    /// 跳过（附原因）
+    Skip(String),
+    /// 目标目录已有内容相同的文件（附该文件路径），跳过
+    Duplicate(PathBuf),
+    /// 报错
+    Fail,
 }
 
 impl Executor {
-    /// 创建新的执行器
+    /// 创建新的执行器（历史记录保存在数据目录的 JSON 文件中）
     pub fn new(data_dir: PathBuf) -> Self {
-        let history_file = data_dir.join("history.json");
-        let history = Self::load_history(&history_file).unwrap_or_default();
-        
+        let store = JsonHistoryStore::new(data_dir.join(HISTORY_FILE_NAME));
+        Self::with_history_store(data_dir, Box::new(store))
+    }
+
+    /// 创建使用指定历史记录存储的执行器
+    pub fn with_history_store(data_dir: PathBuf, history_store: Box<dyn HistoryStore>) -> Self {
+        let pending_file = data_dir.join("pending_batch.json");
+        let history = history_store.load_history().unwrap_or_else(|e| {
+            tracing::warn!("加载历史记录失败: {}", e);
+            Vec::new()
+        });
+
         Self {
             history,
-            history_file,
+            history_store,
+            pending_file,
+            write_folder_readme: false,
+            conflict_strategy: ConflictStrategy::default(),
+            use_trash_on_conflict: false,
+            trash_duplicate_sources: false,
+            trash: system_trash,
         }
     }
 
-    /// 从文件加载历史记录
-    fn load_history(path: &PathBuf) -> Result<Vec<HistoryEntry>> {
-        if path.exists() {
-            let content = fs::read_to_string(path)?;
-            Ok(serde_json::from_str(&content)?)
-        } else {
-            Ok(Vec::new())
-        }
+    /// 替换把文件移到回收站的实现（默认为系统回收站，测试中换成不碰系统回收站的实现）
+    pub fn set_trash(&mut self, trash: TrashFn) {
+        self.trash = trash;
+    }
+
+    /// 设置目标已存在时的处理策略
+    pub fn set_conflict_strategy(&mut self, strategy: ConflictStrategy) {
+        self.conflict_strategy = strategy;
+    }
+
+    /// 设置冲突时是否把原有文件移到回收站（代替覆盖或报错）
+    pub fn set_use_trash_on_conflict(&mut self, enabled: bool) {
+        self.use_trash_on_conflict = enabled;
+    }
+
+    /// 设置按内容去重跳过时是否把源文件移到回收站（仅移动模式生效）
+    pub fn set_trash_duplicate_sources(&mut self, enabled: bool) {
+        self.trash_duplicate_sources = enabled;
     }
 
-    /// 保存历史记录到文件
+    /// 设置新建目标目录时是否写入说明文件
+    pub fn set_write_folder_readme(&mut self, enabled: bool) {
+        self.write_folder_readme = enabled;
+    }
+
+    /// 保存历史记录
     fn save_history(&self) -> Result<()> {
-        if let Some(parent) = self.history_file.parent() {
-            fs::create_dir_all(parent)?;
-        }
-        let content = serde_json::to_string_pretty(&self.history)?;
-        fs::write(&self.history_file, content)?;
-        Ok(())
+        self.history_store.save_history(&self.history)
+    }
+
+    /// 只保存第 index 条历史记录
+    fn save_history_entry(&self, index: usize) -> Result<()> {
+        self.history_store.save_entry(&self.history, &self.history[index])
     }
 
     /// Dry Run - 预览执行结果
@@ -59,62 +210,269 @@ impl Executor {
             would_create_dirs: Vec::new(),
             would_move_files: Vec::new(),
             potential_errors: Vec::new(),
+            required_bytes: 0,
+            available_bytes: None,
+            entries: Vec::new(),
         };
 
-        let mut dirs_to_create = std::collections::HashSet::new();
+        let mut dirs_to_create = HashSet::new();
+        let mut claimed = HashSet::new();
+        let mut space_target: Option<PathBuf> = None;
 
         for op in &plan.operations {
+            let mut entry = DryRunEntry {
+                from: op.from.clone(),
+                to: op.to.clone(),
+                will_create_dir: false,
+                error: None,
+            };
+
+            // 生成计划时已标记跳过的操作（如重复文件）不会执行
+            if op.status == OperationStatus::Skipped {
+                entry.error = op
+                    .error
+                    .as_ref()
+                    .map(|reason| format!("{}，将跳过: {}", reason, op.from.display()));
+                result.push_entry(entry);
+                continue;
+            }
+
             // 检查源文件
             if !op.from.exists() {
-                result.potential_errors.push(format!(
+                entry.error = Some(format!(
                     "源文件不存在: {}",
                     op.from.display()
                 ));
+                result.push_entry(entry);
                 continue;
             }
 
             // 检查目标目录
             if let Some(parent) = op.to.parent() {
                 if !parent.exists() {
+                    entry.will_create_dir = true;
                     dirs_to_create.insert(parent.to_path_buf());
                 }
             }
 
-            // 检查目标文件是否已存在
-            if op.to.exists() {
-                result.potential_errors.push(format!(
-                    "目标文件已存在: {}",
-                    op.to.display()
-                ));
+            // 检查目标文件是否已存在（包括本批次前面的操作将占用的路径），按冲突策略得出最终名称
+            let occupied = |p: &Path| p.exists() || claimed.contains(p);
+            let to = match self.resolve_conflict(op, occupied, &claimed) {
+                ConflictResolution::MoveTo(to) => to,
+                ConflictResolution::Overwrite => {
+                    entry.error = Some(format!("将覆盖已有文件: {}", op.to.display()));
+                    op.to.clone()
+                }
+                ConflictResolution::Trash => {
+                    entry.error = Some(format!("将把已有文件移到回收站: {}", op.to.display()));
+                    op.to.clone()
+                }
+                ConflictResolution::Skip(reason) => {
+                    entry.error = Some(format!(
+                        "{}，将跳过: {}",
+                        reason,
+                        op.from.display()
+                    ));
+                    result.push_entry(entry);
+                    continue;
+                }
+                ConflictResolution::Duplicate(duplicate) => {
+                    let action = if self.trash_duplicate_sources && plan.mode == ExecutionMode::Move {
+                        "将跳过并把源文件移到回收站"
+                    } else {
+                        "将跳过"
+                    };
+                    entry.error = Some(format!(
+                        "目标目录已有内容相同的文件: {}，{}: {}",
+                        duplicate.display(),
+                        action,
+                        op.from.display()
+                    ));
+                    result.push_entry(entry);
+                    continue;
+                }
+                ConflictResolution::Fail => {
+                    entry.error = Some(format!(
+                        "目标文件已存在: {}",
+                        op.to.display()
+                    ));
+                    op.to.clone()
+                }
+            };
+
+            // 复制和跨卷移动需要目标卷的空间，同卷移动只是重命名
+            if op.from.is_file() && (plan.mode == ExecutionMode::Copy || !same_volume(&op.from, &to)) {
+                result.required_bytes += fs::metadata(&op.from).map(|m| m.len()).unwrap_or(0);
+                space_target.get_or_insert_with(|| to.clone());
             }
 
-            result.would_move_files.push((op.from.clone(), op.to.clone()));
+            claimed.insert(to.clone());
+            result.would_move_files.push((op.from.clone(), to.clone()));
+            entry.to = to;
+            result.push_entry(entry);
         }
 
+        // 目标通常都在输出目录下，以第一个需要占用空间的目标所在卷为准
+        result.available_bytes = space_target.as_deref().and_then(available_space);
         result.would_create_dirs = dirs_to_create.into_iter().collect();
         result
     }
 
     /// 执行移动计划
     pub fn execute(&mut self, plan: &mut MovePlan) -> ExecutionResult {
+        self.execute_cancellable(plan, &AtomicBool::new(false))
+    }
+
+    /// 执行移动计划，可通过 cancel 中途取消
+    ///
+    /// 执行过程中定期把计划及每个操作的状态写入断点文件；取消或进程意外退出后，
+    /// 可通过 [`Executor::pending_batch`] 取回并继续执行剩余操作。
+    /// 已完成、失败或跳过的操作不会重复执行。
+    pub fn execute_cancellable(&mut self, plan: &mut MovePlan, cancel: &AtomicBool) -> ExecutionResult {
+        self.run_batch(plan, cancel, false)
+    }
+
+    /// 全部成功或全部撤销地执行移动计划
+    ///
+    /// 任一操作失败时不再执行后续操作，并立即回滚本批次已完成的操作；
+    /// 批次仍写入历史记录，但标记为已回滚。按冲突策略跳过的操作不算失败。
+    pub fn execute_transactional(&mut self, plan: &mut MovePlan) -> TransactionOutcome {
+        self.execute_transactional_cancellable(plan, &AtomicBool::new(false))
+    }
+
+    /// 同 [`Executor::execute_transactional`]，取消时同样回滚已完成的操作
+    pub fn execute_transactional_cancellable(
+        &mut self,
+        plan: &mut MovePlan,
+        cancel: &AtomicBool,
+    ) -> TransactionOutcome {
+        let execution = self.run_batch(plan, cancel, true);
+
+        let (failed_index, rollback) = if execution.cancelled {
+            let Some(index) = plan
+                .operations
+                .iter()
+                .position(|op| matches!(op.status, OperationStatus::Pending | OperationStatus::InProgress))
+            else {
+                return TransactionOutcome::Committed(execution);
+            };
+            (index, self.rollback_pending(plan))
+        } else {
+            let Some(index) = plan
+                .operations
+                .iter()
+                .position(|op| op.status == OperationStatus::Failed)
+            else {
+                return TransactionOutcome::Committed(execution);
+            };
+            (index, self.rollback(&plan.batch_id))
+        };
+
+        // 把回滚后的状态同步回调用方的计划
+        if let Some(entry) = self.history.iter().find(|e| e.batch_id == plan.batch_id) {
+            for (op, recorded) in plan.operations.iter_mut().zip(&entry.operations) {
+                op.status = recorded.status;
+            }
+        }
+
+        let error = if execution.cancelled {
+            "已取消".to_string()
+        } else {
+            plan.operations[failed_index].error.clone().unwrap_or_default()
+        };
+        tracing::warn!(
+            "批次 {} 在第 {} 个操作处中止（{}），已回滚 {} 个操作",
+            plan.batch_id,
+            failed_index + 1,
+            error,
+            rollback.successful
+        );
+        TransactionOutcome::RolledBack {
+            failed_index,
+            error,
+            execution,
+            rollback,
+        }
+    }
+
+    /// 逐个执行计划中未完成的操作；stop_on_failure 时遇到失败即停止，其余操作标记为跳过
+    fn run_batch(
+        &mut self,
+        plan: &mut MovePlan,
+        cancel: &AtomicBool,
+        stop_on_failure: bool,
+    ) -> ExecutionResult {
         let mut result = ExecutionResult {
             successful: 0,
             failed: 0,
             skipped: 0,
             errors: Vec::new(),
+            cancelled: false,
+            copied_bytes: 0,
+            copy_duration: Duration::ZERO,
+            mode: plan.mode,
         };
 
-        for op in plan.operations.iter_mut() {
+        // 断点只有一个：覆盖前必须先处理（继续或回滚）另一个未完成的批次，否则它会丢失
+        if let Some(other) = self.conflicting_pending_batch(plan) {
+            result.errors.push(format!(
+                "存在未完成的批次 {}，请先继续或回滚后再执行",
+                other
+            ));
+            tracing::warn!("批次 {} 未执行：存在未完成的批次 {}", plan.batch_id, other);
+            return result;
+        }
+
+        self.save_checkpoint(plan);
+
+        // 本批次已放到目标位置的文件（含断点续做时已完成的操作），后面的操作不能覆盖它们
+        let mut claimed: HashSet<PathBuf> = plan
+            .operations
+            .iter()
+            .filter(|op| op.status == OperationStatus::Completed)
+            .map(|op| op.to.clone())
+            .collect();
+        let mut since_checkpoint = 0;
+        for i in 0..plan.operations.len() {
+            let op = &mut plan.operations[i];
+            if !matches!(op.status, OperationStatus::Pending | OperationStatus::InProgress) {
+                continue;
+            }
+            if cancel.load(Ordering::Relaxed) {
+                result.cancelled = true;
+                break;
+            }
+
             op.status = OperationStatus::InProgress;
 
-            match self.execute_single_operation(op) {
-                Ok(()) => {
+            match self.execute_single_operation(op, plan.mode, &claimed) {
+                Ok(OperationOutcome::Moved) => {
                     op.status = OperationStatus::Completed;
+                    claimed.insert(op.to.clone());
                     result.successful += 1;
                 }
+                Ok(OperationOutcome::Copied { bytes, elapsed }) => {
+                    op.status = OperationStatus::Completed;
+                    claimed.insert(op.to.clone());
+                    result.successful += 1;
+                    result.copied_bytes += bytes;
+                    result.copy_duration += elapsed;
+                    // 复制后源文件仍在，无法从磁盘判断目标是否由本批次创建，因此每次复制都立即保存断点
+                    since_checkpoint = CHECKPOINT_INTERVAL;
+                }
+                Ok(OperationOutcome::Skipped(reason)) => {
+                    op.status = OperationStatus::Skipped;
+                    op.error = Some(reason);
+                    result.skipped += 1;
+                }
                 Err(e) => {
                     op.status = OperationStatus::Failed;
-                    op.error = Some(e.to_string());
+                    // 原有文件已进回收站而移动失败时一并说明
+                    op.error = Some(if op.trashed_target {
+                        format!("{}（{}: {}）", e, TRASHED_TARGET_NOTE, op.to.display())
+                    } else {
+                        e.to_string()
+                    });
                     result.failed += 1;
                     result.errors.push(format!(
                         "移动 {} 失败: {}",
@@ -123,42 +481,295 @@ impl Executor {
                     ));
                 }
             }
+
+            if stop_on_failure && op.status == OperationStatus::Failed {
+                break;
+            }
+
+            since_checkpoint += 1;
+            if since_checkpoint >= CHECKPOINT_INTERVAL {
+                self.save_checkpoint(plan);
+                since_checkpoint = 0;
+            }
         }
 
-        // 记录历史
+        if stop_on_failure && result.failed > 0 {
+            for op in plan.operations.iter_mut().filter(|op| op.status == OperationStatus::Pending) {
+                op.status = OperationStatus::Skipped;
+                op.error = Some("批次中前面的操作失败，未执行".to_string());
+                result.skipped += 1;
+            }
+        }
+
+        if result.cancelled {
+            // 保留断点，等待继续或回滚
+            self.save_checkpoint(plan);
+            tracing::info!("批次 {} 已取消，剩余操作已保存", plan.batch_id);
+            return result;
+        }
+
+        self.record_history(plan);
+        self.clear_checkpoint();
+
+        result
+    }
+
+    /// 读取上次未完成的批次（若有）
+    ///
+    /// 断点可能落后于实际进度：目标已存在且源已不存在的移动操作视为已完成。
+    /// 复制批次每完成一个操作就保存断点，未记录为完成的操作一律视为未执行，
+    /// 避免把执行前就存在的同名文件当成本批次的副本，回滚时删掉用户自己的文件。
+    pub fn pending_batch(&self) -> Option<MovePlan> {
+        let content = fs::read_to_string(&self.pending_file).ok()?;
+        let mut plan: MovePlan = match serde_json::from_str(&content) {
+            Ok(p) => p,
+            Err(e) => {
+                tracing::warn!("未完成批次记录损坏，已忽略: {}", e);
+                return None;
+            }
+        };
+
+        let copying = plan.mode == ExecutionMode::Copy;
+        for op in plan.operations.iter_mut() {
+            if matches!(op.status, OperationStatus::Pending | OperationStatus::InProgress) {
+                op.status = if !copying && !op.from.exists() && op.to.exists() {
+                    OperationStatus::Completed
+                } else {
+                    OperationStatus::Pending
+                };
+            }
+        }
+        Some(plan)
+    }
+
+    /// 与指定计划不同的未完成批次ID（若有），此时执行会覆盖其断点，应先处理它
+    pub fn conflicting_pending_batch(&self, plan: &MovePlan) -> Option<String> {
+        self.pending_batch()
+            .map(|pending| pending.batch_id)
+            .filter(|id| *id != plan.batch_id)
+    }
+
+    /// 放弃未完成的批次：记录到历史并回滚其中已完成的操作
+    pub fn rollback_pending(&mut self, plan: &MovePlan) -> RollbackResult {
+        self.record_history(plan);
+        self.clear_checkpoint();
+        self.rollback(&plan.batch_id)
+    }
+
+    /// 把批次写入历史记录并保存
+    fn record_history(&mut self, plan: &MovePlan) {
         let entry = HistoryEntry {
             batch_id: plan.batch_id.clone(),
             executed_at: Utc::now(),
             operations: plan.operations.clone(),
             rolled_back: false,
+            mode: plan.mode,
         };
         self.history.push(entry);
 
-        // 保存历史
-        if let Err(e) = self.save_history() {
+        if let Err(e) = self.save_history_entry(self.history.len() - 1) {
             tracing::warn!("保存历史记录失败: {}", e);
         }
+    }
 
-        result
+    /// 保存执行断点
+    fn save_checkpoint(&self, plan: &MovePlan) {
+        let saved = serde_json::to_string(plan)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| {
+                if let Some(parent) = self.pending_file.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&self.pending_file, content)?;
+                Ok(())
+            });
+        if let Err(e) = saved {
+            tracing::warn!("保存执行断点失败: {}", e);
+        }
+    }
+
+    /// 删除执行断点
+    fn clear_checkpoint(&self) {
+        if self.pending_file.exists() {
+            if let Err(e) = fs::remove_file(&self.pending_file) {
+                tracing::warn!("删除执行断点失败: {}", e);
+            }
+        }
+    }
+
+    /// 按冲突策略在目标目录中查找与源文件内容相同的文件
+    ///
+    /// 与 `Planner::dedupe_plan` 一样依次比较大小、文件开头的哈希、完整哈希；
+    /// 源文件的两种哈希每个操作最多各算一次，计划中已记录的源文件哈希直接复用。
+    fn find_duplicate(&self, op: &MoveOperation) -> Option<PathBuf> {
+        if self.conflict_strategy != ConflictStrategy::DedupeByHash {
+            return None;
+        }
+        let size = fs::metadata(&op.from).ok()?.len();
+        let candidates: Vec<PathBuf> = fs::read_dir(op.to.parent()?)
+            .ok()?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_file() && fs::metadata(p).map(|m| m.len() == size).unwrap_or(false))
+            .collect();
+        if candidates.is_empty() {
+            return None;
+        }
+
+        let source_partial = partial_content_hash(&op.from).ok()?;
+        let mut source_full = op.source_hash.clone();
+        for candidate in candidates {
+            if partial_content_hash(&candidate).ok().as_ref() != Some(&source_partial) {
+                continue;
+            }
+            let source_hash = match &source_full {
+                Some(hash) => hash,
+                None => source_full.insert(hash_file_content(&op.from).ok()?),
+            };
+            if hash_file_content(&candidate).ok().as_ref() == Some(source_hash) {
+                return Some(candidate);
+            }
+        }
+        None
+    }
+
+    /// 目标被占用时按冲突策略决定如何处理；occupied 判断路径是否已被占用，
+    /// claimed 是本批次前面的操作已放到目标位置的路径
+    fn resolve_conflict(
+        &self,
+        op: &MoveOperation,
+        occupied: impl Fn(&Path) -> bool,
+        claimed: &HashSet<PathBuf>,
+    ) -> ConflictResolution {
+        if !occupied(&op.to) {
+            return ConflictResolution::MoveTo(op.to.clone());
+        }
+        if let Some(duplicate) = self.find_duplicate(op) {
+            return ConflictResolution::Duplicate(duplicate);
+        }
+        match self.conflict_strategy {
+            ConflictStrategy::Skip => {
+                ConflictResolution::Skip(format!("目标文件已存在: {}", op.to.display()))
+            }
+            // 按内容去重没找到相同文件时同自动改名，不因内容不同而失败
+            ConflictStrategy::Rename | ConflictStrategy::DedupeByHash => {
+                ConflictResolution::MoveTo(numbered_path(&op.to, occupied))
+            }
+            // 占用者是本批次刚移来的文件：覆盖或移到回收站会丢掉它，回滚也还原不了，改名
+            _ if claimed.contains(&op.to)
+                && (self.use_trash_on_conflict || self.conflict_strategy == ConflictStrategy::Overwrite) =>
+            {
+                ConflictResolution::MoveTo(numbered_path(&op.to, occupied))
+            }
+            _ if self.use_trash_on_conflict => ConflictResolution::Trash,
+            ConflictStrategy::Overwrite => ConflictResolution::Overwrite,
+            ConflictStrategy::Fail => ConflictResolution::Fail,
+        }
     }
 
     /// 执行单个移动操作
-    fn execute_single_operation(&self, op: &MoveOperation) -> Result<()> {
+    ///
+    /// 按冲突策略自动改名时，op.to 会更新为实际使用的路径，以便历史记录和回滚。
+    /// claimed 是本批次已放到目标位置的路径，它们不会被覆盖。
+    fn execute_single_operation(
+        &self,
+        op: &mut MoveOperation,
+        mode: ExecutionMode,
+        claimed: &HashSet<PathBuf>,
+    ) -> Result<OperationOutcome> {
+        // 计划生成后源文件可能已被其他程序删除或替换，此时跳过而不是失败
+        if let Some(reason) = source_changed_since_plan(op) {
+            tracing::warn!("{}: {}", reason, op.from.display());
+            return Ok(OperationOutcome::Skipped(reason.to_string()));
+        }
+
         // 创建目标目录
         if let Some(parent) = op.to.parent() {
+            let newly_created = !parent.exists();
             fs::create_dir_all(parent)?;
+            if newly_created && self.write_folder_readme {
+                if let Err(e) = write_folder_readme(parent, op) {
+                    tracing::warn!("写入目录说明文件失败 {}: {}", parent.display(), e);
+                }
+            }
         }
 
         // 检查目标是否已存在
-        if op.to.exists() {
-            return Err(anyhow::anyhow!("目标文件已存在"));
+        match self.resolve_conflict(op, |p| p.exists() || claimed.contains(p), claimed) {
+            ConflictResolution::MoveTo(to) => {
+                if to != op.to {
+                    tracing::info!("目标已存在，改名为: {}", to.display());
+                    op.to = to;
+                }
+            }
+            ConflictResolution::Overwrite => {
+                if op.to.is_dir() {
+                    return Err(anyhow::anyhow!("目标是已存在的目录，无法覆盖"));
+                }
+                tracing::warn!("覆盖已有文件: {}", op.to.display());
+                fs::remove_file(&op.to)?;
+            }
+            ConflictResolution::Trash => {
+                (self.trash)(&op.to)
+                    .map_err(|e| anyhow::anyhow!("把已有文件移到回收站失败: {}", e))?;
+                tracing::info!("{}: {}", TRASHED_TARGET_NOTE, op.to.display());
+                op.trashed_target = true;
+            }
+            ConflictResolution::Skip(reason) => {
+                tracing::info!("跳过 {}: {}", op.from.display(), reason);
+                return Ok(OperationOutcome::Skipped(reason));
+            }
+            ConflictResolution::Duplicate(duplicate) => {
+                let mut reason = format!("目标目录已有内容相同的文件: {}", duplicate.display());
+                // 复制模式本就要保留原文件，只在移动模式下清理多余的源文件
+                if self.trash_duplicate_sources && mode == ExecutionMode::Move {
+                    match (self.trash)(&op.from) {
+                        Ok(()) => reason.push_str("，源文件已移到回收站"),
+                        Err(e) => reason.push_str(&format!("，源文件移到回收站失败: {}", e)),
+                    }
+                }
+                tracing::info!("跳过 {}: {}", op.from.display(), reason);
+                return Ok(OperationOutcome::Skipped(reason));
+            }
+            ConflictResolution::Fail => return Err(anyhow::anyhow!("目标文件已存在")),
         }
 
-        // 执行移动
-        fs::rename(&op.from, &op.to)?;
+        // 原子目录只能在同一卷内通过一次 rename 整体移动
+        let is_directory = op.from.is_dir();
+        if is_directory && mode == ExecutionMode::Copy {
+            return Err(anyhow::anyhow!("复制模式不支持整体复制目录"));
+        }
+
+        // 复制模式：保留源文件，副本沿用源文件的修改时间
+        if mode == ExecutionMode::Copy {
+            let started = Instant::now();
+            let bytes = copy_via_temp(&op.from, &op.to)?;
+            let elapsed = started.elapsed();
+            if let Err(e) = preserve_modified_time(&op.from, &op.to) {
+                tracing::warn!("保留修改时间失败 {}: {}", op.to.display(), e);
+            }
+            tracing::info!("已复制: {} -> {}", op.from.display(), op.to.display());
+            return Ok(OperationOutcome::Copied { bytes, elapsed });
+        }
+
+        // 执行移动；跨设备时 rename 不可用，改为经临时文件复制后删除源文件
+        match fs::rename(&op.from, &op.to) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices && is_directory => {
+                return Err(anyhow::anyhow!("目录不能跨卷整体移动: {}", e));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+                let started = Instant::now();
+                let bytes = copy_then_delete(&op.from, &op.to)?;
+                let elapsed = started.elapsed();
+                tracing::info!("已跨设备移动: {} -> {}", op.from.display(), op.to.display());
+                return Ok(OperationOutcome::Copied { bytes, elapsed });
+            }
+            Err(e) => return Err(e.into()),
+        }
 
         tracing::info!("已移动: {} -> {}", op.from.display(), op.to.display());
-        Ok(())
+        Ok(OperationOutcome::Moved)
     }
 
     /// 回滚指定批次的操作
@@ -167,6 +778,7 @@ impl Executor {
             successful: 0,
             failed: 0,
             errors: Vec::new(),
+            left_in_trash: Vec::new(),
         };
 
         // 查找历史记录索引
@@ -186,20 +798,27 @@ impl Executor {
         }
 
         // 逆序回滚 - 先收集需要回滚的操作
-        let ops_to_rollback: Vec<(usize, std::path::PathBuf, std::path::PathBuf)> = self.history[entry_idx]
+        let ops_to_rollback: Vec<(usize, std::path::PathBuf, std::path::PathBuf, bool)> = self.history[entry_idx]
             .operations
             .iter()
             .enumerate()
             .filter(|(_, op)| op.status == OperationStatus::Completed)
-            .map(|(i, op)| (i, op.from.clone(), op.to.clone()))
+            .map(|(i, op)| {
+                (i, op.from.clone(), op.to.clone(), op.trashed_target)
+            })
             .collect();
 
         // 执行回滚
-        for (op_idx, from, to) in ops_to_rollback.into_iter().rev() {
-            match Self::rollback_operation_static(&from, &to) {
+        let mode = self.history[entry_idx].mode;
+        for (op_idx, from, to, trashed) in ops_to_rollback.into_iter().rev() {
+            match Self::rollback_operation_static(&from, &to, mode) {
                 Ok(()) => {
                     self.history[entry_idx].operations[op_idx].status = OperationStatus::RolledBack;
                     result.successful += 1;
+                    // 回收站中的原有文件不会自动还原，提示用户手动恢复
+                    if trashed {
+                        result.left_in_trash.push(to);
+                    }
                 }
                 Err(e) => {
                     result.failed += 1;
@@ -215,30 +834,53 @@ impl Executor {
         self.history[entry_idx].rolled_back = true;
 
         // 保存历史
-        if let Err(e) = self.save_history() {
+        if let Err(e) = self.save_history_entry(entry_idx) {
             tracing::warn!("保存历史记录失败: {}", e);
         }
 
         result
     }
 
-    /// 静态回滚操作（避免借用冲突）
-    fn rollback_operation_static(from: &std::path::Path, to: &std::path::Path) -> Result<()> {
+    /// 静态回滚操作（避免借用冲突）；复制批次删除副本，移动批次移回原位置
+    fn rollback_operation_static(
+        from: &std::path::Path,
+        to: &std::path::Path,
+        mode: ExecutionMode,
+    ) -> Result<()> {
         // 检查新位置是否存在
         if !to.exists() {
             return Err(anyhow::anyhow!("新位置文件不存在"));
         }
 
-        // 创建原始目录（如果需要）
-        if let Some(parent) = from.parent() {
-            fs::create_dir_all(parent)?;
-        }
+        if mode == ExecutionMode::Copy {
+            fs::remove_file(to)?;
+        } else {
+            // 创建原始目录（如果需要）
+            if let Some(parent) = from.parent() {
+                fs::create_dir_all(parent)?;
+            }
 
-        // 移回原位置
-        fs::rename(to, from)?;
+            // 移回原位置（跨设备移动的批次同样需要复制后删除）
+            match fs::rename(to, from) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+                    copy_then_delete(to, from)?;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
 
-        // 尝试清理空目录
+        // 尝试清理空目录（只剩说明文件时一并删除）
         if let Some(parent) = to.parent() {
+            let only_readme = fs::read_dir(parent)
+                .map(|entries| {
+                    let names: Vec<_> = entries.filter_map(|e| e.ok()).map(|e| e.file_name()).collect();
+                    names.len() == 1 && names[0] == FOLDER_README_NAME
+                })
+                .unwrap_or(false);
+            if only_readme {
+                let _ = fs::remove_file(parent.join(FOLDER_README_NAME));
+            }
             let _ = fs::remove_dir(parent); // 忽略错误（目录可能不为空）
         }
 
@@ -248,8 +890,8 @@ impl Executor {
 
     /// 回滚单个操作
     #[allow(dead_code)]
-    fn rollback_single_operation(&self, op: &MoveOperation) -> Result<()> {
-        Self::rollback_operation_static(&op.from, &op.to)
+    fn rollback_single_operation(&self, op: &MoveOperation, mode: ExecutionMode) -> Result<()> {
+        Self::rollback_operation_static(&op.from, &op.to, mode)
     }
 
     /// 获取历史记录
@@ -262,6 +904,11 @@ impl Executor {
         self.history.iter().rev().take(count).collect()
     }
 
+    /// 把全部历史操作导出为 CSV 文本（每个操作一行）
+    pub fn export_history_csv(&self) -> String {
+        history_to_csv(&self.history)
+    }
+
     /// 清理旧历史记录
     pub fn cleanup_old_history(&mut self, keep_count: usize) {
         if self.history.len() > keep_count {
@@ -272,8 +919,189 @@ impl Executor {
     }
 }
 
+/// 检查源文件自计划生成以来是否被删除或修改，返回跳过原因
+fn source_changed_since_plan(op: &MoveOperation) -> Option<&'static str> {
+    if !op.from.exists() {
+        return Some("源文件在计划后被删除");
+    }
+    let expected = op.source_hash.as_ref()?;
+    match hash_file_content(&op.from) {
+        Ok(actual) if &actual == expected => None,
+        _ => Some("源文件在计划后被修改"),
+    }
+}
+
+/// 历史 CSV 的表头
+pub const HISTORY_CSV_HEADER: &str = "batch_id,executed_at,from,to,status,rolled_back";
+
+/// 把历史记录展开为 CSV：表头之后每个操作一行，时间为 RFC 3339
+pub fn history_to_csv(history: &[HistoryEntry]) -> String {
+    let mut csv = String::from(HISTORY_CSV_HEADER);
+    csv.push('\n');
+    for entry in history {
+        let executed_at = entry.executed_at.to_rfc3339();
+        for op in &entry.operations {
+            let row = [
+                csv_field(&entry.batch_id),
+                csv_field(&executed_at),
+                csv_field(&op.from.to_string_lossy()),
+                csv_field(&op.to.to_string_lossy()),
+                csv_field(&format!("{:?}", op.status)),
+                entry.rolled_back.to_string(),
+            ];
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+    }
+    csv
+}
+
+/// 按 RFC 4180 转义 CSV 字段：含逗号、引号或换行时整体加引号，内部引号加倍
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// 生成与目标同目录、不与现有文件冲突的临时路径（`目标名.orderly-tmp`，被占用时追加序号）
+fn unique_temp_path(to: &Path) -> PathBuf {
+    let name = to
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut candidate = to.with_file_name(format!("{}{}", name, TEMP_SUFFIX));
+    let mut n = 1;
+    while candidate.exists() {
+        candidate = to.with_file_name(format!("{}.{}{}", name, n, TEMP_SUFFIX));
+        n += 1;
+    }
+    candidate
+}
+
+/// 在目标名称的主干后追加 ` (1)`、` (2)`……直到得到未被占用的路径
+fn numbered_path(to: &Path, occupied: impl Fn(&Path) -> bool) -> PathBuf {
+    let stem = to
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = to
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    let mut n = 1;
+    loop {
+        let candidate = to.with_file_name(format!("{} ({}){}", stem, n, extension));
+        if !occupied(&candidate) {
+            return candidate;
+        }
+        n += 1;
+    }
+}
+
+/// 跨设备移动：经临时文件复制（保留修改时间）后删除源文件，返回复制的字节数
+///
+/// 删除源文件失败时删除副本，保证文件只在一处。
+fn copy_then_delete(from: &Path, to: &Path) -> Result<u64> {
+    let bytes = copy_via_temp(from, to)?;
+    if let Err(e) = preserve_modified_time(from, to) {
+        tracing::warn!("保留修改时间失败 {}: {}", to.display(), e);
+    }
+    if let Err(e) = fs::remove_file(from) {
+        let _ = fs::remove_file(to);
+        return Err(anyhow::anyhow!("复制完成但删除源文件失败: {}", e));
+    }
+    Ok(bytes)
+}
+
+/// 把源文件的修改时间写到副本上
+fn preserve_modified_time(from: &Path, to: &Path) -> Result<()> {
+    let modified = fs::metadata(from)?.modified()?;
+    fs::File::options().write(true).open(to)?.set_modified(modified)?;
+    Ok(())
+}
+
+/// 先复制到临时文件，再原子地重命名为最终文件名
+///
+/// 复制中断时最终路径上不会出现不完整的文件，临时文件会被清理。返回复制的字节数。
+fn copy_via_temp(from: &Path, to: &Path) -> Result<u64> {
+    copy_via_temp_with(from, to, |src, dst| fs::copy(src, dst))
+}
+
+fn copy_via_temp_with(
+    from: &Path,
+    to: &Path,
+    copy: impl FnOnce(&Path, &Path) -> std::io::Result<u64>,
+) -> Result<u64> {
+    let temp = unique_temp_path(to);
+
+    let finished = copy(from, &temp).and_then(|bytes| {
+        // 同目录内 rename 是原子的；先检查避免覆盖已有文件
+        if to.exists() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                "目标文件已存在",
+            ));
+        }
+        fs::rename(&temp, to).map(|_| bytes)
+    });
+
+    finished.map_err(|e| {
+        let _ = fs::remove_file(&temp);
+        anyhow::anyhow!("复制 {} 失败: {}", from.display(), e)
+    })
+}
+
+/// 在新建的分类目录中写入说明文件（已存在则不覆盖）
+fn write_folder_readme(dir: &Path, op: &MoveOperation) -> Result<()> {
+    let mut file = match fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(dir.join(FOLDER_README_NAME))
+    {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let category = dir
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let first_file = op
+        .to
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    write!(
+        file,
+        "此目录由 Orderly 整理时创建。\n分类: {}\n创建时间: {}\n首个移入文件: {}\n",
+        category,
+        Utc::now().format("%Y-%m-%d %H:%M:%S UTC"),
+        first_file
+    )?;
+    Ok(())
+}
+
+/// Dry Run 中的单个操作（导出用）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DryRunEntry {
+    /// 源路径
+    pub from: PathBuf,
+    /// 按冲突策略解析后的目标路径
+    pub to: PathBuf,
+    /// 目标目录是否需要新建
+    pub will_create_dir: bool,
+    /// 该操作的潜在问题
+    pub error: Option<String>,
+}
+
+/// Dry Run 导出 CSV 的表头
+pub const DRY_RUN_CSV_HEADER: &str = "from,to,will_create_dir,error";
+
 /// Dry Run 结果
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct DryRunResult {
     /// 将要创建的目录
     pub would_create_dirs: Vec<PathBuf>,
@@ -281,14 +1109,95 @@ pub struct DryRunResult {
     pub would_move_files: Vec<(PathBuf, PathBuf)>,
     /// 潜在错误
     pub potential_errors: Vec<String>,
+    /// 需要占用目标卷的字节数（复制和跨卷移动的文件大小之和）
+    pub required_bytes: u64,
+    /// 目标卷的可用空间（无需占用空间或无法查询时为 None）
+    pub available_bytes: Option<u64>,
+    /// 计划中每个操作的预览结果（按计划顺序）
+    pub entries: Vec<DryRunEntry>,
 }
 
 impl DryRunResult {
+    /// 记录一个操作的预览结果，有问题时同时计入潜在错误
+    fn push_entry(&mut self, entry: DryRunEntry) {
+        if let Some(ref error) = entry.error {
+            self.potential_errors.push(error.clone());
+        }
+        self.entries.push(entry);
+    }
+
+    /// 导出为 JSON（含摘要、新建目录、逐个操作和潜在问题）
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// 导出为 CSV：表头之后每个操作一行，含逗号或引号的字段加引号
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(DRY_RUN_CSV_HEADER);
+        csv.push('\n');
+        for entry in &self.entries {
+            let row = [
+                csv_field(&entry.from.to_string_lossy()),
+                csv_field(&entry.to.to_string_lossy()),
+                entry.will_create_dir.to_string(),
+                csv_field(entry.error.as_deref().unwrap_or("")),
+            ];
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+
     /// 是否有错误
     pub fn has_errors(&self) -> bool {
-        !self.potential_errors.is_empty()
+        !self.potential_errors.is_empty() || self.space_shortage().is_some()
+    }
+
+    /// 目标卷空间不足时的说明
+    pub fn space_shortage(&self) -> Option<String> {
+        let available = self.available_bytes?;
+        (available < self.required_bytes).then(|| {
+            format!(
+                "目标卷空间不足：需要 {}，可用 {}",
+                format_bytes(self.required_bytes),
+                format_bytes(available)
+            )
+        })
     }
     
+    /// 导出为类似 unified diff 的文本，便于附到工单中评审
+    ///
+    /// 新建目录按路径排序输出为 `+ dir/`；每个移动输出 `- 源路径` 与 `+ 目标路径` 两行。
+    pub fn to_diff(&self) -> String {
+        let mut out = format!("# Orderly 预览: {}\n", self.summary());
+
+        if !self.would_create_dirs.is_empty() {
+            let mut dirs: Vec<&PathBuf> = self.would_create_dirs.iter().collect();
+            dirs.sort();
+            out.push_str("\n# 新建目录\n");
+            for dir in dirs {
+                out.push_str(&format!("+ {}/\n", dir.display()));
+            }
+        }
+
+        if !self.would_move_files.is_empty() {
+            out.push_str("\n# 移动文件\n");
+            for (from, to) in &self.would_move_files {
+                out.push_str(&format!("- {}\n+ {}\n", from.display(), to.display()));
+            }
+        }
+
+        let shortage = self.space_shortage();
+        if !self.potential_errors.is_empty() || shortage.is_some() {
+            out.push_str("\n# 潜在问题\n");
+            for error in shortage.iter().chain(&self.potential_errors) {
+                out.push_str(&format!("! {}\n", error));
+            }
+        }
+
+        out
+    }
+
     /// 获取摘要
     pub fn summary(&self) -> String {
         format!(
@@ -300,6 +1209,30 @@ impl DryRunResult {
     }
 }
 
+/// 清理结果
+#[derive(Debug, Default)]
+pub struct CleanupResult {
+    /// 已移到回收站的文件数
+    pub trashed: usize,
+    /// 错误信息
+    pub errors: Vec<String>,
+}
+
+/// 把文件移到系统回收站（可从回收站恢复），用于清理系统缓存文件
+pub fn move_to_trash(paths: &[PathBuf]) -> CleanupResult {
+    let mut result = CleanupResult::default();
+    for path in paths {
+        match trash::delete(path) {
+            Ok(()) => {
+                tracing::info!("已移到回收站: {}", path.display());
+                result.trashed += 1;
+            }
+            Err(e) => result.errors.push(format!("{}: {}", path.display(), e)),
+        }
+    }
+    result
+}
+
 /// 执行结果
 #[derive(Debug)]
 pub struct ExecutionResult {
@@ -311,23 +1244,148 @@ pub struct ExecutionResult {
     pub skipped: usize,
     /// 错误信息
     pub errors: Vec<String>,
+    /// 是否被中途取消（剩余操作保存在断点中）
+    pub cancelled: bool,
+    /// 执行方式
+    pub mode: ExecutionMode,
+    /// 跨设备复制的字节数
+    pub copied_bytes: u64,
+    /// 跨设备复制的总耗时
+    pub copy_duration: Duration,
 }
 
 impl ExecutionResult {
+    /// 测速所需的最少复制量（太少时耗时主要是固定开销，不具代表性）
+    const MIN_THROUGHPUT_SAMPLE_BYTES: u64 = 1024 * 1024;
+
     /// 是否全部成功
     pub fn is_all_successful(&self) -> bool {
         self.failed == 0
     }
+
+    /// 本次跨设备复制的实测速度（字节/秒），样本不足时为 None
+    pub fn copy_throughput(&self) -> Option<f64> {
+        let secs = self.copy_duration.as_secs_f64();
+        (self.copied_bytes >= Self::MIN_THROUGHPUT_SAMPLE_BYTES && secs > 0.0)
+            .then(|| self.copied_bytes as f64 / secs)
+    }
     
     /// 获取摘要
     pub fn summary(&self) -> String {
-        format!(
+        let summary = format!(
             "成功: {}, 失败: {}, 跳过: {}",
             self.successful, self.failed, self.skipped
+        );
+        if self.cancelled {
+            format!("{}（已取消）", summary)
+        } else {
+            summary
+        }
+    }
+}
+
+/// 一次执行的回执，供界面显示结果卡片（含撤销入口）
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperationReceipt {
+    /// 批次ID（撤销时使用）
+    pub batch_id: String,
+    /// 成功移动（或复制）的数量
+    pub moved: usize,
+    /// 失败数量
+    pub failed: usize,
+    /// 跳过数量
+    pub skipped: usize,
+    /// 成功移动的文件总大小（字节）
+    pub bytes: u64,
+    /// 执行总耗时
+    pub elapsed: Duration,
+    /// 是否被中途取消
+    pub cancelled: bool,
+    /// 执行方式
+    pub mode: ExecutionMode,
+}
+
+impl OperationReceipt {
+    /// 由执行后的计划和结果汇总回执
+    ///
+    /// `sizes` 为文件ID到文件大小的映射，只统计状态为已完成的操作。
+    pub fn from_execution(
+        plan: &MovePlan,
+        result: &ExecutionResult,
+        sizes: &HashMap<String, u64>,
+        elapsed: Duration,
+    ) -> Self {
+        let bytes = plan
+            .operations
+            .iter()
+            .filter(|op| op.status == OperationStatus::Completed)
+            .filter_map(|op| sizes.get(&op.file_id))
+            .sum();
+        Self {
+            batch_id: plan.batch_id.clone(),
+            moved: result.successful,
+            failed: result.failed,
+            skipped: result.skipped,
+            bytes,
+            elapsed,
+            cancelled: result.cancelled,
+            mode: result.mode,
+        }
+    }
+
+    /// 是否有可撤销的移动（被取消的批次未写入历史，需走断点回滚）
+    pub fn can_undo(&self) -> bool {
+        self.moved > 0 && !self.cancelled
+    }
+
+    /// 卡片标题
+    pub fn headline(&self) -> String {
+        let verb = self.mode.label();
+        if self.cancelled {
+            format!("已取消，已{} {} 个文件", verb, self.moved)
+        } else if self.failed > 0 {
+            format!("已{} {} 个文件，{} 个失败", verb, self.moved, self.failed)
+        } else {
+            format!("已{} {} 个文件", verb, self.moved)
+        }
+    }
+
+    /// 卡片详情
+    pub fn detail(&self) -> String {
+        format!(
+            "{} · 跳过 {} · {}",
+            format_bytes(self.bytes),
+            self.skipped,
+            PlanStats::format_duration(self.elapsed)
         )
     }
 }
 
+/// 全部成功或全部撤销执行的结果
+#[derive(Debug)]
+pub enum TransactionOutcome {
+    /// 没有失败的操作，批次已提交
+    Committed(ExecutionResult),
+    /// 第 failed_index 个操作失败（或在此处取消），已完成的操作已自动回滚
+    RolledBack {
+        /// 中止处的操作下标
+        failed_index: usize,
+        /// 失败原因
+        error: String,
+        /// 回滚前的执行结果
+        execution: ExecutionResult,
+        /// 自动回滚的结果
+        rollback: RollbackResult,
+    },
+}
+
+impl TransactionOutcome {
+    /// 是否已提交
+    pub fn is_committed(&self) -> bool {
+        matches!(self, TransactionOutcome::Committed(_))
+    }
+}
+
 /// 回滚结果
 #[derive(Debug)]
 pub struct RollbackResult {
@@ -337,6 +1395,8 @@ pub struct RollbackResult {
     pub failed: usize,
     /// 错误信息
     pub errors: Vec<String>,
+    /// 执行时被移到回收站、需要手动从回收站恢复的原有文件
+    pub left_in_trash: Vec<PathBuf>,
 }
 
 impl RollbackResult {
@@ -347,6 +1407,724 @@ impl RollbackResult {
     
     /// 获取摘要
     pub fn summary(&self) -> String {
-        format!("回滚成功: {}, 失败: {}", self.successful, self.failed)
+        let summary = format!("回滚成功: {}, 失败: {}", self.successful, self.failed);
+        if self.left_in_trash.is_empty() {
+            summary
+        } else {
+            format!(
+                "{}；{} 个被替换的原有文件仍在回收站中，可从回收站恢复",
+                summary,
+                self.left_in_trash.len()
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use tempfile::tempdir;
+
+    const STUB_TRASH_SUFFIX: &str = ".trashed";
+
+    thread_local! {
+        static STUB_TRASHED: RefCell<Vec<PathBuf>> = const { RefCell::new(Vec::new()) };
+    }
+
+    /// 测试用回收站：改名为同目录下的 `*.trashed` 并记录调用，不碰系统回收站
+    fn stub_trash(path: &Path) -> std::io::Result<()> {
+        let mut trashed = path.as_os_str().to_owned();
+        trashed.push(STUB_TRASH_SUFFIX);
+        fs::rename(path, trashed)?;
+        STUB_TRASHED.with(|calls| calls.borrow_mut().push(path.to_path_buf()));
+        Ok(())
+    }
+
+    fn take_stub_trashed() -> Vec<PathBuf> {
+        STUB_TRASHED.with(|calls| std::mem::take(&mut *calls.borrow_mut()))
+    }
+
+    #[test]
+    fn test_resume_after_crash_mid_batch() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("input");
+        let output = dir.path().join("output");
+        let data = dir.path().join("data");
+        fs::create_dir_all(&input).unwrap();
+
+        let mut plan = MovePlan::new();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            fs::write(input.join(name), name).unwrap();
+            plan.add_operation(input.join(name), output.join(name), name.to_string());
+        }
+
+        // 模拟崩溃：断点写在第一个操作开始前，此后 a.txt 已实际移动，b.txt 执行到一半
+        let executor = Executor::new(data.clone());
+        executor.save_checkpoint(&plan);
+        fs::create_dir_all(&output).unwrap();
+        fs::rename(input.join("a.txt"), output.join("a.txt")).unwrap();
+        drop(executor);
+
+        // 重新启动
+        let mut executor = Executor::new(data);
+        let mut pending = executor.pending_batch().expect("应检测到未完成批次");
+        assert_eq!(pending.batch_id, plan.batch_id);
+        assert_eq!(pending.operations[0].status, OperationStatus::Completed);
+        assert_eq!(pending.operations[1].status, OperationStatus::Pending);
+
+        let result = executor.execute(&mut pending);
+        assert_eq!(result.successful, 2);
+        assert_eq!(result.failed, 0);
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            assert!(output.join(name).exists());
+        }
+        assert!(executor.pending_batch().is_none());
+
+        // 整批（包括崩溃前完成的操作）都可回滚
+        let rollback = executor.rollback(&plan.batch_id);
+        assert_eq!(rollback.successful, 3);
+        assert!(input.join("a.txt").exists());
+    }
+
+    #[test]
+    fn test_interrupted_copy_leaves_no_final_file() {
+        let dir = tempdir().unwrap();
+        let from = dir.path().join("video.mp4");
+        let to = dir.path().join("out").join("video.mp4");
+        fs::write(&from, vec![7u8; 4096]).unwrap();
+        fs::create_dir_all(to.parent().unwrap()).unwrap();
+
+        // 写入一半后中断
+        let result = copy_via_temp_with(&from, &to, |_, temp| {
+            fs::write(temp, [7u8; 100])?;
+            Err(std::io::Error::new(std::io::ErrorKind::Interrupted, "设备已断开"))
+        });
+
+        assert!(result.is_err());
+        assert!(!to.exists());
+        assert_eq!(fs::read_dir(to.parent().unwrap()).unwrap().count(), 0, "临时文件应被清理");
+        assert!(from.exists());
+
+        // 正常复制
+        assert_eq!(copy_via_temp(&from, &to).unwrap(), 4096);
+        assert_eq!(fs::read(&to).unwrap().len(), 4096);
+        assert_eq!(fs::read_dir(to.parent().unwrap()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_copy_then_delete_preserves_mtime_and_reverses() {
+        let dir = tempdir().unwrap();
+        let from = dir.path().join("c/photo.jpg");
+        let to = dir.path().join("d/Pictures/photo.jpg");
+        fs::create_dir_all(from.parent().unwrap()).unwrap();
+        fs::create_dir_all(to.parent().unwrap()).unwrap();
+        fs::write(&from, vec![1u8; 2048]).unwrap();
+        let modified = std::time::SystemTime::UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        fs::File::options().write(true).open(&from).unwrap().set_modified(modified).unwrap();
+
+        // 跨设备移动走的复制+删除路径
+        assert_eq!(copy_then_delete(&from, &to).unwrap(), 2048);
+        assert!(!from.exists());
+        assert_eq!(fs::metadata(&to).unwrap().modified().unwrap(), modified);
+
+        // 回滚时反向走同一路径
+        assert_eq!(copy_then_delete(&to, &from).unwrap(), 2048);
+        assert!(!to.exists());
+        assert_eq!(fs::metadata(&from).unwrap().modified().unwrap(), modified);
+    }
+
+    #[test]
+    fn test_unique_temp_path_avoids_collisions() {
+        let dir = tempdir().unwrap();
+        let to = dir.path().join("a.txt");
+        let first = unique_temp_path(&to);
+        assert_eq!(first, dir.path().join("a.txt.orderly-tmp"));
+
+        fs::write(&first, "stale").unwrap();
+        assert_eq!(unique_temp_path(&to), dir.path().join("a.txt.1.orderly-tmp"));
+    }
+
+    #[test]
+    fn test_identical_collision_is_deduped() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("input");
+        let category = dir.path().join("out/Documents");
+        fs::create_dir_all(&input).unwrap();
+        fs::create_dir_all(&category).unwrap();
+
+        fs::write(input.join("report.pdf"), "same bytes").unwrap();
+        fs::write(category.join("report.pdf"), "same bytes").unwrap();
+        fs::write(input.join("notes.txt"), "new").unwrap();
+        fs::write(category.join("notes.txt"), "old").unwrap();
+
+        let mut plan = MovePlan::new();
+        plan.add_operation(input.join("report.pdf"), category.join("report.pdf"), "r".to_string());
+        plan.add_operation(input.join("notes.txt"), category.join("notes.txt"), "n".to_string());
+
+        let mut executor = Executor::new(dir.path().join("data"));
+        executor.set_conflict_strategy(ConflictStrategy::DedupeByHash);
+        let result = executor.execute(&mut plan);
+
+        // 内容相同的跳过，不产生 "(1)" 副本；内容不同的自动改名
+        assert_eq!(result.skipped, 1);
+        assert_eq!(result.failed, 0);
+        assert_eq!(result.successful, 1);
+        assert_eq!(plan.operations[0].status, OperationStatus::Skipped);
+        assert!(plan.operations[0].error.as_deref().unwrap().contains("内容相同"));
+        assert_eq!(plan.operations[1].to, category.join("notes (1).txt"));
+        assert_eq!(fs::read_to_string(category.join("notes.txt")).unwrap(), "old");
+        assert_eq!(fs::read_to_string(category.join("notes (1).txt")).unwrap(), "new");
+        assert_eq!(fs::read_dir(&category).unwrap().count(), 3);
+        assert!(input.join("report.pdf").exists());
+    }
+
+    #[test]
+    fn test_dedupe_compares_full_hash_after_same_prefix() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("input");
+        let category = dir.path().join("out/Documents");
+        fs::create_dir_all(&input).unwrap();
+        fs::create_dir_all(&category).unwrap();
+
+        // 大小和开头部分都相同，只有末尾不同
+        let mut existing = vec![b'x'; 64 * 1024 + 1];
+        fs::write(category.join("scan.bin"), &existing).unwrap();
+        *existing.last_mut().unwrap() = b'y';
+        fs::write(input.join("scan.bin"), &existing).unwrap();
+
+        let mut plan = MovePlan::new();
+        plan.add_operation(input.join("scan.bin"), category.join("scan.bin"), "s".to_string());
+
+        let mut executor = Executor::new(dir.path().join("data"));
+        executor.set_conflict_strategy(ConflictStrategy::DedupeByHash);
+        let dry_run = executor.dry_run(&plan);
+        assert!(dry_run.potential_errors.is_empty());
+        assert_eq!(dry_run.would_move_files[0].1, category.join("scan (1).bin"));
+
+        let result = executor.execute(&mut plan);
+        assert_eq!(result.successful, 1);
+        assert_eq!(plan.operations[0].to, category.join("scan (1).bin"));
+    }
+
+    #[test]
+    fn test_dedupe_can_trash_identical_source() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("input");
+        let category = dir.path().join("out/Documents");
+        fs::create_dir_all(&input).unwrap();
+        fs::create_dir_all(&category).unwrap();
+        fs::write(input.join("report.pdf"), "same").unwrap();
+        fs::write(category.join("report-copy.pdf"), "same").unwrap();
+        fs::write(category.join("report.pdf"), "other").unwrap();
+
+        let mut plan = MovePlan::new();
+        plan.add_operation(input.join("report.pdf"), category.join("report.pdf"), "r".to_string());
+
+        let mut executor = Executor::new(dir.path().join("data"));
+        executor.set_trash(stub_trash);
+        executor.set_conflict_strategy(ConflictStrategy::DedupeByHash);
+        executor.set_trash_duplicate_sources(true);
+        let dry_run = executor.dry_run(&plan);
+        assert!(dry_run.potential_errors[0].contains("回收站"));
+
+        let result = executor.execute(&mut plan);
+        assert_eq!(result.skipped, 1);
+        assert!(!input.join("report.pdf").exists());
+        assert!(plan.operations[0].error.as_deref().unwrap().contains("源文件已移到回收站"));
+        assert_eq!(fs::read_dir(&category).unwrap().count(), 2);
+        assert_eq!(take_stub_trashed(), vec![input.join("report.pdf")]);
+        assert!(input.join(format!("report.pdf{}", STUB_TRASH_SUFFIX)).exists());
+    }
+
+    #[test]
+    fn test_rename_on_conflict_matches_dry_run() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("input");
+        let category = dir.path().join("out/Documents");
+        fs::create_dir_all(input.join("x")).unwrap();
+        fs::create_dir_all(&category).unwrap();
+
+        // 已有一个 report.pdf，本批次又有两个文件要移到同一名称
+        fs::write(category.join("report.pdf"), "old").unwrap();
+        fs::write(input.join("report.pdf"), "a").unwrap();
+        fs::write(input.join("x/report.pdf"), "b").unwrap();
+
+        let mut plan = MovePlan::new();
+        plan.add_operation(input.join("report.pdf"), category.join("report.pdf"), "a".to_string());
+        plan.add_operation(input.join("x/report.pdf"), category.join("report.pdf"), "b".to_string());
+
+        // 默认跳过，保留已有文件
+        let executor = Executor::new(dir.path().join("data"));
+        let preview = executor.dry_run(&plan);
+        assert!(preview.would_move_files.is_empty());
+        assert_eq!(preview.potential_errors.len(), 2);
+
+        let mut executor = Executor::new(dir.path().join("data"));
+        executor.set_conflict_strategy(ConflictStrategy::Rename);
+        let preview = executor.dry_run(&plan);
+        let expected = vec![category.join("report (1).pdf"), category.join("report (2).pdf")];
+        let previewed: Vec<PathBuf> = preview.would_move_files.iter().map(|(_, to)| to.clone()).collect();
+        assert_eq!(previewed, expected);
+
+        let result = executor.execute(&mut plan);
+        assert_eq!(result.successful, 2);
+        let executed: Vec<PathBuf> = plan.operations.iter().map(|op| op.to.clone()).collect();
+        assert_eq!(executed, expected);
+        assert_eq!(fs::read_to_string(category.join("report.pdf")).unwrap(), "old");
+        assert_eq!(fs::read_to_string(category.join("report (2).pdf")).unwrap(), "b");
+
+        // 回滚按实际名称移回
+        assert_eq!(executor.rollback(&plan.batch_id).successful, 2);
+        assert!(input.join("x/report.pdf").exists());
+    }
+
+    #[test]
+    fn test_overwrite_never_replaces_file_moved_in_same_batch() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("input");
+        let category = dir.path().join("out/Documents");
+        fs::create_dir_all(input.join("x")).unwrap();
+        fs::write(input.join("report.pdf"), "a").unwrap();
+        fs::write(input.join("x/report.pdf"), "b").unwrap();
+
+        let mut plan = MovePlan::new();
+        plan.add_operation(input.join("report.pdf"), category.join("report.pdf"), "a".to_string());
+        plan.add_operation(input.join("x/report.pdf"), category.join("report.pdf"), "b".to_string());
+
+        let mut executor = Executor::new(dir.path().join("data"));
+        executor.set_conflict_strategy(ConflictStrategy::Overwrite);
+        let expected = vec![category.join("report.pdf"), category.join("report (1).pdf")];
+        let preview = executor.dry_run(&plan);
+        let previewed: Vec<PathBuf> = preview.would_move_files.iter().map(|(_, to)| to.clone()).collect();
+        assert_eq!(previewed, expected);
+
+        let result = executor.execute(&mut plan);
+        assert_eq!(result.successful, 2);
+        let executed: Vec<PathBuf> = plan.operations.iter().map(|op| op.to.clone()).collect();
+        assert_eq!(executed, expected);
+        assert_eq!(fs::read_to_string(category.join("report.pdf")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(category.join("report (1).pdf")).unwrap(), "b");
+
+        // 回滚后两个文件各自回到原处，内容不串
+        assert_eq!(executor.rollback(&plan.batch_id).successful, 2);
+        assert_eq!(fs::read_to_string(input.join("report.pdf")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(input.join("x/report.pdf")).unwrap(), "b");
+    }
+
+    #[test]
+    fn test_copy_mode_keeps_source_and_rollback_deletes_copy() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("input");
+        let output = dir.path().join("nas/Pictures");
+        fs::create_dir_all(&input).unwrap();
+        fs::write(input.join("photo.jpg"), "pixels").unwrap();
+        let modified = std::time::SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        fs::File::options()
+            .write(true)
+            .open(input.join("photo.jpg"))
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+
+        let mut plan = MovePlan::new();
+        plan.mode = ExecutionMode::Copy;
+        plan.add_operation(input.join("photo.jpg"), output.join("photo.jpg"), "p".to_string());
+
+        let mut executor = Executor::new(dir.path().join("data"));
+        let result = executor.execute(&mut plan);
+        assert_eq!(result.successful, 1);
+        assert!(input.join("photo.jpg").exists(), "复制模式不删除源文件");
+        assert_eq!(fs::read_to_string(output.join("photo.jpg")).unwrap(), "pixels");
+        assert_eq!(fs::metadata(output.join("photo.jpg")).unwrap().modified().unwrap(), modified);
+
+        // 回滚删除副本，源文件保持不变
+        assert_eq!(executor.rollback(&plan.batch_id).successful, 1);
+        assert!(!output.join("photo.jpg").exists());
+        assert!(!output.exists(), "空的目标目录应被清理");
+        assert_eq!(fs::read_to_string(input.join("photo.jpg")).unwrap(), "pixels");
+    }
+
+    #[test]
+    fn test_trash_on_conflict_preview_and_rollback_note() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("input");
+        let category = dir.path().join("out/Documents");
+        fs::create_dir_all(&input).unwrap();
+        fs::create_dir_all(&category).unwrap();
+        fs::write(input.join("a.txt"), "new").unwrap();
+        fs::write(category.join("a.txt"), "old").unwrap();
+
+        let mut plan = MovePlan::new();
+        plan.add_operation(input.join("a.txt"), category.join("a.txt"), "a".to_string());
+
+        // 报错和覆盖策略下都改为先移到回收站；跳过策略不受影响
+        let mut executor = Executor::new(dir.path().join("data"));
+        executor.set_trash(stub_trash);
+        executor.set_use_trash_on_conflict(true);
+        for strategy in [ConflictStrategy::Fail, ConflictStrategy::Overwrite] {
+            executor.set_conflict_strategy(strategy);
+            let preview = executor.dry_run(&plan);
+            assert_eq!(preview.would_move_files.len(), 1);
+            assert!(preview.potential_errors[0].contains("回收站"));
+        }
+        executor.set_conflict_strategy(ConflictStrategy::Skip);
+        assert!(executor.dry_run(&plan).would_move_files.is_empty());
+
+        // 真正执行：原有文件进回收站，新文件就位，操作上记下这一点
+        executor.set_conflict_strategy(ConflictStrategy::Fail);
+        assert_eq!(executor.execute(&mut plan).successful, 1);
+        assert!(plan.operations[0].trashed_target);
+        assert!(plan.operations[0].error.is_none());
+        assert_eq!(fs::read_to_string(category.join("a.txt")).unwrap(), "new");
+        assert_eq!(take_stub_trashed(), vec![category.join("a.txt")]);
+        let trashed = category.join(format!("a.txt{}", STUB_TRASH_SUFFIX));
+        assert_eq!(fs::read_to_string(trashed).unwrap(), "old");
+
+        // 回滚时提示被替换的原有文件仍在回收站中
+        let rollback = executor.rollback(&plan.batch_id);
+        assert_eq!(rollback.left_in_trash, vec![category.join("a.txt")]);
+        assert!(rollback.summary().contains("1 个被替换的原有文件仍在回收站中"));
+    }
+
+    #[test]
+    fn test_transactional_rolls_back_on_mid_batch_failure() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("input");
+        let output = dir.path().join("output");
+        fs::create_dir_all(&input).unwrap();
+        fs::create_dir_all(&output).unwrap();
+
+        let mut plan = MovePlan::new();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            fs::write(input.join(name), name).unwrap();
+            plan.add_operation(input.join(name), output.join(name), name.to_string());
+        }
+        // 第二个操作的目标已被占用，按报错策略失败
+        fs::write(output.join("b.txt"), "occupied").unwrap();
+
+        let mut executor = Executor::new(dir.path().join("data"));
+        executor.set_conflict_strategy(ConflictStrategy::Fail);
+        let outcome = executor.execute_transactional(&mut plan);
+
+        let TransactionOutcome::RolledBack { failed_index, error, execution, rollback } = outcome else {
+            panic!("应自动回滚");
+        };
+        assert_eq!(failed_index, 1);
+        assert!(error.contains("目标文件已存在"));
+        assert_eq!((execution.successful, execution.failed, execution.skipped), (1, 1, 1));
+        assert_eq!(rollback.successful, 1);
+
+        // a 已移回，c 未执行，占位文件不受影响
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            assert!(input.join(name).exists());
+        }
+        assert!(!output.join("a.txt").exists());
+        assert_eq!(fs::read_to_string(output.join("b.txt")).unwrap(), "occupied");
+        assert_eq!(plan.operations[0].status, OperationStatus::RolledBack);
+        assert_eq!(plan.operations[2].status, OperationStatus::Skipped);
+
+        let entry = executor.get_history().iter().find(|h| h.batch_id == plan.batch_id).unwrap();
+        assert!(entry.rolled_back);
+        assert!(executor.pending_batch().is_none());
+
+        // 冲突解除后整批提交
+        fs::remove_file(output.join("b.txt")).unwrap();
+        let mut retry = MovePlan::new();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            retry.add_operation(input.join(name), output.join(name), name.to_string());
+        }
+        assert!(executor.execute_transactional(&mut retry).is_committed());
+        assert!(output.join("c.txt").exists());
+    }
+
+    #[test]
+    fn test_dry_run_exports_csv_and_json() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("in, box");
+        fs::create_dir_all(&input).unwrap();
+        fs::write(input.join("a.txt"), "a").unwrap();
+
+        let mut plan = MovePlan::new();
+        plan.add_operation(input.join("a.txt"), dir.path().join("out/a.txt"), "a".to_string());
+        plan.add_operation(input.join("gone.txt"), dir.path().join("out/gone.txt"), "g".to_string());
+        let preview = Executor::new(dir.path().join("data")).dry_run(&plan);
+
+        let csv = preview.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], DRY_RUN_CSV_HEADER);
+        // 路径含逗号时整体加引号
+        let from = input.join("a.txt").to_string_lossy().to_string();
+        let to = dir.path().join("out/a.txt").to_string_lossy().to_string();
+        assert_eq!(lines[1], format!("\"{}\",{},true,", from, to));
+        assert!(lines[2].ends_with(&format!("\"源文件不存在: {}\"", input.join("gone.txt").display())));
+
+        let json: serde_json::Value = serde_json::from_str(&preview.to_json().unwrap()).unwrap();
+        assert_eq!(json["entries"].as_array().unwrap().len(), 2);
+        assert_eq!(json["entries"][0]["will_create_dir"], true);
+        assert_eq!(json["potential_errors"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_dry_run_reports_space_needed_for_copies() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("input");
+        fs::create_dir_all(&input).unwrap();
+        fs::write(input.join("a.bin"), vec![0u8; 3000]).unwrap();
+        fs::write(input.join("b.bin"), vec![0u8; 1000]).unwrap();
+
+        let mut plan = MovePlan::new();
+        for name in ["a.bin", "b.bin"] {
+            plan.add_operation(input.join(name), dir.path().join("out").join(name), name.to_string());
+        }
+        let executor = Executor::new(dir.path().join("data"));
+
+        // 同卷移动只是重命名，不占空间
+        let preview = executor.dry_run(&plan);
+        assert_eq!((preview.required_bytes, preview.available_bytes), (0, None));
+
+        plan.mode = ExecutionMode::Copy;
+        let mut preview = executor.dry_run(&plan);
+        assert_eq!(preview.required_bytes, 4000);
+        assert!(preview.available_bytes.is_some());
+        assert!(!preview.has_errors());
+
+        // 可用空间不足时视为错误，并写进差异文本
+        preview.available_bytes = Some(1024);
+        assert!(preview.has_errors());
+        let shortage = preview.space_shortage().unwrap();
+        assert_eq!(shortage, "目标卷空间不足：需要 3.91 KB，可用 1.00 KB");
+        assert!(preview.to_diff().contains(&shortage));
+    }
+
+    #[test]
+    fn test_dry_run_to_diff() {
+        let result = DryRunResult {
+            would_create_dirs: vec![PathBuf::from("/out/Pictures"), PathBuf::from("/out/Documents")],
+            would_move_files: vec![
+                (PathBuf::from("/in/a.txt"), PathBuf::from("/out/Documents/a.txt")),
+                (PathBuf::from("/in/b.jpg"), PathBuf::from("/out/Pictures/b.jpg")),
+            ],
+            potential_errors: Vec::new(),
+            required_bytes: 0,
+            available_bytes: None,
+            entries: Vec::new(),
+        };
+
+        let diff = result.to_diff();
+        let lines: Vec<&str> = diff.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "# Orderly 预览: 将创建 2 个目录，移动 2 个文件，0 个潜在问题",
+                "",
+                "# 新建目录",
+                "+ /out/Documents/",
+                "+ /out/Pictures/",
+                "",
+                "# 移动文件",
+                "- /in/a.txt",
+                "+ /out/Documents/a.txt",
+                "- /in/b.jpg",
+                "+ /out/Pictures/b.jpg",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cancel_keeps_pending_batch() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("input");
+        fs::create_dir_all(&input).unwrap();
+        fs::write(input.join("a.txt"), "a").unwrap();
+
+        let mut plan = MovePlan::new();
+        plan.add_operation(input.join("a.txt"), dir.path().join("out/a.txt"), "a".to_string());
+
+        let mut executor = Executor::new(dir.path().join("data"));
+        let result = executor.execute_cancellable(&mut plan, &AtomicBool::new(true));
+        assert!(result.cancelled);
+        assert_eq!(result.successful, 0);
+        assert!(executor.get_history().is_empty());
+
+        let pending = executor.pending_batch().unwrap();
+        let rollback = executor.rollback_pending(&pending);
+        assert!(rollback.is_all_successful());
+        assert!(executor.pending_batch().is_none());
+        assert!(input.join("a.txt").exists());
+    }
+
+    #[test]
+    fn test_rollback_of_cancelled_copy_keeps_existing_target() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("input");
+        let out = dir.path().join("out");
+        fs::create_dir_all(&input).unwrap();
+        fs::create_dir_all(&out).unwrap();
+        fs::write(input.join("a.txt"), "a").unwrap();
+        fs::write(input.join("b.txt"), "b").unwrap();
+        // 执行前就存在的用户文件，冲突处理本不会碰它
+        fs::write(out.join("b.txt"), "mine").unwrap();
+
+        let mut plan = MovePlan::new();
+        plan.mode = ExecutionMode::Copy;
+        plan.add_operation(input.join("a.txt"), out.join("a.txt"), "a".to_string());
+        plan.add_operation(input.join("b.txt"), out.join("b.txt"), "b".to_string());
+
+        let mut executor = Executor::new(dir.path().join("data"));
+        assert!(executor.execute_cancellable(&mut plan, &AtomicBool::new(true)).cancelled);
+
+        let pending = executor.pending_batch().unwrap();
+        assert!(pending.operations.iter().all(|op| op.status == OperationStatus::Pending));
+        assert!(executor.rollback_pending(&pending).is_all_successful());
+        assert_eq!(fs::read_to_string(out.join("b.txt")).unwrap(), "mine");
+        assert!(input.join("a.txt").exists() && input.join("b.txt").exists());
+    }
+
+    #[test]
+    fn test_deferred_pending_batch_blocks_new_batch() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("input");
+        fs::create_dir_all(&input).unwrap();
+        fs::write(input.join("a.txt"), "a").unwrap();
+        fs::write(input.join("b.txt"), "b").unwrap();
+
+        let mut executor = Executor::new(dir.path().join("data"));
+        let mut deferred = MovePlan::new();
+        deferred.add_operation(input.join("a.txt"), dir.path().join("out/a.txt"), "a".to_string());
+        executor.execute_cancellable(&mut deferred, &AtomicBool::new(true));
+
+        // 用户选择“稍后处理”后又执行新批次：拒绝执行，保留原断点
+        let mut next = MovePlan::new();
+        next.add_operation(input.join("b.txt"), dir.path().join("out/b.txt"), "b".to_string());
+        assert_eq!(executor.conflicting_pending_batch(&next), Some(deferred.batch_id.clone()));
+        let result = executor.execute(&mut next);
+        assert_eq!(result.successful, 0);
+        assert_eq!(result.errors.len(), 1);
+        assert!(input.join("b.txt").exists());
+        assert_eq!(executor.pending_batch().unwrap().batch_id, deferred.batch_id);
+
+        // 继续原批次不受影响
+        let mut resumed = executor.pending_batch().unwrap();
+        assert_eq!(executor.conflicting_pending_batch(&resumed), None);
+        assert!(executor.execute(&mut resumed).is_all_successful());
+        assert!(dir.path().join("out/a.txt").exists());
+    }
+
+    #[test]
+    fn test_history_csv_export() {
+        let op = |from: &str, to: &str| MoveOperation {
+            from: PathBuf::from(from),
+            to: PathBuf::from(to),
+            file_id: from.to_string(),
+            status: OperationStatus::Completed,
+            error: None,
+            source_hash: None,
+            trashed_target: false,
+        };
+        let history = vec![
+            HistoryEntry {
+                batch_id: "batch-1".to_string(),
+                executed_at: Utc::now(),
+                operations: vec![
+                    op("/in/a.txt", "/out/a.txt"),
+                    op("/in/report, final.pdf", "/out/Docs/report, final.pdf"),
+                ],
+                rolled_back: false,
+                mode: ExecutionMode::Move,
+            },
+            HistoryEntry {
+                batch_id: "batch-2".to_string(),
+                executed_at: Utc::now(),
+                operations: vec![op("/in/say \"hi\".txt", "/out/say \"hi\".txt")],
+                rolled_back: true,
+                mode: ExecutionMode::Move,
+            },
+        ];
+
+        let csv = history_to_csv(&history);
+        let lines: Vec<&str> = csv.lines().collect();
+
+        let total_ops: usize = history.iter().map(|h| h.operations.len()).sum();
+        assert_eq!(lines.len(), total_ops + 1);
+        assert_eq!(lines[0], HISTORY_CSV_HEADER);
+        assert!(lines[2].contains(",\"/in/report, final.pdf\",\"/out/Docs/report, final.pdf\",Completed,false"));
+        assert!(lines[3].starts_with("batch-2,"));
+        assert!(lines[3].contains("\"/in/say \"\"hi\"\".txt\""));
+        assert!(lines[3].ends_with(",Completed,true"));
+    }
+
+    #[test]
+    fn test_source_swapped_after_plan_is_skipped() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("input");
+        let output = dir.path().join("output");
+        fs::create_dir_all(&input).unwrap();
+        for name in ["kept.txt", "swapped.txt", "deleted.txt"] {
+            fs::write(input.join(name), name).unwrap();
+        }
+
+        let mut plan = MovePlan::new();
+        for name in ["kept.txt", "swapped.txt", "deleted.txt"] {
+            plan.add_operation(input.join(name), output.join(name), name.to_string());
+            plan.operations.last_mut().unwrap().source_hash =
+                Some(hash_file_content(&input.join(name)).unwrap());
+        }
+
+        // 计划生成后，另一个程序替换了一个文件、删除了另一个
+        fs::write(input.join("swapped.txt"), "replaced by another process").unwrap();
+        fs::remove_file(input.join("deleted.txt")).unwrap();
+
+        let mut executor = Executor::new(dir.path().join("data"));
+        let result = executor.execute(&mut plan);
+
+        assert_eq!(result.successful, 1);
+        assert_eq!(result.skipped, 2);
+        assert_eq!(result.failed, 0);
+        assert_eq!(plan.operations[1].status, OperationStatus::Skipped);
+        assert_eq!(plan.operations[1].error.as_deref(), Some("源文件在计划后被修改"));
+        assert_eq!(plan.operations[2].error.as_deref(), Some("源文件在计划后被删除"));
+        // 被替换的文件留在原处，未被移动
+        assert!(input.join("swapped.txt").exists());
+        assert!(!output.join("swapped.txt").exists());
+        assert!(output.join("kept.txt").exists());
+    }
+
+    #[test]
+    fn test_operation_receipt_aggregates_result() {
+        let mut plan = MovePlan::new();
+        for name in ["a", "b", "c", "d"] {
+            plan.add_operation(PathBuf::from(name), PathBuf::from("out").join(name), name.to_string());
+        }
+        plan.operations[0].status = OperationStatus::Completed;
+        plan.operations[1].status = OperationStatus::Completed;
+        plan.operations[2].status = OperationStatus::Failed;
+        plan.operations[3].status = OperationStatus::Skipped;
+
+        let result = ExecutionResult {
+            successful: 2,
+            failed: 1,
+            skipped: 1,
+            errors: vec!["移动 c 失败".to_string()],
+            cancelled: false,
+            copied_bytes: 0,
+            copy_duration: Duration::ZERO,
+            mode: ExecutionMode::Move,
+        };
+        let sizes: HashMap<String, u64> =
+            [("a", 100), ("b", 2048), ("c", 5000), ("d", 7000)]
+                .into_iter()
+                .map(|(id, size)| (id.to_string(), size))
+                .collect();
+
+        let receipt = OperationReceipt::from_execution(&plan, &result, &sizes, Duration::from_secs(3));
+        assert_eq!(receipt.batch_id, plan.batch_id);
+        assert_eq!((receipt.moved, receipt.failed, receipt.skipped), (2, 1, 1));
+        // 只统计已完成操作的大小
+        assert_eq!(receipt.bytes, 2148);
+        assert_eq!(receipt.elapsed, Duration::from_secs(3));
+        assert!(receipt.can_undo());
+        assert_eq!(receipt.headline(), "已移动 2 个文件，1 个失败");
+        assert_eq!(receipt.detail(), "2.10 KB · 跳过 1 · 约 3 秒");
     }
 }
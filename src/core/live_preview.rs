@@ -0,0 +1,235 @@
+//! 实时预览模块
+//!
+//! 开启后，勾选、置信度阈值或规则发生变化时自动重新生成计划并做 Dry Run，
+//! 只刷新一行统计，不必每次点击“预览执行”。
+//! 变化以指纹比较，停止变化一小段时间后才重新计算（防抖）；
+//! 文件数过多时不做实时预览，避免每次改动都卡住界面。
+
+use crate::core::executor::{DryRunResult, Executor};
+use crate::core::models::{FileDescriptor, RuleDefinition, SuggestionSource};
+use crate::core::planner::Planner;
+use crate::core::rule_engine::RuleEngine;
+use crate::core::rule_miner::session_suggestion;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+/// 超过此文件数时不做实时预览
+pub const LIVE_PREVIEW_MAX_FILES: usize = 5_000;
+
+/// 防抖间隔：最后一次变化后等待多久再重新计算
+pub const LIVE_PREVIEW_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// 计算影响计划的状态指纹：文件数与勾选、阈值、规则（启用状态、优先级、修改时间）
+///
+/// 每帧都会调用，规则的条件与目标不逐项比较，编辑规则时会更新 updated_at。
+pub fn fingerprint(files: &[FileDescriptor], threshold: f32, rules: &[RuleDefinition]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    files.len().hash(&mut hasher);
+    for file in files.iter().filter(|f| f.selected) {
+        file.id.hash(&mut hasher);
+    }
+    threshold.to_bits().hash(&mut hasher);
+    rules_fingerprint(rules).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// 计算规则列表的指纹：数量与每条规则的ID、启用状态、优先级、修改时间
+pub fn rules_fingerprint(rules: &[RuleDefinition]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    rules.len().hash(&mut hasher);
+    for rule in rules {
+        rule.id.hash(&mut hasher);
+        rule.enabled.hash(&mut hasher);
+        rule.priority.hash(&mut hasher);
+        rule.updated_at.timestamp_micros().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// 实时预览状态
+#[derive(Debug, Default)]
+pub struct LivePreview {
+    /// 是否开启
+    pub enabled: bool,
+    /// 上次看到的状态指纹
+    fingerprint: Option<u64>,
+    /// 指纹变化的时间（等待防抖）
+    dirty_since: Option<Instant>,
+    /// 最近一次的统计
+    summary: Option<String>,
+}
+
+impl LivePreview {
+    /// 记录当前状态指纹，变化时开始防抖计时
+    pub fn observe(&mut self, fingerprint: u64, now: Instant) {
+        if self.fingerprint != Some(fingerprint) {
+            self.fingerprint = Some(fingerprint);
+            self.dirty_since = Some(now);
+        }
+    }
+
+    /// 是否到了重新计算的时候
+    pub fn is_due(&self, now: Instant) -> bool {
+        self.enabled
+            && self
+                .dirty_since
+                .is_some_and(|since| now.duration_since(since) >= LIVE_PREVIEW_DEBOUNCE)
+    }
+
+    /// 重新匹配规则（规则引擎未匹配的再用本次运行临时规则）、生成计划并 Dry Run，更新统计
+    ///
+    /// 与“预览执行”一样，安全模式拒绝的越界操作不计入，列入 Dry Run 的警告。
+    pub fn refresh(
+        &mut self,
+        files: &mut [FileDescriptor],
+        engine: &RuleEngine,
+        session_rules: &[RuleDefinition],
+        planner: &Planner,
+        executor: &Executor,
+    ) -> DryRunResult {
+        self.dirty_since = None;
+
+        rematch_rule_suggestions(files, engine, session_rules);
+        let mut plan = planner.generate_plan(files);
+        let rejected = planner.reject_outside_output_base(&mut plan);
+        let mut dry_run = executor.dry_run(&plan);
+        dry_run
+            .potential_errors
+            .extend(rejected.into_iter().map(|e| format!("已拒绝: {}", e.message)));
+        self.summary = Some(dry_run.summary());
+        dry_run
+    }
+
+    /// 文件过多而停用实时预览时返回 true
+    pub fn too_many_files(file_count: usize) -> bool {
+        file_count > LIVE_PREVIEW_MAX_FILES
+    }
+
+    /// 最近一次的统计
+    pub fn summary(&self) -> Option<&str> {
+        self.summary.as_deref()
+    }
+
+    /// 清除统计（重新扫描时）
+    pub fn reset(&mut self) {
+        self.fingerprint = None;
+        self.dirty_since = None;
+        self.summary = None;
+    }
+}
+
+/// 按当前规则重新计算规则建议；AI、记忆、规则与AI融合的结果以及本次运行临时规则的建议保持不变，
+/// 失去规则建议的文件再尝试临时规则
+fn rematch_rule_suggestions(
+    files: &mut [FileDescriptor],
+    engine: &RuleEngine,
+    session_rules: &[RuleDefinition],
+) {
+    for file in files.iter_mut() {
+        let from_engine_rule = match &file.suggested_action {
+            None => true,
+            Some(s) => {
+                s.source == SuggestionSource::Rule
+                    && !s.fused
+                    && s.matched_rule_id
+                        .as_ref()
+                        .is_some_and(|id| engine.get_rules().iter().any(|r| &r.id == id))
+            }
+        };
+        if from_engine_rule {
+            file.suggested_action = engine
+                .suggest(file)
+                .or_else(|| session_suggestion(session_rules, file));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::models::MoveSuggestion;
+    use std::fs;
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_toggling_rule_updates_live_summary() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("input");
+        let output = dir.path().join("output");
+        fs::create_dir_all(&input).unwrap();
+        for name in ["a.jpg", "b.png", "c.pdf"] {
+            fs::write(input.join(name), name).unwrap();
+        }
+        let mut files = crate::core::scanner::FileScanner::new(input).scan().unwrap();
+
+        let mut engine = RuleEngine::new();
+        let planner = Planner::new(output, 0.5);
+        let executor = Executor::new(dir.path().join("data"));
+        let mut preview = LivePreview {
+            enabled: true,
+            ..Default::default()
+        };
+
+        let start = Instant::now();
+        preview.observe(fingerprint(&files, 0.5, engine.get_rules()), start);
+        assert!(!preview.is_due(start));
+        assert!(preview.is_due(start + LIVE_PREVIEW_DEBOUNCE));
+        preview.refresh(&mut files, &engine, &[], &planner, &executor);
+        assert!(preview.summary().unwrap().contains("移动 3 个文件"));
+
+        // 在规则面板中关闭图片规则
+        engine.set_rule_enabled("builtin_images", false);
+        let later = start + Duration::from_secs(1);
+        preview.observe(fingerprint(&files, 0.5, engine.get_rules()), later);
+        assert!(!preview.is_due(later));
+        assert!(preview.is_due(later + LIVE_PREVIEW_DEBOUNCE));
+        preview.refresh(&mut files, &engine, &[], &planner, &executor);
+        assert!(preview.summary().unwrap().contains("移动 1 个文件"));
+
+        // 未变化时不再重复计算
+        preview.observe(fingerprint(&files, 0.5, engine.get_rules()), later + Duration::from_secs(5));
+        assert!(!preview.is_due(later + Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_refresh_keeps_fused_and_rejects_outside_output() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("input");
+        fs::create_dir_all(&input).unwrap();
+        for name in ["a.jpg", "b.txt"] {
+            fs::write(input.join(name), name).unwrap();
+        }
+        let mut files = crate::core::scanner::FileScanner::new(input).scan().unwrap();
+        files.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let engine = RuleEngine::new();
+        let planner = Planner::new(dir.path().join("output"), 0.5);
+        let fused = planner.fuse_suggestions(
+            engine.suggest(&files[0]).as_ref(),
+            Some(&MoveSuggestion {
+                target_path: PathBuf::from("Pictures/Trips"),
+                reason: "旅行照片".to_string(),
+                source: SuggestionSource::AI,
+                confidence: 0.3,
+                matched_rule_id: None,
+                rule_origin: None,
+                fused: false,
+            }),
+        );
+        files[0].suggested_action = fused.clone();
+        files[1].suggested_action = Some(MoveSuggestion::manual(dir.path().join("elsewhere")));
+
+        let mut preview = LivePreview::default();
+        let dry_run = preview.refresh(&mut files, &engine, &[], &planner, &Executor::new(dir.path().join("data")));
+
+        assert!(files[0].suggested_action.as_ref().unwrap().fused);
+        assert_eq!(
+            files[0].suggested_action.as_ref().unwrap().reason,
+            fused.unwrap().reason
+        );
+        assert_eq!(dry_run.would_move_files.len(), 1);
+        assert!(dry_run.potential_errors.iter().any(|e| e.starts_with("已拒绝")));
+    }
+}
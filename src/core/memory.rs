@@ -0,0 +1,146 @@
+//! 记忆模块
+//!
+//! 记住用户实际把哪类文件整理到了哪里：执行成功后，按文件特征
+//! （扩展名 + 主标签 + 大小量级）把目标目录写入记忆缓存；下次遇到同类文件时
+//! 先查缓存，命中即给出“记忆”来源的建议，再回退到规则和AI。
+//!
+//! 缓存中保存的是相对输出目录的分类目录，和规则建议一样在生成计划时才解析到输出目录下。
+
+use crate::core::models::{
+    FileDescriptor, MoveOperation, MoveSuggestion, OperationStatus, RuleOrigin, SuggestionSource,
+};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// 记忆建议的置信度
+pub const MEMORY_CONFIDENCE: f32 = 0.85;
+
+/// 文件特征哈希：扩展名 + 主标签 + 大小量级（按 10 的幂分档）
+pub fn feature_hash(file: &FileDescriptor) -> String {
+    let tag = file
+        .semantic
+        .as_ref()
+        .and_then(|s| s.tags.first())
+        .map(|t| t.to_lowercase())
+        .unwrap_or_default();
+    let size_bucket = file.size.checked_ilog10().unwrap_or(0);
+
+    let mut hasher = Sha256::new();
+    hasher.update(format!("{}|{}|{}", file.extension.to_lowercase(), tag, size_bucket).as_bytes());
+    hex::encode(&hasher.finalize()[..16])
+}
+
+/// 用记忆缓存为文件生成建议，返回命中的数量
+///
+/// 用户确认的规则是明确意图，优先于记忆；内置规则的建议和空建议会被记忆覆盖。
+pub fn apply_memory(
+    files: &mut [FileDescriptor],
+    mut lookup: impl FnMut(&str) -> Option<String>,
+) -> usize {
+    let mut hits = 0;
+    for file in files.iter_mut() {
+        if file.atomic || file.is_directory {
+            continue;
+        }
+        let keep_existing = file.suggested_action.as_ref().is_some_and(|s| {
+            matches!(s.source, SuggestionSource::Memory | SuggestionSource::Manual)
+                || s.rule_origin == Some(RuleOrigin::UserConfirmed)
+        });
+        if keep_existing {
+            continue;
+        }
+
+        let Some(target) = lookup(&feature_hash(file)) else {
+            continue;
+        };
+        file.suggested_action = Some(MoveSuggestion {
+            target_path: PathBuf::from(&target),
+            reason: format!("之前同类文件整理到了 {}", target),
+            source: SuggestionSource::Memory,
+            confidence: MEMORY_CONFIDENCE,
+            matched_rule_id: None,
+            rule_origin: None,
+            fused: false,
+        });
+        hits += 1;
+    }
+    hits
+}
+
+/// 从执行成功的操作中取出要记住的（特征哈希, 相对输出目录的目标目录）
+///
+/// 目标不在输出目录下的操作（如整理到原目录外的路径）不记忆。
+pub fn learned_destinations(
+    files: &[FileDescriptor],
+    operations: &[MoveOperation],
+    output_base: &Path,
+) -> Vec<(String, String)> {
+    let by_id: HashMap<&str, &FileDescriptor> = files.iter().map(|f| (f.id.as_str(), f)).collect();
+
+    operations
+        .iter()
+        .filter(|op| op.status == OperationStatus::Completed)
+        .filter_map(|op| {
+            let file = by_id.get(op.file_id.as_str())?;
+            if file.atomic || file.is_directory {
+                return None;
+            }
+            let dir = op.to.parent()?.strip_prefix(output_base).ok()?;
+            let dir = dir.to_string_lossy().replace('\\', "/");
+            (!dir.is_empty()).then(|| (feature_hash(file), dir))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::models::SemanticResult;
+
+    fn file(name: &str, extension: &str, size: u64, tag: Option<&str>) -> FileDescriptor {
+        let mut file = FileDescriptor::new(
+            PathBuf::from("/inbox").join(name),
+            name.to_string(),
+            extension.to_string(),
+            size,
+            chrono::Utc::now(),
+            false,
+        );
+        file.semantic = tag.map(|t| SemanticResult {
+            tags: vec![t.to_string()],
+            ..Default::default()
+        });
+        file
+    }
+
+    #[test]
+    fn test_learned_destination_suggested_for_similar_file() {
+        let moved = file("2023-电费.pdf", ".pdf", 120_000, Some("Invoice"));
+        let op = MoveOperation {
+            from: moved.full_path.clone(),
+            to: PathBuf::from("/out/Finance/Utilities/2023-电费.pdf"),
+            file_id: moved.id.clone(),
+            status: OperationStatus::Completed,
+            error: None,
+            source_hash: None,
+            trashed_target: false,
+        };
+
+        let learned = learned_destinations(std::slice::from_ref(&moved), &[op], Path::new("/out"));
+        assert_eq!(learned, vec![(feature_hash(&moved), "Finance/Utilities".to_string())]);
+        let cache: HashMap<String, String> = learned.into_iter().collect();
+
+        // 同扩展名、同主标签、同大小量级的文件命中；大小量级不同的不命中
+        let mut files = vec![
+            file("2024-电费.pdf", ".PDF", 450_000, Some("invoice")),
+            file("扫描件.pdf", ".pdf", 9_000_000, Some("invoice")),
+        ];
+        assert_eq!(apply_memory(&mut files, |h| cache.get(h).cloned()), 1);
+        let suggestion = files[0].suggested_action.as_ref().unwrap();
+        assert_eq!(suggestion.source, SuggestionSource::Memory);
+        assert_eq!(suggestion.target_path, PathBuf::from("Finance/Utilities"));
+        assert_eq!(suggestion.confidence, MEMORY_CONFIDENCE);
+        assert!(files[1].suggested_action.is_none());
+    }
+}
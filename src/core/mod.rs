@@ -7,6 +7,14 @@ pub mod semantic;
 pub mod rule_engine;
 pub mod planner;
 pub mod executor;
+pub mod pipeline;
+pub mod rule_miner;
+pub mod regression;
+pub mod naming;
+pub mod live_preview;
+pub mod document;
+pub mod memory;
+pub mod model_compare;
 
 #[cfg(test)]
 mod sim_integration_tests;
@@ -0,0 +1,193 @@
+//! 模型对比模块
+//!
+//! 选定模型之前，用同一批文件分别询问两个AI配置的路径建议，
+//! 统计两者的一致率并列出分歧，帮助判断换模型会改变哪些整理结果。
+
+use crate::core::models::{AIConfig, FileDescriptor};
+use crate::core::semantic::SemanticEngine;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// 一次对比最多询问的文件数（每个文件每个模型一次请求）
+pub const MAX_COMPARE_FILES: usize = 50;
+
+/// 两个模型给出不同建议的文件
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelDisagreement {
+    /// 文件名
+    pub file_name: String,
+    /// 模型A的建议（请求失败时为 None）
+    pub left: Option<PathBuf>,
+    /// 模型B的建议（请求失败时为 None）
+    pub right: Option<PathBuf>,
+}
+
+/// 模型对比报告
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModelComparison {
+    /// 模型A名称
+    pub left_model: String,
+    /// 模型B名称
+    pub right_model: String,
+    /// 参与对比的文件数
+    pub total: usize,
+    /// 两个模型建议相同的文件数
+    pub agreed: usize,
+    /// 建议不同（含任一方失败）的文件
+    pub disagreements: Vec<ModelDisagreement>,
+}
+
+impl ModelComparison {
+    /// 用两个分类器逐个文件比较（目录按不区分大小写、忽略首尾分隔符比较）
+    pub fn compare(
+        files: &[FileDescriptor],
+        mut left: impl FnMut(&FileDescriptor) -> Option<PathBuf>,
+        mut right: impl FnMut(&FileDescriptor) -> Option<PathBuf>,
+    ) -> Self {
+        let mut report = Self::default();
+        for file in files {
+            let (l, r) = (left(file), right(file));
+            report.total += 1;
+            match (&l, &r) {
+                (Some(a), Some(b)) if same_target(a, b) => report.agreed += 1,
+                _ => report.disagreements.push(ModelDisagreement {
+                    file_name: file.name.clone(),
+                    left: l,
+                    right: r,
+                }),
+            }
+        }
+        report
+    }
+
+    /// 一致率（0-1），没有文件时为 0
+    pub fn agreement_rate(&self) -> f32 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.agreed as f32 / self.total as f32
+        }
+    }
+
+    /// 一行摘要
+    pub fn summary(&self) -> String {
+        format!(
+            "{} 与 {}：{} 个文件中 {} 个建议一致（{:.0}%），{} 个分歧",
+            self.left_model,
+            self.right_model,
+            self.total,
+            self.agreed,
+            self.agreement_rate() * 100.0,
+            self.disagreements.len()
+        )
+    }
+}
+
+/// 用两个AI配置对同一批文件生成路径建议并比较
+///
+/// 原子项和目录不参与，最多取前 MAX_COMPARE_FILES 个文件。
+pub async fn compare_models(
+    left: AIConfig,
+    right: AIConfig,
+    files: &[FileDescriptor],
+    candidates: &[String],
+) -> ModelComparison {
+    let files: Vec<FileDescriptor> = files
+        .iter()
+        .filter(|f| !f.atomic && !f.is_directory)
+        .take(MAX_COMPARE_FILES)
+        .cloned()
+        .collect();
+
+    // 两个端点不同时在名称后注明端点，同名模型也能分清
+    let label = |config: &AIConfig| {
+        if left.api_endpoint == right.api_endpoint {
+            config.model_name.clone()
+        } else {
+            format!("{} @ {}", config.model_name, config.api_endpoint)
+        }
+    };
+    let left_model = label(&left);
+    let right_model = label(&right);
+    let left_targets = suggest_all(SemanticEngine::new(left), &files, candidates).await;
+    let right_targets = suggest_all(SemanticEngine::new(right), &files, candidates).await;
+
+    ModelComparison {
+        left_model,
+        right_model,
+        ..ModelComparison::compare(
+            &files,
+            |f| left_targets.get(&f.id).cloned().flatten(),
+            |f| right_targets.get(&f.id).cloned().flatten(),
+        )
+    }
+}
+
+/// 逐个文件询问路径建议，失败的记为 None
+async fn suggest_all(
+    engine: SemanticEngine,
+    files: &[FileDescriptor],
+    candidates: &[String],
+) -> HashMap<String, Option<PathBuf>> {
+    let mut targets = HashMap::new();
+    for file in files {
+        let target = match engine.suggest_path(file, candidates).await {
+            Ok(suggestion) => Some(suggestion.target_path),
+            Err(e) => {
+                tracing::warn!("模型对比中请求失败 {}: {}", file.name, e);
+                None
+            }
+        };
+        targets.insert(file.id.clone(), target);
+    }
+    targets
+}
+
+/// 两个目标目录是否相同
+fn same_target(a: &Path, b: &Path) -> bool {
+    let normalize = |p: &Path| {
+        p.to_string_lossy()
+            .replace('\\', "/")
+            .trim_matches('/')
+            .to_lowercase()
+    };
+    normalize(a) == normalize(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str) -> FileDescriptor {
+        FileDescriptor::new(
+            PathBuf::from("/inbox").join(name),
+            name.to_string(),
+            ".pdf".to_string(),
+            1,
+            chrono::Utc::now(),
+            false,
+        )
+    }
+
+    #[test]
+    fn test_agreement_rate_with_stub_classifiers() {
+        let files = vec![file("a.pdf"), file("b.pdf"), file("c.pdf"), file("d.pdf")];
+
+        // 模型A：全部归到 Documents；模型B：b 归到 Finance，d 请求失败，c 只差大小写和分隔符
+        let left = |_: &FileDescriptor| Some(PathBuf::from("Documents/Reports"));
+        let right = |f: &FileDescriptor| match f.name.as_str() {
+            "b.pdf" => Some(PathBuf::from("Finance")),
+            "c.pdf" => Some(PathBuf::from("documents/reports/")),
+            "d.pdf" => None,
+            _ => Some(PathBuf::from("Documents/Reports")),
+        };
+
+        let report = ModelComparison::compare(&files, left, right);
+        assert_eq!(report.total, 4);
+        assert_eq!(report.agreed, 2);
+        assert_eq!(report.agreement_rate(), 0.5);
+        let names: Vec<&str> = report.disagreements.iter().map(|d| d.file_name.as_str()).collect();
+        assert_eq!(names, vec!["b.pdf", "d.pdf"]);
+        assert_eq!(report.disagreements[1].right, None);
+    }
+}
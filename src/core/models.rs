@@ -2,9 +2,14 @@
 //! 
 //! 所有数据结构必须严格遵守设计文档定义，不允许自行添加未定义的字段。
 
+use crate::core::naming::NamingPolicy;
 use chrono::{DateTime, Utc};
+use globset::{GlobBuilder, GlobMatcher};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
 
 /// 目录类型枚举
 /// 用于标识目录的性质，决定是否可以拆分处理
@@ -48,9 +53,12 @@ pub struct FileDescriptor {
     pub extension: String,
     /// 完整路径
     pub full_path: PathBuf,
+    /// 相对扫描根目录的路径（由扫描器填写）
+    #[serde(default)]
+    pub relative_path: PathBuf,
     /// 父目录路径
     pub parent_dir: PathBuf,
-    /// 文件大小（字节），目录为0或子文件总大小
+    /// 文件大小（字节），目录为扫描到的子文件总大小
     pub size: u64,
     /// 最后修改时间
     pub modified_at: DateTime<Utc>,
@@ -66,6 +74,12 @@ pub struct FileDescriptor {
     pub suggested_action: Option<MoveSuggestion>,
     /// 用户是否选中此项进行操作
     pub selected: bool,
+    /// 是否为云同步的在线占位文件（OneDrive/Dropbox 仅在线文件），移动会触发下载
+    #[serde(default)]
+    pub is_cloud_placeholder: bool,
+    /// 用户备注（按文件ID保存在数据库中，重新扫描后恢复）
+    #[serde(default)]
+    pub user_note: Option<String>,
 }
 
 impl FileDescriptor {
@@ -92,6 +106,7 @@ impl FileDescriptor {
             name,
             extension,
             full_path,
+            relative_path: PathBuf::new(),
             parent_dir,
             size,
             modified_at,
@@ -101,6 +116,8 @@ impl FileDescriptor {
             semantic: None,
             suggested_action: None,
             selected: true, // 默认选中
+            is_cloud_placeholder: false,
+            user_note: None,
         }
     }
 }
@@ -137,7 +154,10 @@ impl Default for SemanticResult {
 /// 描述AI或规则引擎给出的文件移动建议
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MoveSuggestion {
-    /// 建议的目标路径
+    /// 建议的目标目录
+    ///
+    /// 规则和AI给出的是相对输出目录的分类路径（如 "Pictures/2023"），
+    /// 生成计划时才解析到输出目录下，因此扫描分析后仍可更换输出目录；绝对路径原样使用。
     pub target_path: PathBuf,
     /// 建议理由
     pub reason: String,
@@ -145,6 +165,35 @@ pub struct MoveSuggestion {
     pub source: SuggestionSource,
     /// 置信度 (0.0 - 1.0)
     pub confidence: f32,
+    /// 命中的规则ID（仅规则建议）
+    #[serde(default)]
+    pub matched_rule_id: Option<String>,
+    /// 命中规则的来源（内置或用户确认），用于区分是哪类规则生效
+    #[serde(default)]
+    pub rule_origin: Option<RuleOrigin>,
+    /// 是否由规则建议与AI建议融合而来；融合结果不会再被单独的规则匹配覆盖
+    #[serde(default)]
+    pub fused: bool,
+}
+
+impl MoveSuggestion {
+    /// 用户手动指定的目标（置信度视为 1.0）
+    pub fn manual(target_path: PathBuf) -> Self {
+        Self {
+            target_path,
+            reason: "手动指定".to_string(),
+            source: SuggestionSource::Manual,
+            confidence: 1.0,
+            matched_rule_id: None,
+            rule_origin: None,
+            fused: false,
+        }
+    }
+
+    /// 把目标目录解析到输出目录下
+    pub fn resolve_target(&self, output_base: &Path) -> PathBuf {
+        output_base.join(&self.target_path)
+    }
 }
 
 /// 建议来源枚举
@@ -156,6 +205,10 @@ pub enum SuggestionSource {
     Rule,
     /// 历史记忆
     Memory,
+    /// 用户在预览中手动指定
+    Manual,
+    /// 没有模型可用时按本地分析标签推断的分类目录
+    Offline,
 }
 
 impl std::fmt::Display for SuggestionSource {
@@ -164,6 +217,8 @@ impl std::fmt::Display for SuggestionSource {
             SuggestionSource::AI => write!(f, "AI"),
             SuggestionSource::Rule => write!(f, "规则"),
             SuggestionSource::Memory => write!(f, "记忆"),
+            SuggestionSource::Manual => write!(f, "手动"),
+            SuggestionSource::Offline => write!(f, "离线推断"),
         }
     }
 }
@@ -192,6 +247,9 @@ pub struct RuleDefinition {
     pub updated_at: DateTime<Utc>,
     /// 命中次数（统计用）
     pub hit_count: u64,
+    /// 最后一次命中的时间
+    #[serde(default)]
+    pub last_hit: Option<DateTime<Utc>>,
 }
 
 impl RuleDefinition {
@@ -210,12 +268,13 @@ impl RuleDefinition {
             created_at: now,
             updated_at: now,
             hit_count: 0,
+            last_hit: None,
         }
     }
 }
 
 /// 规则匹配条件
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct RuleCondition {
     /// 需要匹配的语义标签（任一匹配即可）
     #[serde(default)]
@@ -226,82 +285,541 @@ pub struct RuleCondition {
     /// 需要匹配的文件名关键词（任一包含即可）
     #[serde(default)]
     pub filename_keywords: Vec<String>,
-    /// 排除的目录路径模式
+    /// 文件名需匹配的正则表达式（无效的表达式视为不匹配）
+    #[serde(default)]
+    pub filename_regex: Option<String>,
+    /// 排除的目录路径模式：含 * ? [ { 的按 glob 匹配（相对扫描根，文件或任一上级目录命中即排除），
+    /// 否则按不区分大小写的子串匹配完整路径
     #[serde(default)]
     pub directory_excludes: Vec<String>,
+    /// 仅在这些一级目录（相对扫描根）下生效，为空表示不限
+    #[serde(default)]
+    pub scope_roots: Vec<String>,
     /// 最小文件大小（字节）
     pub min_size: Option<u64>,
     /// 最大文件大小（字节）
     pub max_size: Option<u64>,
+    /// 要求AI识别出至少一个实体（公司名、人名等），配合 {entity} 模板使用
+    #[serde(default)]
+    pub require_entity: bool,
+    /// 修改时间不早于此时刻
+    #[serde(default)]
+    pub modified_after: Option<DateTime<Utc>>,
+    /// 修改时间早于此时刻
+    #[serde(default)]
+    pub modified_before: Option<DateTime<Utc>>,
+    /// 目录标记：目录中直接包含其中任一文件或子目录时匹配（如 package.json、.git）。
+    /// 设置后规则只作用于原子目录，命中的目录整体移动；未设置的规则不匹配目录
+    #[serde(default)]
+    pub directory_markers: Vec<String>,
+    /// 已设置的各项条件之间的组合方式
+    #[serde(default)]
+    pub match_mode: ConditionMatchMode,
+}
+
+/// 条件组合方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum ConditionMatchMode {
+    /// 全部满足（AND）
+    #[default]
+    AllOf,
+    /// 任一满足（OR）
+    AnyOf,
+}
+
+/// 规则条件中的各项检查
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConditionField {
+    /// 扩展名
+    Extension,
+    /// 文件名关键词
+    Keywords,
+    /// 文件名正则
+    Regex,
+    /// 语义标签
+    Tags,
+    /// 排除目录
+    Excludes,
+    /// 作用范围
+    Scope,
+    /// 最小大小
+    MinSize,
+    /// 最大大小
+    MaxSize,
+    /// 识别到实体
+    Entity,
+    /// 修改时间起
+    ModifiedAfter,
+    /// 修改时间止
+    ModifiedBefore,
+    /// 目录标记
+    DirectoryMarkers,
+}
+
+impl ConditionField {
+    /// 显示名称
+    pub fn label(&self) -> &'static str {
+        match self {
+            ConditionField::Extension => "扩展名",
+            ConditionField::Keywords => "文件名关键词",
+            ConditionField::Regex => "文件名正则",
+            ConditionField::Tags => "语义标签",
+            ConditionField::Excludes => "排除目录",
+            ConditionField::Scope => "作用范围",
+            ConditionField::MinSize => "最小大小",
+            ConditionField::MaxSize => "最大大小",
+            ConditionField::Entity => "识别到实体",
+            ConditionField::ModifiedAfter => "修改时间起",
+            ConditionField::ModifiedBefore => "修改时间止",
+            ConditionField::DirectoryMarkers => "目录标记",
+        }
+    }
+
+    /// 排除目录和作用范围是限定范围的过滤条件，任一满足模式下也必须满足
+    pub fn is_filter(&self) -> bool {
+        matches!(self, ConditionField::Excludes | ConditionField::Scope)
+    }
+}
+
+impl ConditionMatchMode {
+    /// 显示名称
+    pub fn label(&self) -> &'static str {
+        match self {
+            ConditionMatchMode::AllOf => "全部满足",
+            ConditionMatchMode::AnyOf => "任一满足",
+        }
+    }
+
+    /// 按组合方式合并各项检查结果 (条件项, 是否满足)；没有设置任何条件时视为匹配
+    fn combine(&self, checks: impl IntoIterator<Item = (ConditionField, bool)>) -> bool {
+        match self {
+            ConditionMatchMode::AllOf => checks.into_iter().all(|(_, passed)| passed),
+            ConditionMatchMode::AnyOf => {
+                let mut any_criterion = None;
+                for (field, passed) in checks {
+                    if field.is_filter() {
+                        if !passed {
+                            return false;
+                        }
+                    } else {
+                        any_criterion = Some(any_criterion.unwrap_or(false) || passed);
+                    }
+                }
+                any_criterion.unwrap_or(true)
+            }
+        }
+    }
 }
 
 impl RuleCondition {
     /// 检查文件是否匹配此条件
     pub fn matches(&self, file: &FileDescriptor) -> bool {
-        let normalize_ext = |ext: &str| {
-            let ext = ext.trim().to_lowercase();
-            if ext.is_empty() {
-                ext
-            } else if ext.starts_with('.') {
-                ext
-            } else {
-                format!(".{}", ext)
-            }
+        self.match_mode.combine(
+            Self::CHECKS
+                .iter()
+                .filter_map(|(field, check)| check(self, file).map(|passed| (*field, passed))),
+        )
+    }
+
+    /// 逐项解释文件与此条件的匹配情况（未设置的条件不列出）
+    pub fn explain_match(&self, file: &FileDescriptor) -> MatchExplanation {
+        MatchExplanation {
+            checks: Self::CHECKS
+                .iter()
+                .filter_map(|(field, check)| {
+                    check(self, file).map(|passed| ConditionCheck { field: *field, passed })
+                })
+                .collect(),
+            match_mode: self.match_mode,
+        }
+    }
+
+    /// 各项条件：(条件项, 检查函数)，检查函数在该项未设置时返回 None
+    #[allow(clippy::type_complexity)]
+    const CHECKS: [(ConditionField, fn(&Self, &FileDescriptor) -> Option<bool>); 12] = [
+        (ConditionField::Extension, Self::check_extension),
+        (ConditionField::Keywords, Self::check_keywords),
+        (ConditionField::Regex, Self::check_regex),
+        (ConditionField::Tags, Self::check_tags),
+        (ConditionField::Excludes, Self::check_excludes),
+        (ConditionField::Scope, Self::check_scope),
+        (ConditionField::MinSize, Self::check_min_size),
+        (ConditionField::MaxSize, Self::check_max_size),
+        (ConditionField::Entity, Self::check_entity),
+        (ConditionField::ModifiedAfter, Self::check_modified_after),
+        (ConditionField::ModifiedBefore, Self::check_modified_before),
+        (ConditionField::DirectoryMarkers, Self::check_directory_markers),
+    ];
+
+    /// 是否为整体移动原子目录的规则
+    pub fn targets_directories(&self) -> bool {
+        !self.directory_markers.is_empty()
+    }
+
+    /// 判断本条件是否覆盖另一个条件：凡满足 other 的文件必然也满足 self
+    ///
+    /// 只做保守的逐项判断（扩展名、标签、范围为子集，关键词更长，大小范围更窄，
+    /// 排除项更宽），判断不了的组合按“不覆盖”处理。
+    pub fn covers(&self, other: &RuleCondition) -> bool {
+        // 任一满足的组合无法逐项比较
+        if self.match_mode == ConditionMatchMode::AnyOf || other.match_mode == ConditionMatchMode::AnyOf {
+            return false;
+        }
+
+        let lower = |items: &[String]| -> Vec<String> { items.iter().map(|s| s.trim().to_lowercase()).collect() };
+        let subset = |mine: Vec<String>, theirs: Vec<String>| {
+            mine.is_empty() || (!theirs.is_empty() && theirs.iter().all(|t| mine.contains(t)))
+        };
+        let scope = |items: &[String]| -> Vec<String> {
+            items
+                .iter()
+                .map(|r| r.trim().trim_matches(|c| c == '/' || c == '\\').to_lowercase())
+                .collect()
+        };
+
+        let extensions = subset(
+            self.file_extensions.iter().map(|e| normalize_ext(e)).collect(),
+            other.file_extensions.iter().map(|e| normalize_ext(e)).collect(),
+        );
+        // 对方的每个关键词都包含我方某个关键词时，对方命中的文件名我方一定命中
+        let mine_keywords = lower(&self.filename_keywords);
+        let keywords = mine_keywords.is_empty()
+            || (!other.filename_keywords.is_empty()
+                && lower(&other.filename_keywords)
+                    .iter()
+                    .all(|k| mine_keywords.iter().any(|m| k.contains(m.as_str()))));
+        let tags = subset(lower(&self.semantic_tags), lower(&other.semantic_tags));
+        let scope_roots = subset(scope(&self.scope_roots), scope(&other.scope_roots));
+        // 目录规则与文件规则作用对象不同，互不覆盖
+        let markers = self.targets_directories() == other.targets_directories()
+            && subset(lower(&self.directory_markers), lower(&other.directory_markers));
+        // 我方排除的路径，对方也必须排除
+        let other_excludes = lower(&other.directory_excludes);
+        let excludes = lower(&self.directory_excludes)
+            .iter()
+            .all(|d| other_excludes.iter().any(|e| d.contains(e.as_str())));
+        let min_size = match (self.min_size, other.min_size) {
+            (None, _) => true,
+            (Some(mine), Some(theirs)) => theirs >= mine,
+            (Some(_), None) => false,
         };
+        let max_size = match (self.max_size, other.max_size) {
+            (None, _) => true,
+            (Some(mine), Some(theirs)) => theirs <= mine,
+            (Some(_), None) => false,
+        };
+
+        let entity = !self.require_entity || other.require_entity;
+        let modified_after = match (self.modified_after, other.modified_after) {
+            (None, _) => true,
+            (Some(mine), Some(theirs)) => theirs >= mine,
+            (Some(_), None) => false,
+        };
+        let modified_before = match (self.modified_before, other.modified_before) {
+            (None, _) => true,
+            (Some(mine), Some(theirs)) => theirs <= mine,
+            (Some(_), None) => false,
+        };
+        // 正则之间无法判断包含关系，只认完全相同的表达式
+        let regex = self.filename_regex.is_none() || self.filename_regex == other.filename_regex;
+
+        extensions
+            && keywords
+            && regex
+            && tags
+            && scope_roots
+            && markers
+            && excludes
+            && min_size
+            && max_size
+            && entity
+            && modified_after
+            && modified_before
+    }
+
+    /// 判断两个条件是否可能同时匹配同一个文件
+    ///
+    /// 只排除明显矛盾的组合（扩展名、标签、作用范围没有交集，大小或修改时间范围不相交，
+    /// 一个匹配目录一个匹配文件），其余一律视为可能重叠。关键词可以同时出现在一个文件名里，不算矛盾。
+    pub fn may_overlap(&self, other: &RuleCondition) -> bool {
+        if self.targets_directories() != other.targets_directories() {
+            return false;
+        }
+        // 任一满足的组合很难排除，保守地视为重叠
+        if self.match_mode == ConditionMatchMode::AnyOf || other.match_mode == ConditionMatchMode::AnyOf {
+            return true;
+        }
 
-        // 检查扩展名
-        if !self.file_extensions.is_empty() {
-            let ext_lower = normalize_ext(&file.extension);
-            if !self
-                .file_extensions
+        let disjoint = |mine: Vec<String>, theirs: Vec<String>| {
+            !mine.is_empty() && !theirs.is_empty() && !mine.iter().any(|m| theirs.contains(m))
+        };
+        let lower = |items: &[String]| -> Vec<String> { items.iter().map(|s| s.trim().to_lowercase()).collect() };
+        let scope = |items: &[String]| -> Vec<String> {
+            items
                 .iter()
-                .any(|e| normalize_ext(e) == ext_lower)
-            {
-                return false;
-            }
+                .map(|r| r.trim().trim_matches(|c| c == '/' || c == '\\').to_lowercase())
+                .collect()
+        };
+        let extensions = |items: &[String]| -> Vec<String> { items.iter().map(|e| normalize_ext(e)).collect() };
+
+        if disjoint(extensions(&self.file_extensions), extensions(&other.file_extensions))
+            || disjoint(lower(&self.semantic_tags), lower(&other.semantic_tags))
+            || disjoint(scope(&self.scope_roots), scope(&other.scope_roots))
+        {
+            return false;
         }
 
-        // 检查文件名关键词
-        if !self.filename_keywords.is_empty() {
-            let name_lower = file.name.to_lowercase();
-            if !self.filename_keywords.iter().any(|k| name_lower.contains(&k.to_lowercase())) {
-                return false;
-            }
+        // 区间 [min, max] 是否相交
+        let min_size = self.min_size.max(other.min_size);
+        let max_size = match (self.max_size, other.max_size) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        if matches!((min_size, max_size), (Some(min), Some(max)) if min > max) {
+            return false;
         }
+        // 修改时间区间 [after, before)
+        let after = self.modified_after.max(other.modified_after);
+        let before = match (self.modified_before, other.modified_before) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        !matches!((after, before), (Some(after), Some(before)) if after >= before)
+    }
 
-        // 检查语义标签
-        if !self.semantic_tags.is_empty() {
-            if let Some(ref semantic) = file.semantic {
-                let has_match = self.semantic_tags.iter().any(|t| {
-                    semantic.tags.iter().any(|st| st.to_lowercase() == t.to_lowercase())
-                });
-                if !has_match {
-                    return false;
-                }
-            } else {
-                return false;
-            }
+    fn check_extension(&self, file: &FileDescriptor) -> Option<bool> {
+        if self.file_extensions.is_empty() {
+            return None;
+        }
+        let ext_lower = normalize_ext(&file.extension);
+        Some(self.file_extensions.iter().any(|e| normalize_ext(e) == ext_lower))
+    }
+
+    fn check_keywords(&self, file: &FileDescriptor) -> Option<bool> {
+        if self.filename_keywords.is_empty() {
+            return None;
         }
+        let name_lower = file.name.to_lowercase();
+        Some(self.filename_keywords.iter().any(|k| name_lower.contains(&k.to_lowercase())))
+    }
+
+    fn check_regex(&self, file: &FileDescriptor) -> Option<bool> {
+        let pattern = self.filename_regex.as_deref().filter(|p| !p.is_empty())?;
+        Some(cached_regex(pattern).is_some_and(|re| re.is_match(&file.name)))
+    }
 
-        // 检查排除目录
+    fn check_tags(&self, file: &FileDescriptor) -> Option<bool> {
+        if self.semantic_tags.is_empty() {
+            return None;
+        }
+        let has_match = file.semantic.as_ref().is_some_and(|semantic| {
+            self.semantic_tags.iter().any(|t| {
+                semantic.tags.iter().any(|st| st.to_lowercase() == t.to_lowercase())
+            })
+        });
+        Some(has_match)
+    }
+
+    fn check_excludes(&self, file: &FileDescriptor) -> Option<bool> {
+        if self.directory_excludes.is_empty() {
+            return None;
+        }
         let path_str = file.full_path.to_string_lossy().to_lowercase();
-        if self.directory_excludes.iter().any(|d| path_str.contains(&d.to_lowercase())) {
-            return false;
+        let path = if file.relative_path.as_os_str().is_empty() {
+            &file.full_path
+        } else {
+            &file.relative_path
+        };
+        Some(!self.directory_excludes.iter().any(|d| exclude_matches(d, &path_str, path)))
+    }
+
+    /// 文件必须位于所列的一级目录之下
+    fn check_scope(&self, file: &FileDescriptor) -> Option<bool> {
+        if self.scope_roots.is_empty() {
+            return None;
         }
+        let mut components = file.relative_path.components();
+        let top = components.next().map(|c| c.as_os_str().to_string_lossy().to_lowercase());
+        let in_scope = match top {
+            Some(top) if components.next().is_some() => self.scope_roots.iter().any(|r| {
+                r.trim().trim_matches(|c| c == '/' || c == '\\').to_lowercase() == top
+            }),
+            _ => false,
+        };
+        Some(in_scope)
+    }
 
-        // 检查文件大小
-        if let Some(min) = self.min_size {
-            if file.size < min {
-                return false;
-            }
+    fn check_min_size(&self, file: &FileDescriptor) -> Option<bool> {
+        self.min_size.map(|min| file.size >= min)
+    }
+
+    fn check_max_size(&self, file: &FileDescriptor) -> Option<bool> {
+        self.max_size.map(|max| file.size <= max)
+    }
+
+    fn check_entity(&self, file: &FileDescriptor) -> Option<bool> {
+        self.require_entity.then(|| first_entity(file).is_some())
+    }
+
+    fn check_modified_after(&self, file: &FileDescriptor) -> Option<bool> {
+        self.modified_after.map(|after| file.modified_at >= after)
+    }
+
+    fn check_modified_before(&self, file: &FileDescriptor) -> Option<bool> {
+        self.modified_before.map(|before| file.modified_at < before)
+    }
+
+    /// 目录中直接包含任一标记（检查磁盘）
+    fn check_directory_markers(&self, file: &FileDescriptor) -> Option<bool> {
+        if self.directory_markers.is_empty() {
+            return None;
         }
-        if let Some(max) = self.max_size {
-            if file.size > max {
-                return false;
+        Some(
+            file.is_directory
+                && self
+                    .directory_markers
+                    .iter()
+                    .any(|m| file.full_path.join(m.trim()).exists()),
+        )
+    }
+}
+
+/// 取编译好的正则（按表达式缓存，避免逐个文件重复编译）
+///
+/// 无效的表达式也缓存为 None，只在第一次编译失败时记录日志。
+fn cached_regex(pattern: &str) -> Option<Regex> {
+    static CACHE: OnceLock<Mutex<HashMap<String, Option<Regex>>>> = OnceLock::new();
+    let mut cache = CACHE
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    cache
+        .entry(pattern.to_string())
+        .or_insert_with(|| match Regex::new(pattern) {
+            Ok(re) => Some(re),
+            Err(e) => {
+                tracing::warn!("规则中的文件名正则无效，按不匹配处理: {}: {}", pattern, e);
+                None
             }
+        })
+        .clone()
+}
+
+/// 排除模式是否命中：glob 模式匹配路径本身或任一上级目录，普通文字保持子串匹配
+fn exclude_matches(pattern: &str, full_path_lower: &str, path: &Path) -> bool {
+    let pattern = pattern.trim();
+    if !pattern.contains(['*', '?', '[', '{']) {
+        return full_path_lower.contains(&pattern.to_lowercase());
+    }
+    let Some(glob) = cached_glob(pattern) else {
+        return false;
+    };
+    path.ancestors()
+        .filter(|p| !p.as_os_str().is_empty())
+        .any(|p| glob.is_match(p))
+}
+
+/// 取编译好的 glob（按模式缓存，无效模式记录一次日志并视为不匹配）
+fn cached_glob(pattern: &str) -> Option<GlobMatcher> {
+    static CACHE: OnceLock<Mutex<HashMap<String, Option<GlobMatcher>>>> = OnceLock::new();
+    let mut cache = CACHE
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    cache
+        .entry(pattern.to_string())
+        .or_insert_with(|| {
+            match GlobBuilder::new(pattern)
+                .case_insensitive(true)
+                .literal_separator(true)
+                .build()
+            {
+                Ok(glob) => Some(glob.compile_matcher()),
+                Err(e) => {
+                    tracing::warn!("规则中的排除模式无效，按不匹配处理: {}: {}", pattern, e);
+                    None
+                }
+            }
+        })
+        .clone()
+}
+
+/// 规范化扩展名：小写并补上前导点
+fn normalize_ext(ext: &str) -> String {
+    let ext = ext.trim().to_lowercase();
+    if ext.is_empty() || ext.starts_with('.') {
+        ext
+    } else {
+        format!(".{}", ext)
+    }
+}
+
+/// 把用户输入的扩展名列表整理为保存格式：小写、带前导点、去重
+///
+/// 空项和无法匹配的输入（只有点、含多段扩展名、空白或路径分隔符）被丢弃。
+pub fn canonical_extensions(exts: &[String]) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+    for ext in exts {
+        let ext = normalize_ext(ext);
+        let body = ext.trim_start_matches('.');
+        let valid = !body.is_empty()
+            && !body.chars().any(|c| c.is_whitespace() || matches!(c, '/' | '\\' | '.'));
+        if valid && !result.contains(&ext) {
+            result.push(ext);
+        }
+    }
+    result
+}
+
+/// 单项条件的检查结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConditionCheck {
+    /// 条件项
+    pub field: ConditionField,
+    /// 是否满足
+    pub passed: bool,
+}
+
+/// 规则条件的逐项匹配解释
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MatchExplanation {
+    /// 已设置的各项条件的检查结果
+    pub checks: Vec<ConditionCheck>,
+    /// 条件组合方式
+    pub match_mode: ConditionMatchMode,
+}
+
+impl MatchExplanation {
+    /// 按组合方式是否匹配
+    pub fn is_match(&self) -> bool {
+        self.match_mode.combine(self.checks.iter().map(|c| (c.field, c.passed)))
+    }
+
+    /// 满足的条件比例（用于挑选最接近的规则），没有条件时为 1
+    pub fn closeness(&self) -> f32 {
+        if self.checks.is_empty() {
+            return 1.0;
         }
+        self.checks.iter().filter(|c| c.passed).count() as f32 / self.checks.len() as f32
+    }
 
-        true
+    /// 一句话描述，如 "扩展名匹配，但文件名关键词未匹配"
+    pub fn summary(&self) -> String {
+        let join = |passed: bool| {
+            self.checks
+                .iter()
+                .filter(|c| c.passed == passed)
+                .map(|c| c.field.label())
+                .collect::<Vec<_>>()
+                .join("、")
+        };
+        let (matched, missed) = (join(true), join(false));
+
+        match (matched.is_empty(), missed.is_empty()) {
+            (_, true) => "全部条件匹配".to_string(),
+            (true, false) => format!("{}未匹配", missed),
+            (false, false) => format!("{}匹配，但{}未匹配", matched, missed),
+        }
     }
 }
 
@@ -315,7 +833,20 @@ pub struct RuleAction {
 
 impl RuleAction {
     /// 根据文件信息渲染实际目标路径
-    pub fn render_path(&self, file: &FileDescriptor, base_path: &PathBuf) -> PathBuf {
+    pub fn render_path(&self, file: &FileDescriptor, base_path: &Path) -> PathBuf {
+        base_path.join(self.render_relative(file))
+    }
+
+    /// 根据文件信息渲染相对输出目录的分类路径
+    ///
+    /// 支持的变量：
+    /// - `{year}`：AI识别的年份，没有时取修改年份
+    /// - `{month}` / `{day}`：修改时间的月、日（两位数字）
+    /// - `{extension}` / `{ext_upper}`：不带点的扩展名，原样 / 大写
+    /// - `{entity}` / `{first_entity}`：AI识别的第一个实体，没有时分别为“未识别” / `unknown`
+    /// - `{tag}` / `{first_tag}`：第一个语义标签，没有时分别为“未分类” / `untagged`
+    /// - `{size_bucket}`：按大小分档，`small`（< 1 MB）、`medium`（< 100 MB）、`large`
+    pub fn render_relative(&self, file: &FileDescriptor) -> PathBuf {
         let mut path = self.move_to.clone();
         
         // 替换年份变量
@@ -333,15 +864,99 @@ impl RuleAction {
         // 替换扩展名变量
         let ext = file.extension.trim_start_matches('.');
         path = path.replace("{extension}", ext);
+        path = path.replace("{ext_upper}", &ext.to_uppercase());
         
-        // 替换月份变量
+        // 替换月份、日期变量
         let month = file.modified_at.format("%m").to_string();
         path = path.replace("{month}", &month);
-        
-        base_path.join(path)
+        path = path.replace("{day}", &file.modified_at.format("%d").to_string());
+
+        path = path.replace("{size_bucket}", size_bucket(file.size));
+
+        // 替换实体/标签变量：取AI识别的第一个，清理成合法的目录名
+        if path.contains("{entity}") {
+            let entity = first_entity(file).map(sanitize_path_segment);
+            path = path.replace("{entity}", entity.as_deref().unwrap_or("未识别"));
+        }
+        if path.contains("{first_entity}") {
+            let entity = first_entity(file).map(sanitize_path_segment);
+            path = path.replace("{first_entity}", entity.as_deref().unwrap_or("unknown"));
+        }
+        if path.contains("{tag}") || path.contains("{first_tag}") {
+            let tag = file
+                .semantic
+                .as_ref()
+                .and_then(|s| s.tags.iter().map(|t| t.trim()).find(|t| !t.is_empty()))
+                .map(sanitize_path_segment);
+            path = path.replace("{tag}", tag.as_deref().unwrap_or("未分类"));
+            path = path.replace("{first_tag}", tag.as_deref().unwrap_or("untagged"));
+        }
+
+        sanitize_relative_path(&path)
+    }
+}
+
+/// 模板变量 {size_bucket} 的分档
+fn size_bucket(size: u64) -> &'static str {
+    const MB: u64 = 1024 * 1024;
+    match size {
+        s if s < MB => "small",
+        s if s < 100 * MB => "medium",
+        _ => "large",
+    }
+}
+
+/// 文件的第一个非空实体（AI识别结果）
+fn first_entity(file: &FileDescriptor) -> Option<&str> {
+    file.semantic
+        .as_ref()?
+        .entities
+        .iter()
+        .map(|e| e.trim())
+        .find(|e| !e.is_empty())
+}
+
+/// 把任意文本清理成单个目录名：路径分隔符和 Windows 禁用字符替换为下划线，
+/// 去掉首尾空格和点；清理后为空时返回 "_"
+fn sanitize_path_segment(value: &str) -> String {
+    let cleaned: String = value
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .take(MAX_PATH_SEGMENT_CHARS)
+        .collect();
+    let cleaned = cleaned.trim_matches(|c: char| c == '.' || c.is_whitespace());
+    if cleaned.is_empty() {
+        return "_".to_string();
+    }
+    // Windows 保留的设备名（CON、NUL、COM1 等）不能作为目录名
+    let stem = cleaned.split('.').next().unwrap_or_default().to_uppercase();
+    let reserved = matches!(stem.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+        || (stem.len() == 4
+            && (stem.starts_with("COM") || stem.starts_with("LPT"))
+            && stem.as_bytes()[3].is_ascii_digit());
+    if reserved {
+        format!("{}_", cleaned)
+    } else {
+        cleaned.to_string()
     }
 }
 
+/// 渲染后每段目录名的最大字符数
+const MAX_PATH_SEGMENT_CHARS: usize = 100;
+
+/// 逐段清理渲染后的相对路径：非法字符替换为下划线、去掉首尾的点和空格、限制长度；
+/// 空段和表示当前目录的 "." 被丢弃，".." 之类的段被清理成 "_"，不会跳出输出目录
+pub fn sanitize_relative_path(path: &str) -> PathBuf {
+    path.split(['/', '\\'])
+        .filter(|segment| !matches!(segment.trim(), "" | "."))
+        .map(sanitize_path_segment)
+        .collect()
+}
+
 /// 规则来源
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RuleOrigin {
@@ -351,6 +966,134 @@ pub enum RuleOrigin {
     BuiltIn,
 }
 
+/// 输出目录结构预设
+/// 为不熟悉路径模板的用户提供的命名布局，作用于内置规则的目标模板
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LayoutPreset {
+    /// 按类型：Pictures/
+    ByType,
+    /// 先类型后年份：Pictures/2024/
+    ByTypeThenYear,
+    /// 先年份后类型：2024/Pictures/
+    ByYearThenType,
+    /// 平铺：全部直接放在输出目录下
+    Flat,
+}
+
+impl LayoutPreset {
+    /// 所有预设（用于界面选择）
+    pub const ALL: [LayoutPreset; 4] = [
+        LayoutPreset::ByType,
+        LayoutPreset::ByTypeThenYear,
+        LayoutPreset::ByYearThenType,
+        LayoutPreset::Flat,
+    ];
+
+    /// 显示名称
+    pub fn label(&self) -> &'static str {
+        match self {
+            LayoutPreset::ByType => "按类型",
+            LayoutPreset::ByTypeThenYear => "按类型/年份",
+            LayoutPreset::ByYearThenType => "按年份/类型",
+            LayoutPreset::Flat => "平铺",
+        }
+    }
+
+    /// 根据类别目录（如 "Pictures"）生成路径模板
+    pub fn template_for(&self, category: &str) -> String {
+        match self {
+            LayoutPreset::ByType => category.to_string(),
+            LayoutPreset::ByTypeThenYear => format!("{}/{{year}}", category),
+            LayoutPreset::ByYearThenType => format!("{{year}}/{}", category),
+            LayoutPreset::Flat => String::new(),
+        }
+    }
+}
+
+/// 目标位置已有同名文件时的处理策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum ConflictStrategy {
+    /// 跳过，保留已有文件
+    #[default]
+    Skip,
+    /// 改名为 `名称 (1).扩展名`、`名称 (2).扩展名`……直到不冲突
+    Rename,
+    /// 覆盖已有文件（被覆盖的文件无法通过回滚恢复）；同一批次内移到同一名称的文件改名，不互相覆盖
+    Overwrite,
+    /// 报错，不移动
+    Fail,
+    /// 目标目录中已有内容相同的文件时跳过（视为重复），否则同 Rename 自动改名
+    DedupeByHash,
+}
+
+impl ConflictStrategy {
+    /// 所有策略（用于界面选择）
+    pub const ALL: [ConflictStrategy; 5] = [
+        ConflictStrategy::Skip,
+        ConflictStrategy::Rename,
+        ConflictStrategy::Overwrite,
+        ConflictStrategy::Fail,
+        ConflictStrategy::DedupeByHash,
+    ];
+
+    /// 显示名称
+    pub fn label(&self) -> &'static str {
+        match self {
+            ConflictStrategy::Skip => "跳过",
+            ConflictStrategy::Rename => "自动改名",
+            ConflictStrategy::Overwrite => "覆盖",
+            ConflictStrategy::Fail => "报错",
+            ConflictStrategy::DedupeByHash => "内容相同则跳过，否则改名",
+        }
+    }
+}
+
+/// 计划中内容相同的文件（每组只移动第一个）其余文件的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum DuplicateHandling {
+    /// 留在原处，计划中标记为跳过
+    #[default]
+    Skip,
+    /// 移到输出目录下的 Duplicates/，保留相对扫描目录的子路径
+    MoveToDuplicates,
+}
+
+impl DuplicateHandling {
+    /// 所有方式（用于界面选择）
+    pub const ALL: [DuplicateHandling; 2] = [DuplicateHandling::Skip, DuplicateHandling::MoveToDuplicates];
+
+    /// 显示名称
+    pub fn label(&self) -> &'static str {
+        match self {
+            DuplicateHandling::Skip => "留在原处",
+            DuplicateHandling::MoveToDuplicates => "移到 Duplicates/",
+        }
+    }
+}
+
+/// 执行方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum ExecutionMode {
+    /// 移动文件
+    #[default]
+    Move,
+    /// 复制文件，原文件保留在原处（回滚时删除副本）
+    Copy,
+}
+
+impl ExecutionMode {
+    /// 所有方式（用于界面选择）
+    pub const ALL: [ExecutionMode; 2] = [ExecutionMode::Move, ExecutionMode::Copy];
+
+    /// 显示名称（动词）
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExecutionMode::Move => "移动",
+            ExecutionMode::Copy => "复制",
+        }
+    }
+}
+
 /// 移动计划 - 描述一批文件的移动操作
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MovePlan {
@@ -360,6 +1103,9 @@ pub struct MovePlan {
     pub created_at: DateTime<Utc>,
     /// 操作列表
     pub operations: Vec<MoveOperation>,
+    /// 移动还是复制（随断点保存，继续执行时沿用）
+    #[serde(default)]
+    pub mode: ExecutionMode,
 }
 
 impl MovePlan {
@@ -369,6 +1115,7 @@ impl MovePlan {
             batch_id: uuid::Uuid::new_v4().to_string(),
             created_at: Utc::now(),
             operations: Vec::new(),
+            mode: ExecutionMode::default(),
         }
     }
     
@@ -380,6 +1127,8 @@ impl MovePlan {
             file_id,
             status: OperationStatus::Pending,
             error: None,
+            source_hash: None,
+            trashed_target: false,
         });
     }
 }
@@ -403,6 +1152,12 @@ pub struct MoveOperation {
     pub status: OperationStatus,
     /// 错误信息（如果有）
     pub error: Option<String>,
+    /// 计划时源文件的内容哈希（开启校验时填写），执行前据此发现外部修改
+    #[serde(default)]
+    pub source_hash: Option<String>,
+    /// 执行时目标位置的原有文件被移到了回收站（回滚不会自动还原它）
+    #[serde(default)]
+    pub trashed_target: bool,
 }
 
 /// 操作状态
@@ -433,6 +1188,9 @@ pub struct HistoryEntry {
     pub operations: Vec<MoveOperation>,
     /// 是否已回滚
     pub rolled_back: bool,
+    /// 执行方式（复制批次回滚时删除副本）
+    #[serde(default)]
+    pub mode: ExecutionMode,
 }
 
 /// AI配置
@@ -448,6 +1206,153 @@ pub struct AIConfig {
     pub max_tokens: u32,
     /// 温度参数
     pub temperature: f32,
+    /// 置信度校准（作用于AI返回的置信度，默认不变换）
+    #[serde(default)]
+    pub confidence_calibration: ConfidenceCalibration,
+    /// 自定义系统提示词（追加在内置提示词之前，如语言或领域偏好）
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    /// 内容摘要最多读取的字节数（KB），按字节截断，不受行结构影响
+    #[serde(default = "default_content_summary_kb")]
+    pub content_summary_kb: usize,
+    /// 备用端点：当前端点连接失败时按顺序尝试（如本地 Ollama 不可用时改用云端）
+    #[serde(default)]
+    pub fallback_endpoints: Vec<AIConfig>,
+    /// 暂时性失败（连接失败、429、5xx）的最多重试次数
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// 首次重试前的等待时间（毫秒），之后每次翻倍
+    #[serde(default = "default_retry_base_delay_ms")]
+    pub retry_base_delay_ms: u64,
+    /// 单次请求的超时时间（秒），模型服务卡住时不会无限等待
+    #[serde(default = "default_request_timeout_secs")]
+    pub request_timeout_secs: u64,
+    /// 模型对比时的另一个模型，为空表示未设置
+    #[serde(default)]
+    pub comparison_model: String,
+    /// 对比模型的端点，为空时与当前端点相同
+    #[serde(default)]
+    pub comparison_endpoint: String,
+    /// 对比模型端点的 API 密钥（仅在填写了对比端点时使用）
+    #[serde(default)]
+    pub comparison_api_key: String,
+}
+
+fn default_content_summary_kb() -> usize {
+    1
+}
+
+/// 覆盖 AI API 密钥的环境变量
+pub const ENV_AI_API_KEY: &str = "ORDERLY_AI_API_KEY";
+/// 覆盖 AI API 端点的环境变量
+pub const ENV_AI_ENDPOINT: &str = "ORDERLY_AI_ENDPOINT";
+/// 覆盖 AI 模型名称的环境变量
+pub const ENV_AI_MODEL: &str = "ORDERLY_AI_MODEL";
+
+/// 可以覆盖AI配置的全部环境变量
+pub const AI_ENV_OVERRIDES: [&str; 3] = [ENV_AI_API_KEY, ENV_AI_ENDPOINT, ENV_AI_MODEL];
+
+impl AIConfig {
+    /// 环境变量对应的字段
+    fn env_field_mut(&mut self, var: &str) -> Option<&mut String> {
+        match var {
+            ENV_AI_API_KEY => Some(&mut self.api_key),
+            ENV_AI_ENDPOINT => Some(&mut self.api_endpoint),
+            ENV_AI_MODEL => Some(&mut self.model_name),
+            _ => None,
+        }
+    }
+}
+
+/// 读取非空的环境变量
+fn env_override(var: &str) -> Option<String> {
+    std::env::var(var).ok().filter(|v| !v.trim().is_empty())
+}
+
+fn default_max_retries() -> u32 {
+    2
+}
+
+fn default_retry_base_delay_ms() -> u64 {
+    500
+}
+
+fn default_request_timeout_secs() -> u64 {
+    60
+}
+
+/// AI置信度校准方式
+/// 不同模型有的普遍偏高、有的偏低；校准是单调映射，不改变置信度之间的排序
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub enum ConfidenceCalibration {
+    /// 不做变换
+    #[default]
+    Identity,
+    /// 幂函数：c' = c^gamma（gamma > 1 压低置信度，gamma < 1 抬高置信度）
+    Gamma(f32),
+    /// 分段线性映射：(原始置信度, 校准后置信度) 控制点，两端隐含 (0,0) 与 (1,1)
+    Piecewise(Vec<(f32, f32)>),
+}
+
+impl ConfidenceCalibration {
+    /// 整理分段映射的控制点，加载配置时调用：按原始置信度排序，
+    /// 校准后置信度比前一个控制点低的抬高到前一个值，保证映射单调不减
+    pub fn normalize(&mut self) {
+        if let ConfidenceCalibration::Piecewise(points) = self {
+            let knots = monotonic_knots(points);
+            if knots != *points {
+                tracing::warn!("置信度分段映射的控制点无序或不单调，已调整为: {:?}", knots);
+                *points = knots;
+            }
+        }
+    }
+
+    /// 对置信度进行校准，结果限制在 0.0 - 1.0
+    pub fn apply(&self, confidence: f32) -> f32 {
+        let c = confidence.clamp(0.0, 1.0);
+        let calibrated = match self {
+            ConfidenceCalibration::Identity => c,
+            ConfidenceCalibration::Gamma(gamma) if *gamma > 0.0 => c.powf(*gamma),
+            ConfidenceCalibration::Gamma(_) => c,
+            ConfidenceCalibration::Piecewise(points) => {
+                let mut knots = vec![(0.0f32, 0.0f32)];
+                knots.extend(monotonic_knots(points));
+                knots.push((1.0, 1.0));
+
+                knots
+                    .windows(2)
+                    .find(|w| c >= w[0].0 && c <= w[1].0)
+                    .map(|w| {
+                        let (x0, y0) = w[0];
+                        let (x1, y1) = w[1];
+                        if x1 - x0 <= f32::EPSILON {
+                            y1
+                        } else {
+                            // 限制在本段两端之间，避免浮点误差在控制点处越过下一段
+                            (y0 + (y1 - y0) * (c - x0) / (x1 - x0)).clamp(y0, y1)
+                        }
+                    })
+                    .unwrap_or(c)
+            }
+        };
+        calibrated.clamp(0.0, 1.0)
+    }
+}
+
+/// 限制在 0.0 - 1.0 并按 x 排序的控制点，y 不低于前面的控制点（丢弃非有限值）
+fn monotonic_knots(points: &[(f32, f32)]) -> Vec<(f32, f32)> {
+    let mut knots: Vec<(f32, f32)> = points
+        .iter()
+        .filter(|(x, y)| x.is_finite() && y.is_finite())
+        .map(|(x, y)| (x.clamp(0.0, 1.0), y.clamp(0.0, 1.0)))
+        .collect();
+    knots.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+    let mut floor = 0.0f32;
+    for knot in &mut knots {
+        floor = floor.max(knot.1);
+        knot.1 = floor;
+    }
+    knots
 }
 
 impl Default for AIConfig {
@@ -458,7 +1363,41 @@ impl Default for AIConfig {
             model_name: "qwen3:30b-a3b".to_string(),
             max_tokens: 2048,
             temperature: 0.3,
+            confidence_calibration: ConfidenceCalibration::Identity,
+            system_prompt: None,
+            content_summary_kb: default_content_summary_kb(),
+            fallback_endpoints: Vec::new(),
+            max_retries: default_max_retries(),
+            retry_base_delay_ms: default_retry_base_delay_ms(),
+            request_timeout_secs: default_request_timeout_secs(),
+            comparison_model: String::new(),
+            comparison_endpoint: String::new(),
+            comparison_api_key: String::new(),
+        }
+    }
+}
+
+impl AIConfig {
+    /// 模型对比用的配置：换成对比模型，填写了对比端点时连同端点和密钥一起替换；
+    /// 不使用备用端点以免混入其他模型
+    pub fn comparison_config(&self) -> Option<AIConfig> {
+        let model = self.comparison_model.trim();
+        if model.is_empty() {
+            return None;
         }
+        let endpoint = self.comparison_endpoint.trim();
+        let (api_endpoint, api_key) = if endpoint.is_empty() {
+            (self.api_endpoint.clone(), self.api_key.clone())
+        } else {
+            (endpoint.to_string(), self.comparison_api_key.clone())
+        };
+        Some(AIConfig {
+            api_endpoint,
+            api_key,
+            model_name: model.to_string(),
+            fallback_endpoints: Vec::new(),
+            ..self.clone()
+        })
     }
 }
 
@@ -477,6 +1416,296 @@ pub struct AppConfig {
     pub confidence_threshold: f32,
     /// 是否默认Dry Run模式
     pub dry_run_default: bool,
+    /// 输出目录结构预设（None 表示使用内置规则自带的模板）
+    #[serde(default)]
+    pub layout_preset: Option<LayoutPreset>,
+    /// 最近使用的AI端点与模型（最新的在前）
+    #[serde(default)]
+    pub recent_endpoints: Vec<RecentEndpoint>,
+    /// 新建分类目录时写入说明文件（_orderly.txt）
+    #[serde(default)]
+    pub write_folder_readme: bool,
+    /// 目标位置已有同名文件时的处理策略
+    #[serde(default)]
+    pub conflict_strategy: ConflictStrategy,
+    /// 移动还是复制文件
+    #[serde(default)]
+    pub execution_mode: ExecutionMode,
+    /// 冲突时先把原有文件移到回收站（代替覆盖或报错）
+    #[serde(default)]
+    pub use_trash_on_conflict: bool,
+    /// 按内容去重跳过时把源文件移到回收站
+    #[serde(default)]
+    pub trash_duplicate_sources: bool,
+    /// 全部成功或全部撤销：任一操作失败时自动回滚整批
+    #[serde(default)]
+    pub transactional_execution: bool,
+    /// 生成计划时找出内容相同的文件，每组只移动一个
+    #[serde(default)]
+    pub dedupe_identical: bool,
+    /// 重复文件中其余文件的处理方式
+    #[serde(default)]
+    pub duplicate_handling: DuplicateHandling,
+    /// 把原子目录之外的独立可执行文件（.exe/.msi/.scr）集中到 Installers/
+    #[serde(default)]
+    pub route_loose_executables: bool,
+    /// 发票/合同按AI识别的机构名归档到 Finance/{entity}/{year}
+    #[serde(default)]
+    pub organize_by_entity: bool,
+    /// 目录内文件数超过此值时整体视为原子目录（0 表示不限制）
+    #[serde(default = "default_atomic_dir_max_files")]
+    pub atomic_dir_max_files: usize,
+    /// 目录总大小超过此值（字节）时整体视为原子目录（0 表示不限制）
+    #[serde(default = "default_atomic_dir_max_bytes")]
+    pub atomic_dir_max_bytes: u64,
+    /// 预览表格的列宽
+    #[serde(default)]
+    pub preview_column_widths: ColumnWidths,
+    /// 移动时的文件名规范化策略
+    #[serde(default)]
+    pub naming_policy: NamingPolicy,
+    /// 上次关闭时的窗口与面板状态
+    #[serde(default)]
+    pub window: WindowState,
+    /// 上次使用的扫描目录（启动时优先于档案的默认扫描路径）
+    #[serde(default)]
+    pub last_scan_path: Option<PathBuf>,
+    /// 上次使用的输出目录（启动时优先于档案的默认输出路径）
+    #[serde(default)]
+    pub last_output_path: Option<PathBuf>,
+    /// 上次执行实测的跨设备复制速度（字节/秒），用于估算耗时
+    #[serde(default)]
+    pub copy_throughput: Option<f64>,
+    /// 扫描时检测系统缓存文件（.DS_Store、Thumbs.db、desktop.ini）并提供清理
+    #[serde(default)]
+    pub cleanup_os_cruft: bool,
+    /// 生成计划时记录源文件哈希，执行前校验内容未被外部修改
+    #[serde(default)]
+    pub verify_source_hash: bool,
+    /// 安全模式：拒绝目标不在输出目录内的操作
+    #[serde(default = "default_true")]
+    pub strict_output_containment: bool,
+    /// 预览中勾选、阈值或规则变化时自动刷新计划统计
+    #[serde(default)]
+    pub live_preview: bool,
+    /// 也移动云端占位文件（默认跳过，移动会触发下载）
+    #[serde(default)]
+    pub move_cloud_placeholders: bool,
+    /// 融合规则建议和AI建议时规则置信度的权重
+    #[serde(default = "default_fusion_rule_weight")]
+    pub fusion_rule_weight: f32,
+    /// 融合规则建议和AI建议时AI置信度的权重
+    #[serde(default = "default_fusion_ai_weight")]
+    pub fusion_ai_weight: f32,
+    /// 规则已匹配的文件也请模型给出路径并与规则融合（每个文件多一次请求，默认关闭）
+    #[serde(default)]
+    pub fuse_rule_matches_with_ai: bool,
+    /// 命名配置档案（名称 -> 档案）；顶层的扫描/输出路径与置信度阈值即 "default" 档案
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ConfigProfile>,
+    /// 当前使用的配置档案名称
+    #[serde(default = "default_active_profile")]
+    pub active_profile: String,
+}
+
+/// 默认配置档案名称
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// 配置档案：不同磁盘或用途各自的扫描/输出路径与置信度阈值
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfigProfile {
+    /// 默认扫描路径
+    #[serde(default)]
+    pub default_scan_path: Option<PathBuf>,
+    /// 默认输出基础路径
+    #[serde(default)]
+    pub default_output_base: Option<PathBuf>,
+    /// 置信度阈值（低于此值需要人工确认）
+    #[serde(default = "default_confidence_threshold")]
+    pub confidence_threshold: f32,
+}
+
+fn default_confidence_threshold() -> f32 {
+    0.7
+}
+
+fn default_active_profile() -> String {
+    DEFAULT_PROFILE.to_string()
+}
+
+fn default_atomic_dir_max_files() -> usize {
+    10_000
+}
+
+fn default_atomic_dir_max_bytes() -> u64 {
+    20 * 1024 * 1024 * 1024
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_fusion_rule_weight() -> f32 {
+    0.6
+}
+
+fn default_fusion_ai_weight() -> f32 {
+    0.4
+}
+
+/// 窗口几何与面板显示状态（关闭时保存，启动时恢复）
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowState {
+    /// 窗口内部尺寸（None 表示使用默认尺寸）
+    pub inner_size: Option<[f32; 2]>,
+    /// 窗口左上角位置（None 表示由系统决定）
+    pub position: Option<[f32; 2]>,
+    /// 是否最大化
+    pub maximized: bool,
+    /// 是否显示规则面板
+    pub show_rule_panel: bool,
+    /// 是否显示历史面板
+    pub show_history_panel: bool,
+}
+
+/// 预览表格各列宽度（像素）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColumnWidths {
+    /// 文件名列
+    pub name: f32,
+    /// 当前路径列
+    pub path: f32,
+    /// 建议路径列
+    pub target: f32,
+    /// 置信度列
+    pub confidence: f32,
+    /// 来源列
+    pub source: f32,
+}
+
+impl Default for ColumnWidths {
+    fn default() -> Self {
+        Self {
+            name: 220.0,
+            path: 260.0,
+            target: 320.0,
+            confidence: 70.0,
+            source: 80.0,
+        }
+    }
+}
+
+impl ColumnWidths {
+    /// 按列顺序排列的宽度
+    pub fn to_array(self) -> [f32; 5] {
+        [self.name, self.path, self.target, self.confidence, self.source]
+    }
+
+    /// 由按列顺序排列的宽度构造
+    pub fn from_array(widths: [f32; 5]) -> Self {
+        let [name, path, target, confidence, source] = widths;
+        Self { name, path, target, confidence, source }
+    }
+}
+
+/// 最近使用的AI端点与模型
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecentEndpoint {
+    /// API端点URL
+    pub endpoint: String,
+    /// 模型名称
+    pub model_name: String,
+}
+
+impl AppConfig {
+    /// 最近使用列表的最大长度
+    pub const MAX_RECENT_ENDPOINTS: usize = 8;
+
+    /// 记录一次使用的端点与模型：移到列表最前，去重并截断
+    pub fn remember_endpoint(&mut self, endpoint: &str, model_name: &str) {
+        let endpoint = endpoint.trim();
+        let model_name = model_name.trim();
+        if endpoint.is_empty() {
+            return;
+        }
+
+        self.recent_endpoints
+            .retain(|r| !(r.endpoint == endpoint && r.model_name == model_name));
+        self.recent_endpoints.insert(
+            0,
+            RecentEndpoint {
+                endpoint: endpoint.to_string(),
+                model_name: model_name.to_string(),
+            },
+        );
+        self.recent_endpoints.truncate(Self::MAX_RECENT_ENDPOINTS);
+    }
+
+    /// 全部配置档案名称（"default" 在最前，其余按名称排序）
+    pub fn profile_names(&self) -> Vec<String> {
+        std::iter::once(DEFAULT_PROFILE.to_string())
+            .chain(self.profiles.keys().filter(|n| n.as_str() != DEFAULT_PROFILE).cloned())
+            .collect()
+    }
+
+    /// 指定名称的配置档案，"default" 取顶层字段
+    pub fn profile(&self, name: &str) -> Option<ConfigProfile> {
+        if name == DEFAULT_PROFILE {
+            return Some(ConfigProfile {
+                default_scan_path: self.default_scan_path.clone(),
+                default_output_base: self.default_output_base.clone(),
+                confidence_threshold: self.confidence_threshold,
+            });
+        }
+        self.profiles.get(name).cloned()
+    }
+
+    /// 当前使用的配置档案（档案已被删除时退回 "default"）
+    pub fn current_profile(&self) -> ConfigProfile {
+        self.profile(&self.active_profile)
+            .or_else(|| self.profile(DEFAULT_PROFILE))
+            .expect("default 档案总是存在")
+    }
+
+    /// 写入配置档案，"default" 写入顶层字段，其余名称新建或替换
+    pub fn set_profile(&mut self, name: &str, profile: ConfigProfile) {
+        if name == DEFAULT_PROFILE {
+            self.default_scan_path = profile.default_scan_path;
+            self.default_output_base = profile.default_output_base;
+            self.confidence_threshold = profile.confidence_threshold;
+        } else {
+            self.profiles.insert(name.to_string(), profile);
+        }
+    }
+
+    /// 用环境变量覆盖AI配置（环境变量优先于配置文件），未设置或为空的变量不生效
+    pub fn apply_env_overrides(&mut self) {
+        for var in AI_ENV_OVERRIDES {
+            if let (Some(value), Some(field)) = (env_override(var), self.ai_config.env_field_mut(var)) {
+                *field = value;
+            }
+        }
+    }
+
+    /// 撤销环境变量带来的覆盖，恢复为 stored（配置文件中原有的配置）里的值
+    ///
+    /// 保存配置前调用，避免把只放在环境变量里的密钥写进配置文件。
+    /// 字段被改成与环境变量不同的值时保留修改。
+    pub fn remove_env_overrides(&mut self, stored: &AppConfig) {
+        let mut stored = stored.ai_config.clone();
+        for var in AI_ENV_OVERRIDES {
+            let Some(value) = env_override(var) else {
+                continue;
+            };
+            if let (Some(field), Some(original)) = (self.ai_config.env_field_mut(var), stored.env_field_mut(var)) {
+                if *field == value {
+                    *field = std::mem::take(original);
+                }
+            }
+        }
+    }
 }
 
 impl Default for AppConfig {
@@ -488,6 +1717,36 @@ impl Default for AppConfig {
             ai_enabled: true,
             confidence_threshold: 0.7,
             dry_run_default: true,
+            layout_preset: None,
+            recent_endpoints: Vec::new(),
+            write_folder_readme: false,
+            route_loose_executables: false,
+            organize_by_entity: false,
+            conflict_strategy: ConflictStrategy::default(),
+            execution_mode: ExecutionMode::default(),
+            use_trash_on_conflict: false,
+            trash_duplicate_sources: false,
+            transactional_execution: false,
+            dedupe_identical: false,
+            duplicate_handling: DuplicateHandling::default(),
+            atomic_dir_max_files: default_atomic_dir_max_files(),
+            preview_column_widths: ColumnWidths::default(),
+            naming_policy: NamingPolicy::default(),
+            window: WindowState::default(),
+            last_scan_path: None,
+            last_output_path: None,
+            copy_throughput: None,
+            cleanup_os_cruft: false,
+            verify_source_hash: false,
+            strict_output_containment: true,
+            live_preview: false,
+            move_cloud_placeholders: false,
+            atomic_dir_max_bytes: default_atomic_dir_max_bytes(),
+            fusion_rule_weight: default_fusion_rule_weight(),
+            fusion_ai_weight: default_fusion_ai_weight(),
+            fuse_rule_matches_with_ai: false,
+            profiles: BTreeMap::new(),
+            active_profile: default_active_profile(),
         }
     }
 }
@@ -511,6 +1770,7 @@ pub struct ErrorCluster {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::TimeZone;
 
     #[test]
     fn test_file_descriptor_id_stability() {
@@ -583,6 +1843,135 @@ mod tests {
         assert!(condition.matches(&file));
     }
 
+    #[test]
+    fn test_rule_condition_matches_regex() {
+        let file = |name: &str| {
+            FileDescriptor::new(
+                PathBuf::from("/bank").join(name),
+                name.to_string(),
+                ".pdf".to_string(),
+                1024,
+                Utc::now(),
+                false,
+            )
+        };
+        let condition = RuleCondition {
+            filename_regex: Some(r"^\d{4}-\d{2}\.pdf$".to_string()),
+            ..Default::default()
+        };
+        assert!(condition.matches(&file("2023-07.pdf")));
+        assert!(!condition.matches(&file("statement-2023-07.pdf")));
+
+        // 无效的表达式视为不匹配，而不是 panic
+        let invalid = RuleCondition {
+            filename_regex: Some("([".to_string()),
+            ..Default::default()
+        };
+        assert!(!invalid.matches(&file("2023-07.pdf")));
+        assert!(!invalid.matches(&file("2023-08.pdf")));
+    }
+
+    #[test]
+    fn test_directory_excludes_glob_and_substring() {
+        let file = |relative: &str| {
+            let mut file = FileDescriptor::new(
+                PathBuf::from("/scan").join(relative),
+                relative.rsplit('/').next().unwrap().to_string(),
+                ".txt".to_string(),
+                1,
+                Utc::now(),
+                false,
+            );
+            file.relative_path = PathBuf::from(relative);
+            file
+        };
+        let excluding = |pattern: &str| RuleCondition {
+            directory_excludes: vec![pattern.to_string()],
+            ..Default::default()
+        };
+
+        // 不含通配符的旧写法仍按子串匹配
+        let substring = excluding("Archive");
+        assert!(!substring.matches(&file("my-archive-notes/a.txt")));
+        assert!(substring.matches(&file("docs/a.txt")));
+
+        // glob 按路径组件匹配
+        let node_modules = excluding("**/node_modules/**");
+        assert!(!node_modules.matches(&file("web/node_modules/react/index.txt")));
+        assert!(node_modules.matches(&file("web/node_modules_backup.txt")));
+
+        // temp/* 排除 temp 下的文件及子目录，但不影响别处的 temp
+        let temp = excluding("temp/*");
+        assert!(!temp.matches(&file("temp/a.txt")));
+        assert!(!temp.matches(&file("Temp/sub/b.txt")));
+        assert!(temp.matches(&file("other/temp/c.txt")));
+    }
+
+    #[test]
+    fn test_rule_condition_modified_range() {
+        let file = |year: i32| {
+            FileDescriptor::new(
+                PathBuf::from("/docs/old.txt"),
+                "old.txt".to_string(),
+                ".txt".to_string(),
+                1,
+                Utc.with_ymd_and_hms(year, 6, 1, 0, 0, 0).unwrap(),
+                false,
+            )
+        };
+        let condition = RuleCondition {
+            modified_before: Some(Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap()),
+            ..Default::default()
+        };
+        assert!(condition.matches(&file(2019)));
+        assert!(!condition.matches(&file(2023)));
+
+        // RFC3339 序列化，缺省为 None
+        let json = serde_json::to_string(&condition).unwrap();
+        assert!(json.contains("\"modified_before\":\"2020-01-01T00:00:00Z\""));
+        let parsed: RuleCondition = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.modified_before, condition.modified_before);
+        let legacy: RuleCondition = serde_json::from_str(r#"{"min_size":null,"max_size":null}"#).unwrap();
+        assert!(legacy.modified_after.is_none());
+    }
+
+    #[test]
+    fn test_any_of_mode_combines_with_or() {
+        let file = |name: &str, ext: &str, relative: &str| {
+            let mut file = FileDescriptor::new(
+                PathBuf::from("/scan").join(relative),
+                name.to_string(),
+                ext.to_string(),
+                1,
+                Utc::now(),
+                false,
+            );
+            file.relative_path = PathBuf::from(relative);
+            file
+        };
+        let mut condition = RuleCondition {
+            file_extensions: vec![".pdf".to_string()],
+            filename_keywords: vec!["invoice".to_string()],
+            directory_excludes: vec!["drafts".to_string()],
+            ..Default::default()
+        };
+        let pdf = file("manual.pdf", ".pdf", "docs/manual.pdf");
+        let invoice = file("invoice-03.png", ".png", "docs/invoice-03.png");
+        let draft = file("invoice.pdf", ".pdf", "drafts/invoice.pdf");
+
+        // 默认全部满足
+        assert!(!condition.matches(&pdf));
+        assert!(!condition.matches(&invoice));
+
+        // 任一满足：PDF 或含 invoice 即可，但排除目录仍然生效
+        condition.match_mode = ConditionMatchMode::AnyOf;
+        assert!(condition.matches(&pdf));
+        assert!(condition.matches(&invoice));
+        assert!(!condition.matches(&draft));
+        assert!(!condition.matches(&file("notes.txt", ".txt", "docs/notes.txt")));
+        assert_eq!(condition.explain_match(&pdf).is_match(), condition.matches(&pdf));
+    }
+
     #[test]
     fn test_rule_action_render_path() {
         let action = RuleAction {
@@ -605,11 +1994,88 @@ mod tests {
         assert!(rendered.to_string_lossy().contains("pdf"));
     }
 
+    #[test]
+    fn test_rendered_path_is_valid_on_windows() {
+        let mut file = FileDescriptor::new(
+            PathBuf::from("/test/a.pdf"),
+            "a.pdf".to_string(),
+            ".pdf".to_string(),
+            1,
+            Utc::now(),
+            false,
+        );
+        file.semantic = Some(SemanticResult {
+            tags: vec!["invoice:2023?".to_string()],
+            entities: vec!["a".repeat(300)],
+            ..Default::default()
+        });
+        let action = RuleAction {
+            move_to: "Docs/{tag} */../con/{entity}/".to_string(),
+        };
+
+        let base = PathBuf::from("C:/Users/me/Out: base");
+        let rendered = action.render_path(&file, &base);
+        // 输出目录保持原样，只清理渲染出的部分
+        let relative = rendered.strip_prefix(&base).unwrap();
+        let segments: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(segments[..4], ["Docs", "invoice_2023_ _", "_", "con_"]);
+        assert_eq!(segments[4].chars().count(), MAX_PATH_SEGMENT_CHARS);
+        for segment in &segments {
+            assert!(!segment.contains([':', '*', '?', '"', '<', '>', '|']));
+            assert!(!segment.ends_with('.') && !segment.ends_with(' '));
+        }
+
+        // "./" 表示当前目录，直接去掉
+        let action = RuleAction {
+            move_to: "./Docs/./{extension}".to_string(),
+        };
+        assert_eq!(action.render_relative(&file), PathBuf::from("Docs/pdf"));
+    }
+
+    #[test]
+    fn test_render_extended_template_variables() {
+        let mut file = FileDescriptor::new(
+            PathBuf::from("/test/scan.pdf"),
+            "scan.pdf".to_string(),
+            ".pdf".to_string(),
+            5 * 1024 * 1024,
+            Utc.with_ymd_and_hms(2023, 4, 7, 12, 0, 0).unwrap(),
+            false,
+        );
+        let render = |template: &str, file: &FileDescriptor| {
+            RuleAction { move_to: template.to_string() }.render_relative(file)
+        };
+
+        assert_eq!(render("{year}/{month}/{day}", &file), PathBuf::from("2023/04/07"));
+        assert_eq!(render("{ext_upper}", &file), PathBuf::from("PDF"));
+        assert_eq!(render("{size_bucket}", &file), PathBuf::from("medium"));
+        // 没有语义结果时使用后备值
+        assert_eq!(render("{first_tag}/{first_entity}", &file), PathBuf::from("untagged/unknown"));
+
+        file.semantic = Some(SemanticResult {
+            tags: vec!["Bank/Statement".to_string()],
+            entities: vec!["招商银行".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(
+            render("Documents/{first_tag}/{first_entity}", &file),
+            PathBuf::from("Documents/Bank_Statement/招商银行")
+        );
+        file.size = 10;
+        assert_eq!(render("{size_bucket}", &file), PathBuf::from("small"));
+        file.size = 200 * 1024 * 1024;
+        assert_eq!(render("{size_bucket}", &file), PathBuf::from("large"));
+    }
+
     #[test]
     fn test_suggestion_source_display() {
         assert_eq!(SuggestionSource::AI.to_string(), "AI");
         assert_eq!(SuggestionSource::Rule.to_string(), "规则");
         assert_eq!(SuggestionSource::Memory.to_string(), "记忆");
+        assert_eq!(SuggestionSource::Manual.to_string(), "手动");
     }
 
     #[test]
@@ -634,4 +2100,183 @@ mod tests {
         assert!(config.api_endpoint.contains("localhost"));
         assert!(config.model_name.contains("qwen"));
     }
+
+    #[test]
+    fn test_comparison_config_can_use_another_endpoint() {
+        let mut config = AIConfig {
+            api_key: "sk-local".to_string(),
+            ..Default::default()
+        };
+        assert!(config.comparison_config().is_none());
+
+        // 只填模型：同一端点、同一密钥
+        config.comparison_model = "llama3".to_string();
+        let same = config.comparison_config().unwrap();
+        assert_eq!(same.api_endpoint, config.api_endpoint);
+        assert_eq!(same.api_key, "sk-local");
+        assert_eq!(same.model_name, "llama3");
+
+        // 填了对比端点：换成另一端点和它的密钥
+        config.comparison_endpoint = "https://api.openai.com/v1/chat/completions".to_string();
+        config.comparison_api_key = "sk-cloud".to_string();
+        let other = config.comparison_config().unwrap();
+        assert_eq!(other.api_endpoint, "https://api.openai.com/v1/chat/completions");
+        assert_eq!(other.api_key, "sk-cloud");
+        assert!(other.fallback_endpoints.is_empty());
+    }
+
+    #[test]
+    fn test_confidence_calibration_gamma_preserves_order() {
+        let calibration = ConfidenceCalibration::Gamma(2.0);
+        let raw = [0.5f32, 0.8, 0.95];
+        let calibrated: Vec<f32> = raw.iter().map(|c| calibration.apply(*c)).collect();
+
+        assert!((calibrated[0] - 0.25).abs() < 1e-6);
+        assert!((calibrated[1] - 0.64).abs() < 1e-6);
+        assert!(calibrated.windows(2).all(|w| w[0] < w[1]));
+
+        assert_eq!(ConfidenceCalibration::Identity.apply(0.95), 0.95);
+    }
+
+    #[test]
+    fn test_confidence_calibration_piecewise() {
+        let calibration = ConfidenceCalibration::Piecewise(vec![(0.9, 0.6)]);
+        assert!((calibration.apply(0.9) - 0.6).abs() < 1e-6);
+        assert!((calibration.apply(0.95) - 0.8).abs() < 1e-6);
+        assert!(calibration.apply(0.45) < calibration.apply(0.9));
+    }
+
+    #[test]
+    fn test_confidence_calibration_piecewise_out_of_order_stays_monotonic() {
+        let mut calibration = ConfidenceCalibration::Piecewise(vec![(0.6, 0.3), (0.2, 0.8)]);
+        calibration.normalize();
+        assert_eq!(calibration, ConfidenceCalibration::Piecewise(vec![(0.2, 0.8), (0.6, 0.8)]));
+
+        // 未整理的配置在计算时同样按单调处理
+        let raw = ConfidenceCalibration::Piecewise(vec![(0.6, 0.3), (0.2, 0.8)]);
+        let samples: Vec<f32> = (0..=20).map(|i| raw.apply(i as f32 / 20.0)).collect();
+        assert!(samples.windows(2).all(|w| w[0] <= w[1]), "{:?}", samples);
+        assert!((raw.apply(0.4) - 0.8).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_remember_endpoint_mru() {
+        let mut config = AppConfig::default();
+        config.remember_endpoint("http://localhost:11434/api/generate", "qwen3:30b-a3b");
+        config.remember_endpoint("https://api.openai.com/v1/chat/completions", "gpt-4o-mini");
+        config.remember_endpoint(" http://localhost:11434/api/generate ", "qwen3:30b-a3b");
+        config.remember_endpoint("", "ignored");
+
+        // 重复项移到最前且不重复出现
+        assert_eq!(config.recent_endpoints.len(), 2);
+        assert_eq!(config.recent_endpoints[0].endpoint, "http://localhost:11434/api/generate");
+        assert_eq!(config.recent_endpoints[1].model_name, "gpt-4o-mini");
+
+        for i in 0..20 {
+            config.remember_endpoint("http://localhost:8080/v1/chat/completions", &format!("model-{}", i));
+        }
+        assert_eq!(config.recent_endpoints.len(), AppConfig::MAX_RECENT_ENDPOINTS);
+        assert_eq!(config.recent_endpoints[0].model_name, "model-19");
+    }
+
+    #[test]
+    fn test_rule_condition_scope_roots() {
+        let condition = RuleCondition {
+            file_extensions: vec![".jpg".to_string()],
+            scope_roots: vec!["Camera".to_string()],
+            ..Default::default()
+        };
+
+        let make = |rel: &str| {
+            let mut file = FileDescriptor::new(
+                PathBuf::from("/scan").join(rel),
+                "photo.jpg".to_string(),
+                ".jpg".to_string(),
+                1024,
+                Utc::now(),
+                false,
+            );
+            file.relative_path = PathBuf::from(rel);
+            file
+        };
+
+        assert!(condition.matches(&make("Camera/2024/photo.jpg")));
+        assert!(condition.matches(&make("camera/photo.jpg")));
+        assert!(!condition.matches(&make("Downloads/photo.jpg")));
+        // 扫描根下的文件不属于任何一级目录
+        assert!(!condition.matches(&make("photo.jpg")));
+    }
+
+    #[test]
+    fn test_column_widths_serde() {
+        let mut config = AppConfig::default();
+        config.preview_column_widths.target = 512.0;
+
+        let json = serde_json::to_string(&config).unwrap();
+        let loaded: AppConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.preview_column_widths.target, 512.0);
+        assert_eq!(loaded.preview_column_widths, config.preview_column_widths);
+
+        // 旧配置没有列宽字段，缺少的列使用默认值
+        let partial: ColumnWidths = serde_json::from_str(r#"{"name": 150.0}"#).unwrap();
+        assert_eq!(partial.name, 150.0);
+        assert_eq!(partial.source, ColumnWidths::default().source);
+        assert_eq!(ColumnWidths::from_array(partial.to_array()), partial);
+    }
+
+    #[test]
+    fn test_window_state_roundtrip() {
+        let config = AppConfig {
+            window: WindowState {
+                inner_size: Some([1440.0, 900.0]),
+                position: Some([10.0, 20.0]),
+                maximized: false,
+                show_rule_panel: true,
+                show_history_panel: true,
+            },
+            ..AppConfig::default()
+        };
+
+        let json = serde_json::to_string(&config).unwrap();
+        let loaded: AppConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.window, config.window);
+
+        // 旧配置没有窗口状态时面板默认隐藏
+        let partial: WindowState = serde_json::from_str(r#"{"show_rule_panel": true}"#).unwrap();
+        assert!(partial.show_rule_panel);
+        assert!(!partial.show_history_panel);
+        assert_eq!(partial.inner_size, None);
+    }
+
+    #[test]
+    fn test_explain_match_near_miss() {
+        let condition = RuleCondition {
+            file_extensions: vec![".pdf".to_string()],
+            filename_keywords: vec!["invoice".to_string(), "发票".to_string()],
+            max_size: Some(10 * 1024 * 1024),
+            ..Default::default()
+        };
+        let file = FileDescriptor::new(
+            PathBuf::from("/in/scan_0042.pdf"),
+            "scan_0042.pdf".to_string(),
+            ".pdf".to_string(),
+            2048,
+            Utc::now(),
+            false,
+        );
+
+        let explanation = condition.explain_match(&file);
+        assert_eq!(
+            explanation.checks,
+            vec![
+                ConditionCheck { field: ConditionField::Extension, passed: true },
+                ConditionCheck { field: ConditionField::Keywords, passed: false },
+                ConditionCheck { field: ConditionField::MaxSize, passed: true },
+            ]
+        );
+        assert!(!explanation.is_match());
+        assert!(!condition.matches(&file));
+        assert_eq!(explanation.summary(), "扩展名、最大大小匹配，但文件名关键词未匹配");
+        assert!((explanation.closeness() - 2.0 / 3.0).abs() < 1e-6);
+    }
 }
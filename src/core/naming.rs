@@ -0,0 +1,108 @@
+//! 文件名规范化模块
+//!
+//! 不依赖AI的确定性重命名：在移动的同时按策略改写目标文件名
+//! （小写、空格替换为下划线、补上识别出的年份前缀）。
+//! 重命名只作用于移动计划的目标路径，回滚时按原路径移回，自然恢复原名。
+
+use crate::core::models::FileDescriptor;
+use crate::core::semantic::extract_year_from_filename;
+use serde::{Deserialize, Serialize};
+
+/// 文件名规范化策略（默认全部关闭，即保持原名）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NamingPolicy {
+    /// 转为小写
+    pub lowercase: bool,
+    /// 空白字符替换为下划线（连续空白合并为一个）
+    pub replace_spaces: bool,
+    /// 文件名中没有年份时，加上识别出的年份前缀（如 2023_report.pdf）
+    pub prefix_year: bool,
+}
+
+impl NamingPolicy {
+    /// 是否不做任何改写
+    pub fn is_noop(&self) -> bool {
+        !self.lowercase && !self.replace_spaces && !self.prefix_year
+    }
+
+    /// 计算规范化后的文件名；目录和无需改写的文件返回原名
+    pub fn apply(&self, file: &FileDescriptor) -> String {
+        if self.is_noop() || file.is_directory {
+            return file.name.clone();
+        }
+
+        let (stem, extension) = match file.name.len().checked_sub(file.extension.len()) {
+            Some(split)
+                if !file.extension.is_empty()
+                    && split > 0
+                    && file.name.is_char_boundary(split)
+                    && file.name[split..].eq_ignore_ascii_case(&file.extension) =>
+            {
+                file.name.split_at(split)
+            }
+            _ => (file.name.as_str(), ""),
+        };
+
+        let mut stem = stem.to_string();
+        let mut extension = extension.to_string();
+
+        if self.replace_spaces {
+            stem = stem.split_whitespace().collect::<Vec<_>>().join("_");
+        }
+        if self.lowercase {
+            stem = stem.to_lowercase();
+            extension = extension.to_lowercase();
+        }
+        if self.prefix_year && extract_year_from_filename(&stem).is_none() {
+            if let Some(year) = file.semantic.as_ref().and_then(|s| s.year) {
+                stem = format!("{}_{}", year, stem);
+            }
+        }
+
+        if stem.is_empty() {
+            return file.name.clone();
+        }
+        format!("{}{}", stem, extension)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::models::SemanticResult;
+    use std::path::PathBuf;
+
+    fn file(name: &str, extension: &str, year: Option<i32>) -> FileDescriptor {
+        let mut file = FileDescriptor::new(
+            PathBuf::from("/in").join(name),
+            name.to_string(),
+            extension.to_string(),
+            1,
+            chrono::Utc::now(),
+            false,
+        );
+        file.semantic = year.map(|year| SemanticResult {
+            year: Some(year),
+            ..Default::default()
+        });
+        file
+    }
+
+    #[test]
+    fn test_naming_policy_apply() {
+        let all = NamingPolicy {
+            lowercase: true,
+            replace_spaces: true,
+            prefix_year: true,
+        };
+
+        assert_eq!(all.apply(&file("My  Tax Report.PDF", ".PDF", Some(2023))), "2023_my_tax_report.pdf");
+        // 已含年份时不重复添加
+        assert_eq!(all.apply(&file("Invoice 2022.pdf", ".pdf", Some(2023))), "invoice_2022.pdf");
+        // 没有扩展名、没有识别出年份
+        assert_eq!(all.apply(&file("READ ME", "", None)), "read_me");
+        // 默认策略保持原名
+        assert_eq!(NamingPolicy::default().apply(&file("A B.txt", ".txt", None)), "A B.txt");
+    }
+}
@@ -0,0 +1,103 @@
+//! 扫描流水线模块
+//!
+//! 扫描与规则匹配重叠执行：扫描线程产出的文件描述符经通道送入匹配阶段，
+//! 不再等待整个目录扫描完成。
+//! 原子目录检测需要看到同级和子级文件，因此边界分析以"扫描根下的一级子树"
+//! 为缓冲窗口：一个窗口内的文件全部到齐后，先做边界分析再做规则匹配。
+
+use crate::core::boundary::{quick_check_atomic, BoundaryAnalyzer};
+use crate::core::models::FileDescriptor;
+use crate::core::rule_engine::RuleEngine;
+use crate::core::scanner::{accumulate_dir_sizes, FileScanner, ScanProgress, ScanResult};
+use anyhow::Result;
+use std::ffi::OsString;
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
+
+/// 流式扫描并匹配规则
+///
+/// 结果与"先完整扫描，再边界分析，再规则匹配"的批处理方式一致。
+/// 扫描根本身是原子目录（程序/项目目录）时拒绝扫描，不整理其内部结构。
+pub fn scan_and_match(
+    scanner: &FileScanner,
+    analyzer: &BoundaryAnalyzer,
+    engine: &mut RuleEngine,
+) -> Result<ScanResult> {
+    scan_and_match_with_progress(scanner, analyzer, engine, &mut |_| {})
+}
+
+/// 流式扫描并匹配规则，progress 在扫描线程上定期被调用
+///
+/// 扫描被取消时，最后一个窗口的子树可能不完整，边界分析无法可靠判断，
+/// 因此整个窗口被丢弃，只返回已完整处理的窗口。
+pub fn scan_and_match_with_progress(
+    scanner: &FileScanner,
+    analyzer: &BoundaryAnalyzer,
+    engine: &mut RuleEngine,
+    progress: &mut (dyn FnMut(ScanProgress) + Send),
+) -> Result<ScanResult> {
+    let root = scanner.root_path().to_path_buf();
+    if quick_check_atomic(&root, analyzer) {
+        return Err(anyhow::anyhow!(
+            "扫描根目录是程序/项目目录（原子目录），拒绝整理其内部结构: {}",
+            root.display()
+        ));
+    }
+    let (tx, rx) = mpsc::channel::<FileDescriptor>();
+
+    thread::scope(|scope| {
+        let producer = scope.spawn(move || scanner.scan_to_channel_with_progress(tx, progress));
+
+        let mut results = Vec::new();
+        let mut window: Vec<FileDescriptor> = Vec::new();
+        let mut window_key: Option<OsString> = None;
+
+        for descriptor in rx {
+            let key = top_level_key(&root, &descriptor.full_path);
+            if window_key.is_some() && window_key != key {
+                flush_window(&mut window, analyzer, engine, &mut results);
+            }
+            window_key = key;
+            window.push(descriptor);
+        }
+
+        let mut scanned = match producer.join() {
+            Ok(scanned) => scanned?,
+            Err(_) => return Err(anyhow::anyhow!("扫描线程异常退出")),
+        };
+        if scanned.cancelled {
+            tracing::info!("扫描已取消，丢弃未完整的窗口（{} 个条目）", window.len());
+        } else {
+            flush_window(&mut window, analyzer, engine, &mut results);
+        }
+
+        accumulate_dir_sizes(&mut results);
+        tracing::info!("流水线扫描完成，共处理 {} 个文件/目录", results.len());
+        scanned.files = results;
+        Ok(scanned)
+    })
+}
+
+/// 对一个窗口执行边界分析和规则匹配，并移入结果
+fn flush_window(
+    window: &mut Vec<FileDescriptor>,
+    analyzer: &BoundaryAnalyzer,
+    engine: &mut RuleEngine,
+    results: &mut Vec<FileDescriptor>,
+) {
+    if window.is_empty() {
+        return;
+    }
+    analyzer.analyze(window);
+    engine.match_files(window);
+    results.append(window);
+}
+
+/// 文件相对扫描根的第一级路径组件（窗口划分依据）
+fn top_level_key(root: &Path, path: &Path) -> Option<OsString> {
+    path.strip_prefix(root)
+        .ok()
+        .and_then(|rel| rel.components().next())
+        .map(|c| c.as_os_str().to_os_string())
+}
@@ -2,15 +2,54 @@
 //! 
 //! 负责整合规则引擎和AI分析结果，生成最终的移动计划。
 
-use crate::core::models::{FileDescriptor, MovePlan, MoveSuggestion, SuggestionSource};
-use std::path::PathBuf;
+use crate::core::models::{
+    DuplicateHandling, FileDescriptor, LayoutPreset, MoveOperation, MovePlan, MoveSuggestion, OperationStatus,
+    SuggestionSource,
+};
+use crate::core::naming::NamingPolicy;
+use crate::core::scanner::hash_file_content;
+use crate::core::semantic::offline_target_suggestion;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// 默认的跨设备复制速度（字节/秒），尚未实测时使用
+pub const DEFAULT_COPY_THROUGHPUT: f64 = 50.0 * 1024.0 * 1024.0;
+
+/// 同卷重命名的单次耗时估计（秒）
+const RENAME_SECS: f64 = 0.002;
+
+/// 重复文件移入的目录（相对输出目录）
+pub const DUPLICATES_DIR: &str = "Duplicates";
+
+/// 查找重复文件时先比较的文件开头字节数
+const PARTIAL_HASH_BYTES: u64 = 64 * 1024;
 
 /// 移动计划生成器
+#[derive(Clone)]
 pub struct Planner {
     /// 输出基础路径
     output_base: PathBuf,
     /// 置信度阈值
     confidence_threshold: f32,
+    /// 目标文件名规范化策略
+    naming_policy: NamingPolicy,
+    /// 是否记录源文件哈希，供执行前校验
+    verify_source_hash: bool,
+    /// 是否拒绝目标不在输出目录内的操作
+    strict_output_containment: bool,
+    /// 是否跳过云端占位文件
+    skip_cloud_placeholders: bool,
+    /// 重复文件的处理方式
+    duplicate_handling: DuplicateHandling,
+    /// 融合建议时规则置信度的权重
+    rule_weight: f32,
+    /// 融合建议时AI置信度的权重
+    ai_weight: f32,
+    /// 目录结构预设，离线推断的分类目录按它展开
+    layout_preset: Option<LayoutPreset>,
 }
 
 impl Planner {
@@ -19,9 +58,60 @@ impl Planner {
         Self {
             output_base,
             confidence_threshold,
+            naming_policy: NamingPolicy::default(),
+            verify_source_hash: false,
+            strict_output_containment: true,
+            skip_cloud_placeholders: true,
+            duplicate_handling: DuplicateHandling::default(),
+            rule_weight: 0.6,
+            ai_weight: 0.4,
+            layout_preset: None,
         }
     }
 
+    /// 设置融合规则建议和AI建议时两者置信度的权重（默认 0.6 / 0.4）
+    pub fn set_fusion_weights(&mut self, rule_weight: f32, ai_weight: f32) {
+        self.rule_weight = rule_weight;
+        self.ai_weight = ai_weight;
+    }
+
+    /// 设置目录结构预设（与规则引擎的 [`RuleEngine::apply_layout_preset`] 保持一致）
+    ///
+    /// [`RuleEngine::apply_layout_preset`]: crate::core::rule_engine::RuleEngine::apply_layout_preset
+    pub fn set_layout_preset(&mut self, preset: Option<LayoutPreset>) {
+        self.layout_preset = preset;
+    }
+
+    /// 设置置信度阈值
+    pub fn set_confidence_threshold(&mut self, threshold: f32) {
+        self.confidence_threshold = threshold;
+    }
+
+    /// 设置目标文件名规范化策略
+    pub fn set_naming_policy(&mut self, policy: NamingPolicy) {
+        self.naming_policy = policy;
+    }
+
+    /// 设置是否在计划中记录源文件哈希
+    pub fn set_verify_source_hash(&mut self, enabled: bool) {
+        self.verify_source_hash = enabled;
+    }
+
+    /// 设置是否拒绝目标不在输出目录内的操作（默认开启）
+    pub fn set_strict_output_containment(&mut self, enabled: bool) {
+        self.strict_output_containment = enabled;
+    }
+
+    /// 设置是否跳过云端占位文件（默认跳过）
+    pub fn set_skip_cloud_placeholders(&mut self, skip: bool) {
+        self.skip_cloud_placeholders = skip;
+    }
+
+    /// 设置重复文件的处理方式（用于 [`Planner::generate_plan_dedup`]）
+    pub fn set_duplicate_handling(&mut self, handling: DuplicateHandling) {
+        self.duplicate_handling = handling;
+    }
+
     /// 设置输出基础路径
     pub fn set_output_base(&mut self, path: PathBuf) {
         self.output_base = path;
@@ -48,14 +138,20 @@ impl Planner {
                 continue;
             }
 
+            // 云端占位文件移动时会触发下载或失败
+            if file.is_cloud_placeholder && self.skip_cloud_placeholders {
+                tracing::debug!("云端占位文件已跳过: {}", file.full_path.display());
+                continue;
+            }
+
             // 跳过低置信度的建议
             if suggestion.confidence < self.confidence_threshold {
                 continue;
             }
 
-            // 只做“分类移动”，不允许改文件名：最终目标路径必须使用原文件名。
-            // suggestion.target_path 视为目录；若它看起来像“文件路径”，则取 parent 作为目录。
-            let mut target_dir = suggestion.target_path.clone();
+            // 建议本身不能改文件名：suggestion.target_path 视为目录，
+            // 若它看起来像“文件路径”，则取 parent 作为目录。文件名只由命名策略决定。
+            let mut target_dir = suggestion.resolve_target(&self.output_base);
             let leaf = target_dir
                 .file_name()
                 .and_then(|s| s.to_str())
@@ -72,22 +168,135 @@ impl Planner {
                 }
             }
 
-            let target = target_dir.join(&file.name);
+            // 原地整理时模板可能解析回文件所在目录，名称也不变时无需移动；
+            // 原子目录整体移动，保留目录名
+            let target_name = if file.is_directory {
+                file.name.clone()
+            } else {
+                self.naming_policy.apply(file)
+            };
+            if file.full_path.parent() == Some(target_dir.as_path()) && target_name == file.name {
+                tracing::debug!("目标目录即当前目录，跳过: {}", file.full_path.display());
+                continue;
+            }
+
+            let target = target_dir.join(target_name);
 
             plan.add_operation(
                 file.full_path.clone(),
                 target,
                 file.id.clone(),
             );
+
+            if self.verify_source_hash && !file.is_directory {
+                match hash_file_content(&file.full_path) {
+                    Ok(hash) => {
+                        if let Some(op) = plan.operations.last_mut() {
+                            op.source_hash = Some(hash);
+                        }
+                    }
+                    Err(e) => tracing::warn!("计算源文件哈希失败 {}: {}", file.full_path.display(), e),
+                }
+            }
         }
 
+        // 嵌套的原子目录（如项目下的 node_modules）已随外层目录整体移动
+        let moved_dirs: Vec<PathBuf> = files
+            .iter()
+            .filter(|f| f.is_directory && plan.operations.iter().any(|op| op.file_id == f.id))
+            .map(|f| f.full_path.clone())
+            .collect();
+        plan.operations.retain(|op| {
+            !moved_dirs
+                .iter()
+                .any(|dir| op.from != *dir && op.from.starts_with(dir))
+        });
+
         plan
     }
 
+    /// 生成移动计划，并找出计划中内容完全相同的文件
+    ///
+    /// 依次按大小、文件开头的 SHA256、完整 SHA256 分组；每组保留计划中的第一个正常移动，
+    /// 其余按重复文件处理方式标记为跳过或改为移到 Duplicates/。空文件和目录不参与。
+    pub fn generate_plan_dedup(&self, files: &[FileDescriptor]) -> (MovePlan, DedupReport) {
+        self.dedupe_plan(self.generate_plan(files), files)
+    }
+
+    /// 在已生成的计划中标记重复文件；操作上已记录的源文件哈希直接复用，不再读文件
+    pub fn dedupe_plan(&self, mut plan: MovePlan, files: &[FileDescriptor]) -> (MovePlan, DedupReport) {
+        let sizes: HashMap<&str, u64> = files
+            .iter()
+            .filter(|f| !f.is_directory)
+            .map(|f| (f.id.as_str(), f.size))
+            .collect();
+        let relative_dirs: HashMap<&str, &Path> = files
+            .iter()
+            .filter_map(|f| Some((f.id.as_str(), f.relative_path.parent()?)))
+            .collect();
+
+        // 按大小分组，只有同大小的才需要读文件
+        let mut by_size: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (i, op) in plan.operations.iter().enumerate() {
+            if let Some(&size) = sizes.get(op.file_id.as_str()).filter(|s| **s > 0) {
+                by_size.entry(size).or_default().push(i);
+            }
+        }
+
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        for candidates in by_size.into_values().filter(|c| c.len() > 1) {
+            let by_partial = group_by_hash(&plan, candidates, |op| partial_content_hash(&op.from));
+            for partial in by_partial.into_iter().filter(|g| g.len() > 1) {
+                groups.extend(
+                    group_by_hash(&plan, partial, |op| match &op.source_hash {
+                        Some(hash) => Ok(hash.clone()),
+                        None => hash_file_content(&op.from),
+                    })
+                        .into_iter()
+                        .filter(|g| g.len() > 1),
+                );
+            }
+        }
+        // 保持计划中的顺序，报告稳定
+        for group in &mut groups {
+            group.sort_unstable();
+        }
+        groups.sort_unstable();
+
+        let mut report = DedupReport::default();
+        for group in groups {
+            let kept = plan.operations[group[0]].from.clone();
+            let mut duplicates = Vec::new();
+            for &i in &group[1..] {
+                let op = &mut plan.operations[i];
+                match self.duplicate_handling {
+                    DuplicateHandling::Skip => {
+                        op.status = OperationStatus::Skipped;
+                        op.error = Some(format!("与 {} 内容相同，未移动", kept.display()));
+                    }
+                    DuplicateHandling::MoveToDuplicates => {
+                        // 保留相对扫描目录的子路径，不同目录下的同名重复文件不会在 Duplicates/ 中相撞
+                        if let Some(name) = op.to.file_name() {
+                            let mut to = self.output_base.join(DUPLICATES_DIR);
+                            if let Some(dir) = relative_dirs.get(op.file_id.as_str()) {
+                                to.push(dir);
+                            }
+                            op.to = to.join(name);
+                        }
+                    }
+                }
+                duplicates.push(op.from.clone());
+            }
+            report.groups.push(DuplicateGroup { kept, duplicates });
+        }
+
+        (plan, report)
+    }
+
     /// 融合规则和AI建议
     /// 
-    /// 置信度融合公式：
-    /// - rule_score × 0.6 + ai_score × 0.4
+    /// 置信度融合公式（权重见 [`Planner::set_fusion_weights`]）：
+    /// - rule_score × rule_weight + ai_score × ai_weight
     /// - uncertainty 作为降权因子
     pub fn fuse_suggestions(
         &self,
@@ -97,17 +306,20 @@ impl Planner {
         match (rule_suggestion, ai_suggestion) {
             (Some(rule), Some(ai)) => {
                 // 两者都有，进行融合
-                let rule_score = rule.confidence * 0.6;
-                let ai_score = ai.confidence * 0.4;
+                let rule_score = rule.confidence * self.rule_weight;
+                let ai_score = ai.confidence * self.ai_weight;
                 let fused_confidence = rule_score + ai_score;
 
-                // 如果路径相同，提高置信度
-                if rule.target_path == ai.target_path {
+                // 目标目录一致（一方是另一方的上级目录也算），提高置信度
+                if targets_agree(&rule.target_path, &ai.target_path) {
                     Some(MoveSuggestion {
                         target_path: rule.target_path.clone(),
                         reason: format!("规则+AI一致: {} | {}", rule.reason, ai.reason),
                         source: SuggestionSource::Rule,
                         confidence: (fused_confidence * 1.1).min(1.0),
+                        matched_rule_id: rule.matched_rule_id.clone(),
+                        rule_origin: rule.rule_origin,
+                        fused: true,
                     })
                 } else {
                     // 路径不同，选择置信度更高的
@@ -117,13 +329,19 @@ impl Planner {
                             reason: format!("规则优先: {}", rule.reason),
                             source: SuggestionSource::Rule,
                             confidence: fused_confidence,
+                            matched_rule_id: rule.matched_rule_id.clone(),
+                            rule_origin: rule.rule_origin,
+                            fused: true,
                         })
                     } else {
                         Some(MoveSuggestion {
                             target_path: ai.target_path.clone(),
-                            reason: format!("AI建议: {}", ai.reason),
-                            source: SuggestionSource::AI,
+                            reason: format!("{}建议: {}", ai.source, ai.reason),
+                            source: ai.source,
                             confidence: fused_confidence,
+                            matched_rule_id: None,
+                            rule_origin: None,
+                            fused: true,
                         })
                     }
                 }
@@ -134,11 +352,48 @@ impl Planner {
         }
     }
 
+    /// 语义分析完成后，为每个文件融合规则建议和模型给出的路径建议，返回两者都有的文件数
+    ///
+    /// 已有的规则建议直接参与融合，没有时用 rule_for 匹配规则；模型建议由 ai_for 提供。
+    /// 规则和模型都没有建议时，按语义标签给出离线推断（不参与融合，避免固定的分类表与规则互相“否决”）。
+    /// 记忆建议和手动指定的目标来自用户，保持不变。
+    pub fn fuse_file_suggestions(
+        &self,
+        files: &mut [FileDescriptor],
+        mut rule_for: impl FnMut(&FileDescriptor) -> Option<MoveSuggestion>,
+        mut ai_for: impl FnMut(&FileDescriptor) -> Option<MoveSuggestion>,
+    ) -> usize {
+        let mut fused = 0;
+        for file in files.iter_mut().filter(|f| !f.atomic && !f.is_directory) {
+            let rule = match file.suggested_action.take() {
+                Some(s) if matches!(s.source, SuggestionSource::Memory | SuggestionSource::Manual) => {
+                    file.suggested_action = Some(s);
+                    continue;
+                }
+                Some(s) if s.source == SuggestionSource::Rule && !s.fused => Some(s),
+                _ => rule_for(file),
+            };
+            let ai = match (&rule, ai_for(file)) {
+                (None, None) => offline_target_suggestion(file, self.layout_preset),
+                (_, ai) => ai,
+            };
+            if rule.is_some() && ai.is_some() {
+                fused += 1;
+            }
+            file.suggested_action = self.fuse_suggestions(rule.as_ref(), ai.as_ref());
+        }
+        fused
+    }
+
     /// 验证移动计划
     pub fn validate_plan(&self, plan: &MovePlan) -> Vec<PlanValidationError> {
         let mut errors = Vec::new();
 
         for (i, op) in plan.operations.iter().enumerate() {
+            if op.status == OperationStatus::Skipped {
+                continue;
+            }
+
             // 检查源文件是否存在
             if !op.from.exists() {
                 errors.push(PlanValidationError {
@@ -157,9 +412,22 @@ impl Planner {
                 });
             }
 
+            // 严格模式下目标必须位于输出目录内，防止模板或AI给出的绝对路径动到系统目录
+            if self.strict_output_containment && !is_within(&op.to, &self.output_base) {
+                errors.push(PlanValidationError {
+                    operation_index: i,
+                    error_type: ValidationErrorType::OutsideOutputBase,
+                    message: format!(
+                        "目标不在输出目录 {} 内: {}",
+                        self.output_base.display(),
+                        op.to.display()
+                    ),
+                });
+            }
+
             // 检查是否有冲突（多个文件移动到同一位置）
             for (j, other_op) in plan.operations.iter().enumerate() {
-                if i != j && op.to == other_op.to {
+                if i != j && op.to == other_op.to && other_op.status != OperationStatus::Skipped {
                     errors.push(PlanValidationError {
                         operation_index: i,
                         error_type: ValidationErrorType::TargetConflict,
@@ -177,16 +445,41 @@ impl Planner {
         errors
     }
 
+    /// 从计划中移除目标超出输出目录的操作，返回对应的验证错误（严格模式关闭时不做处理）
+    pub fn reject_outside_output_base(&self, plan: &mut MovePlan) -> Vec<PlanValidationError> {
+        let rejected: Vec<PlanValidationError> = self
+            .validate_plan(plan)
+            .into_iter()
+            .filter(|e| e.error_type == ValidationErrorType::OutsideOutputBase)
+            .collect();
+        for error in rejected.iter().rev() {
+            let op = plan.operations.remove(error.operation_index);
+            tracing::warn!("已拒绝越界操作: {} -> {}", op.from.display(), op.to.display());
+        }
+        rejected
+    }
+
     /// 获取计划统计信息
     pub fn get_plan_stats(&self, plan: &MovePlan) -> PlanStats {
-        let total_operations = plan.operations.len();
+        // 生成时已标记跳过的操作（如重复文件）不计入
+        let pending = || {
+            plan.operations
+                .iter()
+                .filter(|op| op.status != OperationStatus::Skipped)
+        };
+        let total_operations = pending().count();
         
         let mut total_size: u64 = 0;
+        let mut cross_device_bytes: u64 = 0;
+        let mut cross_device_operations = 0;
         let mut target_dirs = std::collections::HashSet::new();
 
-        for op in &plan.operations {
-            if let Ok(metadata) = std::fs::metadata(&op.from) {
-                total_size += metadata.len();
+        for op in pending() {
+            let size = std::fs::metadata(&op.from).map(|m| m.len()).unwrap_or(0);
+            total_size += size;
+            if !same_volume(&op.from, &op.to) {
+                cross_device_bytes += size;
+                cross_device_operations += 1;
             }
             if let Some(parent) = op.to.parent() {
                 target_dirs.insert(parent.to_path_buf());
@@ -197,10 +490,79 @@ impl Planner {
             total_operations,
             total_size,
             target_directories: target_dirs.len(),
+            cross_device_operations,
+            cross_device_bytes,
         }
     }
 }
 
+/// 判断两个路径是否位于同一卷（同卷移动只需重命名）
+///
+/// 目标路径可能尚不存在，取其最近的已存在上级目录比较。无法判断时视为同卷。
+pub fn same_volume(from: &Path, to: &Path) -> bool {
+    let existing_target = to.ancestors().find(|p| p.exists());
+    let Some(target) = existing_target else {
+        return true;
+    };
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        match (std::fs::metadata(from), std::fs::metadata(target)) {
+            (Ok(a), Ok(b)) => a.dev() == b.dev(),
+            _ => true,
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        use std::path::Component;
+        let prefix = |p: &Path| match p.components().next() {
+            Some(Component::Prefix(prefix)) => {
+                Some(prefix.as_os_str().to_string_lossy().to_lowercase())
+            }
+            _ => None,
+        };
+        prefix(from) == prefix(target)
+    }
+}
+
+/// 查询 path 所在卷的可用空间（字节）
+///
+/// 路径可能尚不存在，取其最近的已存在上级目录查询。无法查询时返回 None。
+pub fn available_space(path: &Path) -> Option<u64> {
+    let existing = path.ancestors().find(|p| p.exists())?;
+    match fs4::available_space(existing) {
+        Ok(bytes) => Some(bytes),
+        Err(e) => {
+            tracing::debug!("查询可用空间失败 {}: {}", existing.display(), e);
+            None
+        }
+    }
+}
+
+/// 词法上判断 path 是否位于 base 之内（处理 `..`，不访问文件系统）
+fn is_within(path: &Path, base: &Path) -> bool {
+    lexical_normalize(path).starts_with(lexical_normalize(base))
+}
+
+/// 去掉路径中的 `.` 并按 `..` 回退上一级
+fn lexical_normalize(path: &Path) -> PathBuf {
+    use std::path::Component;
+
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
 /// 计划验证错误
 #[derive(Debug)]
 pub struct PlanValidationError {
@@ -212,8 +574,68 @@ pub struct PlanValidationError {
     pub message: String,
 }
 
+/// 一组内容相同的文件
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateGroup {
+    /// 正常移动的文件
+    pub kept: PathBuf,
+    /// 内容与之相同的其余文件
+    pub duplicates: Vec<PathBuf>,
+}
+
+/// 计划去重报告
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DedupReport {
+    /// 重复文件组
+    pub groups: Vec<DuplicateGroup>,
+}
+
+impl DedupReport {
+    /// 被去重的文件数（不含每组保留的那个）
+    pub fn duplicate_count(&self) -> usize {
+        self.groups.iter().map(|g| g.duplicates.len()).sum()
+    }
+
+    /// 一行摘要
+    pub fn summary(&self) -> String {
+        format!(
+            "发现 {} 组重复文件，去重 {} 个",
+            self.groups.len(),
+            self.duplicate_count()
+        )
+    }
+}
+
+/// 按哈希把操作下标分组（读取失败的文件不参与）
+fn group_by_hash(
+    plan: &MovePlan,
+    indices: Vec<usize>,
+    hash: impl Fn(&MoveOperation) -> Result<String>,
+) -> Vec<Vec<usize>> {
+    let mut groups: HashMap<String, Vec<usize>> = HashMap::new();
+    for i in indices {
+        let op = &plan.operations[i];
+        match hash(op) {
+            Ok(h) => groups.entry(h).or_default().push(i),
+            Err(e) => tracing::warn!("计算文件哈希失败 {}: {}", op.from.display(), e),
+        }
+    }
+    groups.into_values().collect()
+}
+
+/// 文件开头 PARTIAL_HASH_BYTES 字节的 SHA256
+pub(crate) fn partial_content_hash(path: &Path) -> Result<String> {
+    use sha2::{Digest, Sha256};
+
+    let mut buffer = Vec::new();
+    std::fs::File::open(path)?
+        .take(PARTIAL_HASH_BYTES)
+        .read_to_end(&mut buffer)?;
+    Ok(hex::encode(Sha256::digest(&buffer)))
+}
+
 /// 验证错误类型
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ValidationErrorType {
     /// 源文件不存在
     SourceNotFound,
@@ -223,6 +645,20 @@ pub enum ValidationErrorType {
     TargetConflict,
     /// 权限不足
     PermissionDenied,
+    /// 目标不在输出目录内
+    OutsideOutputBase,
+}
+
+impl ValidationErrorType {
+    /// 是否需要用户处理后才能执行（源文件丢失和越界目标在执行时会被跳过或已被剔除，只作提示）
+    pub fn is_blocking(&self) -> bool {
+        matches!(
+            self,
+            ValidationErrorType::CircularPath
+                | ValidationErrorType::TargetConflict
+                | ValidationErrorType::PermissionDenied
+        )
+    }
 }
 
 /// 计划统计信息
@@ -234,22 +670,67 @@ pub struct PlanStats {
     pub total_size: u64,
     /// 目标目录数
     pub target_directories: usize,
+    /// 需要跨设备复制的操作数
+    pub cross_device_operations: usize,
+    /// 需要跨设备复制的字节数
+    pub cross_device_bytes: u64,
 }
 
 impl PlanStats {
-    /// 格式化文件大小
-    pub fn format_size(&self) -> String {
-        let size = self.total_size as f64;
-        if size < 1024.0 {
-            format!("{} B", self.total_size)
-        } else if size < 1024.0 * 1024.0 {
-            format!("{:.2} KB", size / 1024.0)
-        } else if size < 1024.0 * 1024.0 * 1024.0 {
-            format!("{:.2} MB", size / (1024.0 * 1024.0))
+    /// 估算执行耗时：同卷重命名几乎瞬间完成，跨设备按复制速度计算
+    pub fn estimate_duration(&self, copy_throughput: f64) -> Duration {
+        let renames = self.total_operations.saturating_sub(self.cross_device_operations);
+        let mut secs = renames as f64 * RENAME_SECS;
+        if copy_throughput > 0.0 {
+            secs += self.cross_device_bytes as f64 / copy_throughput;
+        }
+        Duration::from_secs_f64(secs)
+    }
+
+    /// 格式化耗时估计
+    pub fn format_duration(duration: Duration) -> String {
+        let secs = duration.as_secs();
+        if secs < 1 {
+            "不到 1 秒".to_string()
+        } else if secs < 60 {
+            format!("约 {} 秒", secs)
+        } else if secs < 3600 {
+            format!("约 {} 分 {} 秒", secs / 60, secs % 60)
         } else {
-            format!("{:.2} GB", size / (1024.0 * 1024.0 * 1024.0))
+            format!("约 {} 小时 {} 分", secs / 3600, secs % 3600 / 60)
         }
     }
+
+    /// 格式化文件大小
+    pub fn format_size(&self) -> String {
+        format_bytes(self.total_size)
+    }
+}
+
+/// 把字节数格式化为 B / KB / MB / GB
+pub fn format_bytes(bytes: u64) -> String {
+    let size = bytes as f64;
+    if size < 1024.0 {
+        format!("{} B", bytes)
+    } else if size < 1024.0 * 1024.0 {
+        format!("{:.2} KB", size / 1024.0)
+    } else if size < 1024.0 * 1024.0 * 1024.0 {
+        format!("{:.2} MB", size / (1024.0 * 1024.0))
+    } else {
+        format!("{:.2} GB", size / (1024.0 * 1024.0 * 1024.0))
+    }
+}
+
+/// 两个目标目录是否一致：相同，或一方是另一方的上级目录（如 `Pictures/2024` 与 `Pictures/2024/05`）
+fn targets_agree(a: &Path, b: &Path) -> bool {
+    let components = |p: &Path| -> Vec<String> {
+        p.components()
+            .map(|c| c.as_os_str().to_string_lossy().to_lowercase())
+            .collect()
+    };
+    let (a, b) = (components(a), components(b));
+    let shared = a.len().min(b.len());
+    shared > 0 && a[..shared] == b[..shared]
 }
 
 #[cfg(test)]
@@ -265,6 +746,9 @@ mod tests {
             reason: "规则匹配".to_string(),
             source: SuggestionSource::Rule,
             confidence: 0.9,
+            matched_rule_id: None,
+            rule_origin: None,
+            fused: false,
         };
 
         let ai = MoveSuggestion {
@@ -272,6 +756,9 @@ mod tests {
             reason: "AI建议".to_string(),
             source: SuggestionSource::AI,
             confidence: 0.8,
+            matched_rule_id: None,
+            rule_origin: None,
+            fused: false,
         };
 
         let fused = planner.fuse_suggestions(Some(&rule), Some(&ai));
@@ -280,5 +767,356 @@ mod tests {
         let fused = fused.unwrap();
         // 路径相同应该提高置信度
         assert!(fused.confidence > 0.9);
+
+        // 按目录前缀比较：模型给出上级目录也算一致，保留规则更具体的目标
+        let photo_rule = MoveSuggestion { target_path: PathBuf::from("Pictures/2024/05"), ..rule.clone() };
+        let photo_ai = MoveSuggestion { target_path: PathBuf::from("Pictures/2024"), ..ai.clone() };
+        let fused = planner.fuse_suggestions(Some(&photo_rule), Some(&photo_ai)).unwrap();
+        assert!(fused.reason.starts_with("规则+AI一致"));
+        assert_eq!(fused.target_path, PathBuf::from("Pictures/2024/05"));
+
+        let other_ai = MoveSuggestion { target_path: PathBuf::from("Pictures/2023"), ..ai };
+        let fused = planner.fuse_suggestions(Some(&photo_rule), Some(&other_ai)).unwrap();
+        assert!(fused.reason.starts_with("规则优先"));
+    }
+
+    #[test]
+    fn test_in_place_same_dir_is_skipped() {
+        let root = PathBuf::from("/data/inbox");
+        let planner = Planner::new(root.clone(), 0.5);
+
+        let suggestion = |target: PathBuf| MoveSuggestion {
+            target_path: target,
+            reason: "规则匹配".to_string(),
+            source: SuggestionSource::Rule,
+            confidence: 0.9,
+            matched_rule_id: None,
+            rule_origin: None,
+            fused: false,
+        };
+
+        let mut stay = FileDescriptor::new(
+            root.join("report.pdf"),
+            "report.pdf".to_string(),
+            ".pdf".to_string(),
+            10,
+            chrono::Utc::now(),
+            false,
+        );
+        stay.suggested_action = Some(suggestion(root.clone()));
+
+        let mut moved = FileDescriptor::new(
+            root.join("photo.jpg"),
+            "photo.jpg".to_string(),
+            ".jpg".to_string(),
+            10,
+            chrono::Utc::now(),
+            false,
+        );
+        moved.suggested_action = Some(suggestion(root.join("Pictures")));
+
+        let plan = planner.generate_plan(&[stay, moved]);
+
+        assert_eq!(plan.operations.len(), 1);
+        assert_eq!(plan.operations[0].to, root.join("Pictures").join("photo.jpg"));
+    }
+
+    #[test]
+    fn test_estimate_duration() {
+        let stats = PlanStats {
+            total_operations: 1000,
+            total_size: 0,
+            target_directories: 3,
+            cross_device_operations: 500,
+            cross_device_bytes: 10 * 1024 * 1024 * 1024,
+        };
+
+        // 500 次重命名 × 2ms + 10 GiB ÷ 100 MiB/s
+        let estimate = stats.estimate_duration(100.0 * 1024.0 * 1024.0);
+        assert!((estimate.as_secs_f64() - (1.0 + 102.4)).abs() < 1e-6);
+        assert_eq!(PlanStats::format_duration(estimate), "约 1 分 43 秒");
+
+        let same_volume_only = PlanStats {
+            cross_device_operations: 0,
+            cross_device_bytes: 0,
+            ..stats
+        };
+        assert_eq!(
+            PlanStats::format_duration(same_volume_only.estimate_duration(DEFAULT_COPY_THROUGHPUT)),
+            "约 2 秒"
+        );
+    }
+
+    #[test]
+    fn test_targets_outside_output_base_rejected() {
+        let mut planner = Planner::new(PathBuf::from("/output"), 0.5);
+        let mut plan = MovePlan::new();
+        plan.add_operation(PathBuf::from("/in/a.txt"), PathBuf::from("/output/Documents/a.txt"), "a".to_string());
+        // 模板写成了绝对路径
+        plan.add_operation(PathBuf::from("/in/b.dll"), PathBuf::from("/Windows/System32/b.dll"), "b".to_string());
+        // 借 .. 逃出输出目录
+        plan.add_operation(PathBuf::from("/in/c.txt"), PathBuf::from("/output/../etc/c.txt"), "c".to_string());
+
+        let errors: Vec<_> = planner
+            .validate_plan(&plan)
+            .into_iter()
+            .filter(|e| e.error_type == ValidationErrorType::OutsideOutputBase)
+            .collect();
+        assert_eq!(errors.iter().map(|e| e.operation_index).collect::<Vec<_>>(), vec![1, 2]);
+
+        let rejected = planner.reject_outside_output_base(&mut plan);
+        assert_eq!(rejected.len(), 2);
+        assert_eq!(plan.operations.len(), 1);
+        assert_eq!(plan.operations[0].file_id, "a");
+
+        // 关闭安全模式后不再拦截
+        planner.set_strict_output_containment(false);
+        let mut plan = MovePlan::new();
+        plan.add_operation(PathBuf::from("/in/b.dll"), PathBuf::from("/Windows/b.dll"), "b".to_string());
+        assert!(planner.reject_outside_output_base(&mut plan).is_empty());
+        assert_eq!(plan.operations.len(), 1);
+    }
+
+    #[test]
+    fn test_cloud_placeholder_excluded_from_plan() {
+        let suggest = |file: &mut FileDescriptor| {
+            file.suggested_action = Some(MoveSuggestion {
+                target_path: PathBuf::from("/output/Documents"),
+                reason: String::new(),
+                source: SuggestionSource::Rule,
+                confidence: 0.9,
+                matched_rule_id: None,
+                rule_origin: None,
+                fused: false,
+            });
+        };
+        let mut local = FileDescriptor::new(
+            PathBuf::from("/in/local.pdf"),
+            "local.pdf".to_string(),
+            ".pdf".to_string(),
+            1024,
+            chrono::Utc::now(),
+            false,
+        );
+        let mut online = FileDescriptor::new(
+            PathBuf::from("/in/online.pdf"),
+            "online.pdf".to_string(),
+            ".pdf".to_string(),
+            1024,
+            chrono::Utc::now(),
+            false,
+        );
+        suggest(&mut local);
+        suggest(&mut online);
+        online.is_cloud_placeholder = true;
+        let files = vec![local, online];
+
+        let mut planner = Planner::new(PathBuf::from("/output"), 0.5);
+        let plan = planner.generate_plan(&files);
+        assert_eq!(plan.operations.len(), 1);
+        assert_eq!(plan.operations[0].file_id, files[0].id);
+
+        planner.set_skip_cloud_placeholders(false);
+        assert_eq!(planner.generate_plan(&files).operations.len(), 2);
+    }
+
+    #[test]
+    fn test_output_base_chosen_after_analysis() {
+        use crate::core::rule_engine::RuleEngine;
+
+        let mut files: Vec<FileDescriptor> = ["photo.jpg", "song.mp3", "report.docx"]
+            .iter()
+            .map(|name| {
+                let ext = Path::new(name).extension().unwrap().to_string_lossy();
+                FileDescriptor::new(
+                    PathBuf::from("/inbox").join(name),
+                    name.to_string(),
+                    format!(".{}", ext),
+                    10,
+                    chrono::Utc::now(),
+                    false,
+                )
+            })
+            .collect();
+        // 分析阶段不知道输出目录，只得到相对分类路径
+        RuleEngine::new().match_files(&mut files);
+        assert!(files
+            .iter()
+            .all(|f| f.suggested_action.as_ref().is_some_and(|s| s.target_path.is_relative())));
+
+        let mut planner = Planner::new(PathBuf::from("/first"), 0.5);
+        let first = planner.generate_plan(&files);
+        assert_eq!(first.operations.len(), 3);
+        assert!(first.operations.iter().all(|op| op.to.starts_with("/first")));
+
+        planner.set_output_base(PathBuf::from("/second"));
+        let second = planner.generate_plan(&files);
+        assert_eq!(second.operations.len(), 3);
+        for (a, b) in first.operations.iter().zip(&second.operations) {
+            assert!(b.to.starts_with("/second"));
+            assert_eq!(
+                a.to.strip_prefix("/first").unwrap(),
+                b.to.strip_prefix("/second").unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_generate_plan_dedup_keeps_one_per_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let inbox = dir.path().join("inbox");
+        std::fs::create_dir_all(inbox.join("backup")).unwrap();
+
+        // a 与 backup/a 完全相同；c 与 a 开头 64KB 相同、结尾不同，不算重复
+        let content = vec![7u8; 70_000];
+        let mut different_tail = content.clone();
+        *different_tail.last_mut().unwrap() = 8;
+        let entries = [
+            ("a.jpg", &content),
+            ("backup/a.jpg", &content),
+            ("c.jpg", &different_tail),
+        ];
+
+        let files: Vec<FileDescriptor> = entries
+            .iter()
+            .map(|(name, bytes)| {
+                let path = inbox.join(name);
+                std::fs::write(&path, bytes).unwrap();
+                let mut file = FileDescriptor::new(
+                    path.clone(),
+                    path.file_name().unwrap().to_string_lossy().to_string(),
+                    ".jpg".to_string(),
+                    bytes.len() as u64,
+                    chrono::Utc::now(),
+                    false,
+                );
+                file.suggested_action = Some(MoveSuggestion {
+                    target_path: PathBuf::from("Pictures"),
+                    reason: String::new(),
+                    source: SuggestionSource::Rule,
+                    confidence: 0.9,
+                    matched_rule_id: None,
+                    rule_origin: None,
+                    fused: false,
+                });
+                file
+            })
+            .collect();
+
+        let output = dir.path().join("out");
+        let mut planner = Planner::new(output.clone(), 0.5);
+        let (plan, report) = planner.generate_plan_dedup(&files);
+        assert_eq!(
+            report.groups,
+            vec![DuplicateGroup {
+                kept: inbox.join("a.jpg"),
+                duplicates: vec![inbox.join("backup/a.jpg")],
+            }]
+        );
+        assert_eq!(plan.operations[0].status, OperationStatus::Pending);
+        assert_eq!(plan.operations[1].status, OperationStatus::Skipped);
+        assert!(plan.operations[1].error.as_deref().unwrap().contains("内容相同"));
+        assert_eq!(plan.operations[2].status, OperationStatus::Pending);
+        // 跳过的重复文件不计入统计，也不再报目标冲突
+        assert_eq!(planner.get_plan_stats(&plan).total_operations, 2);
+        assert!(planner.validate_plan(&plan).is_empty());
+
+        planner.set_duplicate_handling(DuplicateHandling::MoveToDuplicates);
+        let (plan, report) = planner.generate_plan_dedup(&files);
+        assert_eq!(report.duplicate_count(), 1);
+        assert_eq!(plan.operations[1].status, OperationStatus::Pending);
+        assert_eq!(plan.operations[1].to, output.join(DUPLICATES_DIR).join("a.jpg"));
+    }
+
+    #[test]
+    fn test_same_named_duplicates_keep_their_subpaths() {
+        let dir = tempfile::tempdir().unwrap();
+        let inbox = dir.path().join("inbox");
+        let files: Vec<FileDescriptor> = ["a.jpg", "x/a.jpg", "y/a.jpg"]
+            .iter()
+            .map(|relative| {
+                let path = inbox.join(relative);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(&path, b"same photo").unwrap();
+                let mut file = FileDescriptor::new(
+                    path,
+                    "a.jpg".to_string(),
+                    ".jpg".to_string(),
+                    10,
+                    chrono::Utc::now(),
+                    false,
+                );
+                file.relative_path = PathBuf::from(relative);
+                file.suggested_action = Some(MoveSuggestion {
+                    target_path: PathBuf::from("Pictures"),
+                    reason: String::new(),
+                    source: SuggestionSource::Rule,
+                    confidence: 0.9,
+                    matched_rule_id: None,
+                    rule_origin: None,
+                    fused: false,
+                });
+                file
+            })
+            .collect();
+
+        let output = dir.path().join("out");
+        let mut planner = Planner::new(output.clone(), 0.5);
+        planner.set_duplicate_handling(DuplicateHandling::MoveToDuplicates);
+        let (plan, report) = planner.generate_plan_dedup(&files);
+        assert_eq!(report.duplicate_count(), 2);
+        let targets: Vec<PathBuf> = plan.operations.iter().map(|op| op.to.clone()).collect();
+        assert_eq!(
+            targets,
+            vec![
+                output.join("Pictures/a.jpg"),
+                output.join(DUPLICATES_DIR).join("x/a.jpg"),
+                output.join(DUPLICATES_DIR).join("y/a.jpg"),
+            ]
+        );
+        assert!(planner.validate_plan(&plan).is_empty());
+    }
+
+    #[test]
+    fn test_dedupe_reuses_recorded_source_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let files: Vec<FileDescriptor> = ["a.txt", "b.txt"]
+            .iter()
+            .map(|name| {
+                let path = dir.path().join(name);
+                std::fs::write(&path, b"same").unwrap();
+                let mut file = FileDescriptor::new(
+                    path,
+                    name.to_string(),
+                    ".txt".to_string(),
+                    4,
+                    chrono::Utc::now(),
+                    false,
+                );
+                file.suggested_action = Some(MoveSuggestion {
+                    target_path: PathBuf::from("Documents"),
+                    reason: String::new(),
+                    source: SuggestionSource::Rule,
+                    confidence: 0.9,
+                    matched_rule_id: None,
+                    rule_origin: None,
+                    fused: false,
+                });
+                file
+            })
+            .collect();
+
+        let mut planner = Planner::new(dir.path().join("out"), 0.5);
+        planner.set_verify_source_hash(true);
+        let plan = planner.generate_plan(&files);
+        assert!(plan.operations.iter().all(|op| op.source_hash.is_some()));
+        let (_, report) = planner.dedupe_plan(plan.clone(), &files);
+        assert_eq!(report.duplicate_count(), 1);
+
+        // 完整哈希取自计划里记录的值，而不是重新读文件
+        let mut recorded = plan;
+        recorded.operations[1].source_hash = Some("recorded".to_string());
+        let (_, report) = planner.dedupe_plan(recorded, &files);
+        assert_eq!(report.duplicate_count(), 0);
     }
 }
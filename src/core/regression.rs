@@ -0,0 +1,255 @@
+//! 回归模拟模块
+//!
+//! 把一次扫描结果连同当时规则给出的目标路径保存为快照；修改规则或配置后，
+//! 用当前规则对快照重新匹配，与保存的预期目标逐个比较，
+//! 从而发现一次规则改动意外改变了多少文件的去向。
+//!
+//! 预期目标只来自规则匹配，不含AI、记忆或手动指定的目标：
+//! 重放时只运行规则，两边比较的是同一来源的结果。
+//!
+//! 整个过程只读快照，不访问文件系统中的原始文件。
+
+use crate::core::models::FileDescriptor;
+use crate::core::rule_engine::RuleEngine;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// 源路径 -> 目标目录（None 表示没有建议，文件保持不动）
+pub type TargetMap = BTreeMap<PathBuf, Option<PathBuf>>;
+
+/// 扫描快照
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanSnapshot {
+    /// 保存时间
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    /// 扫描到的文件（含边界分析结果）
+    pub files: Vec<FileDescriptor>,
+    /// 保存时规则给出的预期目标
+    pub targets: TargetMap,
+}
+
+impl ScanSnapshot {
+    /// 由当前扫描结果创建快照，以当前规则的匹配结果作为预期目标
+    pub fn capture(files: &[FileDescriptor], engine: &RuleEngine) -> Self {
+        Self {
+            created_at: chrono::Utc::now(),
+            files: files.to_vec(),
+            targets: collect_targets(&simulate(engine, files)),
+        }
+    }
+
+    /// 保存到文件
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// 从文件加载
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(|e| anyhow::anyhow!("快照格式无效: {}", e))
+    }
+
+    /// 用当前规则重新匹配快照，并与保存的预期目标比较
+    pub fn replay(&self, engine: &RuleEngine) -> TargetDiff {
+        diff_targets(&simulate(engine, &self.files), &self.targets)
+    }
+}
+
+/// 单个文件的目标变化
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetChange {
+    /// 源路径
+    pub source: PathBuf,
+    /// 预期目标
+    pub before: Option<PathBuf>,
+    /// 当前目标
+    pub after: Option<PathBuf>,
+}
+
+/// 目标差异
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TargetDiff {
+    /// 目标未变的文件数
+    pub unchanged: usize,
+    /// 目标发生变化的文件（按源路径排序）
+    pub changed: Vec<TargetChange>,
+    /// 快照中有、但预期里没有的文件
+    pub added: Vec<PathBuf>,
+    /// 预期里有、但快照中没有的文件
+    pub removed: Vec<PathBuf>,
+}
+
+impl TargetDiff {
+    /// 是否与预期完全一致
+    pub fn is_clean(&self) -> bool {
+        self.changed.is_empty() && self.added.is_empty() && self.removed.is_empty()
+    }
+
+    /// 获取摘要
+    pub fn summary(&self) -> String {
+        format!(
+            "{} 个文件去向改变，{} 个不变，新增 {} 个，缺少 {} 个",
+            self.changed.len(),
+            self.unchanged,
+            self.added.len(),
+            self.removed.len()
+        )
+    }
+}
+
+/// 提取文件当前的目标目录
+pub fn collect_targets(files: &[FileDescriptor]) -> TargetMap {
+    files
+        .iter()
+        .map(|f| {
+            let target = f.suggested_action.as_ref().map(|s| s.target_path.clone());
+            (f.full_path.clone(), target)
+        })
+        .collect()
+}
+
+/// 用当前规则对快照文件重新匹配
+///
+/// 在规则引擎的副本上运行，不会改动实际规则的命中计数。
+pub fn simulate(engine: &RuleEngine, files: &[FileDescriptor]) -> Vec<FileDescriptor> {
+    let mut engine = engine.clone();
+    let mut files = files.to_vec();
+    for file in &mut files {
+        file.suggested_action = None;
+    }
+    engine.match_files(&mut files);
+    files
+}
+
+/// 比较快照文件的当前目标与预期目标
+pub fn diff_targets(snapshot: &[FileDescriptor], prev_targets: &TargetMap) -> TargetDiff {
+    let current = collect_targets(snapshot);
+    let mut diff = TargetDiff::default();
+
+    for (source, after) in &current {
+        match prev_targets.get(source) {
+            Some(before) if before == after => diff.unchanged += 1,
+            Some(before) => diff.changed.push(TargetChange {
+                source: source.clone(),
+                before: before.clone(),
+                after: after.clone(),
+            }),
+            None => diff.added.push(source.clone()),
+        }
+    }
+    diff.removed = prev_targets
+        .keys()
+        .filter(|source| !current.contains_key(*source))
+        .cloned()
+        .collect();
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::models::{MoveSuggestion, SuggestionSource};
+    use chrono::Utc;
+
+    fn file_to(path: &str, target: Option<&str>) -> FileDescriptor {
+        let path = PathBuf::from(path);
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        let mut file = FileDescriptor::new(path, name, ".pdf".to_string(), 1, Utc::now(), false);
+        file.suggested_action = target.map(|t| MoveSuggestion {
+            target_path: PathBuf::from(t),
+            reason: String::new(),
+            source: SuggestionSource::Rule,
+            confidence: 0.9,
+            matched_rule_id: None,
+            rule_origin: None,
+            fused: false,
+        });
+        file
+    }
+
+    #[test]
+    fn test_diff_targets() {
+        let prev: TargetMap = [
+            ("/in/a.pdf", Some("/out/Docs")),
+            ("/in/b.pdf", Some("/out/Docs")),
+            ("/in/c.pdf", None),
+            ("/in/gone.pdf", Some("/out/Docs")),
+        ]
+        .into_iter()
+        .map(|(s, t)| (PathBuf::from(s), t.map(PathBuf::from)))
+        .collect();
+
+        let snapshot = vec![
+            file_to("/in/a.pdf", Some("/out/Docs")),
+            file_to("/in/b.pdf", Some("/out/Invoices")),
+            file_to("/in/c.pdf", Some("/out/Docs")),
+            file_to("/in/new.pdf", None),
+        ];
+
+        let diff = diff_targets(&snapshot, &prev);
+
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(
+            diff.changed,
+            vec![
+                TargetChange {
+                    source: PathBuf::from("/in/b.pdf"),
+                    before: Some(PathBuf::from("/out/Docs")),
+                    after: Some(PathBuf::from("/out/Invoices")),
+                },
+                TargetChange {
+                    source: PathBuf::from("/in/c.pdf"),
+                    before: None,
+                    after: Some(PathBuf::from("/out/Docs")),
+                },
+            ]
+        );
+        assert_eq!(diff.added, vec![PathBuf::from("/in/new.pdf")]);
+        assert_eq!(diff.removed, vec![PathBuf::from("/in/gone.pdf")]);
+        assert!(!diff.is_clean());
+    }
+
+    #[test]
+    fn test_replay_unchanged_rules_is_clean() {
+        let engine = RuleEngine::new();
+        let mut files = vec![
+            file_to("/in/report.pdf", None),
+            file_to("/in/photo.jpg", None),
+        ];
+        files[1].extension = ".jpg".to_string();
+        let mut matched = files.clone();
+        engine.clone().match_files(&mut matched);
+
+        let snapshot = ScanSnapshot::capture(&matched, &engine);
+        assert!(snapshot.replay(&engine).is_clean());
+
+        // 禁用全部规则后，所有原本有去向的文件都会报告变化
+        let mut changed_engine = engine.clone();
+        for rule in changed_engine.get_rules_mut() {
+            rule.enabled = false;
+        }
+        let diff = snapshot.replay(&changed_engine);
+        let expected = matched.iter().filter(|f| f.suggested_action.is_some()).count();
+        assert!(expected > 0);
+        assert_eq!(diff.changed.len(), expected);
+    }
+
+    #[test]
+    fn test_ai_placed_files_are_not_reported_as_changed() {
+        let engine = RuleEngine::new();
+        // 规则不匹配的文件由AI指定了目标，快照只记录规则结果
+        let mut file = file_to("/in/notes.xyz", Some("/out/AI/Notes"));
+        file.extension = ".xyz".to_string();
+        if let Some(s) = file.suggested_action.as_mut() {
+            s.source = SuggestionSource::AI;
+        }
+
+        let snapshot = ScanSnapshot::capture(&[file], &engine);
+        assert_eq!(snapshot.targets.values().next(), Some(&None));
+        assert!(snapshot.replay(&engine).is_clean());
+    }
+}
@@ -4,27 +4,156 @@
 //! 规则是用户确认后沉淀的分类逻辑，优先于AI判断。
 
 use crate::core::models::{
-    FileDescriptor, MoveSuggestion, RuleAction, RuleCondition, RuleDefinition, 
+    canonical_extensions, FileDescriptor, LayoutPreset, MatchExplanation, MoveSuggestion, RuleAction, RuleCondition, RuleDefinition,
     RuleOrigin, SuggestionSource,
 };
 use anyhow::Result;
 use chrono::Utc;
-use std::path::PathBuf;
+use std::collections::HashMap;
+
+/// 独立可执行文件隔离规则的ID（默认禁用，由配置开关控制）
+pub const INSTALLERS_RULE_ID: &str = "builtin_installers";
+/// 独立可执行文件隔离规则的名称
+pub const INSTALLERS_RULE_NAME: &str = "独立可执行文件";
+/// 按实体（公司名等）归档票据规则的ID（默认禁用，由配置开关控制）
+pub const ENTITY_RULE_ID: &str = "builtin_finance_by_entity";
+/// 开发项目目录整体移动规则的ID（默认禁用，原子目录规则的示例）
+pub const PROJECTS_RULE_ID: &str = "builtin_dev_projects";
+
+/// 规则检查警告：某条规则被更高优先级的规则完全覆盖，永远不会生效
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleLintWarning {
+    /// 不可达规则的ID
+    pub rule_id: String,
+    /// 不可达规则的名称
+    pub rule_name: String,
+    /// 遮蔽它的规则ID
+    pub shadowed_by_id: String,
+    /// 遮蔽它的规则名称
+    pub shadowed_by_name: String,
+}
+
+impl RuleLintWarning {
+    /// 面向用户的说明
+    pub fn message(&self) -> String {
+        format!(
+            "规则「{}」被更高优先级的「{}」完全覆盖，永远不会生效",
+            self.rule_name, self.shadowed_by_name
+        )
+    }
+}
+
+/// 两条启用的规则可能同时命中同一文件时，优先级高的生效
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleOverlap {
+    /// 生效的规则ID（优先级更高或排在前面）
+    pub winner_id: String,
+    /// 生效的规则名称
+    pub winner_name: String,
+    /// 被压过的规则ID
+    pub loser_id: String,
+    /// 被压过的规则名称
+    pub loser_name: String,
+    /// 被压过的规则是否被完全覆盖（永远不会生效）
+    pub shadowed: bool,
+}
+
+impl RuleOverlap {
+    /// 面向用户的说明
+    pub fn message(&self) -> String {
+        if self.shadowed {
+            format!("「{}」被「{}」完全覆盖，永远不会生效", self.loser_name, self.winner_name)
+        } else {
+            format!(
+                "「{}」与「{}」可能同时命中，重叠的文件按「{}」处理",
+                self.loser_name, self.winner_name, self.winner_name
+            )
+        }
+    }
+}
+
+/// 导入规则时遇到已有同ID用户规则的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImportStrategy {
+    /// 保留已有规则，跳过导入的同ID规则
+    #[default]
+    SkipExisting,
+    /// 用导入的规则覆盖已有的同ID规则
+    Overwrite,
+}
+
+impl ImportStrategy {
+    /// 所有处理方式（用于界面选择）
+    pub const ALL: [ImportStrategy; 2] = [ImportStrategy::SkipExisting, ImportStrategy::Overwrite];
+
+    /// 显示名称
+    pub fn label(&self) -> &'static str {
+        match self {
+            ImportStrategy::SkipExisting => "跳过已有规则",
+            ImportStrategy::Overwrite => "覆盖已有规则",
+        }
+    }
+}
+
+/// 规则导入结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    /// 新增的规则数
+    pub imported: usize,
+    /// 覆盖的已有规则数
+    pub overwritten: usize,
+    /// 跳过的规则数（已存在或为内置规则）
+    pub skipped: usize,
+    /// 新增或覆盖的规则ID（需要写入数据库）
+    pub changed_ids: Vec<String>,
+}
+
+impl ImportReport {
+    /// 一行摘要
+    pub fn summary(&self) -> String {
+        format!(
+            "导入 {} 条规则，覆盖 {} 条，跳过 {} 条",
+            self.imported, self.overwritten, self.skipped
+        )
+    }
+}
+
+/// 规则使用统计
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleUsage {
+    /// 规则ID
+    pub id: String,
+    /// 规则名称
+    pub name: String,
+    /// 命中次数
+    pub hit_count: u64,
+    /// 最后一次命中的时间
+    pub last_hit: Option<chrono::DateTime<Utc>>,
+}
 
 /// 规则引擎
+#[derive(Clone)]
 pub struct RuleEngine {
     /// 规则列表（按优先级排序）
     rules: Vec<RuleDefinition>,
-    /// 输出基础路径
-    output_base: PathBuf,
+    /// 内置规则的原始目标模板（规则ID -> 模板），用于切换/恢复目录结构预设
+    builtin_templates: HashMap<String, String>,
+}
+
+impl Default for RuleEngine {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl RuleEngine {
     /// 创建新的规则引擎
-    pub fn new(output_base: PathBuf) -> Self {
+    ///
+    /// 匹配结果是相对输出目录的分类路径，输出目录在生成计划时才确定。
+    pub fn new() -> Self {
         let mut engine = Self {
             rules: Vec::new(),
-            output_base,
+            builtin_templates: HashMap::new(),
         };
         
         // 加载内置规则
@@ -63,6 +192,7 @@ impl RuleEngine {
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
                 hit_count: 0,
+                last_hit: None,
             },
             // 视频文件规则
             RuleDefinition {
@@ -90,6 +220,7 @@ impl RuleEngine {
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
                 hit_count: 0,
+                last_hit: None,
             },
             // 音频文件规则
             RuleDefinition {
@@ -116,6 +247,7 @@ impl RuleEngine {
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
                 hit_count: 0,
+                last_hit: None,
             },
             // 文档文件规则
             RuleDefinition {
@@ -148,6 +280,7 @@ impl RuleEngine {
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
                 hit_count: 0,
+                last_hit: None,
             },
             // 压缩文件规则
             RuleDefinition {
@@ -174,6 +307,84 @@ impl RuleEngine {
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
                 hit_count: 0,
+                last_hit: None,
+            },
+            // 独立可执行文件：原子程序目录内的文件不参与匹配，这里只会命中散落的安装包
+            RuleDefinition {
+                id: INSTALLERS_RULE_ID.to_string(),
+                name: INSTALLERS_RULE_NAME.to_string(),
+                priority: 40,
+                enabled: false,
+                condition: RuleCondition {
+                    file_extensions: vec![
+                        ".exe".to_string(),
+                        ".msi".to_string(),
+                        ".scr".to_string(),
+                    ],
+                    ..Default::default()
+                },
+                action: RuleAction {
+                    move_to: "Installers".to_string(),
+                },
+                origin: RuleOrigin::BuiltIn,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                hit_count: 0,
+                last_hit: None,
+            },
+            // 按AI识别的实体归档发票/合同：只在识别到实体时生效，否则落到下面的发票规则
+            RuleDefinition {
+                id: ENTITY_RULE_ID.to_string(),
+                name: "按机构归档票据/合同".to_string(),
+                priority: 65,
+                enabled: false,
+                condition: RuleCondition {
+                    semantic_tags: vec![
+                        "invoice".to_string(),
+                        "发票".to_string(),
+                        "bill".to_string(),
+                        "账单".to_string(),
+                        "receipt".to_string(),
+                        "收据".to_string(),
+                        "contract".to_string(),
+                        "合同".to_string(),
+                    ],
+                    require_entity: true,
+                    ..Default::default()
+                },
+                action: RuleAction {
+                    move_to: "Finance/{entity}/{year}".to_string(),
+                },
+                origin: RuleOrigin::BuiltIn,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                hit_count: 0,
+                last_hit: None,
+            },
+            // 开发项目：带 node_modules / venv 的项目目录是原子目录，整个目录移到 Projects/
+            RuleDefinition {
+                id: PROJECTS_RULE_ID.to_string(),
+                name: "开发项目目录".to_string(),
+                priority: 50,
+                enabled: false,
+                condition: RuleCondition {
+                    directory_markers: vec![
+                        "package.json".to_string(),
+                        "Cargo.toml".to_string(),
+                        "pyproject.toml".to_string(),
+                        "go.mod".to_string(),
+                        ".git".to_string(),
+                    ],
+                    ..Default::default()
+                },
+                action: RuleAction {
+                    move_to: "Projects".to_string(),
+                },
+                origin: RuleOrigin::BuiltIn,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                hit_count: 0,
+                last_hit: None,
             },
             // 发票/账单规则
             RuleDefinition {
@@ -200,22 +411,67 @@ impl RuleEngine {
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
                 hit_count: 0,
+                last_hit: None,
             },
         ];
 
+        self.builtin_templates = builtin_rules
+            .iter()
+            .map(|r| (r.id.clone(), r.action.move_to.clone()))
+            .collect();
         self.rules.extend(builtin_rules);
         self.sort_rules();
     }
 
+    /// 应用输出目录结构预设
+    ///
+    /// 只改写内置规则的目标模板，用户规则保持不变；传入 None 恢复内置默认模板。
+    pub fn apply_layout_preset(&mut self, preset: Option<LayoutPreset>) {
+        for rule in self.rules.iter_mut().filter(|r| r.origin == RuleOrigin::BuiltIn) {
+            let Some(default_template) = self.builtin_templates.get(&rule.id) else {
+                continue;
+            };
+            let template = match preset {
+                Some(preset) => preset.template_for(&template_category(default_template)),
+                None => default_template.clone(),
+            };
+            if rule.action.move_to != template {
+                rule.action.move_to = template;
+                rule.updated_at = Utc::now();
+            }
+        }
+    }
+
+    /// 启用/禁用独立可执行文件隔离规则
+    pub fn set_route_loose_executables(&mut self, enabled: bool) {
+        self.set_rule_enabled(INSTALLERS_RULE_ID, enabled);
+    }
+
+    /// 启用/禁用按实体归档票据规则
+    pub fn set_organize_by_entity(&mut self, enabled: bool) {
+        self.set_rule_enabled(ENTITY_RULE_ID, enabled);
+    }
+
     /// 按优先级排序规则
     fn sort_rules(&mut self) {
-        self.rules.sort_by(|a, b| b.priority.cmp(&a.priority));
+        self.rules.sort_by_key(|r| std::cmp::Reverse(r.priority));
     }
 
-    /// 添加新规则
-    pub fn add_rule(&mut self, rule: RuleDefinition) {
+    /// 添加新规则，返回规则最终使用的ID
+    ///
+    /// 导入的规则可能与已有规则（尤其是内置规则）同ID，此时改用新ID，
+    /// 避免之后的启用/禁用、删除作用到另一条规则上。
+    pub fn add_rule(&mut self, mut rule: RuleDefinition) -> String {
+        if self.rules.iter().any(|r| r.id == rule.id) {
+            let new_id = uuid::Uuid::new_v4().to_string();
+            tracing::warn!("规则ID冲突，已重新分配: {} -> {} ({})", rule.id, new_id, rule.name);
+            rule.id = new_id;
+        }
+        rule.condition.file_extensions = canonical_extensions(&rule.condition.file_extensions);
+        let id = rule.id.clone();
         self.rules.push(rule);
         self.sort_rules();
+        id
     }
 
     /// 删除规则
@@ -228,6 +484,22 @@ impl RuleEngine {
         }
     }
 
+    /// 批量删除用户规则，内置规则即使在列表中也保留；返回删除数量
+    pub fn remove_user_rules(&mut self, rule_ids: &[String]) -> usize {
+        let before = self.rules.len();
+        self.rules
+            .retain(|r| r.origin != RuleOrigin::UserConfirmed || !rule_ids.contains(&r.id));
+        before - self.rules.len()
+    }
+
+    /// 批量启用/禁用规则，返回实际变更的数量
+    pub fn set_rules_enabled(&mut self, rule_ids: &[String], enabled: bool) -> usize {
+        rule_ids
+            .iter()
+            .filter(|id| self.set_rule_enabled(id, enabled))
+            .count()
+    }
+
     /// 启用/禁用规则
     pub fn set_rule_enabled(&mut self, rule_id: &str, enabled: bool) -> bool {
         if let Some(rule) = self.rules.iter_mut().find(|r| r.id == rule_id) {
@@ -249,36 +521,109 @@ impl RuleEngine {
         &mut self.rules
     }
 
-    /// 为文件匹配规则
-    pub fn match_file(&mut self, file: &FileDescriptor) -> Option<MoveSuggestion> {
-        // 原子文件不参与规则匹配
-        if file.atomic {
-            return None;
+    /// 检查被遮蔽的规则：条件被排在前面的某条启用规则覆盖时，该规则不可能命中
+    pub fn lint(&self) -> Vec<RuleLintWarning> {
+        let mut warnings = Vec::new();
+        for (i, rule) in self.rules.iter().enumerate() {
+            if !rule.enabled {
+                continue;
+            }
+            let shadow = self.rules[..i]
+                .iter()
+                .find(|earlier| earlier.enabled && earlier.condition.covers(&rule.condition));
+            if let Some(earlier) = shadow {
+                warnings.push(RuleLintWarning {
+                    rule_id: rule.id.clone(),
+                    rule_name: rule.name.clone(),
+                    shadowed_by_id: earlier.id.clone(),
+                    shadowed_by_name: earlier.name.clone(),
+                });
+            }
         }
+        warnings
+    }
 
-        // 目录暂不处理
-        if file.is_directory {
-            return None;
+    /// 找出可能同时命中同一文件的启用规则对，并给出按优先级生效的一方
+    ///
+    /// 规则按优先级排列，排在前面的先匹配；结果按被压过的规则顺序排列。
+    pub fn detect_overlaps(&self) -> Vec<RuleOverlap> {
+        let enabled: Vec<&RuleDefinition> = self.rules.iter().filter(|r| r.enabled).collect();
+        let mut overlaps = Vec::new();
+        for (i, loser) in enabled.iter().enumerate() {
+            for winner in &enabled[..i] {
+                if !winner.condition.may_overlap(&loser.condition) {
+                    continue;
+                }
+                overlaps.push(RuleOverlap {
+                    winner_id: winner.id.clone(),
+                    winner_name: winner.name.clone(),
+                    loser_id: loser.id.clone(),
+                    loser_name: loser.name.clone(),
+                    shadowed: winner.condition.covers(&loser.condition),
+                });
+            }
+        }
+        overlaps
+    }
+
+    /// 规则使用统计，按命中次数从多到少排序（次数相同时保持优先级顺序）
+    pub fn usage_report(&self) -> Vec<RuleUsage> {
+        let mut report: Vec<RuleUsage> = self
+            .rules
+            .iter()
+            .map(|r| RuleUsage {
+                id: r.id.clone(),
+                name: r.name.clone(),
+                hit_count: r.hit_count,
+                last_hit: r.last_hit,
+            })
+            .collect();
+        report.sort_by_key(|u| std::cmp::Reverse(u.hit_count));
+        report
+    }
+
+    /// 为文件匹配规则，并更新命中规则的计数
+    pub fn match_file(&mut self, file: &FileDescriptor) -> Option<MoveSuggestion> {
+        let suggestion = self.suggest(file)?;
+        if let Some(rule) = self
+            .rules
+            .iter_mut()
+            .find(|r| suggestion.matched_rule_id.as_ref() == Some(&r.id))
+        {
+            let now = Utc::now();
+            rule.hit_count += 1;
+            rule.updated_at = now;
+            rule.last_hit = Some(now);
         }
+        Some(suggestion)
+    }
+
+    /// 为文件匹配规则，不更新命中计数（用于实时预览等反复计算的场景）
+    pub fn suggest(&self, file: &FileDescriptor) -> Option<MoveSuggestion> {
+        // 原子目录内的文件和普通目录不参与规则匹配，原子目录只交给目录规则整体移动
+        let as_directory = match (file.is_directory, file.atomic) {
+            (true, true) => true,
+            (false, false) => false,
+            _ => return None,
+        };
 
         // 按优先级顺序匹配规则
-        for rule in self.rules.iter_mut() {
-            if !rule.enabled {
+        for rule in self.rules.iter() {
+            if !rule.enabled || rule.condition.targets_directories() != as_directory {
                 continue;
             }
 
             if rule.condition.matches(file) {
-                // 更新命中计数
-                rule.hit_count += 1;
-                rule.updated_at = Utc::now();
-
-                let target_path = rule.action.render_path(file, &self.output_base);
+                let target_path = rule.action.render_relative(file);
                 
                 return Some(MoveSuggestion {
                     target_path,
                     reason: format!("匹配规则: {}", rule.name),
                     source: SuggestionSource::Rule,
                     confidence: 0.9, // 规则匹配的置信度固定为0.9
+                    matched_rule_id: Some(rule.id.clone()),
+                    rule_origin: Some(rule.origin),
+                    fused: false,
                 });
             }
         }
@@ -286,6 +631,48 @@ impl RuleEngine {
         None
     }
 
+    /// 诊断文件为何没有建议：逐条检查启用的规则，列出最接近的几条
+    pub fn explain_no_suggestion(&self, file: &FileDescriptor) -> Vec<String> {
+        const MAX_NEAR_MISSES: usize = 3;
+
+        if file.atomic && !file.is_directory {
+            return vec!["位于原子目录内，不参与规则匹配（随目录整体移动）".to_string()];
+        }
+        if file.is_directory && !file.atomic {
+            return vec!["普通目录不参与规则匹配，其中的文件单独整理".to_string()];
+        }
+
+        let mut lines = Vec::new();
+        let mut explained: Vec<(&RuleDefinition, MatchExplanation)> = self
+            .rules
+            .iter()
+            .filter(|r| r.enabled && r.condition.targets_directories() == file.is_directory)
+            .map(|r| (r, r.condition.explain_match(file)))
+            .collect();
+
+        if let Some((rule, _)) = explained.iter().find(|(_, e)| e.is_match()) {
+            lines.push(format!("当前规则会命中「{}」，重新扫描后即可得到建议", rule.name));
+            return lines;
+        }
+
+        lines.push(format!("已检查 {} 条启用的规则，均未匹配", explained.len()));
+        // 稳定排序：同等接近时保持规则优先级顺序
+        explained.sort_by(|a, b| b.1.closeness().total_cmp(&a.1.closeness()));
+        for (rule, explanation) in explained.iter().take(MAX_NEAR_MISSES) {
+            lines.push(format!("{}: {}", rule.name, explanation.summary()));
+        }
+
+        match file.semantic {
+            Some(ref semantic) if !semantic.tags.is_empty() => {
+                lines.push(format!("AI标签: {}", semantic.tags.join(", ")));
+            }
+            Some(_) => lines.push("AI未给出任何标签".to_string()),
+            None => lines.push("没有AI语义分析结果（AI未启用或调用失败）".to_string()),
+        }
+
+        lines
+    }
+
     /// 批量匹配文件
     pub fn match_files(&mut self, files: &mut [FileDescriptor]) {
         for file in files.iter_mut() {
@@ -296,17 +683,46 @@ impl RuleEngine {
     }
 
     /// 从JSON加载规则
-    pub fn load_from_json(&mut self, json_str: &str) -> Result<()> {
+    ///
+    /// 只加载用户规则，内置规则保留。按ID去重：已有同ID的用户规则按 strategy 跳过或覆盖，
+    /// 因此重复导入同一文件不会产生重复规则；与内置规则同ID的改用新ID导入，
+    /// 再次导入时按名称和条件找到先前导入的那条，同样跳过或覆盖。
+    pub fn load_from_json(&mut self, json_str: &str, strategy: ImportStrategy) -> Result<ImportReport> {
         let rules: Vec<RuleDefinition> = serde_json::from_str(json_str)?;
-        
-        // 只加载用户规则，保留内置规则
-        for rule in rules {
-            if rule.origin == RuleOrigin::UserConfirmed {
-                self.add_rule(rule);
+        let mut report = ImportReport::default();
+
+        for mut rule in rules {
+            if rule.origin != RuleOrigin::UserConfirmed {
+                report.skipped += 1;
+                continue;
+            }
+            rule.condition.file_extensions = canonical_extensions(&rule.condition.file_extensions);
+            let builtin_id = self
+                .rules
+                .iter()
+                .any(|r| r.id == rule.id && r.origin != RuleOrigin::UserConfirmed);
+            let existing = self.rules.iter().position(|r| {
+                r.origin == RuleOrigin::UserConfirmed
+                    && (r.id == rule.id
+                        || (builtin_id && r.name == rule.name && r.condition == rule.condition))
+            });
+            match (existing, strategy) {
+                (Some(_), ImportStrategy::SkipExisting) => report.skipped += 1,
+                (Some(pos), ImportStrategy::Overwrite) => {
+                    rule.id = self.rules[pos].id.clone();
+                    report.changed_ids.push(rule.id.clone());
+                    self.rules[pos] = rule;
+                    report.overwritten += 1;
+                }
+                (None, _) => {
+                    report.changed_ids.push(self.add_rule(rule));
+                    report.imported += 1;
+                }
             }
         }
-        
-        Ok(())
+        self.sort_rules();
+
+        Ok(report)
     }
 
     /// 导出用户规则为JSON
@@ -319,25 +735,85 @@ impl RuleEngine {
         Ok(serde_json::to_string_pretty(&user_rules)?)
     }
 
-    /// 设置输出基础路径
-    pub fn set_output_base(&mut self, path: PathBuf) {
-        self.output_base = path;
+    /// 把指定的用户规则导出为JSON（内置规则导入时会被忽略，因此不导出）
+    pub fn export_rules_to_json(&self, rule_ids: &[String]) -> Result<String> {
+        let selected: Vec<_> = self
+            .rules
+            .iter()
+            .filter(|r| r.origin == RuleOrigin::UserConfirmed && rule_ids.contains(&r.id))
+            .collect();
+        Ok(serde_json::to_string_pretty(&selected)?)
     }
+}
 
-    /// 获取输出基础路径
-    pub fn get_output_base(&self) -> &PathBuf {
-        &self.output_base
-    }
+/// 取模板中不含变量的前缀目录作为类别（如 "Finance/Invoice/{year}" -> "Finance/Invoice"）
+fn template_category(template: &str) -> String {
+    // {entity} 是分类的一部分（Finance/中国电信），预设只调整年份的位置
+    template
+        .split('/')
+        .take_while(|seg| !seg.contains('{') || *seg == "{entity}")
+        .collect::<Vec<_>>()
+        .join("/")
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use chrono::Utc;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_loose_executables_routed_when_enabled() {
+        let mut engine = RuleEngine::new();
+        let loose = FileDescriptor::new(
+            PathBuf::from("/downloads/setup.exe"),
+            "setup.exe".to_string(),
+            ".exe".to_string(),
+            1024,
+            Utc::now(),
+            false,
+        );
+        let mut inside_program = FileDescriptor::new(
+            PathBuf::from("/downloads/Tool/tool.exe"),
+            "tool.exe".to_string(),
+            ".exe".to_string(),
+            1024,
+            Utc::now(),
+            false,
+        );
+        inside_program.atomic = true;
+
+        // 默认关闭
+        assert!(engine.match_file(&loose).is_none());
+
+        engine.set_route_loose_executables(true);
+        let suggestion = engine.match_file(&loose).unwrap();
+        assert_eq!(suggestion.target_path, PathBuf::from("Installers"));
+        assert!(engine.match_file(&inside_program).is_none());
+    }
+
+    #[test]
+    fn test_explain_no_suggestion_lists_near_miss() {
+        let engine = RuleEngine::new();
+        let file = FileDescriptor::new(
+            PathBuf::from("/test/receipt.heic.bak"),
+            "receipt.heic.bak".to_string(),
+            ".bak".to_string(),
+            1024,
+            Utc::now(),
+            false,
+        );
+
+        let lines = engine.explain_no_suggestion(&file);
+        assert!(lines[0].starts_with("已检查"));
+        // 发票规则的关键词命中，只差扩展名，应排在最前
+        assert_eq!(lines[1], "发票/账单: 文件名关键词匹配，但扩展名未匹配");
+        assert!(lines.last().unwrap().contains("没有AI语义分析结果"));
+    }
 
     #[test]
     fn test_rule_matching() {
-        let mut engine = RuleEngine::new(PathBuf::from("/output"));
+        let mut engine = RuleEngine::new();
         
         let file = FileDescriptor::new(
             PathBuf::from("/test/photo.jpg"),
@@ -357,7 +833,7 @@ mod tests {
 
     #[test]
     fn test_invoice_rule_priority() {
-        let mut engine = RuleEngine::new(PathBuf::from("/output"));
+        let mut engine = RuleEngine::new();
         
         let file = FileDescriptor::new(
             PathBuf::from("/test/发票_2023.pdf"),
@@ -375,4 +851,324 @@ mod tests {
         // 发票规则优先级更高，应该匹配发票规则
         assert!(suggestion.target_path.to_string_lossy().contains("Finance"));
     }
+
+    #[test]
+    fn test_layout_preset_changes_target() {
+        let mut engine = RuleEngine::new();
+
+        let mut file = FileDescriptor::new(
+            PathBuf::from("/test/photo.jpg"),
+            "photo.jpg".to_string(),
+            ".jpg".to_string(),
+            1024,
+            Utc::now(),
+            false,
+        );
+        file.semantic = Some(crate::core::models::SemanticResult {
+            year: Some(2024),
+            ..Default::default()
+        });
+
+        engine.apply_layout_preset(Some(LayoutPreset::ByType));
+        let by_type = engine.match_file(&file).unwrap().target_path;
+        assert_eq!(by_type, PathBuf::from("Pictures"));
+
+        engine.apply_layout_preset(Some(LayoutPreset::ByYearThenType));
+        let by_year = engine.match_file(&file).unwrap().target_path;
+        assert_eq!(by_year, PathBuf::from("2024/Pictures"));
+
+        // 恢复内置默认模板
+        engine.apply_layout_preset(None);
+        let default = engine.match_file(&file).unwrap().target_path;
+        assert!(default.starts_with("Pictures/2024"));
+    }
+
+    #[test]
+    fn test_lint_detects_shadowed_rule() {
+        let mut engine = RuleEngine::new();
+        engine.rules.clear();
+
+        let mut broad = RuleDefinition::new(
+            "所有PDF".to_string(),
+            RuleCondition {
+                file_extensions: vec![".pdf".to_string()],
+                ..Default::default()
+            },
+            RuleAction { move_to: "Documents".to_string() },
+        );
+        broad.priority = 200;
+        let mut specific = RuleDefinition::new(
+            "电信账单".to_string(),
+            RuleCondition {
+                file_extensions: vec!["PDF".to_string()],
+                filename_keywords: vec!["telecom_bill".to_string()],
+                ..Default::default()
+            },
+            RuleAction { move_to: "Bills".to_string() },
+        );
+        specific.priority = 100;
+        let mut images = RuleDefinition::new(
+            "图片".to_string(),
+            RuleCondition {
+                file_extensions: vec![".jpg".to_string(), ".pdf".to_string()],
+                ..Default::default()
+            },
+            RuleAction { move_to: "Pictures".to_string() },
+        );
+        images.priority = 50;
+
+        engine.add_rule(specific.clone());
+        engine.add_rule(broad.clone());
+        engine.add_rule(images);
+
+        let warnings = engine.lint();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].rule_id, specific.id);
+        assert_eq!(warnings[0].shadowed_by_id, broad.id);
+        assert!(warnings[0].message().contains("电信账单"));
+
+        // 提高具体规则的优先级后不再被遮蔽
+        engine.get_rules_mut().iter_mut().find(|r| r.id == specific.id).unwrap().priority = 250;
+        engine.sort_rules();
+        assert!(engine.lint().is_empty());
+    }
+
+    #[test]
+    fn test_invoices_organized_by_entity() {
+        let mut engine = RuleEngine::new();
+        engine.set_organize_by_entity(true);
+
+        let invoice = |name: &str, entities: &[&str]| {
+            let mut file = FileDescriptor::new(
+                PathBuf::from("/downloads").join(name),
+                name.to_string(),
+                ".pdf".to_string(),
+                1024,
+                Utc::now(),
+                false,
+            );
+            file.semantic = Some(crate::core::models::SemanticResult {
+                tags: vec!["invoice".to_string()],
+                entities: entities.iter().map(|e| e.to_string()).collect(),
+                year: Some(2023),
+                confidence: 0.9,
+                explanation: String::new(),
+            });
+            file
+        };
+
+        let telecom = engine.match_file(&invoice("fapiao_1.pdf", &["电信"])).unwrap();
+        let mobile = engine.match_file(&invoice("invoice_2.pdf", &[" China Mobile/HK "])).unwrap();
+        assert_eq!(telecom.target_path, PathBuf::from("Finance/电信/2023"));
+        assert_eq!(mobile.target_path, PathBuf::from("Finance/China Mobile_HK/2023"));
+        assert_eq!(telecom.matched_rule_id.as_deref(), Some(ENTITY_RULE_ID));
+
+        // 未识别出实体时退回普通发票规则
+        let unknown = engine.match_file(&invoice("invoice_3.pdf", &[])).unwrap();
+        assert_eq!(unknown.target_path, PathBuf::from("Finance/Invoice/2023"));
+    }
+
+    #[test]
+    fn test_bulk_delete_only_selected_user_rules() {
+        let mut engine = RuleEngine::new();
+        let builtin_count = engine.get_rules().len();
+        let user_rule = |name: &str| {
+            RuleDefinition::new(
+                name.to_string(),
+                RuleCondition {
+                    filename_keywords: vec![name.to_string()],
+                    ..Default::default()
+                },
+                RuleAction { move_to: name.to_string() },
+            )
+        };
+        let keep = user_rule("keep");
+        let drop_a = user_rule("drop_a");
+        let drop_b = user_rule("drop_b");
+        engine.add_rule(keep.clone());
+        engine.add_rule(drop_a.clone());
+        engine.add_rule(drop_b.clone());
+
+        // 勾选中混入了内置规则，它不应被删除
+        let selected = vec![drop_a.id.clone(), drop_b.id.clone(), "builtin_images".to_string()];
+        assert_eq!(engine.remove_user_rules(&selected), 2);
+
+        let ids: Vec<&str> = engine.get_rules().iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids.len(), builtin_count + 1);
+        assert!(ids.contains(&keep.id.as_str()));
+        assert!(ids.contains(&"builtin_images"));
+
+        // 批量禁用与导出同样只作用于所选规则
+        assert_eq!(engine.set_rules_enabled(&[keep.id.clone(), "builtin_images".to_string()], false), 2);
+        assert!(!engine.get_rules().iter().find(|r| r.id == keep.id).unwrap().enabled);
+        let exported: Vec<RuleDefinition> =
+            serde_json::from_str(&engine.export_rules_to_json(std::slice::from_ref(&keep.id)).unwrap()).unwrap();
+        assert_eq!(exported.len(), 1);
+        assert_eq!(exported[0].id, keep.id);
+    }
+
+    #[test]
+    fn test_added_rule_extensions_are_canonical() {
+        let mut engine = RuleEngine::new();
+        let rule = RuleDefinition::new(
+            "照片".to_string(),
+            RuleCondition {
+                file_extensions: ["JPG", ".Jpg", " jpeg ", "", ".", "a/b"]
+                    .iter()
+                    .map(|e| e.to_string())
+                    .collect(),
+                ..Default::default()
+            },
+            RuleAction { move_to: "Photos".to_string() },
+        );
+        let id = rule.id.clone();
+        engine.add_rule(rule);
+
+        let saved = engine.get_rules().iter().find(|r| r.id == id).unwrap();
+        assert_eq!(saved.condition.file_extensions, vec![".jpg", ".jpeg"]);
+    }
+
+    #[test]
+    fn test_imported_rule_id_collision_is_renamed() {
+        let mut engine = RuleEngine::new();
+        let builtin_count = engine.get_rules().len();
+
+        let mut imported = RuleDefinition::new(
+            "我的图片".to_string(),
+            RuleCondition {
+                file_extensions: vec![".png".to_string()],
+                ..Default::default()
+            },
+            RuleAction { move_to: "Mine".to_string() },
+        );
+        imported.id = "builtin_images".to_string();
+        let json = serde_json::to_string(&vec![imported]).unwrap();
+        engine.load_from_json(&json, ImportStrategy::SkipExisting).unwrap();
+        assert_eq!(engine.get_rules().len(), builtin_count + 1);
+
+        let renamed = engine.get_rules().iter().find(|r| r.name == "我的图片").unwrap();
+        assert_ne!(renamed.id, "builtin_images");
+        let renamed_id = renamed.id.clone();
+
+        // 再次导入按名称和条件认出先前改名的规则，不再重复添加
+        let again = engine.load_from_json(&json, ImportStrategy::SkipExisting).unwrap();
+        assert_eq!((again.imported, again.skipped), (0, 1));
+        let report = engine.load_from_json(&json, ImportStrategy::Overwrite).unwrap();
+        assert_eq!(report.changed_ids, vec![renamed_id.clone()]);
+        assert_eq!(engine.get_rules().len(), builtin_count + 1);
+
+        // 操作内置ID只影响内置规则
+        assert!(engine.set_rule_enabled("builtin_images", false));
+        assert!(engine.get_rules().iter().find(|r| r.id == renamed_id).unwrap().enabled);
+        assert!(engine.remove_rule("builtin_images"));
+        assert!(engine.get_rules().iter().any(|r| r.id == renamed_id));
+    }
+
+    #[test]
+    fn test_reimport_is_idempotent_with_merge_strategy() {
+        let mut engine = RuleEngine::new();
+        let builtin_count = engine.get_rules().len();
+        let rule = |name: &str, target: &str| {
+            let mut rule = RuleDefinition::new(
+                name.to_string(),
+                RuleCondition {
+                    filename_keywords: vec!["scan".to_string()],
+                    ..Default::default()
+                },
+                RuleAction { move_to: target.to_string() },
+            );
+            rule.id = "user-scan".to_string();
+            rule
+        };
+        let json = serde_json::to_string(&vec![rule("扫描件", "Scans")]).unwrap();
+
+        let first = engine.load_from_json(&json, ImportStrategy::SkipExisting).unwrap();
+        assert_eq!((first.imported, first.skipped), (1, 0));
+        let again = engine.load_from_json(&json, ImportStrategy::SkipExisting).unwrap();
+        assert_eq!((again.imported, again.skipped), (0, 1));
+        assert_eq!(engine.get_rules().len(), builtin_count + 1);
+
+        // 覆盖时替换同ID规则，数量不变
+        let updated = serde_json::to_string(&vec![rule("扫描件", "Archive/Scans")]).unwrap();
+        let report = engine.load_from_json(&updated, ImportStrategy::Overwrite).unwrap();
+        assert_eq!((report.imported, report.overwritten, report.skipped), (0, 1, 0));
+        assert_eq!(report.changed_ids, vec!["user-scan".to_string()]);
+        assert_eq!(engine.get_rules().len(), builtin_count + 1);
+        let stored = engine.get_rules().iter().find(|r| r.id == "user-scan").unwrap();
+        assert_eq!(stored.action.move_to, "Archive/Scans");
+    }
+
+    #[test]
+    fn test_detect_overlaps_by_shared_extension() {
+        let mut engine = RuleEngine::new();
+        for id in engine.get_rules().iter().map(|r| r.id.clone()).collect::<Vec<_>>() {
+            engine.set_rule_enabled(&id, false);
+        }
+        let rule = |name: &str, priority: u8, extensions: &[&str], keywords: &[&str]| {
+            let mut rule = RuleDefinition::new(
+                name.to_string(),
+                RuleCondition {
+                    file_extensions: extensions.iter().map(|e| e.to_string()).collect(),
+                    filename_keywords: keywords.iter().map(|k| k.to_string()).collect(),
+                    ..Default::default()
+                },
+                RuleAction { move_to: name.to_string() },
+            );
+            rule.priority = priority;
+            rule
+        };
+        engine.add_rule(rule("Scans", 70, &[".pdf"], &["scan"]));
+        engine.add_rule(rule("Docs", 50, &[".pdf", ".docx"], &[]));
+        engine.add_rule(rule("Photos", 60, &[".jpg"], &[]));
+
+        let overlaps = engine.detect_overlaps();
+        assert_eq!(overlaps.len(), 1, "{:?}", overlaps);
+        assert_eq!(overlaps[0].winner_name, "Scans");
+        assert_eq!(overlaps[0].loser_name, "Docs");
+        assert!(!overlaps[0].shadowed);
+
+        // 低优先级的规则条件更窄时被完全覆盖（Scans 和 Docs 都覆盖它）
+        engine.add_rule(rule("Old scans", 40, &[".pdf"], &["scan_2019"]));
+        let shadowed: Vec<(String, String)> = engine
+            .detect_overlaps()
+            .into_iter()
+            .filter(|o| o.shadowed)
+            .map(|o| (o.winner_name, o.loser_name))
+            .collect();
+        let pair = |w: &str, l: &str| (w.to_string(), l.to_string());
+        assert_eq!(shadowed, vec![pair("Scans", "Old scans"), pair("Docs", "Old scans")]);
+    }
+
+    #[test]
+    fn test_usage_report_counts_hits() {
+        let mut engine = RuleEngine::new();
+        let file = |name: &str, ext: &str| {
+            FileDescriptor::new(
+                PathBuf::from("/inbox").join(name),
+                name.to_string(),
+                ext.to_string(),
+                1024,
+                Utc::now(),
+                false,
+            )
+        };
+        for f in [
+            file("a.jpg", ".jpg"),
+            file("b.png", ".png"),
+            file("c.jpg", ".jpg"),
+            file("song.mp3", ".mp3"),
+            file("unknown.xyz", ".xyz"),
+        ] {
+            engine.match_file(&f);
+        }
+        // 只预览不计数
+        engine.suggest(&file("d.jpg", ".jpg"));
+
+        let report = engine.usage_report();
+        assert_eq!(report.len(), engine.get_rules().len());
+        assert_eq!((report[0].id.as_str(), report[0].hit_count), ("builtin_images", 3));
+        assert_eq!((report[1].id.as_str(), report[1].hit_count), ("builtin_audio", 1));
+        assert!(report[0].last_hit.is_some());
+        assert!(report[2..].iter().all(|u| u.hit_count == 0 && u.last_hit.is_none()));
+    }
 }
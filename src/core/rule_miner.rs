@@ -0,0 +1,416 @@
+//! 规则归纳模块
+//!
+//! 把一次AI主导的整理中反复出现的分类模式归纳成候选规则，
+//! 用户批量确认后即可沉淀为持久规则，以后同类文件不必再调用模型。
+//!
+//! 聚类键为（扩展名，主标签），每个簇取出现最多的目标模板；
+//! 支持数不低于阈值的簇才会被提议。
+//!
+//! 另外，分析结束时把置信度处于阈值附近、尚无建议的文件按主标签聚类，
+//! 让用户为整簇指定一次目标（本次运行的临时规则），而不是事后逐个纠正。
+
+use crate::core::models::{
+    FileDescriptor, MoveOperation, MoveSuggestion, OperationStatus, RuleAction, RuleCondition,
+    RuleDefinition, SuggestionSource,
+};
+use std::collections::HashMap;
+use std::path::{Component, Path};
+
+/// 默认的最小支持数（同一模式至少出现几次才提议）
+pub const DEFAULT_MIN_SUPPORT: usize = 3;
+
+/// 候选规则
+#[derive(Debug, Clone)]
+pub struct ProposedRule {
+    /// 提议的规则（确认前不会加入规则引擎）
+    pub rule: RuleDefinition,
+    /// 支持该规则的文件数
+    pub support: usize,
+    /// 示例文件名
+    pub examples: Vec<String>,
+}
+
+/// 置信度在阈值上下多少范围内视为“拿不准”
+pub const BORDERLINE_MARGIN: f32 = 0.15;
+
+/// 拿不准的相似文件簇
+#[derive(Debug, Clone, PartialEq)]
+pub struct BorderlineCluster {
+    /// 共同的主标签（小写）
+    pub tag: String,
+    /// 簇内文件ID
+    pub file_ids: Vec<String>,
+    /// 示例文件名
+    pub examples: Vec<String>,
+}
+
+impl BorderlineCluster {
+    /// 用用户给出的目标模板生成本次运行的临时规则
+    pub fn to_rule(&self, move_to: &str) -> RuleDefinition {
+        let condition = RuleCondition {
+            semantic_tags: vec![self.tag.clone()],
+            ..Default::default()
+        };
+        RuleDefinition::new(
+            format!("本次临时: {}", self.tag),
+            condition,
+            RuleAction { move_to: move_to.trim().to_string() },
+        )
+    }
+}
+
+/// 本次运行临时规则给出的建议（先加入的规则优先；原子项和目录不参与）
+pub fn session_suggestion(rules: &[RuleDefinition], file: &FileDescriptor) -> Option<MoveSuggestion> {
+    if file.atomic || file.is_directory {
+        return None;
+    }
+    let rule = rules.iter().find(|r| r.enabled && r.condition.matches(file))?;
+    Some(MoveSuggestion {
+        target_path: rule.action.render_relative(file),
+        reason: format!("本次运行临时规则: {}", rule.name),
+        source: SuggestionSource::Rule,
+        confidence: 0.9,
+        matched_rule_id: Some(rule.id.clone()),
+        rule_origin: Some(rule.origin),
+        fused: false,
+    })
+}
+
+/// 把置信度接近阈值、仍无建议的文件按主标签聚类
+///
+/// 只返回至少包含 min_size 个文件的簇，按文件数降序、标签升序排列。
+pub fn cluster_borderline(
+    files: &[FileDescriptor],
+    threshold: f32,
+    margin: f32,
+    min_size: usize,
+) -> Vec<BorderlineCluster> {
+    let mut clusters: HashMap<String, Vec<&FileDescriptor>> = HashMap::new();
+
+    for file in files {
+        if file.atomic || file.is_directory || file.suggested_action.is_some() {
+            continue;
+        }
+        let Some(semantic) = file.semantic.as_ref() else {
+            continue;
+        };
+        if (semantic.confidence - threshold).abs() > margin {
+            continue;
+        }
+        let Some(tag) = semantic.tags.first() else {
+            continue;
+        };
+        clusters.entry(tag.to_lowercase()).or_default().push(file);
+    }
+
+    let mut result: Vec<BorderlineCluster> = clusters
+        .into_iter()
+        .filter(|(_, files)| files.len() >= min_size.max(1))
+        .map(|(tag, files)| BorderlineCluster {
+            tag,
+            file_ids: files.iter().map(|f| f.id.clone()).collect(),
+            examples: files.iter().take(3).map(|f| f.name.clone()).collect(),
+        })
+        .collect();
+    result.sort_by(|a, b| b.file_ids.len().cmp(&a.file_ids.len()).then_with(|| a.tag.cmp(&b.tag)));
+    result
+}
+
+/// 从已完成的移动操作中归纳候选规则
+///
+/// 只统计建议来源为AI、且操作已成功完成的文件。
+pub fn propose_rules(
+    files: &[FileDescriptor],
+    operations: &[MoveOperation],
+    output_base: &Path,
+    min_support: usize,
+) -> Vec<ProposedRule> {
+    let by_id: HashMap<&str, &FileDescriptor> = files.iter().map(|f| (f.id.as_str(), f)).collect();
+
+    // (扩展名, 主标签) -> 模板 -> 文件名列表
+    let mut clusters: HashMap<(String, String), HashMap<String, Vec<String>>> = HashMap::new();
+
+    for op in operations {
+        if op.status != OperationStatus::Completed {
+            continue;
+        }
+        let file = match by_id.get(op.file_id.as_str()) {
+            Some(f) => *f,
+            None => continue,
+        };
+        let from_ai = file
+            .suggested_action
+            .as_ref()
+            .map(|s| s.source == SuggestionSource::AI)
+            .unwrap_or(false);
+        if !from_ai || file.extension.is_empty() {
+            continue;
+        }
+
+        let template = match op.to.parent().and_then(|dir| generalize_target(dir, output_base, file)) {
+            Some(t) => t,
+            None => continue,
+        };
+        let tag = file
+            .semantic
+            .as_ref()
+            .and_then(|s| s.tags.first())
+            .map(|t| t.to_lowercase())
+            .unwrap_or_default();
+
+        clusters
+            .entry((file.extension.to_lowercase(), tag))
+            .or_default()
+            .entry(template)
+            .or_default()
+            .push(file.name.clone());
+    }
+
+    let mut proposals: Vec<ProposedRule> = clusters
+        .into_iter()
+        .filter_map(|((extension, tag), templates)| {
+            // 同一簇内取支持数最多的模板（同数时取字典序较小者，保证结果稳定）
+            let (template, names) = templates
+                .into_iter()
+                .max_by(|a, b| a.1.len().cmp(&b.1.len()).then_with(|| b.0.cmp(&a.0)))?;
+            if names.len() < min_support {
+                return None;
+            }
+
+            let name = if tag.is_empty() {
+                format!("AI归纳: {} 文件", extension)
+            } else {
+                format!("AI归纳: {} ({})", tag, extension)
+            };
+            let condition = RuleCondition {
+                file_extensions: vec![extension],
+                semantic_tags: if tag.is_empty() { Vec::new() } else { vec![tag] },
+                ..Default::default()
+            };
+            let rule = RuleDefinition::new(name, condition, RuleAction { move_to: template });
+
+            Some(ProposedRule {
+                rule,
+                support: names.len(),
+                examples: names.into_iter().take(3).collect(),
+            })
+        })
+        .collect();
+
+    proposals.sort_by(|a, b| b.support.cmp(&a.support).then_with(|| a.rule.name.cmp(&b.rule.name)));
+    proposals
+}
+
+/// 把目标目录还原为相对输出根的模板，年份/月份替换为变量
+fn generalize_target(dir: &Path, output_base: &Path, file: &FileDescriptor) -> Option<String> {
+    let relative = dir.strip_prefix(output_base).ok()?;
+
+    let year = file
+        .semantic
+        .as_ref()
+        .and_then(|s| s.year)
+        .map(|y| y.to_string())
+        .unwrap_or_else(|| file.modified_at.format("%Y").to_string());
+    let month = file.modified_at.format("%m").to_string();
+
+    let mut segments: Vec<String> = Vec::new();
+    for component in relative.components() {
+        let segment = match component {
+            Component::Normal(s) => s.to_string_lossy().to_string(),
+            _ => return None,
+        };
+        let generalized = if segment == year {
+            "{year}".to_string()
+        } else if segment == month && segments.last().map(String::as_str) == Some("{year}") {
+            "{month}".to_string()
+        } else {
+            segment
+        };
+        segments.push(generalized);
+    }
+
+    if segments.is_empty() {
+        return None;
+    }
+    Some(segments.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::models::{MoveSuggestion, SemanticResult};
+    use chrono::{TimeZone, Utc};
+    use std::path::PathBuf;
+
+    fn ai_move(
+        base: &Path,
+        name: &str,
+        tag: &str,
+        year: i32,
+        target_dir: &str,
+    ) -> (FileDescriptor, MoveOperation) {
+        let ext = format!(".{}", name.rsplit('.').next().unwrap());
+        let mut file = FileDescriptor::new(
+            PathBuf::from("/inbox").join(name),
+            name.to_string(),
+            ext,
+            100,
+            Utc.with_ymd_and_hms(year, 3, 15, 0, 0, 0).unwrap(),
+            false,
+        );
+        file.semantic = Some(SemanticResult {
+            tags: vec![tag.to_string()],
+            year: Some(year),
+            ..Default::default()
+        });
+        let to = base.join(target_dir).join(name);
+        file.suggested_action = Some(MoveSuggestion {
+            target_path: to.parent().unwrap().to_path_buf(),
+            reason: "AI".to_string(),
+            source: SuggestionSource::AI,
+            confidence: 0.9,
+            matched_rule_id: None,
+            rule_origin: None,
+            fused: false,
+        });
+        let op = MoveOperation {
+            from: file.full_path.clone(),
+            to,
+            file_id: file.id.clone(),
+            status: OperationStatus::Completed,
+            error: None,
+            source_hash: None,
+            trashed_target: false,
+        };
+        (file, op)
+    }
+
+    #[test]
+    fn test_propose_rules_from_ai_moves() {
+        let base = PathBuf::from("/out");
+        let moves = vec![
+            ai_move(&base, "bill-a.pdf", "telecom", 2022, "Bills/Telecom/2022"),
+            ai_move(&base, "bill-b.pdf", "telecom", 2023, "Bills/Telecom/2023"),
+            ai_move(&base, "bill-c.pdf", "telecom", 2023, "Bills/Telecom/2023"),
+            ai_move(&base, "shot-a.png", "screenshot", 2023, "Pictures/Screenshots/2023/03"),
+            ai_move(&base, "shot-b.png", "screenshot", 2024, "Pictures/Screenshots/2024/03"),
+            ai_move(&base, "shot-c.png", "screenshot", 2024, "Pictures/Screenshots/2024/03"),
+            // 支持数不足，不提议
+            ai_move(&base, "cv.docx", "resume", 2024, "Career"),
+        ];
+        let (files, ops): (Vec<_>, Vec<_>) = moves.into_iter().unzip();
+
+        let proposals = propose_rules(&files, &ops, &base, DEFAULT_MIN_SUPPORT);
+
+        let templates: Vec<(&str, &str)> = proposals
+            .iter()
+            .map(|p| (p.rule.condition.file_extensions[0].as_str(), p.rule.action.move_to.as_str()))
+            .collect();
+        assert_eq!(
+            templates,
+            vec![
+                (".png", "Pictures/Screenshots/{year}/{month}"),
+                (".pdf", "Bills/Telecom/{year}"),
+            ]
+        );
+        assert!(proposals.iter().all(|p| p.support == 3));
+        assert_eq!(proposals[1].rule.condition.semantic_tags, vec!["telecom".to_string()]);
+    }
+
+    #[test]
+    fn test_propose_rules_ignores_rule_and_failed_moves() {
+        let base = PathBuf::from("/out");
+        let mut moves: Vec<_> = (0..3)
+            .map(|i| ai_move(&base, &format!("f{}.txt", i), "notes", 2024, "Notes"))
+            .collect();
+        moves[0].0.suggested_action.as_mut().unwrap().source = SuggestionSource::Rule;
+        moves[1].1.status = OperationStatus::Failed;
+        let (files, ops): (Vec<_>, Vec<_>) = moves.into_iter().unzip();
+
+        assert!(propose_rules(&files, &ops, &base, 2).is_empty());
+        assert_eq!(propose_rules(&files, &ops, &base, 1).len(), 1);
+    }
+
+    #[test]
+    fn test_session_rule_covers_files_outside_cluster() {
+        let mut bill = FileDescriptor::new(
+            PathBuf::from("/inbox/bill-late.pdf"),
+            "bill-late.pdf".to_string(),
+            ".pdf".to_string(),
+            100,
+            Utc::now(),
+            false,
+        );
+        bill.semantic = Some(SemanticResult {
+            tags: vec!["bill".to_string()],
+            confidence: 0.2,
+            ..Default::default()
+        });
+        let cluster = BorderlineCluster {
+            tag: "bill".to_string(),
+            file_ids: vec!["other".to_string()],
+            examples: Vec::new(),
+        };
+        let rule = cluster.to_rule("Finance/Bills");
+
+        // 不在簇内的同类文件同样由临时规则给出建议
+        let suggestion = session_suggestion(std::slice::from_ref(&rule), &bill).unwrap();
+        assert_eq!(suggestion.target_path, PathBuf::from("Finance/Bills"));
+        assert_eq!(suggestion.matched_rule_id, Some(rule.id.clone()));
+
+        bill.atomic = true;
+        assert!(session_suggestion(&[rule], &bill).is_none());
+    }
+
+    #[test]
+    fn test_cluster_borderline_files() {
+        let make = |name: &str, tag: &str, confidence: f32| {
+            let mut file = FileDescriptor::new(
+                PathBuf::from("/inbox").join(name),
+                name.to_string(),
+                ".pdf".to_string(),
+                100,
+                Utc::now(),
+                false,
+            );
+            file.semantic = Some(SemanticResult {
+                tags: vec![tag.to_string()],
+                confidence,
+                ..Default::default()
+            });
+            file
+        };
+
+        let mut files = vec![
+            make("bill-1.pdf", "Bill", 0.65),
+            make("bill-2.pdf", "bill", 0.72),
+            make("bill-3.pdf", "bill", 0.80),
+            // 置信度远离阈值，不算拿不准
+            make("bill-4.pdf", "bill", 0.2),
+            make("contract.pdf", "contract", 0.7),
+            make("memo-1.pdf", "memo", 0.6),
+            make("memo-2.pdf", "memo", 0.75),
+        ];
+        // 已有建议的文件不参与
+        files[6].suggested_action = Some(MoveSuggestion {
+            target_path: PathBuf::from("/out/Memo"),
+            reason: String::new(),
+            source: SuggestionSource::Rule,
+            confidence: 0.9,
+            matched_rule_id: None,
+            rule_origin: None,
+            fused: false,
+        });
+
+        let clusters = cluster_borderline(&files, 0.7, BORDERLINE_MARGIN, 2);
+
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].tag, "bill");
+        assert_eq!(clusters[0].file_ids.len(), 3);
+        assert_eq!(clusters[0].examples[0], "bill-1.pdf");
+
+        let rule = clusters[0].to_rule(" Bills/{year} ");
+        assert_eq!(rule.action.move_to, "Bills/{year}");
+        assert!(rule.condition.matches(&files[1]));
+    }
+}
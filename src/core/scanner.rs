@@ -3,12 +3,109 @@
 //! 负责递归扫描指定目录，生成 FileDescriptor 列表。
 //! 此模块只做IO操作，不做任何智能判断。
 
+use crate::core::boundary::aggregate_dir_stats;
+use crate::core::executor::{FOLDER_README_NAME, TEMP_SUFFIX};
 use crate::core::models::FileDescriptor;
 use anyhow::Result;
 use chrono::{DateTime, Utc};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use rayon::prelude::*;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::sync::mpsc::Sender;
 use walkdir::WalkDir;
 
+/// 扫描警告（单个条目出错，不影响整体扫描）
+#[derive(Debug, Clone)]
+pub struct ScanWarning {
+    /// 出错的路径（无法确定时为空）
+    pub path: Option<PathBuf>,
+    /// 错误信息
+    pub message: String,
+}
+
+impl std::fmt::Display for ScanWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.path {
+            Some(ref path) => write!(f, "{}: {}", path.display(), self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl From<walkdir::Error> for ScanWarning {
+    fn from(e: walkdir::Error) -> Self {
+        Self {
+            path: e.path().map(Path::to_path_buf),
+            message: e.to_string(),
+        }
+    }
+}
+
+/// 扫描根下的 Orderly 专用忽略文件（语法同 .gitignore）
+pub const ORDERLY_IGNORE_NAME: &str = ".orderlyignore";
+
+/// Orderly 自己的配置目录和数据目录（配置、历史记录、数据库）
+///
+/// 扫描范围包含这些目录时自动排除，避免把程序自身的状态文件当成待整理文件移走。
+pub fn app_state_dirs() -> Vec<PathBuf> {
+    let Some(dirs) = directories::ProjectDirs::from("com", "orderly", "Orderly") else {
+        return Vec::new();
+    };
+    let mut paths = vec![dirs.config_dir().to_path_buf(), dirs.data_dir().to_path_buf()];
+    paths.dedup();
+    paths
+}
+
+/// 每产出多少个条目报告一次扫描进度
+pub const PROGRESS_INTERVAL: usize = 100;
+
+/// 流式扫描每攒够多少个条目交给线程池并行读取元数据
+const PARALLEL_BATCH: usize = 256;
+
+/// 扫描进度
+#[derive(Debug, Clone)]
+pub struct ScanProgress {
+    /// 已产出的文件/目录数（被排除的条目不计）
+    pub scanned: usize,
+    /// 最近处理的路径
+    pub current_path: PathBuf,
+}
+
+/// 扫描结果
+#[derive(Debug, Clone, Default)]
+pub struct ScanResult {
+    /// 扫描到的文件/目录
+    pub files: Vec<FileDescriptor>,
+    /// 扫描过程中出错的条目
+    pub warnings: Vec<ScanWarning>,
+    /// 被跳过的符号链接 / 重解析点（Windows 联接点等）
+    pub skipped_links: Vec<PathBuf>,
+    /// 系统缓存文件（.DS_Store、Thumbs.db 等），仅在开启收集时填写
+    pub os_cruft: Vec<PathBuf>,
+    /// 是否被中途取消（files 只包含取消前已完整生成的条目）
+    pub cancelled: bool,
+}
+
+impl ScanResult {
+    /// 是否带有警告
+    pub fn has_warnings(&self) -> bool {
+        !self.warnings.is_empty()
+    }
+
+    /// 合并另一个扫描结果（多个扫描根分别扫描时使用）
+    pub fn merge(&mut self, other: ScanResult) {
+        self.files.extend(other.files);
+        self.warnings.extend(other.warnings);
+        self.skipped_links.extend(other.skipped_links);
+        self.os_cruft.extend(other.os_cruft);
+        self.cancelled |= other.cancelled;
+    }
+}
+
 /// 文件扫描器
 pub struct FileScanner {
     /// 扫描根路径
@@ -19,6 +116,16 @@ pub struct FileScanner {
     max_depth: usize,
     /// 排除的目录名称
     exclude_dirs: Vec<String>,
+    /// 排除的路径（其下的所有条目都不扫描），默认包含程序自身的配置/数据目录
+    exclude_paths: Vec<PathBuf>,
+    /// 是否把符号链接 / 重解析点本身作为条目输出（始终不会深入遍历）
+    include_links: bool,
+    /// 是否单独收集系统缓存文件（收集的文件不进入整理列表）
+    collect_os_cruft: bool,
+    /// 是否遵循 .gitignore 和扫描根下的 .orderlyignore
+    respect_ignore_files: bool,
+    /// 取消标志，置位后遍历在下一个条目前停止
+    cancel: Option<Arc<AtomicBool>>,
 }
 
 impl FileScanner {
@@ -32,6 +139,11 @@ impl FileScanner {
                 "$RECYCLE.BIN".to_string(),
                 "System Volume Information".to_string(),
             ],
+            exclude_paths: app_state_dirs(),
+            include_links: false,
+            collect_os_cruft: false,
+            respect_ignore_files: true,
+            cancel: None,
         }
     }
 
@@ -53,31 +165,224 @@ impl FileScanner {
         self
     }
 
+    /// 添加排除的路径
+    pub fn exclude_path(mut self, path: PathBuf) -> Self {
+        self.exclude_paths.push(path);
+        self
+    }
+
+    /// 设置是否输出符号链接 / 重解析点条目（默认跳过并记录）
+    pub fn include_links(mut self, include: bool) -> Self {
+        self.include_links = include;
+        self
+    }
+
+    /// 设置是否单独收集系统缓存文件以便清理
+    pub fn collect_os_cruft(mut self, collect: bool) -> Self {
+        self.collect_os_cruft = collect;
+        self
+    }
+
+    /// 设置是否遵循 .gitignore / .orderlyignore（默认遵循）
+    pub fn respect_ignore_files(mut self, respect: bool) -> Self {
+        self.respect_ignore_files = respect;
+        self
+    }
+
+    /// 设置取消标志，置位后扫描停止并返回已扫描的部分结果
+    pub fn cancel_flag(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// 是否已请求取消
+    fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(|c| c.load(Ordering::Relaxed))
+    }
+
+    /// 获取扫描根路径
+    pub fn root_path(&self) -> &Path {
+        &self.root_path
+    }
+
     /// 执行扫描
     pub fn scan(&self) -> Result<Vec<FileDescriptor>> {
+        self.scan_detailed().map(|result| result.files)
+    }
+
+    /// 执行扫描，同时收集出错条目
+    ///
+    /// 目录遍历在当前线程完成，读取元数据、生成描述符分发到线程池并行执行，
+    /// 因此返回的文件顺序不固定（预览表格会重新排序）。
+    pub fn scan_detailed(&self) -> Result<ScanResult> {
+        let mut entries = Vec::new();
+        let mut result = self.walk(&mut |entry| entries.push(entry));
+
+        let descriptors: Vec<_> = entries
+            .into_par_iter()
+            .filter_map(|entry| entry.and_then(|entry| self.create_descriptor(&entry)).transpose())
+            .collect();
+        for descriptor in descriptors {
+            match descriptor {
+                Ok(descriptor) => result.files.push(descriptor),
+                Err(e) => {
+                    tracing::warn!("扫描文件时出错: {}", e);
+                    result.warnings.push(ScanWarning::from(e));
+                }
+            }
+        }
+        accumulate_dir_sizes(&mut result.files);
+
+        tracing::info!(
+            "扫描{}，共发现 {} 个文件/目录，{} 个条目出错，跳过 {} 个链接",
+            if result.cancelled { "已取消" } else { "完成" },
+            result.files.len(),
+            result.warnings.len(),
+            result.skipped_links.len()
+        );
+        Ok(result)
+    }
+
+    /// 扫描并收集结果，每产出 [`PROGRESS_INTERVAL`] 个条目在遍历线程上调用一次 progress
+    pub fn scan_with_progress(&self, mut progress: impl FnMut(ScanProgress)) -> Result<ScanResult> {
         let mut files = Vec::new();
-        
+        let mut result = self.scan_each(&mut |descriptor| files.push(descriptor), &mut progress);
+        accumulate_dir_sizes(&mut files);
+        result.files = files;
+        Ok(result)
+    }
+
+    /// 流式扫描：每产出一个文件描述符就立即发送到通道
+    ///
+    /// 接收端被关闭时不再发送，返回不含文件列表的扫描结果（警告与跳过的链接）。
+    pub fn scan_to_channel(&self, tx: Sender<FileDescriptor>) -> Result<ScanResult> {
+        self.scan_to_channel_with_progress(tx, &mut |_| {})
+    }
+
+    /// 流式扫描，同时在遍历线程上定期报告进度
+    pub fn scan_to_channel_with_progress(
+        &self,
+        tx: Sender<FileDescriptor>,
+        progress: &mut dyn FnMut(ScanProgress),
+    ) -> Result<ScanResult> {
+        let mut receiver_alive = true;
+        let result = self.scan_each(
+            &mut |descriptor| {
+                if receiver_alive && tx.send(descriptor).is_err() {
+                    receiver_alive = false;
+                }
+            },
+            progress,
+        );
+        Ok(result)
+    }
+
+    /// 遍历目录，对每个文件描述符调用 sink，返回出错条目和跳过的链接
+    ///
+    /// 遍历产出的条目每 [`PARALLEL_BATCH`] 个一批，在线程池上并行读取元数据；
+    /// sink 和 progress 仍在遍历线程上按遍历顺序调用，取消在两次 sink 之间检查。
+    fn scan_each(
+        &self,
+        sink: &mut dyn FnMut(FileDescriptor),
+        progress: &mut dyn FnMut(ScanProgress),
+    ) -> ScanResult {
+        let mut warnings = Vec::new();
+        let mut scanned = 0;
+        let mut cancelled = false;
+        let mut flush = |batch: &mut Vec<walkdir::Result<walkdir::DirEntry>>| {
+            let descriptors: Vec<_> = batch
+                .par_drain(..)
+                .map(|entry| entry.and_then(|entry| self.create_descriptor(&entry)))
+                .collect();
+            for descriptor in descriptors {
+                if self.is_cancelled() {
+                    cancelled = true;
+                    break;
+                }
+                match descriptor {
+                    Ok(Some(descriptor)) => {
+                        scanned += 1;
+                        if scanned % PROGRESS_INTERVAL == 0 {
+                            progress(ScanProgress {
+                                scanned,
+                                current_path: descriptor.full_path.clone(),
+                            });
+                        }
+                        sink(descriptor)
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        tracing::warn!("扫描文件时出错: {}", e);
+                        warnings.push(ScanWarning::from(e));
+                    }
+                }
+            }
+        };
+
+        let mut batch = Vec::with_capacity(PARALLEL_BATCH);
+        let mut result = self.walk(&mut |entry| {
+            batch.push(entry);
+            if batch.len() >= PARALLEL_BATCH {
+                flush(&mut batch);
+            }
+        });
+        flush(&mut batch);
+        result.cancelled |= cancelled;
+        result.warnings.extend(warnings);
+        result
+    }
+
+    /// 按过滤规则遍历目录，把每个条目交给 visit，返回跳过的链接和系统缓存文件
+    ///
+    /// 排除目录、隐藏文件、链接的过滤都在这里完成，串行与并行扫描共用。
+    /// 取消只在两个条目之间检查，已交给 visit 的条目总是完整的。
+    fn walk(&self, visit: &mut dyn FnMut(walkdir::Result<walkdir::DirEntry>)) -> ScanResult {
+        let mut skipped_links = Vec::new();
+        let mut os_cruft = Vec::new();
+        let mut ignores = IgnoreFiles::new(self.root_path.clone());
+
         let walker = if self.max_depth > 0 {
             WalkDir::new(&self.root_path).max_depth(self.max_depth)
         } else {
             WalkDir::new(&self.root_path)
         };
 
-        for entry in walker.into_iter().filter_entry(|e| self.should_include(e)) {
-            match entry {
-                Ok(entry) => {
-                    if let Some(descriptor) = self.create_descriptor(&entry) {
-                        files.push(descriptor);
-                    }
-                }
-                Err(e) => {
-                    tracing::warn!("扫描文件时出错: {}", e);
-                }
+        let entries = walker.into_iter().filter_entry(|e| {
+            // 链接的目标可能在扫描根之外或形成环路，不能当作本地内容整理
+            if e.depth() > 0 && !self.include_links && is_link_or_reparse_point(e) {
+                tracing::info!("跳过链接/重解析点: {}", e.path().display());
+                skipped_links.push(e.path().to_path_buf());
+                return false;
+            }
+            // 系统缓存文件多为隐藏文件，需在隐藏文件过滤之前识别
+            if self.collect_os_cruft && e.file_type().is_file() && is_os_cruft(&e.file_name().to_string_lossy()) {
+                os_cruft.push(e.path().to_path_buf());
+                return false;
             }
+            if !self.should_include(e) {
+                return false;
+            }
+            // 被忽略的目录整体不深入，与 git 一致：其中的文件无法再被 ! 规则找回
+            !(self.respect_ignore_files && e.depth() > 0 && ignores.is_ignored(e.path(), e.file_type().is_dir()))
+        });
+
+        let mut cancelled = false;
+        for entry in entries {
+            if self.is_cancelled() {
+                tracing::info!("扫描已取消: {}", self.root_path.display());
+                cancelled = true;
+                break;
+            }
+            visit(entry);
         }
 
-        tracing::info!("扫描完成，共发现 {} 个文件/目录", files.len());
-        Ok(files)
+        ScanResult {
+            files: Vec::new(),
+            warnings: Vec::new(),
+            skipped_links,
+            os_cruft,
+            cancelled,
+        }
     }
 
     /// 判断是否应该包含此条目
@@ -94,26 +399,39 @@ impl FileScanner {
             return false;
         }
 
+        // Orderly 自己写入的目录说明文件和复制中的临时文件
+        if entry.file_type().is_file() && (name == FOLDER_README_NAME || name.ends_with(TEMP_SUFFIX)) {
+            return false;
+        }
+
         // 检查排除目录
-        if entry.file_type().is_dir() {
-            if self.exclude_dirs.iter().any(|d| name.eq_ignore_ascii_case(d)) {
-                return false;
-            }
+        if entry.file_type().is_dir() && self.exclude_dirs.iter().any(|d| name.eq_ignore_ascii_case(d)) {
+            return false;
+        }
+
+        // 检查排除路径
+        if self.exclude_paths.iter().any(|p| entry.path().starts_with(p)) {
+            tracing::debug!("跳过排除路径: {}", entry.path().display());
+            return false;
         }
 
         true
     }
 
     /// 创建文件描述符
-    fn create_descriptor(&self, entry: &walkdir::DirEntry) -> Option<FileDescriptor> {
-        let metadata = entry.metadata().ok()?;
+    fn create_descriptor(
+        &self,
+        entry: &walkdir::DirEntry,
+    ) -> std::result::Result<Option<FileDescriptor>, walkdir::Error> {
         let full_path = entry.path().to_path_buf();
-        
+
         // 跳过根目录本身
         if full_path == self.root_path {
-            return None;
+            return Ok(None);
         }
 
+        let metadata = entry.metadata()?;
+
         let name = entry.file_name().to_string_lossy().to_string();
         let is_directory = metadata.is_dir();
         
@@ -131,48 +449,205 @@ impl FileScanner {
         let modified_at = metadata
             .modified()
             .ok()
-            .map(|t| DateTime::<Utc>::from(t))
+            .map(DateTime::<Utc>::from)
             .unwrap_or_else(Utc::now);
 
-        Some(FileDescriptor::new(
+        let relative_path = full_path
+            .strip_prefix(&self.root_path)
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        let mut descriptor = FileDescriptor::new(
             full_path,
             name,
             extension,
             size,
             modified_at,
             is_directory,
-        ))
+        );
+        descriptor.relative_path = relative_path;
+        descriptor.is_cloud_placeholder = !is_directory && is_cloud_placeholder(&metadata);
+        Ok(Some(descriptor))
     }
 }
 
-/// 辅助函数：获取文件的内容摘要（用于AI分析）
-pub fn get_content_summary(path: &Path, max_chars: usize) -> Result<String> {
-    use std::fs::File;
-    use std::io::{BufRead, BufReader};
+/// 自底向上把子文件大小累加到各目录描述符（递归总大小）
+///
+/// 在边界分析之前完成，原子目录的子项之后即使被隐藏，目录大小仍是其全部内容之和；
+/// 未被扫描的条目（隐藏文件、被忽略的文件）不计入。
+pub fn accumulate_dir_sizes(files: &mut [FileDescriptor]) {
+    let stats = aggregate_dir_stats(files);
+    for dir in files.iter_mut().filter(|f| f.is_directory) {
+        dir.size = stats.get(&dir.full_path).map(|s| s.total_bytes).unwrap_or(0);
+    }
+}
 
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
-    let mut content = String::new();
-    let mut chars_read = 0;
+/// 扫描过程中遇到的忽略规则
+///
+/// 每个目录的 .gitignore 只作用于该目录之下，深层规则优先于浅层规则；
+/// 扫描根的 .orderlyignore 与根目录 .gitignore 合并，且排在其后（同层冲突时生效）。
+struct IgnoreFiles {
+    /// 扫描根
+    root: PathBuf,
+    /// 目录 -> 该目录的忽略规则（没有忽略文件时为 None）
+    cache: HashMap<PathBuf, Option<Gitignore>>,
+}
 
-    for line in reader.lines() {
-        if chars_read >= max_chars {
-            break;
+impl IgnoreFiles {
+    fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            cache: HashMap::new(),
         }
-        if let Ok(line) = line {
-            let remaining = max_chars - chars_read;
-            if line.len() <= remaining {
-                content.push_str(&line);
-                content.push('\n');
-                chars_read += line.len() + 1;
-            } else {
-                content.push_str(&line[..remaining]);
-                break;
+    }
+
+    /// 从最近的目录向上查找，第一个给出结论（忽略或 ! 取反）的规则生效
+    fn is_ignored(&mut self, path: &Path, is_dir: bool) -> bool {
+        let dirs: Vec<PathBuf> = path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(&self.root))
+            .map(Path::to_path_buf)
+            .collect();
+
+        for dir in dirs {
+            let Some(matcher) = self.matcher(&dir) else {
+                continue;
+            };
+            match matcher.matched(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
             }
         }
+        false
     }
 
-    Ok(content)
+    /// 读取（并缓存）目录下的忽略规则
+    fn matcher(&mut self, dir: &Path) -> Option<&Gitignore> {
+        let is_root = dir == self.root;
+        self.cache
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| load_ignore_rules(dir, is_root))
+            .as_ref()
+    }
+}
+
+/// 读取目录下的 .gitignore（根目录还包括 .orderlyignore），都不存在时返回 None
+fn load_ignore_rules(dir: &Path, is_root: bool) -> Option<Gitignore> {
+    let mut names = vec![".gitignore"];
+    if is_root {
+        names.push(ORDERLY_IGNORE_NAME);
+    }
+
+    let mut builder = GitignoreBuilder::new(dir);
+    let mut found = false;
+    for name in names {
+        let path = dir.join(name);
+        if !path.is_file() {
+            continue;
+        }
+        found = true;
+        if let Some(e) = builder.add(&path) {
+            tracing::warn!("解析忽略文件出错 {}: {}", path.display(), e);
+        }
+    }
+    if !found {
+        return None;
+    }
+
+    match builder.build() {
+        Ok(gitignore) => Some(gitignore),
+        Err(e) => {
+            tracing::warn!("加载忽略规则失败 {}: {}", dir.display(), e);
+            None
+        }
+    }
+}
+
+/// 操作系统自动生成的缓存文件名（不区分大小写）
+pub const OS_CRUFT_NAMES: [&str; 4] = [".DS_Store", "Thumbs.db", "ehthumbs.db", "desktop.ini"];
+
+/// 判断文件名是否为系统缓存文件
+pub fn is_os_cruft(name: &str) -> bool {
+    OS_CRUFT_NAMES.iter().any(|c| c.eq_ignore_ascii_case(name))
+}
+
+/// 判断文件是否为云同步的在线占位文件（内容不在本地，访问时才下载）
+#[cfg(windows)]
+fn is_cloud_placeholder(metadata: &std::fs::Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    is_placeholder_attributes(metadata.file_attributes())
+}
+
+#[cfg(not(windows))]
+fn is_cloud_placeholder(_metadata: &std::fs::Metadata) -> bool {
+    false
+}
+
+/// Windows 文件属性是否表明内容不在本地
+#[cfg(windows)]
+fn is_placeholder_attributes(attributes: u32) -> bool {
+    const FILE_ATTRIBUTE_OFFLINE: u32 = 0x1000;
+    const FILE_ATTRIBUTE_RECALL_ON_OPEN: u32 = 0x40000;
+    const FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS: u32 = 0x400000;
+    attributes & (FILE_ATTRIBUTE_OFFLINE | FILE_ATTRIBUTE_RECALL_ON_OPEN | FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS) != 0
+}
+
+/// 判断条目是否为符号链接或重解析点（Windows 联接点、挂载点等）
+fn is_link_or_reparse_point(entry: &walkdir::DirEntry) -> bool {
+    if entry.path_is_symlink() {
+        return true;
+    }
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+        if let Ok(metadata) = std::fs::symlink_metadata(entry.path()) {
+            return metadata.file_attributes() & FILE_ATTRIBUTE_REPARSE_POINT != 0;
+        }
+    }
+
+    false
+}
+
+/// 辅助函数：按字节上限读取文件开头作为内容摘要（用于AI分析）
+///
+/// 最多读取 max_bytes 字节，不按行读取，因此单行的巨型文件（压缩过的 JSON/CSV）
+/// 也不会被整体读入内存。非 UTF-8 内容按有损方式解码，被截断的末尾字符会丢弃。
+pub fn get_content_summary_bytes(path: &Path, max_bytes: usize) -> Result<String> {
+    use std::io::Read;
+
+    let file = std::fs::File::open(path)?;
+    let mut buffer = Vec::with_capacity(max_bytes.min(64 * 1024));
+    file.take(max_bytes as u64).read_to_end(&mut buffer)?;
+
+    // 截断处可能落在多字节字符中间，去掉不完整的尾部再解码
+    let valid = match std::str::from_utf8(&buffer) {
+        Ok(_) => buffer.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(_) => buffer.len(),
+    };
+    Ok(String::from_utf8_lossy(&buffer[..valid]).into_owned())
+}
+
+/// 辅助函数：计算文件内容的 SHA-256（十六进制）
+pub fn hash_file_content(path: &Path) -> Result<String> {
+    use sha2::{Digest, Sha256};
+    use std::io::Read;
+
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
 }
 
 /// 辅助函数：获取文件类型（基于magic number）
@@ -202,4 +677,338 @@ mod tests {
         assert_eq!(files[0].name, "test.txt");
         assert_eq!(files[0].extension, ".txt");
     }
+
+    #[test]
+    fn test_hash_file_content() {
+        let dir = tempdir().unwrap();
+        let a = dir.path().join("a.bin");
+        let b = dir.path().join("b.bin");
+        fs::write(&a, "same").unwrap();
+        fs::write(&b, "same").unwrap();
+
+        assert_eq!(hash_file_content(&a).unwrap(), hash_file_content(&b).unwrap());
+        fs::write(&b, "different").unwrap();
+        assert_ne!(hash_file_content(&a).unwrap(), hash_file_content(&b).unwrap());
+    }
+
+    #[test]
+    fn test_os_cruft_collected_separately() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join(".DS_Store"), "x").unwrap();
+        fs::create_dir(dir.path().join("photos")).unwrap();
+        fs::write(dir.path().join("photos").join("thumbs.db"), "x").unwrap();
+        fs::write(dir.path().join("photos").join("desktop.ini"), "x").unwrap();
+        fs::write(dir.path().join("photos").join("desktop.ini.txt"), "x").unwrap();
+        fs::write(dir.path().join("report.db"), "x").unwrap();
+
+        // 默认不收集
+        let result = FileScanner::new(dir.path().to_path_buf()).scan_detailed().unwrap();
+        assert!(result.os_cruft.is_empty());
+
+        let result = FileScanner::new(dir.path().to_path_buf())
+            .collect_os_cruft(true)
+            .scan_detailed()
+            .unwrap();
+        let mut cruft: Vec<_> = result
+            .os_cruft
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        cruft.sort();
+        assert_eq!(cruft, vec![".DS_Store", "desktop.ini", "thumbs.db"]);
+
+        let mut names: Vec<_> = result.files.iter().map(|f| f.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["desktop.ini.txt", "photos", "report.db"]);
+    }
+
+    #[test]
+    fn test_scan_collects_walk_errors() {
+        let dir = tempdir().unwrap();
+        let missing = dir.path().join("does-not-exist");
+
+        let result = FileScanner::new(missing.clone()).scan_detailed().unwrap();
+
+        assert!(result.files.is_empty());
+        assert!(result.has_warnings());
+        assert_eq!(result.warnings[0].path.as_deref(), Some(missing.as_path()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_dir_is_skipped() {
+        let outside = tempdir().unwrap();
+        fs::write(outside.path().join("elsewhere.txt"), "x").unwrap();
+
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("local.txt"), "x").unwrap();
+        let link = dir.path().join("linked");
+        std::os::unix::fs::symlink(outside.path(), &link).unwrap();
+
+        let result = FileScanner::new(dir.path().to_path_buf()).scan_detailed().unwrap();
+
+        let names: Vec<_> = result.files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["local.txt"]);
+        assert_eq!(result.skipped_links, vec![link]);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_junction_is_not_traversed() {
+        let outside = tempdir().unwrap();
+        fs::write(outside.path().join("elsewhere.txt"), "x").unwrap();
+
+        let dir = tempdir().unwrap();
+        let junction = dir.path().join("junction");
+        let status = std::process::Command::new("cmd")
+            .args(["/C", "mklink", "/J"])
+            .arg(&junction)
+            .arg(outside.path())
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let result = FileScanner::new(dir.path().to_path_buf()).scan_detailed().unwrap();
+
+        assert!(result.files.iter().all(|f| f.name != "elsewhere.txt"));
+        assert_eq!(result.skipped_links, vec![junction]);
+    }
+
+    #[test]
+    fn test_content_summary_reads_only_byte_cap() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("minified.json");
+        // 4 MB 的单行文件，末尾才有换行
+        let line = format!("[{}]\n", "\"值\",".repeat(4 * 1024 * 1024 / 6));
+        fs::write(&path, &line).unwrap();
+
+        let summary = get_content_summary_bytes(&path, 1024).unwrap();
+
+        assert!(summary.len() <= 1024);
+        assert!(summary.len() > 1000);
+        assert!(summary.starts_with("[\"值\","));
+        assert!(!summary.contains('\u{FFFD}'));
+        assert!(!summary.contains('\n'));
+    }
+
+    #[test]
+    fn test_parallel_scan_matches_serial_scan() {
+        let dir = tempdir().unwrap();
+        for d in 0..50 {
+            let sub = dir.path().join(format!("dir_{:02}", d));
+            fs::create_dir(&sub).unwrap();
+            for f in 0..100 {
+                fs::write(sub.join(format!("file_{:03}.txt", f)), "x").unwrap();
+            }
+        }
+        // 过滤规则与串行扫描一致
+        fs::write(dir.path().join(".hidden"), "h").unwrap();
+        fs::create_dir(dir.path().join("node_cache")).unwrap();
+        fs::write(dir.path().join("node_cache/skip.txt"), "s").unwrap();
+
+        let scanner = FileScanner::new(dir.path().to_path_buf()).exclude_dir("node_cache".to_string());
+        let started = std::time::Instant::now();
+        let parallel = scanner.scan_detailed().unwrap();
+        tracing::info!("并行扫描 5000 个文件耗时 {:?}", started.elapsed());
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        scanner.scan_to_channel(tx).unwrap();
+        let serial: Vec<FileDescriptor> = rx.into_iter().collect();
+
+        // 5000 个文件 + 50 个目录
+        assert_eq!(parallel.files.len(), 5050);
+        assert!(parallel.warnings.is_empty());
+        let mut parallel_ids: Vec<String> = parallel.files.iter().map(|f| f.id.clone()).collect();
+        let mut serial_ids: Vec<String> = serial.iter().map(|f| f.id.clone()).collect();
+        parallel_ids.sort();
+        serial_ids.sort();
+        assert_eq!(parallel_ids, serial_ids);
+    }
+
+    #[test]
+    fn test_streaming_scan_keeps_walk_order() {
+        let dir = tempdir().unwrap();
+        for d in 0..5 {
+            let sub = dir.path().join(format!("dir_{}", d));
+            fs::create_dir(&sub).unwrap();
+            for f in 0..PARALLEL_BATCH {
+                fs::write(sub.join(format!("file_{:03}.txt", f)), "x").unwrap();
+            }
+        }
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        FileScanner::new(dir.path().to_path_buf()).scan_to_channel(tx).unwrap();
+        let streamed: Vec<FileDescriptor> = rx.into_iter().collect();
+
+        // 跨越多个并行批次，目录仍先于其中的文件送出
+        assert_eq!(streamed.len(), 5 * (PARALLEL_BATCH + 1));
+        for (i, file) in streamed.iter().enumerate() {
+            if let Some(parent) = file.full_path.parent().filter(|p| *p != dir.path()) {
+                let parent_index = streamed.iter().position(|f| f.full_path == parent).unwrap();
+                assert!(parent_index < i);
+            }
+        }
+    }
+
+    #[test]
+    fn test_scan_progress_reported_on_walking_thread() {
+        let dir = tempdir().unwrap();
+        for i in 0..250 {
+            fs::write(dir.path().join(format!("f{:03}.txt", i)), "x").unwrap();
+        }
+        let excluded = dir.path().join("Excluded");
+        fs::create_dir(&excluded).unwrap();
+        for i in 0..150 {
+            fs::write(excluded.join(format!("e{:03}.txt", i)), "x").unwrap();
+        }
+
+        let scanner = FileScanner::new(dir.path().to_path_buf()).exclude_dir("excluded".to_string());
+        let caller = std::thread::current().id();
+        let mut reports = Vec::new();
+        let result = scanner
+            .scan_with_progress(|p| {
+                assert_eq!(std::thread::current().id(), caller);
+                reports.push(p);
+            })
+            .unwrap();
+
+        assert_eq!(result.files.len(), 250);
+        // 被排除的条目不计入进度
+        assert_eq!(reports.iter().map(|p| p.scanned).collect::<Vec<_>>(), vec![100, 200]);
+        assert!(reports.iter().all(|p| !p.current_path.starts_with(&excluded)));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_cloud_placeholder_attributes_flagged() {
+        const FILE_ATTRIBUTE_ARCHIVE: u32 = 0x20;
+        const FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS: u32 = 0x400000;
+        assert!(is_placeholder_attributes(FILE_ATTRIBUTE_ARCHIVE | FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS));
+        assert!(!is_placeholder_attributes(FILE_ATTRIBUTE_ARCHIVE));
+
+        // 普通本地文件不会被标记
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("local.txt"), "x").unwrap();
+        let files = FileScanner::new(dir.path().to_path_buf()).scan().unwrap();
+        assert!(!files[0].is_cloud_placeholder);
+    }
+
+    #[test]
+    fn test_ignore_files_compound_and_negate() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+        fs::write(root.join(ORDERLY_IGNORE_NAME), "*.tmp\n").unwrap();
+        fs::create_dir_all(root.join("target/debug")).unwrap();
+        fs::write(root.join("target/debug/app.bin"), "b").unwrap();
+        fs::write(root.join("build.log"), "l").unwrap();
+        fs::write(root.join("scratch.tmp"), "t").unwrap();
+        fs::write(root.join("readme.txt"), "r").unwrap();
+        // 子目录的 .gitignore 叠加在上层之上，并用 ! 找回被上层忽略的文件
+        fs::create_dir(root.join("logs")).unwrap();
+        fs::write(root.join("logs/.gitignore"), "!keep.log\n*.csv\n").unwrap();
+        fs::write(root.join("logs/keep.log"), "k").unwrap();
+        fs::write(root.join("logs/drop.log"), "d").unwrap();
+        fs::write(root.join("logs/data.csv"), "c").unwrap();
+
+        let names = |scanner: FileScanner| {
+            let mut names: Vec<String> = scanner
+                .scan()
+                .unwrap()
+                .into_iter()
+                .filter(|f| !f.is_directory)
+                .map(|f| f.relative_path.to_string_lossy().replace('\\', "/"))
+                .collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(
+            names(FileScanner::new(root.to_path_buf())),
+            vec!["logs/keep.log", "readme.txt"]
+        );
+        assert_eq!(
+            names(FileScanner::new(root.to_path_buf()).respect_ignore_files(false)).len(),
+            7
+        );
+    }
+
+    #[test]
+    fn test_cancel_returns_complete_partial_results() {
+        let dir = tempdir().unwrap();
+        for i in 0..250 {
+            fs::write(dir.path().join(format!("file_{:03}.txt", i)), "x").unwrap();
+        }
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        let scanner = FileScanner::new(dir.path().to_path_buf()).cancel_flag(Arc::clone(&cancel));
+        let result = scanner
+            .scan_with_progress(|_| cancel.store(true, Ordering::Relaxed))
+            .unwrap();
+
+        assert!(result.cancelled);
+        assert_eq!(result.files.len(), PROGRESS_INTERVAL);
+        assert!(result.files.iter().all(|f| f.full_path.exists() && f.size == 1));
+
+        // 未置位时完整扫描
+        cancel.store(false, Ordering::Relaxed);
+        let full = scanner.scan_detailed().unwrap();
+        assert!(!full.cancelled);
+        assert_eq!(full.files.len(), 250);
+    }
+
+    #[test]
+    fn test_directory_size_is_recursive_total() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("photos/2023")).unwrap();
+        fs::write(root.join("photos/a.jpg"), vec![0u8; 100]).unwrap();
+        fs::write(root.join("photos/2023/b.jpg"), vec![0u8; 250]).unwrap();
+        // 原子目录：子项随后被隐藏，目录大小仍应包含全部内容
+        fs::create_dir(root.join("app")).unwrap();
+        fs::write(root.join("app/app.exe"), vec![0u8; 10]).unwrap();
+        fs::write(root.join("app/core.dll"), vec![0u8; 40]).unwrap();
+
+        let mut files = FileScanner::new(root.to_path_buf()).scan().unwrap();
+        crate::core::boundary::BoundaryAnalyzer::new().analyze(&mut files);
+        let size_of = |rel: &str| {
+            files
+                .iter()
+                .find(|f| f.full_path == root.join(rel))
+                .map(|f| f.size)
+                .unwrap()
+        };
+
+        assert_eq!(size_of("photos/2023"), 250);
+        assert_eq!(size_of("photos"), 350);
+        assert_eq!(size_of("app"), 50);
+        assert!(files.iter().any(|f| f.full_path == root.join("app") && f.atomic));
+
+        // 串行扫描与并行扫描结果一致
+        let serial = FileScanner::new(root.to_path_buf()).scan_with_progress(|_| {}).unwrap();
+        let photos = serial.files.iter().find(|f| f.full_path == root.join("photos")).unwrap();
+        assert_eq!(photos.size, 350);
+    }
+
+    #[test]
+    fn test_app_state_dirs_excluded() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let data_dir = root.join("AppData/orderly/data");
+        fs::create_dir_all(&data_dir).unwrap();
+        fs::write(data_dir.join("orderly.db"), "db").unwrap();
+        fs::write(data_dir.join("history.json"), "[]").unwrap();
+        fs::write(root.join("AppData/other.txt"), "o").unwrap();
+
+        // 扫描根是数据目录的上层时，数据目录整体跳过
+        let files = FileScanner::new(root.to_path_buf())
+            .exclude_path(data_dir.clone())
+            .scan()
+            .unwrap();
+        assert!(files.iter().all(|f| !f.full_path.starts_with(&data_dir)));
+        assert!(files.iter().any(|f| f.name == "other.txt"));
+
+        // 默认就排除程序自身的目录
+        assert_eq!(FileScanner::new(root.to_path_buf()).exclude_paths, app_state_dirs());
+    }
 }
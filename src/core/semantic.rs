@@ -9,13 +9,22 @@
 //! - 禁止AI自由发挥
 
 use crate::core::models::{
-    AIConfig, FileDescriptor, MoveSuggestion, RuleAction, RuleCondition, 
+    AIConfig, FileDescriptor, LayoutPreset, MoveSuggestion, RuleAction, RuleCondition, 
     RuleDefinition, SemanticResult, SuggestionSource,
 };
-use crate::core::scanner::get_content_summary;
+use crate::core::document::{document_summary, is_document_file, DOCUMENT_SUMMARY_CHARS};
+use crate::core::scanner::get_content_summary_bytes;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
 use std::path::PathBuf;
+use std::time::Duration;
+
+/// 建立连接的超时时间
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 批量语义分析时每个请求包含的文件数
+pub const ANALYSIS_BATCH_SIZE: usize = 10;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum AiApiKind {
@@ -30,8 +39,6 @@ pub struct SemanticEngine {
     config: AIConfig,
     /// HTTP客户端
     client: reqwest::Client,
-    /// 输出基础路径
-    output_base: PathBuf,
 }
 
 /// 文件档案（发送给AI的结构化输入）
@@ -55,6 +62,14 @@ struct SemanticResponse {
     explanation: String,
 }
 
+/// 批量语义分析响应中的一项，按文件名对应回文件
+#[derive(Debug, Deserialize)]
+struct BatchSemanticItem {
+    file: String,
+    #[serde(flatten)]
+    response: SemanticResponse,
+}
+
 /// AI路径建议响应
 #[derive(Debug, Deserialize)]
 struct PathSuggestionResponse {
@@ -86,11 +101,13 @@ struct ExtractedAction {
 
 impl SemanticEngine {
     /// 创建新的语义引擎
-    pub fn new(config: AIConfig, output_base: PathBuf) -> Self {
+    pub fn new(config: AIConfig) -> Self {
         Self {
             config,
-            client: reqwest::Client::new(),
-            output_base,
+            client: reqwest::Client::builder()
+                .connect_timeout(CONNECT_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
         }
     }
 
@@ -124,6 +141,59 @@ impl SemanticEngine {
         self.parse_semantic_response(&response)
     }
 
+    /// 批量分析文件语义，每 batch_size 个文件合并为一个请求
+    ///
+    /// 返回值与 files 一一对应。请求失败或模型漏掉的文件为 None，
+    /// 调用方可以再用 analyze_file 逐个补充；原子文件和目录与 analyze_file 一样返回空结果。
+    pub async fn analyze_files_batch(
+        &self,
+        files: &[FileDescriptor],
+        batch_size: usize,
+    ) -> Vec<Option<SemanticResult>> {
+        let mut results: Vec<Option<SemanticResult>> = files
+            .iter()
+            .map(|f| (f.atomic || f.is_directory).then(SemanticResult::default))
+            .collect();
+
+        let pending: Vec<usize> = (0..files.len()).filter(|&i| results[i].is_none()).collect();
+        for batch in pending.chunks(batch_size.max(1)) {
+            let profiles: Vec<FileProfile> = batch
+                .iter()
+                .map(|&i| self.build_file_profile(&files[i]))
+                .collect();
+            let prompt = self.build_batch_semantic_prompt(&profiles);
+
+            let response = match self.call_ai(&prompt).await {
+                Ok(response) => response,
+                Err(e) => {
+                    tracing::warn!("批量AI分析失败（{} 个文件）: {}", batch.len(), e);
+                    continue;
+                }
+            };
+
+            // 同名文件按出现顺序依次对应
+            let mut by_name: HashMap<String, VecDeque<SemanticResult>> = HashMap::new();
+            for item in self.parse_batch_semantic_response(&response) {
+                by_name
+                    .entry(item.file)
+                    .or_default()
+                    .push_back(self.to_semantic_result(item.response));
+            }
+            let mut missing = 0;
+            for &i in batch {
+                results[i] = by_name.get_mut(&files[i].name).and_then(VecDeque::pop_front);
+                if results[i].is_none() {
+                    missing += 1;
+                }
+            }
+            if missing > 0 {
+                tracing::debug!("批量AI分析有 {} 个文件未返回结果", missing);
+            }
+        }
+
+        results
+    }
+
     /// 为文件生成路径建议
     pub async fn suggest_path(
         &self,
@@ -137,10 +207,13 @@ impl SemanticEngine {
         let suggestion = self.parse_path_suggestion(&response)?;
 
         Ok(MoveSuggestion {
-            target_path: self.output_base.join(&suggestion.suggested_path),
+            target_path: PathBuf::from(&suggestion.suggested_path),
             reason: suggestion.reason,
             source: SuggestionSource::AI,
-            confidence: suggestion.confidence,
+            confidence: self.config.confidence_calibration.apply(suggestion.confidence),
+            matched_rule_id: None,
+            rule_origin: None,
+            fused: false,
         })
     }
 
@@ -167,11 +240,19 @@ impl SemanticEngine {
         Ok(rule)
     }
 
+    /// 测试AI服务连接，成功时返回模型的简短回复
+    pub async fn test_connection(&self) -> Result<String> {
+        let response = self.call_ai("请只回复：OK").await?;
+        Ok(response.trim().chars().take(50).collect())
+    }
+
     /// 构建文件档案
     fn build_file_profile(&self, file: &FileDescriptor) -> FileProfile {
-        // 尝试获取内容摘要（仅文本文件）
+        // 尝试获取内容摘要（文本文件读开头，PDF/DOCX 抽取正文）
         let content_summary = if self.is_text_file(&file.extension) {
-            get_content_summary(&file.full_path, 500).ok()
+            get_content_summary_bytes(&file.full_path, self.config.content_summary_kb * 1024).ok()
+        } else if is_document_file(&file.extension) {
+            document_summary(&file.full_path, DOCUMENT_SUMMARY_CHARS)
         } else {
             None
         };
@@ -239,6 +320,40 @@ impl SemanticEngine {
         )
     }
 
+    /// 构建批量语义分析提示词
+    fn build_batch_semantic_prompt(&self, profiles: &[FileProfile]) -> String {
+        format!(
+            r#"你是一个文件整理助手，请分析以下 {} 个文件的语义信息。
+
+文件列表（JSON）：
+{}
+
+请为每个文件输出一项，组成JSON数组（不要输出其他内容）：
+[
+  {{
+    "file": "文件名（与输入的 name 完全一致）",
+    "tags": ["标签1", "标签2"],
+    "entities": ["实体1", "实体2"],
+    "year": 2023,
+    "confidence": 0.85,
+    "explanation": "判断理由"
+  }}
+]
+
+要求：
+1. file: 原样填写输入中的文件名，用于对应结果
+2. tags: 描述文件类型、用途、主题的标签（如 invoice, photo, work, personal）
+3. entities: 识别出的实体（如公司名、人名、项目名）
+4. year: 从文件名或内容推断的年份，如果无法确定则为null
+5. confidence: 分析置信度 (0-1)
+6. explanation: 简短的判断理由
+
+只输出JSON数组，不要输出其他任何内容。"#,
+            profiles.len(),
+            serde_json::to_string_pretty(profiles).unwrap_or_default()
+        )
+    }
+
     /// 构建路径建议提示词
     fn build_path_suggestion_prompt(&self, profile: &FileProfile, candidates: &[String]) -> String {
         format!(
@@ -264,7 +379,7 @@ impl SemanticEngine {
 要求：
 1. 如果候选路径中有合适的，从中选择
 2. 如果候选路径都不合适，可以建议新路径
-3. 路径支持变量：{{year}}, {{month}}, {{extension}}
+3. 路径支持变量：{{year}}, {{month}}, {{extension}}, {{entity}}（识别出的机构名）, {{tag}}
 4. confidence: 推荐置信度 (0-1)
 
 只输出JSON，不要输出其他任何内容。"#,
@@ -314,7 +429,7 @@ impl SemanticEngine {
 要求：
 1. rule_name: 简洁描述规则用途
 2. condition: 至少填写一个匹配条件
-3. move_to: 支持变量 {{year}}, {{month}}, {{extension}}
+3. move_to: 支持变量 {{year}}, {{month}}, {{extension}}, {{entity}}, {{tag}}
 4. priority: 0-100，数字越大优先级越高，一般用户规则建议60-80
 
 只输出JSON，不要输出其他任何内容。"#,
@@ -324,17 +439,85 @@ impl SemanticEngine {
     }
 
     /// 调用AI API
+    ///
+    /// 依次尝试主端点和备用端点，只有连接失败（连不上、超时）才换下一个；
+    /// 返回内容无法解析或 4xx 等错误直接返回，不再尝试其他端点。
     async fn call_ai(&self, prompt: &str) -> Result<String> {
-        let (kind, endpoint) = self.normalize_ai_endpoint()?;
-        match kind {
-            AiApiKind::OllamaGenerate => self.call_ollama(prompt, &endpoint).await,
-            AiApiKind::OpenAIChatCompletions => self.call_openai_chat_completions(prompt, &endpoint).await,
-            AiApiKind::OpenAIResponses => self.call_openai_responses(prompt, &endpoint).await,
+        let endpoints = std::iter::once(&self.config).chain(self.config.fallback_endpoints.iter());
+        let mut last_error = None;
+        for (i, config) in endpoints.enumerate() {
+            match self.call_endpoint(config, prompt).await {
+                Ok(text) => {
+                    if i == 0 {
+                        tracing::debug!("AI请求由 {} 处理", config.api_endpoint);
+                    } else {
+                        tracing::info!("AI请求由备用端点 {} 处理", config.api_endpoint);
+                    }
+                    return Ok(text);
+                }
+                Err(e) if is_connection_error(&e) => {
+                    tracing::warn!("AI端点 {} 连接失败: {}", config.api_endpoint, e);
+                    last_error = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
         }
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("没有可用的AI端点")))
     }
 
-    fn normalize_ai_endpoint(&self) -> Result<(AiApiKind, String)> {
-        let raw = self.config.api_endpoint.trim();
+    /// 调用单个AI端点，暂时性失败按指数退避重试，只返回最后一次的错误
+    async fn call_endpoint(&self, config: &AIConfig, prompt: &str) -> Result<String> {
+        let mut attempt = 0;
+        loop {
+            match self.call_endpoint_once(config, prompt).await {
+                Err(e) if attempt < config.max_retries && is_transient_error(&e) => {
+                    let delay = retry_delay(config.retry_base_delay_ms, attempt);
+                    attempt += 1;
+                    tracing::warn!(
+                        "AI请求失败，{} 毫秒后第 {} 次重试: {}",
+                        delay.as_millis(),
+                        attempt,
+                        e
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// 向单个AI端点发送一次请求，超时的错误会注明超时
+    async fn call_endpoint_once(&self, config: &AIConfig, prompt: &str) -> Result<String> {
+        let (kind, endpoint) = Self::normalize_ai_endpoint(config)?;
+        let result = match kind {
+            AiApiKind::OllamaGenerate => self.call_ollama(config, prompt, &endpoint).await,
+            AiApiKind::OpenAIChatCompletions => {
+                self.call_openai_chat_completions(config, prompt, &endpoint).await
+            }
+            AiApiKind::OpenAIResponses => self.call_openai_responses(config, prompt, &endpoint).await,
+        };
+        result.map_err(|e| {
+            let timed_out = e.downcast_ref::<reqwest::Error>().is_some_and(reqwest::Error::is_timeout);
+            if timed_out {
+                e.context(format!(
+                    "AI请求超时（{} 超过 {} 秒未响应）",
+                    endpoint, config.request_timeout_secs
+                ))
+            } else {
+                e
+            }
+        })
+    }
+
+    /// 创建带超时的 POST 请求
+    fn post(&self, config: &AIConfig, endpoint: &str) -> reqwest::RequestBuilder {
+        self.client
+            .post(endpoint)
+            .timeout(Duration::from_secs(config.request_timeout_secs.max(1)))
+    }
+
+    fn normalize_ai_endpoint(config: &AIConfig) -> Result<(AiApiKind, String)> {
+        let raw = config.api_endpoint.trim();
         if raw.is_empty() {
             return Err(anyhow::anyhow!("AI API端点为空"));
         }
@@ -383,32 +566,64 @@ impl SemanticEngine {
         Ok((AiApiKind::OpenAIChatCompletions, endpoint))
     }
 
-    /// 调用Ollama API
-    async fn call_ollama(&self, prompt: &str, endpoint: &str) -> Result<String> {
-        #[derive(Serialize)]
-        struct OllamaRequest {
-            model: String,
-            prompt: String,
-            stream: bool,
+    /// 组装请求体
+    ///
+    /// 自定义系统提示词在 Chat Completions 中作为 system 消息，
+    /// 在 generate / responses 接口中作为前缀；任务提示词（含JSON输出要求）保持不变。
+    fn build_request_body(config: &AIConfig, kind: AiApiKind, prompt: &str) -> serde_json::Value {
+        let system_prompt = config
+            .system_prompt
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty());
+        let prefixed = || match system_prompt {
+            Some(system) => format!("{}\n\n{}", system, prompt),
+            None => prompt.to_string(),
+        };
+
+        match kind {
+            AiApiKind::OllamaGenerate => serde_json::json!({
+                "model": config.model_name,
+                "prompt": prefixed(),
+                "stream": false,
+            }),
+            AiApiKind::OpenAIChatCompletions => {
+                let mut messages = Vec::new();
+                if let Some(system) = system_prompt {
+                    messages.push(serde_json::json!({ "role": "system", "content": system }));
+                }
+                messages.push(serde_json::json!({ "role": "user", "content": prompt }));
+                serde_json::json!({
+                    "model": config.model_name,
+                    "messages": messages,
+                    "temperature": config.temperature,
+                    "max_tokens": config.max_tokens,
+                })
+            }
+            AiApiKind::OpenAIResponses => serde_json::json!({
+                "model": config.model_name,
+                "input": prefixed(),
+                "temperature": config.temperature,
+                "max_output_tokens": config.max_tokens,
+            }),
         }
+    }
 
+    /// 调用Ollama API
+    async fn call_ollama(&self, config: &AIConfig, prompt: &str, endpoint: &str) -> Result<String> {
         #[derive(Deserialize)]
         struct OllamaResponse {
             response: String,
         }
 
-        let request = OllamaRequest {
-            model: self.config.model_name.clone(),
-            prompt: prompt.to_string(),
-            stream: false,
-        };
+        let request = Self::build_request_body(config, AiApiKind::OllamaGenerate, prompt);
 
         let response = self
-            .client
-            .post(endpoint)
+            .post(config, endpoint)
             .json(&request)
             .send()
             .await?
+            .error_for_status()?
             .json::<OllamaResponse>()
             .await?;
 
@@ -416,21 +631,12 @@ impl SemanticEngine {
     }
 
     /// 调用OpenAI兼容API（Chat Completions）
-    async fn call_openai_chat_completions(&self, prompt: &str, endpoint: &str) -> Result<String> {
-        #[derive(Serialize)]
-        struct Message {
-            role: String,
-            content: String,
-        }
-
-        #[derive(Serialize)]
-        struct OpenAIRequest {
-            model: String,
-            messages: Vec<Message>,
-            temperature: f32,
-            max_tokens: u32,
-        }
-
+    async fn call_openai_chat_completions(
+        &self,
+        config: &AIConfig,
+        prompt: &str,
+        endpoint: &str,
+    ) -> Result<String> {
         #[derive(Deserialize)]
         struct Choice {
             message: MessageContent,
@@ -446,23 +652,15 @@ impl SemanticEngine {
             choices: Vec<Choice>,
         }
 
-        let request = OpenAIRequest {
-            model: self.config.model_name.clone(),
-            messages: vec![Message {
-                role: "user".to_string(),
-                content: prompt.to_string(),
-            }],
-            temperature: self.config.temperature,
-            max_tokens: self.config.max_tokens,
-        };
+        let request = Self::build_request_body(config, AiApiKind::OpenAIChatCompletions, prompt);
 
-        let mut req = self.client.post(endpoint).json(&request);
+        let mut req = self.post(config, endpoint).json(&request);
 
-        if !self.config.api_key.is_empty() {
-            req = req.header("Authorization", format!("Bearer {}", self.config.api_key));
+        if !config.api_key.is_empty() {
+            req = req.header("Authorization", format!("Bearer {}", config.api_key));
         }
 
-        let response = req.send().await?.json::<OpenAIResponse>().await?;
+        let response = req.send().await?.error_for_status()?.json::<OpenAIResponse>().await?;
 
         response
             .choices
@@ -472,30 +670,15 @@ impl SemanticEngine {
     }
 
     /// 调用 OpenAI Responses API（如果用户配置了 /v1/responses）
-    async fn call_openai_responses(&self, prompt: &str, endpoint: &str) -> Result<String> {
-        #[derive(Serialize)]
-        struct ResponsesRequest {
-            model: String,
-            input: String,
-            #[serde(skip_serializing_if = "Option::is_none")]
-            temperature: Option<f32>,
-            #[serde(skip_serializing_if = "Option::is_none")]
-            max_output_tokens: Option<u32>,
-        }
+    async fn call_openai_responses(&self, config: &AIConfig, prompt: &str, endpoint: &str) -> Result<String> {
+        let request = Self::build_request_body(config, AiApiKind::OpenAIResponses, prompt);
 
-        let request = ResponsesRequest {
-            model: self.config.model_name.clone(),
-            input: prompt.to_string(),
-            temperature: Some(self.config.temperature),
-            max_output_tokens: Some(self.config.max_tokens),
-        };
-
-        let mut req = self.client.post(endpoint).json(&request);
-        if !self.config.api_key.is_empty() {
-            req = req.header("Authorization", format!("Bearer {}", self.config.api_key));
+        let mut req = self.post(config, endpoint).json(&request);
+        if !config.api_key.is_empty() {
+            req = req.header("Authorization", format!("Bearer {}", config.api_key));
         }
 
-        let value: serde_json::Value = req.send().await?.json().await?;
+        let value: serde_json::Value = req.send().await?.error_for_status()?.json().await?;
 
         // 尽量兼容不同实现：优先找 output_text，其次尝试 output->content->text
         if let Some(s) = value.get("output_text").and_then(|v| v.as_str()) {
@@ -522,13 +705,37 @@ impl SemanticEngine {
         let parsed: SemanticResponse = serde_json::from_str(&json_str)
             .map_err(|e| anyhow::anyhow!("解析AI响应失败: {}, 响应内容: {}", e, response))?;
 
-        Ok(SemanticResult {
+        Ok(self.to_semantic_result(parsed))
+    }
+
+    /// 解析批量语义分析响应，格式不对的项直接跳过
+    fn parse_batch_semantic_response(&self, response: &str) -> Vec<BatchSemanticItem> {
+        let json_str = match (response.find('['), response.rfind(']')) {
+            (Some(start), Some(end)) if start < end => &response[start..=end],
+            _ => response,
+        };
+        let items: Vec<serde_json::Value> = match serde_json::from_str(json_str) {
+            Ok(items) => items,
+            Err(e) => {
+                tracing::warn!("解析批量AI响应失败: {}, 响应内容: {}", e, response);
+                return Vec::new();
+            }
+        };
+        items
+            .into_iter()
+            .filter_map(|item| serde_json::from_value(item).ok())
+            .collect()
+    }
+
+    /// 把AI响应转换为语义结果（置信度经过校准）
+    fn to_semantic_result(&self, parsed: SemanticResponse) -> SemanticResult {
+        SemanticResult {
             tags: parsed.tags,
             entities: parsed.entities,
             year: parsed.year,
-            confidence: parsed.confidence,
+            confidence: self.config.confidence_calibration.apply(parsed.confidence),
             explanation: parsed.explanation,
-        })
+        }
     }
 
     /// 解析路径建议响应
@@ -557,6 +764,30 @@ impl SemanticEngine {
     }
 }
 
+/// 是否为连接层面的失败（连不上、超时），只有这类错误才值得换备用端点
+fn is_connection_error(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<reqwest::Error>()
+        .is_some_and(|e| e.is_connect() || e.is_timeout())
+}
+
+/// 是否为值得重试的暂时性失败：连接失败、超时、限流（429）或服务端错误（5xx）
+fn is_transient_error(error: &anyhow::Error) -> bool {
+    if is_connection_error(error) {
+        return true;
+    }
+    error
+        .downcast_ref::<reqwest::Error>()
+        .and_then(reqwest::Error::status)
+        .is_some_and(|s| s == reqwest::StatusCode::TOO_MANY_REQUESTS || s.is_server_error())
+}
+
+/// 第 attempt 次重试前的等待时间（从 0 开始，每次翻倍，最多 30 秒）
+fn retry_delay(base_ms: u64, attempt: u32) -> Duration {
+    let ms = base_ms.saturating_mul(1u64 << attempt.min(16));
+    Duration::from_millis(ms.min(30_000))
+}
+
 /// 模拟AI响应（用于测试或离线模式）
 pub fn mock_semantic_analysis(file: &FileDescriptor) -> SemanticResult {
     let mut tags = Vec::new();
@@ -596,8 +827,55 @@ pub fn mock_semantic_analysis(file: &FileDescriptor) -> SemanticResult {
     }
 }
 
+/// 离线建议的置信度（与默认置信度阈值持平，默认设置下会进入计划）
+pub const OFFLINE_SUGGESTION_CONFIDENCE: f32 = 0.7;
+
+/// 离线兜底：把本地分析得到的标签映射到默认分类目录
+///
+/// 规则未命中、又没有模型可用时，按标签给出一个保守的目标，让离线模式也能提出整理建议。
+/// 分类目录按当前目录结构预设展开（未设置预设时为 `类别/{year}`），与内置规则一致；
+/// 标签越具体越优先（发票、合同先于一般文档）。来源标为 [`SuggestionSource::Offline`]，与模型建议区分。
+pub fn offline_target_suggestion(
+    file: &FileDescriptor,
+    preset: Option<LayoutPreset>,
+) -> Option<MoveSuggestion> {
+    const CATEGORIES: &[(&str, &str)] = &[
+        ("invoice", "Finance/Invoice"),
+        ("contract", "Documents/Contracts"),
+        ("report", "Documents/Reports"),
+        ("image", "Pictures"),
+        ("video", "Videos"),
+        ("audio", "Music"),
+        ("document", "Documents"),
+        ("word", "Documents"),
+        ("excel", "Documents/Spreadsheets"),
+    ];
+
+    if file.atomic || file.is_directory {
+        return None;
+    }
+    let semantic = file.semantic.as_ref()?;
+    let (tag, category) = CATEGORIES
+        .iter()
+        .find(|(tag, _)| semantic.tags.iter().any(|t| t == tag))?;
+
+    let move_to = match preset {
+        Some(preset) => preset.template_for(category),
+        None => format!("{}/{{year}}", category),
+    };
+    Some(MoveSuggestion {
+        target_path: RuleAction { move_to }.render_relative(file),
+        reason: format!("离线推断：标签 {}", tag),
+        source: SuggestionSource::Offline,
+        confidence: OFFLINE_SUGGESTION_CONFIDENCE,
+        matched_rule_id: None,
+        rule_origin: None,
+        fused: false,
+    })
+}
+
 /// 从文件名中提取年份
-fn extract_year_from_filename(filename: &str) -> Option<i32> {
+pub(crate) fn extract_year_from_filename(filename: &str) -> Option<i32> {
     use std::str::FromStr;
     
     // 匹配4位数字年份（2000-2099）
@@ -623,4 +901,235 @@ mod tests {
         assert_eq!(extract_year_from_filename("2024_invoice.pdf"), Some(2024));
         assert_eq!(extract_year_from_filename("no_year.pdf"), None);
     }
+
+    #[test]
+    fn test_system_prompt_in_request() {
+        let config = AIConfig {
+            system_prompt: Some("You are a tidy archivist. Answer in English.".to_string()),
+            ..AIConfig::default()
+        };
+        let engine = SemanticEngine::new(config);
+        let prompt = "只输出JSON";
+
+        let chat = SemanticEngine::build_request_body(&engine.config, AiApiKind::OpenAIChatCompletions, prompt);
+        assert_eq!(chat["messages"][0]["role"], "system");
+        assert_eq!(chat["messages"][0]["content"], "You are a tidy archivist. Answer in English.");
+        assert_eq!(chat["messages"][1]["content"], prompt);
+
+        for kind in [AiApiKind::OllamaGenerate, AiApiKind::OpenAIResponses] {
+            let body = SemanticEngine::build_request_body(&engine.config, kind, prompt);
+            let text = body.get("prompt").or_else(|| body.get("input")).unwrap().as_str().unwrap();
+            assert!(text.starts_with("You are a tidy archivist."));
+            assert!(text.ends_with(prompt), "JSON 输出要求应保留");
+        }
+
+        // 未配置时只有用户消息
+        let plain = SemanticEngine::new(AIConfig::default());
+        let chat = SemanticEngine::build_request_body(&plain.config, AiApiKind::OpenAIChatCompletions, prompt);
+        assert_eq!(chat["messages"].as_array().unwrap().len(), 1);
+    }
+
+    /// 在本地端口上依次应答若干个 HTTP 请求（每个连接一个），返回端点地址
+    fn serve(responses: Vec<(&'static str, &'static str)>) -> String {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for (status, body) in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                // 读完请求头和请求体再应答
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                loop {
+                    let n = stream.read(&mut buf).unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request).to_lowercase();
+                    if let Some(end) = text.find("\r\n\r\n") {
+                        let length = text
+                            .lines()
+                            .find_map(|l| l.strip_prefix("content-length:"))
+                            .and_then(|v| v.trim().parse::<usize>().ok())
+                            .unwrap_or(0);
+                        if request.len() >= end + 4 + length {
+                            break;
+                        }
+                    }
+                    if n == 0 {
+                        break;
+                    }
+                }
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        format!("http://{}/v1/chat/completions", addr)
+    }
+
+    /// 一个没有服务监听的端点（连接被拒绝）
+    fn unreachable_endpoint() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        format!("http://{}/v1/chat/completions", addr)
+    }
+
+    const OK_BODY: &str = r#"{"choices":[{"message":{"content":"OK"}}]}"#;
+
+    fn endpoint(url: String) -> AIConfig {
+        AIConfig {
+            api_endpoint: url,
+            retry_base_delay_ms: 1,
+            ..AIConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_fallback_endpoint_on_connection_failure() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        // 主端点连不上，备用端点应答
+        let config = AIConfig {
+            fallback_endpoints: vec![endpoint(serve(vec![("200 OK", OK_BODY)]))],
+            ..endpoint(unreachable_endpoint())
+        };
+        let engine = SemanticEngine::new(config);
+        assert_eq!(runtime.block_on(engine.call_ai("ping")).unwrap(), "OK");
+
+        // 主端点有应答但返回 4xx：不换备用端点
+        let config = AIConfig {
+            fallback_endpoints: vec![endpoint(unreachable_endpoint())],
+            ..endpoint(serve(vec![("401 Unauthorized", r#"{"error":"bad key"}"#)]))
+        };
+        let error = runtime
+            .block_on(SemanticEngine::new(config).call_ai("ping"))
+            .unwrap_err();
+        assert!(!is_connection_error(&error));
+    }
+
+    #[test]
+    fn test_retry_transient_errors_only() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let call = |config: AIConfig| runtime.block_on(SemanticEngine::new(config).call_ai("ping"));
+        let status = |error: anyhow::Error| {
+            error.downcast_ref::<reqwest::Error>().and_then(reqwest::Error::status).map(|s| s.as_u16())
+        };
+
+        // 限流和 5xx 重试后成功
+        let url = serve(vec![("429 Too Many Requests", "{}"), ("503 Service Unavailable", "{}"), ("200 OK", OK_BODY)]);
+        assert_eq!(call(endpoint(url)).unwrap(), "OK");
+
+        // 重试次数用尽，返回最后一次的错误
+        let url = serve(vec![("500 Internal Server Error", "{}"), ("502 Bad Gateway", "{}")]);
+        let config = AIConfig { max_retries: 1, ..endpoint(url) };
+        assert_eq!(status(call(config).unwrap_err()), Some(502));
+
+        // 400 / 401 不重试：第二个应答是成功，但不会被请求到
+        for code in ["400 Bad Request", "401 Unauthorized"] {
+            let url = serve(vec![(code, "{}"), ("200 OK", OK_BODY)]);
+            let error = call(endpoint(url)).unwrap_err();
+            assert!(status(error).is_some_and(|s| s == 400 || s == 401));
+        }
+
+        assert_eq!(retry_delay(500, 0).as_millis(), 500);
+        assert_eq!(retry_delay(500, 2).as_millis(), 2000);
+        assert_eq!(retry_delay(500, 20).as_secs(), 30);
+    }
+
+    #[test]
+    fn test_hung_server_times_out() {
+        // 接受连接后一直不应答
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/v1/chat/completions", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let (_stream, _) = listener.accept().unwrap();
+            std::thread::sleep(Duration::from_secs(10));
+        });
+
+        let config = AIConfig {
+            request_timeout_secs: 1,
+            max_retries: 0,
+            ..endpoint(url)
+        };
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let started = std::time::Instant::now();
+        let error = runtime
+            .block_on(SemanticEngine::new(config).call_ai("ping"))
+            .unwrap_err();
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(error.to_string().contains("超时"), "{}", error);
+        assert!(is_transient_error(&error));
+    }
+
+    #[test]
+    fn test_batch_analysis_maps_results_by_name() {
+        const BATCH_BODY: &str = r#"{"choices":[{"message":{"content":"[{\"file\":\"invoice.txt\",\"tags\":[\"invoice\"],\"entities\":[],\"year\":2023,\"confidence\":0.9,\"explanation\":\"发票\"},{\"file\":\"unknown.txt\",\"tags\":[],\"entities\":[],\"year\":null,\"confidence\":0.1,\"explanation\":\"\"}]"}}]}"#;
+        let make = |name: &str, atomic: bool| {
+            let mut file = FileDescriptor::new(
+                PathBuf::from("/nonexistent").join(name),
+                name.to_string(),
+                ".txt".to_string(),
+                1,
+                chrono::Utc::now(),
+                false,
+            );
+            file.atomic = atomic;
+            file
+        };
+        let files = vec![make("notes.txt", false), make("setup.txt", true), make("invoice.txt", false)];
+
+        // 原子文件不进请求，其余两个文件合并为一个请求；模型漏掉了 notes.txt
+        let engine = SemanticEngine::new(endpoint(serve(vec![("200 OK", BATCH_BODY)])));
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let results = runtime.block_on(engine.analyze_files_batch(&files, 10));
+
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_none());
+        assert!(results[1].as_ref().unwrap().tags.is_empty());
+        let invoice = results[2].as_ref().unwrap();
+        assert_eq!(invoice.tags, vec!["invoice"]);
+        assert_eq!(invoice.year, Some(2023));
+    }
+
+    #[test]
+    fn test_offline_image_gets_pictures_suggestion() {
+        let mut photo = FileDescriptor::new(
+            PathBuf::from("/inbox/IMG_2021_beach.PNG"),
+            "IMG_2021_beach.PNG".to_string(),
+            ".PNG".to_string(),
+            2048,
+            chrono::Utc::now(),
+            false,
+        );
+        photo.semantic = Some(mock_semantic_analysis(&photo));
+
+        let suggestion = offline_target_suggestion(&photo, None).unwrap();
+        assert_eq!(suggestion.target_path, PathBuf::from("Pictures/2021"));
+        assert_eq!(suggestion.confidence, OFFLINE_SUGGESTION_CONFIDENCE);
+        assert_eq!(suggestion.source, SuggestionSource::Offline);
+
+        // 跟随目录结构预设
+        let by_year = offline_target_suggestion(&photo, Some(LayoutPreset::ByYearThenType)).unwrap();
+        assert_eq!(by_year.target_path, PathBuf::from("2021/Pictures"));
+        let by_type = offline_target_suggestion(&photo, Some(LayoutPreset::ByType)).unwrap();
+        assert_eq!(by_type.target_path, PathBuf::from("Pictures"));
+
+        // 没有可识别标签的文件仍然不给建议
+        let mut unknown = FileDescriptor::new(
+            PathBuf::from("/inbox/data.bin"),
+            "data.bin".to_string(),
+            ".bin".to_string(),
+            10,
+            chrono::Utc::now(),
+            false,
+        );
+        unknown.semantic = Some(mock_semantic_analysis(&unknown));
+        assert!(offline_target_suggestion(&unknown, None).is_none());
+    }
 }
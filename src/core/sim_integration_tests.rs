@@ -1,7 +1,10 @@
-use crate::core::executor::Executor;
-use crate::core::models::{FileDescriptor, MoveSuggestion, SuggestionSource};
+use crate::core::boundary::BoundaryAnalyzer;
+use crate::core::executor::{Executor, FOLDER_README_NAME};
+use crate::core::models::{FileDescriptor, MoveSuggestion, SemanticResult, SuggestionSource};
+use crate::core::naming::NamingPolicy;
+use crate::core::pipeline::scan_and_match;
 use crate::core::planner::Planner;
-use crate::core::rule_engine::RuleEngine;
+use crate::core::rule_engine::{RuleEngine, PROJECTS_RULE_ID};
 use crate::core::scanner::FileScanner;
 use chrono::{TimeZone, Utc};
 use std::fs;
@@ -19,7 +22,7 @@ fn make_fixed_time() -> chrono::DateTime<Utc> {
     Utc.with_ymd_and_hms(2024, 6, 15, 12, 0, 0).unwrap()
 }
 
-fn find_file<'a>(files: &'a [FileDescriptor], name: &str) -> FileDescriptor {
+fn find_file(files: &[FileDescriptor], name: &str) -> FileDescriptor {
     files
         .iter()
         .find(|f| !f.is_directory && f.name == name)
@@ -46,7 +49,7 @@ fn sim_rule_to_plan_keeps_filename_and_is_deterministic() {
         }
     }
 
-    let mut engine = RuleEngine::new(output.clone());
+    let mut engine = RuleEngine::new();
     for f in files.iter_mut() {
         if let Some(s) = engine.match_file(f) {
             f.suggested_action = Some(s);
@@ -104,6 +107,9 @@ fn sim_planner_ignores_suggestion_filename_part() {
         reason: "simulated".to_string(),
         source: SuggestionSource::AI,
         confidence: 1.0,
+        matched_rule_id: None,
+        rule_origin: None,
+        fused: false,
     });
 
     files.clear();
@@ -142,7 +148,7 @@ fn sim_execute_and_rollback_roundtrip() {
     }
 
     // 走一遍规则引擎生成建议
-    let mut engine = RuleEngine::new(output.clone());
+    let mut engine = RuleEngine::new();
     for f in files.iter_mut() {
         if let Some(s) = engine.match_file(f) {
             f.suggested_action = Some(s);
@@ -176,3 +182,269 @@ fn sim_execute_and_rollback_roundtrip() {
     assert!(!a_target.exists());
     assert!(!b_target.exists());
 }
+
+#[test]
+fn sim_streaming_pipeline_matches_batch_results() {
+    let dir = tempdir().unwrap();
+    let input = dir.path().join("input");
+
+    write_file(&input.join("photo.jpg"), "jpg-bytes");
+    write_file(&input.join("发票_2023.pdf"), "pdf");
+    write_file(&input.join("docs/report.docx"), "docx");
+    write_file(&input.join("docs/deep/song.mp3"), "mp3");
+    write_file(&input.join("MyApp/app.exe"), "exe");
+    write_file(&input.join("MyApp/core.dll"), "dll");
+    write_file(&input.join("MyApp/readme.txt"), "txt");
+    write_file(&input.join("web/package.json"), "{}");
+    write_file(&input.join("web/node_modules/lib/index.js"), "js");
+
+    // 批处理：扫描 -> 边界分析 -> 规则匹配
+    let mut batch = FileScanner::new(input.clone()).scan().unwrap();
+    BoundaryAnalyzer::new().analyze(&mut batch);
+    let mut batch_engine = RuleEngine::new();
+    batch_engine.match_files(&mut batch);
+
+    // 流水线
+    let mut stream_engine = RuleEngine::new();
+    let streamed = scan_and_match(
+        &FileScanner::new(input.clone()),
+        &BoundaryAnalyzer::new(),
+        &mut stream_engine,
+    )
+    .unwrap()
+    .files;
+
+    let summarize = |files: &[FileDescriptor]| {
+        let mut rows: Vec<_> = files
+            .iter()
+            .map(|f| {
+                (
+                    f.id.clone(),
+                    f.atomic,
+                    f.directory_type,
+                    f.suggested_action.as_ref().map(|s| s.target_path.clone()),
+                )
+            })
+            .collect();
+        rows.sort_by(|a, b| a.0.cmp(&b.0));
+        rows
+    };
+
+    assert_eq!(summarize(&batch), summarize(&streamed));
+    assert!(find_file(&streamed, "app.exe").atomic);
+    assert!(find_file(&streamed, "index.js").atomic);
+    assert!(find_file(&streamed, "photo.jpg").suggested_action.is_some());
+}
+
+#[test]
+fn sim_folder_readme_written_once_and_ignored_by_scan() {
+    let dir = tempdir().unwrap();
+    let input = dir.path().join("input");
+    let output = dir.path().join("output");
+    let data = dir.path().join("data");
+
+    write_file(&input.join("a.pdf"), "a");
+    write_file(&input.join("b.pdf"), "b");
+
+    let mut executor = Executor::new(data);
+    executor.set_write_folder_readme(true);
+
+    let category = output.join("Documents");
+    let readme = category.join(FOLDER_README_NAME);
+
+    let mut batch_ids = Vec::new();
+    for name in ["a.pdf", "b.pdf"] {
+        let mut plan = crate::core::models::MovePlan::new();
+        plan.add_operation(input.join(name), category.join(name), name.to_string());
+        let result = executor.execute(&mut plan);
+        assert_eq!(result.successful, 1);
+        batch_ids.push(plan.batch_id.clone());
+
+        // 说明文件只在目录首次创建时写入，第二批不会覆盖
+        let content = fs::read_to_string(&readme).unwrap();
+        assert!(content.contains("分类: Documents"));
+        assert!(content.contains("首个移入文件: a.pdf"));
+    }
+
+    // 重新扫描时说明文件不会被当作待整理文件
+    let files = FileScanner::new(output.clone()).scan().unwrap();
+    assert!(files.iter().all(|f| f.name != FOLDER_README_NAME));
+    assert_eq!(files.iter().filter(|f| !f.is_directory).count(), 2);
+
+    // 全部回滚后目录连同说明文件一起清理
+    for batch_id in batch_ids.iter().rev() {
+        assert!(executor.rollback(batch_id).is_all_successful());
+    }
+    assert!(!category.exists());
+}
+
+#[test]
+fn sim_naming_policy_renames_on_move_and_restores_on_rollback() {
+    let dir = tempdir().unwrap();
+    let input = dir.path().join("input");
+    let output = dir.path().join("output");
+
+    write_file(&input.join("My Holiday Photo.JPG"), "jpg");
+
+    let mut files = FileScanner::new(input.clone()).scan().unwrap();
+    let mut engine = RuleEngine::new();
+    for f in files.iter_mut() {
+        f.modified_at = make_fixed_time();
+        f.suggested_action = engine.match_file(f);
+    }
+
+    let mut planner = Planner::new(output.clone(), 0.0);
+    planner.set_naming_policy(NamingPolicy {
+        lowercase: true,
+        replace_spaces: true,
+        prefix_year: false,
+    });
+    let mut plan = planner.generate_plan(&files);
+    let target = output.join("Pictures/2024/06/my_holiday_photo.jpg");
+    assert_eq!(plan.operations[0].to, target);
+
+    let mut exec = Executor::new(dir.path().join("data"));
+    assert!(exec.execute(&mut plan).is_all_successful());
+    assert!(target.exists());
+
+    let rb = exec.rollback(&plan.batch_id);
+    assert_eq!(rb.failed, 0);
+    assert!(input.join("My Holiday Photo.JPG").exists());
+    assert!(!target.exists());
+}
+
+#[test]
+fn sim_atomic_scan_root_is_refused() {
+    let dir = tempdir().unwrap();
+    let project = dir.path().join("webapp");
+
+    write_file(&project.join("package.json"), "{}");
+    write_file(&project.join("node_modules/left-pad/index.js"), "x");
+    write_file(&project.join("docs/notes.txt"), "notes");
+    write_file(&project.join(".git/HEAD"), "ref");
+
+    let scanner = FileScanner::new(project.clone());
+    let analyzer = BoundaryAnalyzer::new();
+    let mut engine = RuleEngine::new();
+
+    let err = scan_and_match(&scanner, &analyzer, &mut engine).unwrap_err();
+    assert!(err.to_string().contains("原子目录"));
+
+    // 只提供非原子的一级子目录供单独整理
+    let subdirs = analyzer.safe_subdirs(&project);
+    assert_eq!(subdirs, vec![project.join("docs")]);
+    let result = scan_and_match(&FileScanner::new(subdirs[0].clone()), &analyzer, &mut engine).unwrap();
+    assert_eq!(result.files.len(), 1);
+}
+
+#[test]
+fn sim_atomic_project_directory_moves_as_one_unit() {
+    let dir = tempdir().unwrap();
+    let input = dir.path().join("input");
+    let output = dir.path().join("output");
+    let project = input.join("webapp");
+
+    write_file(&project.join("package.json"), "{}");
+    write_file(&project.join("src/index.js"), "main");
+    // 依赖包自身也带 package.json，但已随项目整体移动，不能再单独计划
+    write_file(&project.join("node_modules/left-pad/package.json"), "{}");
+    write_file(&project.join("node_modules/left-pad/index.js"), "x");
+    write_file(&input.join("note.txt"), "hello");
+
+    let mut engine = RuleEngine::new();
+    engine.set_rule_enabled(PROJECTS_RULE_ID, true);
+    let mut files = scan_and_match(&FileScanner::new(input.clone()), &BoundaryAnalyzer::new(), &mut engine)
+        .unwrap()
+        .files;
+    for f in files.iter_mut() {
+        f.selected = true;
+    }
+
+    let mut plan = Planner::new(output.clone(), 0.0).generate_plan(&files);
+    let target = output.join("Projects").join("webapp");
+    let dir_ops: Vec<_> = plan.operations.iter().filter(|op| op.from.starts_with(&project)).collect();
+    assert_eq!(dir_ops.len(), 1, "项目目录应只有一个整体移动操作: {:?}", dir_ops);
+    assert_eq!(dir_ops[0].from, project);
+    assert_eq!(dir_ops[0].to, target);
+
+    let mut exec = Executor::new(dir.path().join("data"));
+    assert!(exec.execute(&mut plan).is_all_successful());
+    assert!(!project.exists());
+    assert!(target.join("node_modules/left-pad/index.js").exists());
+    assert!(target.join("src/index.js").exists());
+
+    assert!(exec.rollback(&plan.batch_id).is_all_successful());
+    assert!(project.join("node_modules/left-pad/package.json").exists());
+    assert!(project.join("src/index.js").exists());
+    assert!(!target.exists());
+}
+
+#[test]
+fn sim_rule_and_ai_agreement_boosts_confidence() {
+    let invoice = |name: &str, tag: &str| {
+        let mut file = FileDescriptor::new(
+            PathBuf::from("/inbox").join(name),
+            name.to_string(),
+            ".pdf".to_string(),
+            1024,
+            make_fixed_time(),
+            false,
+        );
+        file.semantic = Some(SemanticResult {
+            tags: vec![tag.to_string()],
+            year: Some(2024),
+            confidence: 0.9,
+            ..Default::default()
+        });
+        file
+    };
+    // 模型给出的路径：发票与规则的 Finance/Invoice/2024 一致（上级目录），另一个放进合同目录
+    let model = |f: &FileDescriptor| {
+        let target = match f.name.as_str() {
+            "invoice-2024.pdf" => "Finance/Invoice",
+            "bill-2024.pdf" => "Documents/Contracts/2024",
+            _ => return None,
+        };
+        Some(MoveSuggestion {
+            target_path: PathBuf::from(target),
+            reason: "模型建议".to_string(),
+            source: SuggestionSource::AI,
+            confidence: 0.7,
+            matched_rule_id: None,
+            rule_origin: None,
+            fused: false,
+        })
+    };
+    let mut files = vec![
+        invoice("invoice-2024.pdf", "invoice"),
+        invoice("bill-2024.pdf", "contract"),
+        invoice("receipt-2024.pdf", "invoice"),
+    ];
+
+    let mut engine = RuleEngine::new();
+    let mut planner = Planner::new(PathBuf::from("/out"), 0.5);
+    let fused = planner.fuse_file_suggestions(&mut files, |f| engine.match_file(f), model);
+    assert_eq!(fused, 2, "模型没有给出路径的文件不参与融合");
+
+    let agreed = files[0].suggested_action.clone().unwrap();
+    assert_eq!(agreed.target_path, PathBuf::from("Finance/Invoice/2024"));
+    assert_eq!(agreed.source, SuggestionSource::Rule);
+    assert!(agreed.reason.starts_with("规则+AI一致"));
+    assert!(agreed.confidence > 0.9, "一致时置信度应高于单独的规则: {}", agreed.confidence);
+
+    let disagreed = files[1].suggested_action.clone().unwrap();
+    assert_eq!(disagreed.target_path, PathBuf::from("Finance/Invoice/2024"));
+    assert!(disagreed.confidence < agreed.confidence);
+
+    // 离线的标签分类表不与规则融合，规则建议原样保留
+    let rule_only = files[2].suggested_action.clone().unwrap();
+    assert!(!rule_only.fused);
+    assert_eq!(rule_only.source, SuggestionSource::Rule);
+
+    // 权重可配置：加重AI后一致时的置信度随之变化
+    planner.set_fusion_weights(0.5, 0.5);
+    let mut files = vec![invoice("invoice-2024.pdf", "invoice")];
+    planner.fuse_file_suggestions(&mut files, |f| engine.match_file(f), model);
+    let confidence = files[0].suggested_action.as_ref().unwrap().confidence;
+    assert!((confidence - (0.9 * 0.5 + 0.7 * 0.5) * 1.1).abs() < 1e-4);
+}
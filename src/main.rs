@@ -31,7 +31,7 @@ fn setup_custom_fonts(ctx: &egui::Context) {
         if let Ok(font_data) = std::fs::read(path) {
             fonts.font_data.insert(
                 "chinese_font".to_owned(),
-                FontData::from_owned(font_data).into(),
+                FontData::from_owned(font_data),
             );
             
             // 将中文字体设为首选
@@ -67,12 +67,24 @@ fn main() -> Result<()> {
 
     tracing::info!("启动 Orderly - AI增强型文件整理工具");
 
+    // 恢复上次关闭时的窗口几何
+    let window = storage::config::ConfigManager::new(storage::config::ConfigManager::default_path())
+        .load()
+        .map(|config| config.window)
+        .unwrap_or_default();
+
+    let mut viewport = egui::ViewportBuilder::default()
+        .with_inner_size(window.inner_size.unwrap_or([1280.0, 800.0]))
+        .with_min_inner_size([800.0, 600.0])
+        .with_maximized(window.maximized)
+        .with_title("Orderly - AI增强型文件整理工具");
+    if let Some(position) = window.position {
+        viewport = viewport.with_position(position);
+    }
+
     // 启动GUI
     let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default()
-            .with_inner_size([1280.0, 800.0])
-            .with_min_inner_size([800.0, 600.0])
-            .with_title("Orderly - AI增强型文件整理工具"),
+        viewport,
         ..Default::default()
     };
 
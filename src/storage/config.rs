@@ -1,10 +1,29 @@
 //! 配置文件管理模块
 
-use crate::core::models::AppConfig;
-use anyhow::Result;
-use std::path::PathBuf;
+use crate::core::models::{AppConfig, ConfigProfile};
+use anyhow::{anyhow, Result};
+use std::path::{Path, PathBuf};
+
+/// 配置文件格式，由文件扩展名决定
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConfigFormat {
+    Json,
+    Toml,
+}
+
+impl ConfigFormat {
+    /// 按扩展名判断格式，.toml 以外都按 JSON 处理
+    fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => ConfigFormat::Toml,
+            _ => ConfigFormat::Json,
+        }
+    }
+}
 
 /// 配置管理器
+///
+/// 配置文件为 .toml 时按 TOML 读写，否则按 JSON 读写。
 pub struct ConfigManager {
     config_path: PathBuf,
 }
@@ -22,24 +41,55 @@ impl ConfigManager {
             .unwrap_or_else(|| PathBuf::from("config.json"))
     }
 
-    /// 加载配置
+    /// 加载配置，并用环境变量覆盖AI配置（见 AppConfig::apply_env_overrides）
     pub fn load(&self) -> Result<AppConfig> {
+        let mut config = self.read_file()?;
+        config.apply_env_overrides();
+        Ok(config)
+    }
+
+    /// 配置文件中的全部配置档案名称（"default" 在最前）
+    pub fn list_profiles(&self) -> Result<Vec<String>> {
+        Ok(self.read_file()?.profile_names())
+    }
+
+    /// 加载指定名称的配置档案
+    pub fn load_profile(&self, name: &str) -> Result<ConfigProfile> {
+        self.read_file()?
+            .profile(name)
+            .ok_or_else(|| anyhow!("配置档案不存在: {}", name))
+    }
+
+    /// 读取配置文件中的配置（不应用环境变量），文件不存在时为默认配置
+    fn read_file(&self) -> Result<AppConfig> {
         if self.config_path.exists() {
             let content = std::fs::read_to_string(&self.config_path)?;
-            Ok(serde_json::from_str(&content)?)
+            let mut config: AppConfig = match ConfigFormat::from_path(&self.config_path) {
+                ConfigFormat::Json => serde_json::from_str(&content)?,
+                ConfigFormat::Toml => toml::from_str(&content)?,
+            };
+            config.ai_config.confidence_calibration.normalize();
+            Ok(config)
         } else {
             Ok(AppConfig::default())
         }
     }
 
-    /// 保存配置
+    /// 保存配置（环境变量覆盖的值不写入文件，保留文件中原有的值）
     pub fn save(&self, config: &AppConfig) -> Result<()> {
         // 确保目录存在
         if let Some(parent) = self.config_path.parent() {
             std::fs::create_dir_all(parent)?;
         }
 
-        let content = serde_json::to_string_pretty(config)?;
+        let mut config = config.clone();
+        config.remove_env_overrides(&self.read_file().unwrap_or_default());
+        let config = &config;
+
+        let content = match ConfigFormat::from_path(&self.config_path) {
+            ConfigFormat::Json => serde_json::to_string_pretty(config)?,
+            ConfigFormat::Toml => toml::to_string_pretty(config)?,
+        };
         std::fs::write(&self.config_path, content)?;
         Ok(())
     }
@@ -53,21 +103,148 @@ impl ConfigManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::models::{ConfidenceCalibration, ENV_AI_API_KEY, ENV_AI_ENDPOINT, ENV_AI_MODEL};
+    use std::sync::Mutex;
     use tempfile::tempdir;
 
+    /// 读写环境变量的测试互斥执行，避免影响并行的加载测试
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
     #[test]
     fn test_config_save_load() {
+        let _env = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
         let dir = tempdir().unwrap();
         let config_path = dir.path().join("config.json");
         
         let manager = ConfigManager::new(config_path);
         
-        let mut config = AppConfig::default();
-        config.confidence_threshold = 0.8;
+        let config = AppConfig {
+            confidence_threshold: 0.8,
+            ..Default::default()
+        };
         
         manager.save(&config).unwrap();
         
         let loaded = manager.load().unwrap();
         assert_eq!(loaded.confidence_threshold, 0.8);
     }
+
+    #[test]
+    fn test_load_sorts_piecewise_calibration_knots() {
+        let _env = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = tempdir().unwrap();
+        let manager = ConfigManager::new(dir.path().join("config.json"));
+        let mut config = AppConfig::default();
+        config.ai_config.confidence_calibration =
+            ConfidenceCalibration::Piecewise(vec![(0.6, 0.3), (0.2, 0.8)]);
+        manager.save(&config).unwrap();
+
+        let loaded = manager.load().unwrap();
+        assert_eq!(
+            loaded.ai_config.confidence_calibration,
+            ConfidenceCalibration::Piecewise(vec![(0.2, 0.8), (0.6, 0.8)])
+        );
+    }
+
+    #[test]
+    fn test_config_round_trip_json_and_toml() {
+        let _env = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = tempdir().unwrap();
+        let config = AppConfig {
+            confidence_threshold: 0.65,
+            default_output_base: Some(PathBuf::from("/home/user/Sorted")),
+            ..Default::default()
+        };
+
+        for name in ["config.json", "config.toml"] {
+            let path = dir.path().join(name);
+            let manager = ConfigManager::new(path.clone());
+            manager.save(&config).unwrap();
+
+            let loaded = manager.load().unwrap();
+            assert_eq!(
+                serde_json::to_value(&loaded).unwrap(),
+                serde_json::to_value(&config).unwrap(),
+                "{}",
+                name
+            );
+        }
+
+        // TOML 文件是人可编辑的 TOML 而不是 JSON
+        let content = std::fs::read_to_string(dir.path().join("config.toml")).unwrap();
+        assert!(content.contains("confidence_threshold = 0.65"), "{}", content);
+        assert!(toml::from_str::<toml::Table>(&content).is_ok());
+    }
+
+    #[test]
+    fn test_env_overrides_ai_config() {
+        let _env = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = tempdir().unwrap();
+        let manager = ConfigManager::new(dir.path().join("config.json"));
+        let mut config = AppConfig::default();
+        config.ai_config.api_key = "file-key".to_string();
+        config.ai_config.model_name = "file-model".to_string();
+        manager.save(&config).unwrap();
+
+        std::env::set_var(ENV_AI_API_KEY, "env-key");
+        std::env::set_var(ENV_AI_ENDPOINT, "https://api.example.com/v1");
+        std::env::set_var(ENV_AI_MODEL, "  ");
+        let loaded = manager.load();
+        let saved = loaded.as_ref().ok().map(|c| manager.save(c).is_ok());
+        for var in [ENV_AI_API_KEY, ENV_AI_ENDPOINT, ENV_AI_MODEL] {
+            std::env::remove_var(var);
+        }
+
+        // 环境变量优先，空值不生效
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.ai_config.api_key, "env-key");
+        assert_eq!(loaded.ai_config.api_endpoint, "https://api.example.com/v1");
+        assert_eq!(loaded.ai_config.model_name, "file-model");
+
+        // 保存时不把环境变量里的密钥写进文件
+        assert_eq!(saved, Some(true));
+        let stored = manager.load().unwrap();
+        assert_eq!(stored.ai_config.api_key, "file-key");
+        assert_eq!(stored.ai_config.api_endpoint, AppConfig::default().ai_config.api_endpoint);
+    }
+
+    #[test]
+    fn test_named_profiles_alongside_default() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.json");
+
+        // 没有档案的旧配置只有 default 档案
+        let mut old = serde_json::to_value(AppConfig::default()).unwrap();
+        let fields = old.as_object_mut().unwrap();
+        fields.remove("profiles");
+        fields.remove("active_profile");
+        std::fs::write(&path, old.to_string()).unwrap();
+        let manager = ConfigManager::new(path.clone());
+        assert_eq!(manager.list_profiles().unwrap(), vec!["default"]);
+        assert_eq!(manager.load_profile("default").unwrap().confidence_threshold, 0.7);
+
+        let mut config = AppConfig {
+            default_scan_path: Some(PathBuf::from("/data")),
+            ..Default::default()
+        };
+        config.set_profile(
+            "Photos",
+            ConfigProfile {
+                default_scan_path: Some(PathBuf::from("/mnt/camera")),
+                default_output_base: Some(PathBuf::from("/mnt/photos")),
+                confidence_threshold: 0.9,
+            },
+        );
+        manager.save(&config).unwrap();
+
+        // 档案以名称为键保存为映射
+        let raw: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(raw["profiles"]["Photos"]["confidence_threshold"], 0.9);
+
+        assert_eq!(manager.list_profiles().unwrap(), vec!["default", "Photos"]);
+        let photos = manager.load_profile("Photos").unwrap();
+        assert_eq!(photos.default_scan_path, Some(PathBuf::from("/mnt/camera")));
+        assert_eq!(manager.load_profile("default").unwrap().default_scan_path, Some(PathBuf::from("/data")));
+        assert!(manager.load_profile("Work").is_err());
+    }
 }
@@ -1,11 +1,104 @@
 //! 数据库存储模块
 //! 
-//! 使用SQLite存储规则和历史记录
+//! 使用SQLite存储规则、历史记录、整理记忆和文件备注
 
-use crate::core::models::{HistoryEntry, RuleDefinition};
+use crate::core::executor::{HistoryStore, JsonHistoryStore};
+use crate::core::models::{ExecutionMode, FileDescriptor, HistoryEntry, RuleDefinition, RuleOrigin};
 use anyhow::Result;
-use rusqlite::{Connection, params};
-use std::path::PathBuf;
+use rusqlite::{Connection, Row, params};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// 数据目录下的数据库文件名
+pub const DATABASE_FILE_NAME: &str = "orderly.db";
+
+/// 数据库被另一个连接（如执行器的历史记录连接）锁定时等待的时长
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 历史 JSON 文件导入数据库后改成的后缀
+pub const IMPORTED_HISTORY_SUFFIX: &str = ".imported";
+
+/// 数据库结构迁移，按顺序执行，第 N 个迁移完成后结构版本为 N
+///
+/// 只能在末尾追加新迁移；已发布的迁移不要修改。迁移需要幂等，
+/// 没有版本号的旧数据库会从头执行一遍。
+const MIGRATIONS: &[fn(&Connection) -> Result<()>] =
+    &[create_base_tables, add_rule_last_hit, add_history_mode];
+
+/// 当前代码对应的数据库结构版本
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// 版本 1：初始表结构
+fn create_base_tables(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        -- 规则表
+        CREATE TABLE IF NOT EXISTS rules (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            priority INTEGER NOT NULL DEFAULT 50,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            condition_json TEXT NOT NULL,
+            action_json TEXT NOT NULL,
+            origin TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            hit_count INTEGER NOT NULL DEFAULT 0
+        );
+
+        -- 历史记录表
+        CREATE TABLE IF NOT EXISTS history (
+            batch_id TEXT PRIMARY KEY,
+            executed_at TEXT NOT NULL,
+            operations_json TEXT NOT NULL,
+            rolled_back INTEGER NOT NULL DEFAULT 0
+        );
+
+        -- 记忆缓存表（文件特征 -> 路径映射）
+        CREATE TABLE IF NOT EXISTS memory_cache (
+            feature_hash TEXT PRIMARY KEY,
+            target_path TEXT NOT NULL,
+            hit_count INTEGER NOT NULL DEFAULT 1,
+            last_hit TEXT NOT NULL
+        );
+
+        -- 文件备注表（文件ID -> 备注）
+        CREATE TABLE IF NOT EXISTS file_notes (
+            file_id TEXT PRIMARY KEY,
+            note TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+
+        -- 创建索引
+        CREATE INDEX IF NOT EXISTS idx_rules_priority ON rules(priority DESC);
+        CREATE INDEX IF NOT EXISTS idx_rules_enabled ON rules(enabled);
+        CREATE INDEX IF NOT EXISTS idx_history_executed ON history(executed_at DESC);
+        "#,
+    )?;
+    Ok(())
+}
+
+/// 版本 2：规则表增加最后命中时间
+fn add_rule_last_hit(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "rules", "last_hit", "TEXT")
+}
+
+/// 版本 3：历史记录增加执行方式（复制批次回滚时删除副本）
+fn add_history_mode(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "history", "mode", "TEXT NOT NULL DEFAULT 'Move'")
+}
+
+/// 列不存在时才添加，供迁移保持幂等
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    let exists = conn
+        .prepare("SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2")?
+        .exists(params![table, column])?;
+    if !exists {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+    }
+    Ok(())
+}
 
 /// 数据库管理器
 pub struct Database {
@@ -21,51 +114,28 @@ impl Database {
         }
 
         let conn = Connection::open(path)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
         let db = Self { conn };
-        db.init_tables()?;
+        db.migrate()?;
         Ok(db)
     }
 
-    /// 初始化表结构
-    fn init_tables(&self) -> Result<()> {
-        self.conn.execute_batch(
-            r#"
-            -- 规则表
-            CREATE TABLE IF NOT EXISTS rules (
-                id TEXT PRIMARY KEY,
-                name TEXT NOT NULL,
-                priority INTEGER NOT NULL DEFAULT 50,
-                enabled INTEGER NOT NULL DEFAULT 1,
-                condition_json TEXT NOT NULL,
-                action_json TEXT NOT NULL,
-                origin TEXT NOT NULL,
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL,
-                hit_count INTEGER NOT NULL DEFAULT 0
-            );
-
-            -- 历史记录表
-            CREATE TABLE IF NOT EXISTS history (
-                batch_id TEXT PRIMARY KEY,
-                executed_at TEXT NOT NULL,
-                operations_json TEXT NOT NULL,
-                rolled_back INTEGER NOT NULL DEFAULT 0
-            );
-
-            -- 记忆缓存表（文件特征 -> 路径映射）
-            CREATE TABLE IF NOT EXISTS memory_cache (
-                feature_hash TEXT PRIMARY KEY,
-                target_path TEXT NOT NULL,
-                hit_count INTEGER NOT NULL DEFAULT 1,
-                last_hit TEXT NOT NULL
-            );
-
-            -- 创建索引
-            CREATE INDEX IF NOT EXISTS idx_rules_priority ON rules(priority DESC);
-            CREATE INDEX IF NOT EXISTS idx_rules_enabled ON rules(enabled);
-            CREATE INDEX IF NOT EXISTS idx_history_executed ON history(executed_at DESC);
-            "#,
-        )?;
+    /// 当前数据库结构版本（保存在 SQLite 的 user_version 中）
+    pub fn schema_version(&self) -> Result<u32> {
+        Ok(self.conn.query_row("PRAGMA user_version", [], |row| row.get(0))?)
+    }
+
+    /// 按顺序执行尚未应用的迁移，每个迁移在单独的事务中完成并提升版本号
+    fn migrate(&self) -> Result<()> {
+        let current = self.schema_version()? as usize;
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(current) {
+            let version = index + 1;
+            let tx = self.conn.unchecked_transaction()?;
+            migration(&tx)?;
+            tx.pragma_update(None, "user_version", version as u32)?;
+            tx.commit()?;
+            tracing::info!("数据库结构已升级到版本 {}", version);
+        }
         Ok(())
     }
 
@@ -76,12 +146,13 @@ impl Database {
         let origin = format!("{:?}", rule.origin);
         let created_at = rule.created_at.to_rfc3339();
         let updated_at = rule.updated_at.to_rfc3339();
+        let last_hit = rule.last_hit.map(|t| t.to_rfc3339());
 
         self.conn.execute(
             r#"
             INSERT OR REPLACE INTO rules 
-            (id, name, priority, enabled, condition_json, action_json, origin, created_at, updated_at, hit_count)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+            (id, name, priority, enabled, condition_json, action_json, origin, created_at, updated_at, hit_count, last_hit)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
             "#,
             params![
                 rule.id,
@@ -94,6 +165,7 @@ impl Database {
                 created_at,
                 updated_at,
                 rule.hit_count,
+                last_hit,
             ],
         )?;
         Ok(())
@@ -103,7 +175,7 @@ impl Database {
     pub fn load_user_rules(&self) -> Result<Vec<RuleDefinition>> {
         let mut stmt = self.conn.prepare(
             r#"
-            SELECT id, name, priority, enabled, condition_json, action_json, origin, created_at, updated_at, hit_count
+            SELECT id, name, priority, enabled, condition_json, action_json, origin, created_at, updated_at, hit_count, last_hit
             FROM rules
             WHERE origin = 'UserConfirmed'
             ORDER BY priority DESC
@@ -116,6 +188,7 @@ impl Database {
             let origin_str: String = row.get(6)?;
             let created_at_str: String = row.get(7)?;
             let updated_at_str: String = row.get(8)?;
+            let last_hit_str: Option<String> = row.get(10)?;
 
             Ok(RuleDefinition {
                 id: row.get(0)?,
@@ -125,9 +198,9 @@ impl Database {
                 condition: serde_json::from_str(&condition_json).unwrap_or_default(),
                 action: serde_json::from_str(&action_json).unwrap_or_default(),
                 origin: if origin_str == "BuiltIn" {
-                    crate::core::models::RuleOrigin::BuiltIn
+                    RuleOrigin::BuiltIn
                 } else {
-                    crate::core::models::RuleOrigin::UserConfirmed
+                    RuleOrigin::UserConfirmed
                 },
                 created_at: chrono::DateTime::parse_from_rfc3339(&created_at_str)
                     .map(|d| d.with_timezone(&chrono::Utc))
@@ -136,6 +209,9 @@ impl Database {
                     .map(|d| d.with_timezone(&chrono::Utc))
                     .unwrap_or_else(|_| chrono::Utc::now()),
                 hit_count: row.get(9)?,
+                last_hit: last_hit_str
+                    .and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok())
+                    .map(|d| d.with_timezone(&chrono::Utc)),
             })
         })?;
 
@@ -148,21 +224,46 @@ impl Database {
         Ok(())
     }
 
-    /// 保存历史记录
-    pub fn save_history(&self, entry: &HistoryEntry) -> Result<()> {
+    /// 把指定规则的当前状态同步到数据库：用户规则写入，已不存在的规则删除
+    ///
+    /// 内置规则由程序自带，从不写入数据库。
+    pub fn sync_rules(&self, rules: &[RuleDefinition], rule_ids: &[String]) -> Result<()> {
+        for id in rule_ids {
+            match rules.iter().find(|r| &r.id == id) {
+                Some(rule) if rule.origin == RuleOrigin::UserConfirmed => self.save_rule(rule)?,
+                Some(_) => {}
+                None => self.delete_rule(id)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// 保存单条历史记录（内容未变化时不写入）
+    pub fn save_history_entry(&self, entry: &HistoryEntry) -> Result<()> {
         let operations_json = serde_json::to_string(&entry.operations)?;
         let executed_at = entry.executed_at.to_rfc3339();
+        let mode = format!("{:?}", entry.mode);
 
         self.conn.execute(
             r#"
-            INSERT OR REPLACE INTO history (batch_id, executed_at, operations_json, rolled_back)
-            VALUES (?1, ?2, ?3, ?4)
+            INSERT INTO history (batch_id, executed_at, operations_json, rolled_back, mode)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            ON CONFLICT(batch_id) DO UPDATE SET
+                executed_at = excluded.executed_at,
+                operations_json = excluded.operations_json,
+                rolled_back = excluded.rolled_back,
+                mode = excluded.mode
+            WHERE executed_at IS NOT excluded.executed_at
+                OR operations_json IS NOT excluded.operations_json
+                OR rolled_back IS NOT excluded.rolled_back
+                OR mode IS NOT excluded.mode
             "#,
             params![
                 entry.batch_id,
                 executed_at,
                 operations_json,
                 entry.rolled_back,
+                mode,
             ],
         )?;
         Ok(())
@@ -172,28 +273,42 @@ impl Database {
     pub fn load_recent_history(&self, limit: usize) -> Result<Vec<HistoryEntry>> {
         let mut stmt = self.conn.prepare(
             r#"
-            SELECT batch_id, executed_at, operations_json, rolled_back
+            SELECT batch_id, executed_at, operations_json, rolled_back, mode
             FROM history
             ORDER BY executed_at DESC
             LIMIT ?1
             "#,
         )?;
 
-        let entries = stmt.query_map(params![limit], |row| {
-            let operations_json: String = row.get(2)?;
-            let executed_at_str: String = row.get(1)?;
+        let entries = stmt.query_map(params![limit], history_from_row)?;
+        entries.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
 
-            Ok(HistoryEntry {
-                batch_id: row.get(0)?,
-                executed_at: chrono::DateTime::parse_from_rfc3339(&executed_at_str)
-                    .map(|d| d.with_timezone(&chrono::Utc))
-                    .unwrap_or_else(|_| chrono::Utc::now()),
-                operations: serde_json::from_str(&operations_json).unwrap_or_default(),
-                rolled_back: row.get(3)?,
-            })
-        })?;
+    /// 把历史 JSON 文件中数据库里还没有的批次导入数据库，返回导入的批次数
+    ///
+    /// 导入后文件改名加上 IMPORTED_HISTORY_SUFFIX 后缀，之后不会再次导入。
+    pub fn import_history_json(&self, path: &Path) -> Result<usize> {
+        if !path.exists() {
+            return Ok(0);
+        }
+        let entries = JsonHistoryStore::new(path.to_path_buf()).load_history()?;
+        let existing: HashSet<String> = HistoryStore::load_history(self)?
+            .into_iter()
+            .map(|e| e.batch_id)
+            .collect();
 
-        entries.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+        let tx = self.conn.unchecked_transaction()?;
+        let mut imported = 0;
+        for entry in entries.iter().filter(|e| !existing.contains(&e.batch_id)) {
+            self.save_history_entry(entry)?;
+            imported += 1;
+        }
+        tx.commit()?;
+
+        let mut renamed = path.as_os_str().to_owned();
+        renamed.push(IMPORTED_HISTORY_SUFFIX);
+        std::fs::rename(path, renamed)?;
+        Ok(imported)
     }
 
     /// 保存记忆缓存
@@ -228,6 +343,45 @@ impl Database {
         }
     }
 
+    /// 保存文件备注，空备注删除记录
+    pub fn save_note(&self, file_id: &str, note: Option<&str>) -> Result<()> {
+        match note.map(str::trim).filter(|n| !n.is_empty()) {
+            Some(note) => {
+                self.conn.execute(
+                    r#"
+                    INSERT OR REPLACE INTO file_notes (file_id, note, updated_at)
+                    VALUES (?1, ?2, ?3)
+                    "#,
+                    params![file_id, note, chrono::Utc::now().to_rfc3339()],
+                )?;
+            }
+            None => {
+                self.conn.execute("DELETE FROM file_notes WHERE file_id = ?1", params![file_id])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 加载所有文件备注
+    pub fn load_notes(&self) -> Result<HashMap<String, String>> {
+        let mut stmt = self.conn.prepare("SELECT file_id, note FROM file_notes")?;
+        let notes = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        notes.collect::<Result<HashMap<_, _>, _>>().map_err(Into::into)
+    }
+
+    /// 按文件ID把已保存的备注挂回扫描结果，返回恢复的数量
+    pub fn attach_notes(&self, files: &mut [FileDescriptor]) -> Result<usize> {
+        let notes = self.load_notes()?;
+        let mut attached = 0;
+        for file in files.iter_mut() {
+            file.user_note = notes.get(&file.id).cloned();
+            if file.user_note.is_some() {
+                attached += 1;
+            }
+        }
+        Ok(attached)
+    }
+
     /// 清理旧的历史记录
     pub fn cleanup_old_history(&self, keep_count: usize) -> Result<usize> {
         let affected = self.conn.execute(
@@ -245,6 +399,64 @@ impl Database {
     }
 }
 
+impl HistoryStore for Database {
+    fn load_history(&self) -> Result<Vec<HistoryEntry>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT batch_id, executed_at, operations_json, rolled_back, mode
+            FROM history
+            ORDER BY executed_at ASC
+            "#,
+        )?;
+
+        let entries = stmt.query_map([], history_from_row)?;
+        entries.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    /// 删除不在列表中的批次，其余逐条写入（内容未变化的不写入）
+    fn save_history(&self, history: &[HistoryEntry]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        let keep: HashSet<&str> = history.iter().map(|e| e.batch_id.as_str()).collect();
+        let stored: Vec<String> = tx
+            .prepare("SELECT batch_id FROM history")?
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        for batch_id in stored.iter().filter(|id| !keep.contains(id.as_str())) {
+            tx.execute("DELETE FROM history WHERE batch_id = ?1", params![batch_id])?;
+        }
+        for entry in history {
+            self.save_history_entry(entry)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn save_entry(&self, _history: &[HistoryEntry], entry: &HistoryEntry) -> Result<()> {
+        self.save_history_entry(entry)
+    }
+}
+
+/// 从 (batch_id, executed_at, operations_json, rolled_back, mode) 行构造历史记录
+fn history_from_row(row: &Row) -> rusqlite::Result<HistoryEntry> {
+    let executed_at_str: String = row.get(1)?;
+    let operations_json: String = row.get(2)?;
+    let mode_str: String = row.get(4)?;
+
+    Ok(HistoryEntry {
+        batch_id: row.get(0)?,
+        executed_at: chrono::DateTime::parse_from_rfc3339(&executed_at_str)
+            .map(|d| d.with_timezone(&chrono::Utc))
+            .unwrap_or_else(|_| chrono::Utc::now()),
+        operations: serde_json::from_str(&operations_json).unwrap_or_default(),
+        rolled_back: row.get(3)?,
+        mode: if mode_str == "Copy" {
+            ExecutionMode::Copy
+        } else {
+            ExecutionMode::Move
+        },
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -255,7 +467,239 @@ mod tests {
         let dir = tempdir().unwrap();
         let db_path = dir.path().join("test.db");
         
-        let db = Database::open(&db_path).unwrap();
+        let _db = Database::open(&db_path).unwrap();
         assert!(db_path.exists());
     }
+
+    #[test]
+    fn test_old_schema_database_is_migrated() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("old.db");
+
+        // 早期版本的规则表：没有 last_hit 列，也没有结构版本号
+        {
+            let conn = Connection::open(&db_path).unwrap();
+            conn.execute_batch(
+                r#"
+                CREATE TABLE rules (
+                    id TEXT PRIMARY KEY,
+                    name TEXT NOT NULL,
+                    priority INTEGER NOT NULL DEFAULT 50,
+                    enabled INTEGER NOT NULL DEFAULT 1,
+                    condition_json TEXT NOT NULL,
+                    action_json TEXT NOT NULL,
+                    origin TEXT NOT NULL,
+                    created_at TEXT NOT NULL,
+                    updated_at TEXT NOT NULL,
+                    hit_count INTEGER NOT NULL DEFAULT 0
+                );
+                INSERT INTO rules VALUES ('r1', '发票', 60, 1, '{"filename_keywords":["invoice"]}',
+                    '{"move_to":"Finance"}', 'UserConfirmed',
+                    '2024-01-01T00:00:00+00:00', '2024-01-01T00:00:00+00:00', 3);
+                "#,
+            )
+            .unwrap();
+        }
+
+        let db = Database::open(&db_path).unwrap();
+        assert_eq!(db.schema_version().unwrap(), SCHEMA_VERSION);
+        let rules = db.load_user_rules().unwrap();
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].hit_count, 3);
+        assert!(rules[0].last_hit.is_none());
+        drop(db);
+
+        // 再次打开不会重复执行迁移
+        let db = Database::open(&db_path).unwrap();
+        assert_eq!(db.schema_version().unwrap(), SCHEMA_VERSION);
+        assert_eq!(db.load_user_rules().unwrap().len(), 1);
+    }
+
+    /// 写入两个批次、标记回滚后重新加载，内容应保持一致
+    fn assert_history_round_trip(store: &dyn HistoryStore) {
+        use crate::core::models::{ExecutionMode, MoveOperation, OperationStatus};
+
+        let entry = |batch_id: &str, mode: ExecutionMode, minutes: i64| HistoryEntry {
+            batch_id: batch_id.to_string(),
+            executed_at: chrono::Utc::now() - chrono::Duration::minutes(minutes),
+            operations: vec![MoveOperation {
+                from: PathBuf::from("/in").join(batch_id),
+                to: PathBuf::from("/out").join(batch_id),
+                file_id: batch_id.to_string(),
+                status: OperationStatus::Completed,
+                error: None,
+                source_hash: None,
+                trashed_target: false,
+            }],
+            rolled_back: false,
+            mode,
+        };
+        let mut history = vec![entry("old", ExecutionMode::Move, 10), entry("new", ExecutionMode::Copy, 1)];
+        store.save_history(&history).unwrap();
+
+        history[0].rolled_back = true;
+        history[0].operations[0].status = OperationStatus::RolledBack;
+        store.save_history(&history).unwrap();
+
+        let loaded = store.load_history().unwrap();
+        let ids: Vec<&str> = loaded.iter().map(|e| e.batch_id.as_str()).collect();
+        assert_eq!(ids, vec!["old", "new"]);
+        assert!(loaded[0].rolled_back);
+        assert_eq!(loaded[0].operations[0].status, OperationStatus::RolledBack);
+        assert_eq!(loaded[1].mode, ExecutionMode::Copy);
+        assert_eq!(loaded[1].operations[0].to, PathBuf::from("/out/new"));
+
+        // 保存更短的列表会删掉多余的记录
+        store.save_history(&history[1..]).unwrap();
+        assert_eq!(store.load_history().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_history_round_trip_on_both_backends() {
+        let dir = tempdir().unwrap();
+        assert_history_round_trip(&JsonHistoryStore::new(dir.path().join("history.json")));
+        assert_history_round_trip(&Database::open(&dir.path().join("test.db")).unwrap());
+    }
+
+    #[test]
+    fn test_saving_history_only_writes_changed_entries() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(&dir.path().join("test.db")).unwrap();
+        let entry = |batch_id: &str| HistoryEntry {
+            batch_id: batch_id.to_string(),
+            executed_at: chrono::Utc::now(),
+            operations: Vec::new(),
+            rolled_back: false,
+            mode: ExecutionMode::Move,
+        };
+        let mut history = vec![entry("a"), entry("b"), entry("c")];
+        db.save_history(&history).unwrap();
+
+        let before = db.conn.total_changes();
+        db.save_history(&history).unwrap();
+        assert_eq!(db.conn.total_changes(), before, "未变化的批次不应重写");
+
+        history[1].rolled_back = true;
+        db.save_entry(&history, &history[1]).unwrap();
+        assert_eq!(db.conn.total_changes(), before + 1);
+        assert!(db.load_history().unwrap()[1].rolled_back);
+    }
+
+    #[test]
+    fn test_history_json_imported_once() {
+        use crate::core::executor::Executor;
+        use crate::core::models::MovePlan;
+
+        let dir = tempdir().unwrap();
+        let data = dir.path().join("data");
+        std::fs::create_dir_all(dir.path().join("in")).unwrap();
+        std::fs::write(dir.path().join("in/a.txt"), "a").unwrap();
+
+        // 旧版本只把历史写在 JSON 文件里
+        let mut plan = MovePlan::new();
+        plan.add_operation(dir.path().join("in/a.txt"), dir.path().join("out/a.txt"), "a".to_string());
+        Executor::new(data.clone()).execute(&mut plan);
+
+        let db = Database::open(&data.join(DATABASE_FILE_NAME)).unwrap();
+        let json_path = data.join(crate::core::executor::HISTORY_FILE_NAME);
+        assert_eq!(db.import_history_json(&json_path).unwrap(), 1);
+        assert!(!json_path.exists());
+        assert_eq!(db.import_history_json(&json_path).unwrap(), 0);
+
+        // 使用数据库的执行器能看到并回滚导入的批次
+        let mut executor = Executor::with_history_store(data, Box::new(db));
+        assert_eq!(executor.get_history().len(), 1);
+        assert_eq!(executor.rollback(&plan.batch_id).failed, 0);
+        assert!(dir.path().join("in/a.txt").exists());
+    }
+
+    #[test]
+    fn test_copy_mode_history_survives_reopen() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let entry = HistoryEntry {
+            batch_id: "copy-batch".to_string(),
+            executed_at: chrono::Utc::now(),
+            operations: Vec::new(),
+            rolled_back: false,
+            mode: ExecutionMode::Copy,
+        };
+        Database::open(&db_path).unwrap().save_history_entry(&entry).unwrap();
+
+        let loaded = Database::open(&db_path).unwrap().load_recent_history(10).unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].mode, ExecutionMode::Copy);
+    }
+
+    #[test]
+    fn test_note_reattached_after_rescan() {
+        use crate::core::scanner::FileScanner;
+
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("inbox");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("report.txt"), "q3").unwrap();
+        let db = Database::open(&dir.path().join("test.db")).unwrap();
+
+        let scanned = FileScanner::new(root.clone()).scan().unwrap();
+        let report = scanned.iter().find(|f| f.name == "report.txt").unwrap();
+        db.save_note(&report.id, Some("问问 Bob")).unwrap();
+
+        // 重新扫描得到新的描述符，备注按ID恢复
+        let mut rescanned = FileScanner::new(root.clone()).scan().unwrap();
+        assert_eq!(db.attach_notes(&mut rescanned).unwrap(), 1);
+        let report = rescanned.iter().find(|f| f.name == "report.txt").unwrap();
+        assert_eq!(report.user_note.as_deref(), Some("问问 Bob"));
+
+        // 清空备注即删除
+        db.save_note(&report.id, Some("  ")).unwrap();
+        assert!(db.load_notes().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_user_rules_survive_reopen() {
+        use crate::core::models::{RuleAction, RuleCondition};
+        use crate::core::rule_engine::RuleEngine;
+
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+
+        let mut engine = RuleEngine::new();
+        let id = engine.add_rule(RuleDefinition::new(
+            "扫描件".to_string(),
+            RuleCondition {
+                filename_keywords: vec!["scan".to_string()],
+                ..Default::default()
+            },
+            RuleAction { move_to: "Scans".to_string() },
+        ));
+        let scanned = crate::core::models::FileDescriptor::new(
+            std::path::PathBuf::from("/inbox/scan.pdf"),
+            "scan.pdf".to_string(),
+            ".pdf".to_string(),
+            1,
+            chrono::Utc::now(),
+            false,
+        );
+        assert!(engine.match_file(&scanned).is_some());
+        {
+            let db = Database::open(&db_path).unwrap();
+            db.sync_rules(engine.get_rules(), &[id.clone(), "builtin_images".to_string()])
+                .unwrap();
+        }
+
+        // 重新打开后只有用户规则，ID 不变
+        let db = Database::open(&db_path).unwrap();
+        let loaded = db.load_user_rules().unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].id, id);
+        assert_eq!(loaded[0].condition.filename_keywords, vec!["scan"]);
+        assert_eq!(loaded[0].hit_count, 1);
+        assert!(loaded[0].last_hit.is_some());
+
+        // 删除后同步即从数据库移除
+        assert!(engine.remove_rule(&id));
+        db.sync_rules(engine.get_rules(), std::slice::from_ref(&id)).unwrap();
+        assert!(db.load_user_rules().unwrap().is_empty());
+    }
 }
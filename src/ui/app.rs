@@ -2,31 +2,56 @@
 //! 
 //! 整合所有模块，提供完整的用户界面。
 
-use crate::core::boundary::BoundaryAnalyzer;
-use crate::core::executor::{DryRunResult, Executor};
+use crate::core::boundary::{quick_check_atomic, BoundaryAnalyzer, BOUNDARY_CONFIG_FILE};
+use crate::core::executor::{
+    move_to_trash, DryRunResult, Executor, OperationReceipt, TransactionOutcome, HISTORY_FILE_NAME,
+};
+use crate::core::live_preview::{self, LivePreview, LIVE_PREVIEW_DEBOUNCE, LIVE_PREVIEW_MAX_FILES};
+use crate::core::memory::{apply_memory, learned_destinations};
+use crate::core::model_compare::{compare_models, ModelComparison, MAX_COMPARE_FILES};
+use crate::core::pipeline::scan_and_match_with_progress;
 use crate::core::models::{
-    AppConfig, FileDescriptor, MovePlan, RuleAction, RuleCondition, RuleDefinition,
+    AppConfig, ConflictStrategy, FileDescriptor, LayoutPreset, MovePlan, MoveSuggestion, RuleAction, RuleCondition,
+    RuleDefinition, RuleOrigin, SuggestionSource,
+};
+use crate::core::planner::{
+    format_bytes, DedupReport, PlanStats, Planner, ValidationErrorType, DEFAULT_COPY_THROUGHPUT,
+};
+use crate::core::regression::ScanSnapshot;
+use crate::core::rule_engine::{ImportStrategy, RuleEngine, RuleLintWarning, RuleOverlap, INSTALLERS_RULE_ID};
+use crate::core::rule_miner::{
+    cluster_borderline, propose_rules, session_suggestion, BorderlineCluster, BORDERLINE_MARGIN,
+    DEFAULT_MIN_SUPPORT,
+};
+use crate::core::scanner::{FileScanner, ScanResult};
+use crate::core::semantic::{
+    mock_semantic_analysis, SemanticEngine, ANALYSIS_BATCH_SIZE,
 };
-use crate::core::planner::Planner;
-use crate::core::rule_engine::RuleEngine;
-use crate::core::scanner::FileScanner;
-use crate::core::semantic::{mock_semantic_analysis, SemanticEngine};
 use crate::storage::config::ConfigManager;
+use crate::storage::database::{Database, DATABASE_FILE_NAME};
+use crate::ui::command_palette::CommandPalette;
 use crate::ui::dialogs::{
-    ErrorClusterDialog, ErrorClusterResult, ExecuteConfirmDialog, ExecuteConfirmResult,
-    PromptDialog, PromptDialogResult, RuleConfirmDialog, RuleConfirmResult,
-    SettingsDialog, SettingsResult,
+    AtomicRootDialog, AtomicRootResult, BorderlineClusterDialog, BorderlineClusterResult, CruftCleanupDialog, CruftCleanupResult, DiagnosticDialog, ErrorClusterDialog, ErrorClusterResult, ExecuteConfirmDialog, ExecuteConfirmResult,
+    ModelCompareDialog, PendingBatchDialog, PendingBatchResult, PromptDialog, PromptDialogResult, RuleConfirmDialog, RuleConfirmResult, RuleImportDialog,
+    RuleImportResult, RuleMiningDialog, RuleMiningResult, SettingsDialog, SettingsResult, UndoConfirmDialog,
+    UndoConfirmResult,
 };
-use crate::ui::preview_table::{PreviewTable, TableStats};
+use crate::ui::preview_table::{deselect_all, select_all, FileAction, PreviewTable, TableStats};
+use crate::ui::history_panel::{HistoryPanel, HistoryPanelAction, HISTORY_PANEL_ENTRIES};
 use crate::ui::rule_panel::{RulePanel, RulePanelAction};
 use crate::ui::styles::Theme;
 use eframe::egui::{self, RichText};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc;
+use std::sync::Arc;
 use std::sync::mpsc::{Receiver, Sender};
 use std::thread;
 use tokio::runtime::Runtime;
 
+/// 运行中定期保存窗口状态与最近使用目录的间隔
+const SESSION_SAVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
 /// 应用状态
 #[derive(PartialEq)]
 enum AppState {
@@ -43,19 +68,44 @@ enum AppState {
 }
 
 enum BackgroundEvent {
-    ScanFinished(Result<Vec<FileDescriptor>, String>),
+    ScanFinished {
+        result: Result<ScanResult, String>,
+        engine: RuleEngine,
+    },
+    ScanProgress { scanned: usize, current_path: PathBuf },
     AnalysisProgress { done: usize, total: usize },
-    AnalysisFinished(Vec<(String, crate::core::models::SemanticResult)>),
+    /// 语义分析结果，以及模型为规则已匹配的文件给出的路径建议（按文件 id）
+    AnalysisFinished {
+        results: Vec<(String, crate::core::models::SemanticResult)>,
+        ai_targets: std::collections::HashMap<String, MoveSuggestion>,
+    },
     ExecuteFinished {
         executor: Executor,
         batch_id: String,
         result: crate::core::executor::ExecutionResult,
+        receipt: OperationReceipt,
     },
     RollbackFinished {
         executor: Executor,
         batch_id: String,
         result: crate::core::executor::RollbackResult,
     },
+    /// 全部成功或全部撤销模式下有操作失败，整批已自动回滚
+    TransactionRolledBack {
+        executor: Executor,
+        batch_id: String,
+        failed_index: usize,
+        error: String,
+        result: crate::core::executor::RollbackResult,
+    },
+    /// 执行计划已在后台生成（含源文件哈希和重复文件检测）
+    PlanGenerated {
+        plan: MovePlan,
+        dedup: Option<DedupReport>,
+    },
+    AiTestFinished(Result<String, String>),
+    ModelCompareFinished(ModelComparison),
+    CleanupFinished(crate::core::executor::CleanupResult),
 }
 
 /// 主应用程序
@@ -79,6 +129,8 @@ pub struct OrderlyApp {
     output_path: String,
     /// 文件列表
     files: Vec<FileDescriptor>,
+    /// 数据库（用户规则、整理记忆、文件备注）
+    database: Option<Database>,
     /// 规则引擎
     rule_engine: Option<RuleEngine>,
     /// 计划生成器
@@ -101,8 +153,26 @@ pub struct OrderlyApp {
     execute_confirm_dialog: ExecuteConfirmDialog,
     /// 错误聚类对话框
     error_cluster_dialog: ErrorClusterDialog,
+    /// “为何没有建议”诊断对话框
+    diagnostic_dialog: DiagnosticDialog,
+    /// 模型对比结果对话框
+    model_compare_dialog: ModelCompareDialog,
+    /// 扫描根为原子目录时的提示对话框
+    atomic_root_dialog: AtomicRootDialog,
+    /// 导入规则对话框
+    rule_import_dialog: RuleImportDialog,
+    /// 系统缓存文件清理对话框
+    cruft_cleanup_dialog: CruftCleanupDialog,
+    /// 拿不准文件簇对话框
+    borderline_dialog: BorderlineClusterDialog,
     /// 设置对话框
     settings_dialog: SettingsDialog,
+    /// 规则归纳对话框
+    rule_mining_dialog: RuleMiningDialog,
+    /// 未完成批次对话框
+    pending_batch_dialog: PendingBatchDialog,
+    /// 撤销确认对话框
+    undo_confirm_dialog: UndoConfirmDialog,
     /// 状态消息
     status_message: String,
     /// 是否显示规则面板
@@ -113,13 +183,53 @@ pub struct OrderlyApp {
     correction_counter: std::collections::HashMap<String, u32>,
     /// 待确认的规则
     pending_rule: Option<RuleDefinition>,
+    /// 扫描线程占用规则引擎期间接受的规则，扫描结束后再添加
+    deferred_rules: Vec<RuleDefinition>,
+    /// 扫描线程占用规则引擎期间请求的规则导入（文件与合并方式），扫描结束后再导入
+    deferred_imports: Vec<(PathBuf, ImportStrategy)>,
+    /// 后台正在生成执行计划（计算文件哈希）
+    generating_plan: bool,
 
-    /// 历史面板：当前选择的批次
-    selected_batch_id: Option<String>,
+    /// 历史记录面板
+    history_panel: HistoryPanel,
 
     /// 分析进度
     analysis_done: usize,
     analysis_total: usize,
+
+    /// 上次扫描中出错的条目
+    scan_warnings: Vec<String>,
+    /// 扫描警告横幅是否展开
+    scan_warnings_expanded: bool,
+    /// 上次扫描的提示信息（如需要确认来源的可执行文件），不是错误
+    scan_notices: Vec<String>,
+    /// 扫描提示横幅是否展开
+    scan_notices_expanded: bool,
+
+    /// 命令面板（Ctrl+P）
+    command_palette: CommandPalette<OrderlyApp>,
+
+    /// 正在执行的批次中的文件（执行完成后用于归纳规则）
+    executing_files: Vec<FileDescriptor>,
+    /// 取消当前执行的标志
+    cancel_execution: Arc<AtomicBool>,
+    /// 取消当前扫描的标志
+    cancel_scan: Arc<AtomicBool>,
+    /// 上次扫描的唯一根目录（扫描根为程序目录时是所选子目录），原地整理时以它为输出基础
+    scanned_root: Option<PathBuf>,
+
+    /// 分析后等待用户指定目标的拿不准文件簇
+    pending_clusters: std::collections::VecDeque<BorderlineCluster>,
+    /// 本次运行的临时规则（不保存）
+    session_rules: Vec<RuleDefinition>,
+    /// 实时预览状态
+    live_preview: LivePreview,
+    /// 规则面板的检查结果缓存（规则指纹、lint 警告、规则重叠），规则变化时才重新计算
+    rule_checks: Option<(u64, Vec<RuleLintWarning>, Vec<RuleOverlap>)>,
+    /// 最近一次执行的回执（显示为可关闭的结果卡片）
+    last_receipt: Option<OperationReceipt>,
+    /// 上次定期保存窗口状态的时间
+    last_session_save: std::time::Instant,
 }
 
 impl OrderlyApp {
@@ -131,30 +241,33 @@ impl OrderlyApp {
             .unwrap_or_else(|| PathBuf::from("."));
 
         // 加载配置
-        let config_path = ConfigManager::default_path();
-        let config_manager = ConfigManager::new(config_path);
-        let config = match config_manager.load() {
-            Ok(c) => c,
+        let config_manager = ConfigManager::new(ConfigManager::default_path());
+        let StartupSettings {
+            config,
+            scan_path,
+            output_path,
+            settings_dialog,
+        } = StartupSettings::load(&config_manager);
+
+        let database_path = data_dir.join(DATABASE_FILE_NAME);
+        let database = match Database::open(&database_path) {
+            Ok(db) => Some(db),
             Err(e) => {
-                tracing::warn!("加载配置失败，使用默认配置: {}", e);
-                AppConfig::default()
+                tracing::warn!("打开数据库失败，文件备注不会保存: {}", e);
+                None
             }
         };
 
-        let scan_path = config
-            .default_scan_path
-            .as_ref()
-            .map(|p| p.to_string_lossy().to_string())
-            .unwrap_or_default();
-        let output_path = config
-            .default_output_base
-            .as_ref()
-            .map(|p| p.to_string_lossy().to_string())
-            .unwrap_or_default();
+        let rule_engine = Self::load_rule_engine(database.as_ref());
 
         let (bg_tx, bg_rx) = mpsc::channel();
 
-        Self {
+        let mut preview_table = PreviewTable::new();
+        preview_table.set_column_widths(config.preview_column_widths);
+        preview_table.set_naming_policy(config.naming_policy);
+        let window = config.window;
+
+        let mut app = Self {
             state: AppState::Initial,
             config,
             config_manager,
@@ -164,75 +277,787 @@ impl OrderlyApp {
             scan_path,
             output_path,
             files: Vec::new(),
-            rule_engine: None,
+            rule_engine: Some(rule_engine),
+            database,
             planner: None,
-            executor: Some(Executor::new(data_dir)),
+            executor: Some(history_executor(data_dir, &database_path)),
             current_plan: None,
             dry_run_result: None,
-            preview_table: PreviewTable::new(),
+            preview_table,
             rule_panel: RulePanel::new(),
             prompt_dialog: PromptDialog::default(),
             rule_confirm_dialog: RuleConfirmDialog::default(),
             execute_confirm_dialog: ExecuteConfirmDialog::default(),
             error_cluster_dialog: ErrorClusterDialog::default(),
-            settings_dialog: SettingsDialog::default(),
+            diagnostic_dialog: DiagnosticDialog::default(),
+            model_compare_dialog: ModelCompareDialog::default(),
+            atomic_root_dialog: AtomicRootDialog::default(),
+            rule_import_dialog: RuleImportDialog::default(),
+            cruft_cleanup_dialog: CruftCleanupDialog::default(),
+            borderline_dialog: BorderlineClusterDialog::default(),
+            settings_dialog,
+            rule_mining_dialog: RuleMiningDialog::default(),
+            pending_batch_dialog: PendingBatchDialog::default(),
+            undo_confirm_dialog: UndoConfirmDialog::default(),
             status_message: "请选择要整理的目录".to_string(),
-            show_rule_panel: false,
-            show_history_panel: false,
+            show_rule_panel: window.show_rule_panel,
+            show_history_panel: window.show_history_panel,
             correction_counter: std::collections::HashMap::new(),
             pending_rule: None,
+            deferred_rules: Vec::new(),
+            deferred_imports: Vec::new(),
+            generating_plan: false,
 
-            selected_batch_id: None,
+            history_panel: HistoryPanel::new(),
             analysis_done: 0,
             analysis_total: 0,
+            scan_warnings: Vec::new(),
+            scan_warnings_expanded: false,
+            scan_notices: Vec::new(),
+            scan_notices_expanded: false,
+            command_palette: Self::build_command_palette(),
+            executing_files: Vec::new(),
+            cancel_execution: Arc::new(AtomicBool::new(false)),
+            cancel_scan: Arc::new(AtomicBool::new(false)),
+            scanned_root: None,
+            pending_clusters: std::collections::VecDeque::new(),
+            session_rules: Vec::new(),
+            live_preview: LivePreview::default(),
+            rule_checks: None,
+            last_receipt: None,
+            last_session_save: std::time::Instant::now(),
+        };
+        app.live_preview.enabled = app.config.live_preview;
+        app.check_pending_batch();
+        app
+    }
+
+    /// 检查是否有上次未完成的批次，有则提示继续或回滚
+    fn check_pending_batch(&mut self) {
+        let pending = match self.executor.as_ref().and_then(|e| e.pending_batch()) {
+            Some(p) => p,
+            None => return,
+        };
+        let remaining = pending
+            .operations
+            .iter()
+            .filter(|op| op.status == crate::core::models::OperationStatus::Pending)
+            .count();
+        let completed = pending.operations.len() - remaining;
+        self.pending_batch_dialog.show(&pending.batch_id, completed, remaining);
+    }
+
+    /// 注册命令面板中的所有命令
+    fn build_command_palette() -> CommandPalette<OrderlyApp> {
+        let mut palette = CommandPalette::new();
+        palette.register("🚀 开始扫描", |app: &mut OrderlyApp| {
+            if !app.scan_path.is_empty() {
+                app.start_scan();
+            }
+        });
+        palette.register("📂 打开目录", |app: &mut OrderlyApp| {
+            app.pick_scan_folder();
+        });
+        palette.register("⚙️ 设置", |app: &mut OrderlyApp| app.open_settings());
+        palette.register("📤 导出规则", |app: &mut OrderlyApp| app.export_rules());
+        palette.register("📥 导入规则", |app: &mut OrderlyApp| app.pick_rules_to_import());
+        palette.register("📤 导出历史 CSV", |app: &mut OrderlyApp| app.export_history_csv());
+        palette.register("↩️ 撤销上次整理", |app: &mut OrderlyApp| app.undo_last_batch());
+        palette.register("📋 切换规则面板", |app: &mut OrderlyApp| {
+            app.show_rule_panel = !app.show_rule_panel;
+        });
+        palette.register("🕘 切换历史面板", |app: &mut OrderlyApp| {
+            app.show_history_panel = !app.show_history_panel;
+        });
+        palette.register("▶️ 预览执行", |app: &mut OrderlyApp| app.preview_execute());
+        palette.register("🔌 测试AI连接", |app: &mut OrderlyApp| app.test_ai_connection());
+        palette.register("⚖️ 对比两个AI模型", |app: &mut OrderlyApp| app.compare_ai_models());
+        palette.register("📸 保存扫描快照", |app: &mut OrderlyApp| app.save_snapshot());
+        palette.register("🧪 对快照回归模拟", |app: &mut OrderlyApp| app.replay_snapshot());
+        palette
+    }
+
+    /// 诊断文件为何没有建议，并显示诊断对话框
+    fn diagnose_file(&mut self, file_id: &str) {
+        let Some(file) = self.files.iter().find(|f| f.id == file_id) else {
+            return;
+        };
+        let mut lines = match self.rule_engine.as_ref() {
+            Some(engine) => engine.explain_no_suggestion(file),
+            None => vec!["尚未加载规则".to_string()],
+        };
+        if !self.config.ai_enabled {
+            lines.push("AI分析已在设置中关闭".to_string());
+        }
+        self.diagnostic_dialog.show(&file.name, lines);
+    }
+
+    /// 保存窗口几何、面板显示状态与最近使用的目录
+    fn save_window_state(&mut self, ctx: &egui::Context) {
+        self.capture_session_state(ctx);
+        if let Err(e) = self.config_manager.save(&self.config) {
+            tracing::warn!("保存窗口状态失败: {}", e);
+        }
+    }
+
+    /// 把窗口几何、面板显示状态与最近使用的目录记入配置，返回是否有变化
+    fn capture_session_state(&mut self, ctx: &egui::Context) -> bool {
+        let before = (
+            self.config.window,
+            self.config.last_scan_path.clone(),
+            self.config.last_output_path.clone(),
+        );
+        let path_or_none = |text: &str| Some(PathBuf::from(text.trim())).filter(|_| !text.trim().is_empty());
+        self.config.last_scan_path = path_or_none(&self.scan_path);
+        self.config.last_output_path = path_or_none(&self.output_path);
+
+        let (inner, outer, maximized) = ctx.input(|i| {
+            let viewport = i.viewport();
+            (viewport.inner_rect, viewport.outer_rect, viewport.maximized)
+        });
+
+        let window = &mut self.config.window;
+        window.maximized = maximized.unwrap_or(false);
+        // 最大化时保留之前的普通尺寸，恢复最大化后再还原时仍是原来的大小
+        if !window.maximized {
+            if let Some(rect) = inner {
+                window.inner_size = Some([rect.width(), rect.height()]);
+            }
+            if let Some(rect) = outer {
+                window.position = Some([rect.min.x, rect.min.y]);
+            }
+        }
+        window.show_rule_panel = self.show_rule_panel;
+        window.show_history_panel = self.show_history_panel;
+
+        before
+            != (
+                self.config.window,
+                self.config.last_scan_path.clone(),
+                self.config.last_output_path.clone(),
+            )
+    }
+
+    /// 切换配置档案：换用其扫描/输出目录与置信度阈值，并记住选择
+    fn switch_profile(&mut self, name: String) {
+        let Some(profile) = self.config.profile(&name) else {
+            return;
+        };
+        let path_text = |p: &Option<PathBuf>| {
+            p.as_ref().map(|p| p.to_string_lossy().to_string()).unwrap_or_default()
+        };
+        self.scan_path = path_text(&profile.default_scan_path);
+        self.output_path = path_text(&profile.default_output_base);
+        if let Some(ref mut planner) = self.planner {
+            planner.set_confidence_threshold(profile.confidence_threshold);
+        }
+
+        self.config.active_profile = name;
+        if let Err(e) = self.config_manager.save(&self.config) {
+            tracing::warn!("保存配置档案选择失败: {}", e);
+        }
+        self.status_message = format!("已切换到配置档案: {}", self.config.active_profile);
+    }
+
+    /// 选择扫描目录，返回是否选择了目录
+    fn pick_scan_folder(&mut self) -> bool {
+        match rfd::FileDialog::new().pick_folder() {
+            Some(path) => {
+                self.scan_path = path.to_string_lossy().to_string();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 在文件管理器中显示或用默认程序打开文件，失败时显示在状态栏
+    fn run_file_action(&mut self, action: FileAction, path: &Path) {
+        if !path.exists() {
+            self.status_message = format!("文件已不存在: {}", path.display());
+            return;
+        }
+        let result = match action {
+            FileAction::Reveal => opener::reveal(path),
+            FileAction::Open => opener::open(path),
+        };
+        if let Err(e) = result {
+            let verb = match action {
+                FileAction::Reveal => "在文件管理器中显示",
+                FileAction::Open => "打开",
+            };
+            self.status_message = format!("{}失败: {} ({})", verb, path.display(), e);
+        }
+    }
+
+    /// 预览中有勾选的文件时生成计划并显示执行确认
+    fn preview_execute(&mut self) {
+        if self.state == AppState::Preview && self.files.iter().any(|f| f.selected) {
+            self.generate_plan();
+        }
+    }
+
+    /// 处理拖放到窗口的文件或目录：单个目录（或同一目录下的文件）直接开始扫描
+    fn handle_dropped_files(&mut self, ctx: &egui::Context) {
+        let hovering = ctx.input(|i| !i.raw.hovered_files.is_empty());
+        if hovering {
+            let screen = ctx.screen_rect();
+            let painter = ctx.layer_painter(egui::LayerId::new(
+                egui::Order::Foreground,
+                egui::Id::new("drop_overlay"),
+            ));
+            painter.rect_filled(screen, 0.0, egui::Color32::from_black_alpha(160));
+            painter.text(
+                screen.center(),
+                egui::Align2::CENTER_CENTER,
+                "松开以扫描此目录",
+                egui::FontId::proportional(28.0),
+                egui::Color32::WHITE,
+            );
+        }
+
+        let dropped: Vec<PathBuf> =
+            ctx.input(|i| i.raw.dropped_files.iter().filter_map(|f| f.path.clone()).collect());
+        if dropped.is_empty() {
+            return;
+        }
+        if !matches!(self.state, AppState::Initial | AppState::Preview) {
+            self.status_message = "正在处理，完成后再拖放目录".to_string();
+            return;
+        }
+        match dropped_scan_root(&dropped) {
+            Some(root) => {
+                self.scan_path = root.to_string_lossy().to_string();
+                self.start_scan();
+            }
+            None => self.status_message = "一次只能拖放一个目录".to_string(),
+        }
+    }
+
+    /// 处理全局快捷键（文本框获得焦点时不处理，以免抢走编辑快捷键）
+    ///
+    /// Ctrl+O 打开目录并扫描，Ctrl+Enter 预览执行，Ctrl+Z 撤销上次整理，
+    /// 预览中 Ctrl+A 全选、Ctrl+Shift+A 全不选。
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.memory(|m| m.focused().is_some()) {
+            return;
+        }
+        let ctrl = egui::Modifiers::COMMAND;
+        let ctrl_shift = egui::Modifiers::COMMAND | egui::Modifiers::SHIFT;
+        let pressed = |modifiers, key| ctx.input_mut(|i| i.consume_key(modifiers, key));
+
+        let idle = matches!(self.state, AppState::Initial | AppState::Preview);
+        if pressed(ctrl, egui::Key::O) && idle && self.pick_scan_folder() {
+            self.start_scan();
+        }
+        if pressed(ctrl, egui::Key::Enter) {
+            self.preview_execute();
+        }
+        if pressed(ctrl, egui::Key::Z) && idle && !self.any_dialog_open() {
+            self.undo_last_batch();
+        }
+        // 先检查带 Shift 的组合，Ctrl+A 也会匹配按住 Shift 的情况
+        if pressed(ctrl_shift, egui::Key::A) {
+            if self.state == AppState::Preview {
+                deselect_all(&mut self.files);
+            }
+        } else if pressed(ctrl, egui::Key::A) && self.state == AppState::Preview {
+            select_all(&mut self.files);
+        }
+    }
+
+    /// 是否有对话框或命令面板打开（此时 Ctrl+Z 不生效）
+    fn any_dialog_open(&self) -> bool {
+        self.prompt_dialog.visible
+            || self.rule_confirm_dialog.visible
+            || self.execute_confirm_dialog.visible
+            || self.error_cluster_dialog.visible
+            || self.diagnostic_dialog.visible
+            || self.model_compare_dialog.visible
+            || self.atomic_root_dialog.visible
+            || self.rule_import_dialog.visible
+            || self.cruft_cleanup_dialog.visible
+            || self.borderline_dialog.visible
+            || self.settings_dialog.visible
+            || self.rule_mining_dialog.visible
+            || self.pending_batch_dialog.visible
+            || self.undo_confirm_dialog.visible
+            || self.command_palette.visible
+    }
+
+    /// 打开设置对话框
+    fn open_settings(&mut self) {
+        self.settings_dialog.load_from_config(&self.config);
+        self.settings_dialog.visible = true;
+    }
+
+    /// 导出用户规则到 JSON 文件
+    fn export_rules(&mut self) {
+        let json = match self.rule_engine.as_ref().map(|e| e.export_user_rules_to_json()) {
+            Some(Ok(json)) => json,
+            Some(Err(e)) => {
+                self.status_message = format!("导出规则失败: {}", e);
+                return;
+            }
+            None => {
+                self.status_message = "尚未加载规则，请先扫描".to_string();
+                return;
+            }
+        };
+
+        if let Some(path) = rfd::FileDialog::new()
+            .set_file_name("orderly_rules.json")
+            .add_filter("JSON", &["json"])
+            .save_file()
+        {
+            self.status_message = match std::fs::write(&path, json) {
+                Ok(_) => format!("规则已导出到 {}", path.display()),
+                Err(e) => format!("导出规则失败: {}", e),
+            };
+        }
+    }
+
+    /// 选择要导入的规则文件，再选择同ID规则的处理方式
+    fn pick_rules_to_import(&mut self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("JSON", &["json"])
+            .pick_file()
+        {
+            self.rule_import_dialog.show(path);
+        }
+    }
+
+    /// 添加用户接受的规则并写入数据库
+    ///
+    /// 扫描期间规则引擎在扫描线程中，规则先排队，扫描结束后添加；返回是否已立即添加。
+    fn add_accepted_rules(&mut self, rules: Vec<RuleDefinition>) -> bool {
+        let Some(ref mut engine) = self.rule_engine else {
+            self.deferred_rules.extend(rules);
+            return false;
+        };
+        let ids: Vec<String> = rules.into_iter().map(|rule| engine.add_rule(rule)).collect();
+        persist_rules(self.database.as_ref(), engine, &ids);
+        true
+    }
+
+    /// 规则引擎从扫描线程取回后，添加扫描期间排队的规则和规则导入
+    fn apply_deferred_rules(&mut self) {
+        for (path, strategy) in std::mem::take(&mut self.deferred_imports) {
+            self.import_rules(&path, strategy);
+            tracing::info!("扫描完成，已导入扫描期间请求的规则 {}: {}", path.display(), self.status_message);
+        }
+        if self.deferred_rules.is_empty() {
+            return;
+        }
+        let rules = std::mem::take(&mut self.deferred_rules);
+        let count = rules.len();
+        if self.add_accepted_rules(rules) {
+            tracing::info!("扫描完成，已添加扫描期间接受的 {} 条规则", count);
+        }
+    }
+
+    /// 从文件导入用户规则并写入数据库
+    ///
+    /// 扫描期间规则引擎在扫描线程中，导入先排队，扫描结束后执行。
+    fn import_rules(&mut self, path: &std::path::Path, strategy: ImportStrategy) {
+        let Some(ref mut engine) = self.rule_engine else {
+            self.deferred_imports.push((path.to_path_buf(), strategy));
+            self.status_message = "扫描进行中，规则将在扫描结束后导入".to_string();
+            return;
+        };
+        let report = std::fs::read_to_string(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| engine.load_from_json(&json, strategy));
+        self.status_message = match report {
+            Ok(report) => {
+                persist_rules(self.database.as_ref(), engine, &report.changed_ids);
+                report.summary()
+            }
+            Err(e) => format!("导入规则失败: {}", e),
+        };
+    }
+
+    /// 把全部历史操作导出为 CSV，便于在表格软件中分析
+    fn export_history_csv(&mut self) {
+        let Some(csv) = self.executor.as_ref().map(|e| e.export_history_csv()) else {
+            self.status_message = "暂无历史记录".to_string();
+            return;
+        };
+
+        if let Some(path) = rfd::FileDialog::new()
+            .set_file_name("orderly_history.csv")
+            .add_filter("CSV", &["csv"])
+            .save_file()
+        {
+            self.status_message = match std::fs::write(&path, csv) {
+                Ok(_) => format!("历史记录已导出到 {}", path.display()),
+                Err(e) => format!("导出历史记录失败: {}", e),
+            };
+        }
+    }
+
+    /// 把当前的预览结果导出为 JSON 或 CSV（按所选扩展名），便于比较不同次的计划
+    fn export_dry_run(&mut self) {
+        let Some(ref dry_run) = self.dry_run_result else {
+            self.status_message = "没有可导出的预览，请先生成计划".to_string();
+            return;
+        };
+
+        let Some(path) = rfd::FileDialog::new()
+            .set_file_name("orderly_dry_run.csv")
+            .add_filter("CSV", &["csv"])
+            .add_filter("JSON", &["json"])
+            .save_file()
+        else {
+            return;
+        };
+        let is_json = path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("json"));
+        let content = if is_json { dry_run.to_json() } else { Ok(dry_run.to_csv()) };
+        self.status_message = match content.and_then(|c| Ok(std::fs::write(&path, c)?)) {
+            Ok(()) => format!("预览已导出到 {}", path.display()),
+            Err(e) => format!("导出预览失败: {}", e),
+        };
+    }
+
+    /// 把当前扫描结果和规则给出的目标保存为回归快照
+    fn save_snapshot(&mut self) {
+        if self.files.is_empty() {
+            self.status_message = "没有可保存的扫描结果，请先扫描".to_string();
+            return;
+        }
+        let Some(engine) = self.rule_engine.as_ref() else {
+            self.status_message = "正在扫描，规则暂不可用，请稍后再保存快照".to_string();
+            return;
+        };
+
+        if let Some(path) = rfd::FileDialog::new()
+            .set_file_name("orderly_snapshot.json")
+            .add_filter("JSON", &["json"])
+            .save_file()
+        {
+            self.status_message = match ScanSnapshot::capture(&self.files, engine).save(&path) {
+                Ok(_) => format!("快照已保存到 {}", path.display()),
+                Err(e) => format!("保存快照失败: {}", e),
+            };
         }
     }
 
-    /// 开始扫描
+    /// 用当前规则对已保存的快照重新匹配，报告去向变化
+    fn replay_snapshot(&mut self) {
+        let Some(engine) = self.rule_engine.as_ref() else {
+            self.status_message = "尚未加载规则，请先扫描".to_string();
+            return;
+        };
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("JSON", &["json"])
+            .pick_file()
+        else {
+            return;
+        };
+
+        self.status_message = match ScanSnapshot::load(&path) {
+            Ok(snapshot) => {
+                let diff = snapshot.replay(engine);
+                for change in &diff.changed {
+                    tracing::info!(
+                        "去向改变: {} ({:?} -> {:?})",
+                        change.source.display(),
+                        change.before,
+                        change.after
+                    );
+                }
+                format!("回归模拟: {}", diff.summary())
+            }
+            Err(e) => format!("加载快照失败: {}", e),
+        };
+    }
+
+    /// 最近一次尚未回滚的批次（执行中执行器被占用，返回 None）
+    fn last_undoable_batch(&self) -> Option<String> {
+        self.executor.as_ref().and_then(|executor| {
+            executor
+                .get_recent_history(usize::MAX)
+                .into_iter()
+                .find(|entry| !entry.rolled_back)
+                .map(|entry| entry.batch_id.clone())
+        })
+    }
+
+    /// 确认后回滚最近一次尚未回滚的批次（只在空闲或预览时）
+    fn undo_last_batch(&mut self) {
+        if !matches!(self.state, AppState::Initial | AppState::Preview) {
+            return;
+        }
+        match self.last_undoable_batch() {
+            Some(id) => self.request_undo(&id),
+            None => self.status_message = "没有可撤销的整理记录".to_string(),
+        }
+    }
+
+    /// 确认后回滚指定批次（只在空闲或预览时，已回滚的批次忽略）
+    fn request_undo(&mut self, batch_id: &str) {
+        if !matches!(self.state, AppState::Initial | AppState::Preview) {
+            self.status_message = "正在处理，完成后再撤销".to_string();
+            return;
+        }
+        let entry = self.executor.as_ref().and_then(|executor| {
+            executor
+                .get_history()
+                .iter()
+                .find(|entry| entry.batch_id == batch_id && !entry.rolled_back)
+                .map(|entry| (entry.operations.len(), entry.executed_at))
+        });
+        if let Some((operations, executed_at)) = entry {
+            self.undo_confirm_dialog.show(batch_id, operations, executed_at);
+        }
+    }
+
+    /// 后台测试AI连接
+    fn test_ai_connection(&mut self) {
+        self.status_message = "正在测试AI连接...".to_string();
+        let tx = self.bg_tx.clone();
+        let engine = SemanticEngine::new(self.config.ai_config.clone());
+
+        thread::spawn(move || {
+            let result = match Runtime::new() {
+                Ok(runtime) => runtime
+                    .block_on(engine.test_connection())
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(format!("Tokio Runtime 初始化失败: {}", e)),
+            };
+            let _ = tx.send(BackgroundEvent::AiTestFinished(result));
+        });
+    }
+
+    /// 后台用当前模型和对比模型分别为已扫描的文件生成建议，比较两者
+    fn compare_ai_models(&mut self) {
+        let Some(right) = self.config.ai_config.comparison_config() else {
+            self.status_message = "请先在设置中填写对比模型".to_string();
+            return;
+        };
+        if self.files.is_empty() {
+            self.status_message = "请先扫描一个目录，再对比模型".to_string();
+            return;
+        }
+
+        let candidates = self.target_candidates();
+        let left = self.config.ai_config.clone();
+        let files = self.files.clone();
+        let tx = self.bg_tx.clone();
+        self.status_message = format!(
+            "正在对比 {} 与 {}（最多 {} 个文件）...",
+            left.model_name, right.model_name, MAX_COMPARE_FILES
+        );
+
+        thread::spawn(move || {
+            let report = match Runtime::new() {
+                Ok(runtime) => runtime.block_on(compare_models(left, right, &files, &candidates)),
+                Err(e) => {
+                    tracing::warn!("Tokio Runtime 初始化失败: {}", e);
+                    return;
+                }
+            };
+            let _ = tx.send(BackgroundEvent::ModelCompareFinished(report));
+        });
+    }
+
+    /// 请模型建议路径时提供的候选目录：当前已有的建议目录和规则目标
+    fn target_candidates(&self) -> Vec<String> {
+        let mut candidates: Vec<String> = self
+            .files
+            .iter()
+            .filter_map(|f| f.suggested_action.as_ref())
+            .map(|s| s.target_path.to_string_lossy().to_string())
+            .chain(
+                self.rule_engine
+                    .iter()
+                    .flat_map(|e| e.get_rules())
+                    .filter(|r| r.enabled)
+                    .map(|r| r.action.move_to.clone()),
+            )
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+        candidates
+    }
+
+    /// 开始扫描（只在空闲或预览时；其他操作进行中时规则引擎可能在后台线程里）
     fn start_scan(&mut self) {
+        if !matches!(self.state, AppState::Initial | AppState::Preview) {
+            self.status_message = "正在处理，完成后再扫描".to_string();
+            return;
+        }
         let scan_path = PathBuf::from(&self.scan_path);
         if !scan_path.exists() {
             self.status_message = "扫描路径不存在".to_string();
             return;
         }
 
+        // 扫描根本身是程序/项目目录时不整理其内部，只提供非原子子目录
+        let analyzer = boundary_analyzer();
+        if quick_check_atomic(&scan_path, &analyzer) {
+            let subdirs = analyzer.safe_subdirs(&scan_path);
+            self.status_message = "⚠️ 所选目录是程序/项目目录，已拒绝整理其内部结构".to_string();
+            self.atomic_root_dialog.show(scan_path, subdirs);
+            return;
+        }
+
+        self.start_scan_roots(vec![scan_path]);
+    }
+
+    /// 扫描一个或多个根目录（结果合并）
+    fn start_scan_roots(&mut self, roots: Vec<PathBuf>) {
+        if !matches!(self.state, AppState::Initial | AppState::Preview) {
+            self.status_message = "正在处理，完成后再扫描".to_string();
+            return;
+        }
+        self.scanned_root = match roots.as_slice() {
+            [root] => Some(root.clone()),
+            _ => None,
+        };
         self.state = AppState::Scanning;
         self.status_message = "正在扫描目录...".to_string();
+        self.pending_clusters.clear();
+        self.session_rules.clear();
+        self.live_preview.reset();
+
+        // 规则引擎随扫描线程一起工作，扫描产出的文件立即进入规则匹配
+        // 匹配结果是相对分类路径，输出目录到生成计划时才确定
+        let mut engine = self.rule_engine.take().unwrap_or_default();
+        engine.apply_layout_preset(self.config.layout_preset);
+        engine.set_route_loose_executables(self.config.route_loose_executables);
+        engine.set_organize_by_entity(self.config.organize_by_entity);
 
-        let scan_path_str = self.scan_path.clone();
         let tx = self.bg_tx.clone();
+        let max_files = self.config.atomic_dir_max_files;
+        let max_bytes = self.config.atomic_dir_max_bytes;
+        let collect_cruft = self.config.cleanup_os_cruft;
+        self.cancel_scan.store(false, Ordering::Relaxed);
+        let cancel = Arc::clone(&self.cancel_scan);
 
         thread::spawn(move || {
-            let scanner = FileScanner::new(PathBuf::from(scan_path_str));
-            let result = scanner
-                .scan()
-                .map_err(|e| e.to_string())
-                .map(|mut files| {
-                    let analyzer = BoundaryAnalyzer::new();
-                    analyzer.analyze(&mut files);
-                    files
-                });
-            let _ = tx.send(BackgroundEvent::ScanFinished(result));
+            let analyzer = boundary_analyzer().large_dir_thresholds(max_files, max_bytes);
+            let mut scanned = ScanResult::default();
+            let mut result = Ok(());
+            for root in roots {
+                let scanner = FileScanner::new(root)
+                    .collect_os_cruft(collect_cruft)
+                    .cancel_flag(Arc::clone(&cancel));
+                // 多个扫描根时进度累计
+                let offset = scanned.files.len();
+                let progress_tx = tx.clone();
+                let mut progress = move |p: crate::core::scanner::ScanProgress| {
+                    let _ = progress_tx.send(BackgroundEvent::ScanProgress {
+                        scanned: offset + p.scanned,
+                        current_path: p.current_path,
+                    });
+                };
+                match scan_and_match_with_progress(&scanner, &analyzer, &mut engine, &mut progress) {
+                    Ok(r) => {
+                        scanned.merge(r);
+                        if scanned.cancelled {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        result = Err(e.to_string());
+                        break;
+                    }
+                }
+            }
+            let result = result.map(|_| scanned);
+            let _ = tx.send(BackgroundEvent::ScanFinished { result, engine });
         });
+    }
 
+    /// 弹出下一个待确认的拿不准文件簇
+    fn show_next_cluster(&mut self) {
+        if let Some(cluster) = self.pending_clusters.front() {
+            self.borderline_dialog.show(
+                &cluster.tag,
+                cluster.file_ids.len(),
+                cluster.examples.clone(),
+                self.pending_clusters.len() - 1,
+            );
+        }
     }
 
-    fn start_analysis_async(&mut self) {
-        self.state = AppState::Analyzing;
-        self.analysis_done = 0;
+    /// 把用户为一个簇指定的目标作为临时规则，应用到簇内文件及其他尚无建议的同类文件
+    ///
+    /// 临时规则在本次运行中一直生效（实时预览重新匹配时也会用到），重新扫描时清空。
+    fn apply_cluster_target(&mut self, cluster: &BorderlineCluster, move_to: &str) {
+        let rule = cluster.to_rule(move_to);
+        let mut applied = 0;
+        for file in self.files.iter_mut() {
+            if !cluster.file_ids.contains(&file.id) && file.suggested_action.is_some() {
+                continue;
+            }
+            if let Some(suggestion) = session_suggestion(std::slice::from_ref(&rule), file) {
+                file.suggested_action = Some(suggestion);
+                applied += 1;
+            }
+        }
+        self.status_message = format!("已将 {} 个「{}」文件指向 {}", applied, cluster.tag, rule.action.move_to);
+        self.session_rules.push(rule);
+    }
 
+    /// 用记忆缓存为同类文件生成建议
+    fn apply_memory(&mut self) {
+        let Some(ref database) = self.database else {
+            return;
+        };
+        let hits = apply_memory(&mut self.files, |hash| match database.query_memory(hash) {
+            Ok(target) => target,
+            Err(e) => {
+                tracing::warn!("查询整理记忆失败: {}", e);
+                None
+            }
+        });
+        if hits > 0 {
+            tracing::info!("整理记忆命中 {} 个文件", hits);
+        }
+    }
+
+    /// 当前生效的输出基础路径（未设置时在原目录内整理）
+    fn effective_output_base(&self) -> PathBuf {
+        output_base_for(&self.output_path, self.scanned_root.as_deref(), &self.scan_path)
+    }
+
+    fn start_analysis_async(&mut self) {
         // 先规则匹配一轮
         if let Some(ref mut engine) = self.rule_engine {
             engine.match_files(&mut self.files);
         }
 
-        // 找出需要 AI 分析的文件
+        self.start_semantic_analysis();
+    }
+
+    /// 对文件进行语义分析（扫描流水线已完成规则匹配），完成后与规则建议融合
+    fn start_semantic_analysis(&mut self) {
+        self.state = AppState::Analyzing;
+        self.analysis_done = 0;
+
+        // 找出需要 AI 分析的文件：规则未覆盖的文件；开启融合校验时，规则已匹配的也请模型给出路径
+        let fuse_rule_matches = self.config.ai_enabled && self.config.fuse_rule_matches_with_ai;
+        let rule_matched = |f: &FileDescriptor| {
+            f.suggested_action
+                .as_ref()
+                .is_some_and(|s| s.source == SuggestionSource::Rule)
+        };
         let to_analyze: Vec<FileDescriptor> = self
             .files
             .iter()
-            .filter(|f| f.suggested_action.is_none() && !f.atomic && !f.is_directory)
+            .filter(|f| !f.atomic && !f.is_directory && !f.is_cloud_placeholder)
+            .filter(|f| f.suggested_action.is_none() || (fuse_rule_matches && rule_matched(f)))
             .cloned()
             .collect();
+        let fuse_ids: std::collections::HashSet<String> = to_analyze
+            .iter()
+            .filter(|f| rule_matched(f))
+            .map(|f| f.id.clone())
+            .collect();
+        let candidates = if fuse_ids.is_empty() { Vec::new() } else { self.target_candidates() };
 
         self.analysis_total = to_analyze.len();
         if self.analysis_total == 0 {
@@ -252,16 +1077,12 @@ impl OrderlyApp {
         let tx = self.bg_tx.clone();
         let ai_config = self.config.ai_config.clone();
         let ai_enabled = self.config.ai_enabled;
-        let output_base = if self.output_path.is_empty() {
-            PathBuf::from(&self.scan_path)
-        } else {
-            PathBuf::from(&self.output_path)
-        };
 
         thread::spawn(move || {
             let total = to_analyze.len();
             let mut done = 0usize;
             let mut results: Vec<(String, crate::core::models::SemanticResult)> = Vec::new();
+            let mut ai_targets = std::collections::HashMap::new();
 
             if !ai_enabled {
                 for f in to_analyze {
@@ -269,7 +1090,7 @@ impl OrderlyApp {
                     results.push((f.id.clone(), mock_semantic_analysis(&f)));
                     let _ = tx.send(BackgroundEvent::AnalysisProgress { done, total });
                 }
-                let _ = tx.send(BackgroundEvent::AnalysisFinished(results));
+                let _ = tx.send(BackgroundEvent::AnalysisFinished { results, ai_targets });
                 return;
             }
 
@@ -283,42 +1104,113 @@ impl OrderlyApp {
                         let _ = tx.send(BackgroundEvent::AnalysisProgress { done, total });
                     }
                     tracing::warn!("Tokio Runtime 初始化失败，回退模拟AI: {}", e);
-                    let _ = tx.send(BackgroundEvent::AnalysisFinished(results));
+                    let _ = tx.send(BackgroundEvent::AnalysisFinished { results, ai_targets });
                     return;
                 }
             };
 
-            let engine = SemanticEngine::new(ai_config, output_base);
-            for f in to_analyze {
-                let semantic = match runtime.block_on(engine.analyze_file(&f)) {
-                    Ok(s) => s,
-                    Err(e) => {
-                        tracing::warn!("AI分析失败，回退模拟AI: {}", e);
-                        mock_semantic_analysis(&f)
+            let engine = SemanticEngine::new(ai_config);
+            for chunk in to_analyze.chunks(ANALYSIS_BATCH_SIZE) {
+                let batch = runtime.block_on(engine.analyze_files_batch(chunk, ANALYSIS_BATCH_SIZE));
+                for (f, semantic) in chunk.iter().zip(batch) {
+                    // 批量请求漏掉的文件逐个补充分析
+                    let semantic = match semantic {
+                        Some(s) => s,
+                        None => match runtime.block_on(engine.analyze_file(f)) {
+                            Ok(s) => s,
+                            Err(e) => {
+                                tracing::warn!("AI分析失败，回退模拟AI: {}", e);
+                                mock_semantic_analysis(f)
+                            }
+                        },
+                    };
+                    // 规则已匹配的文件再请模型给出路径，按文件展开变量后与规则融合
+                    if fuse_ids.contains(&f.id) {
+                        let mut analyzed = f.clone();
+                        analyzed.semantic = Some(semantic.clone());
+                        match runtime.block_on(engine.suggest_path(&analyzed, &candidates)) {
+                            Ok(mut suggestion) => {
+                                let move_to = suggestion.target_path.to_string_lossy().to_string();
+                                suggestion.target_path = RuleAction { move_to }.render_relative(&analyzed);
+                                ai_targets.insert(f.id.clone(), suggestion);
+                            }
+                            Err(e) => tracing::warn!("AI路径建议失败 {}: {}", f.name, e),
+                        }
                     }
-                };
-                done += 1;
-                results.push((f.id.clone(), semantic));
+                    done += 1;
+                    results.push((f.id.clone(), semantic));
+                }
                 let _ = tx.send(BackgroundEvent::AnalysisProgress { done, total });
             }
 
-            let _ = tx.send(BackgroundEvent::AnalysisFinished(results));
+            let _ = tx.send(BackgroundEvent::AnalysisFinished { results, ai_targets });
         });
     }
 
     /// 生成移动计划
+    ///
+    /// 记录源文件哈希和重复文件检测需要读完每个文件，放到后台线程；
+    /// 完成后由 [`BackgroundEvent::PlanGenerated`] 做 Dry Run 并显示执行确认
     fn generate_plan(&mut self) {
+        if self.generating_plan {
+            return;
+        }
+        let output_base = self.effective_output_base();
+        let Some(ref mut planner) = self.planner else {
+            return;
+        };
+        planner.set_output_base(output_base);
+        planner.set_confidence_threshold(self.config.current_profile().confidence_threshold);
+        planner.set_naming_policy(self.config.naming_policy);
+        planner.set_strict_output_containment(self.config.strict_output_containment);
+        planner.set_skip_cloud_placeholders(!self.config.move_cloud_placeholders);
+        planner.set_duplicate_handling(self.config.duplicate_handling);
+
+        // 哈希只在这份副本上开启，实时预览复用的计划生成器保持不读文件
+        let mut worker = planner.clone();
+        worker.set_verify_source_hash(self.config.verify_source_hash);
+        let dedupe = self.config.dedupe_identical;
+        let files = self.files.clone();
+        let tx = self.bg_tx.clone();
+        self.generating_plan = true;
+        self.status_message = "正在生成执行计划...".to_string();
+        thread::spawn(move || {
+            let (plan, dedup) = if dedupe {
+                let (plan, report) = worker.generate_plan_dedup(&files);
+                (plan, Some(report))
+            } else {
+                (worker.generate_plan(&files), None)
+            };
+            let _ = tx.send(BackgroundEvent::PlanGenerated { plan, dedup });
+        });
+    }
+
+    /// 后台生成的计划：拒绝越界操作、执行 Dry Run，然后显示执行确认
+    fn finish_plan(&mut self, mut plan: MovePlan, dedup: Option<DedupReport>) {
+        self.generating_plan = false;
+        self.status_message = match dedup {
+            Some(report) if !report.groups.is_empty() => report.summary(),
+            _ => String::new(),
+        };
         if let Some(ref planner) = self.planner {
-            let plan = planner.generate_plan(&self.files);
-            
-            // 执行 Dry Run
-            if let Some(ref executor) = self.executor {
-                let dry_run = executor.dry_run(&plan);
+            plan.mode = self.config.execution_mode;
+            let rejected = planner.reject_outside_output_base(&mut plan);
+
+            // 执行 Dry Run；被安全模式拒绝的操作一并列入警告
+            if let Some(ref mut executor) = self.executor {
+                executor.set_conflict_strategy(self.config.conflict_strategy);
+                executor.set_use_trash_on_conflict(self.config.use_trash_on_conflict);
+                executor.set_trash_duplicate_sources(self.config.trash_duplicate_sources);
+                let mut dry_run = executor.dry_run(&plan);
+                dry_run
+                    .potential_errors
+                    .extend(rejected.into_iter().map(|e| format!("已拒绝: {}", e.message)));
                 self.dry_run_result = Some(dry_run);
             }
-            
+
             self.current_plan = Some(plan);
         }
+        self.show_execute_confirm();
     }
 
     /// 显示执行确认
@@ -326,10 +1218,22 @@ impl OrderlyApp {
         if let Some(ref plan) = self.current_plan {
             if let Some(ref planner) = self.planner {
                 let stats = planner.get_plan_stats(plan);
-                let warnings = self.dry_run_result
+                let mut warnings = self.dry_run_result
                     .as_ref()
                     .map(|r| r.potential_errors.clone())
                     .unwrap_or_default();
+
+                // 计划校验：目标冲突、循环路径等需要处理；自动改名策略会在执行时化解目标冲突
+                let mut blocking = Vec::new();
+                for error in planner.validate_plan(plan) {
+                    let resolved_by_rename = error.error_type == ValidationErrorType::TargetConflict
+                        && self.config.conflict_strategy == ConflictStrategy::Rename;
+                    if error.error_type.is_blocking() && !resolved_by_rename {
+                        blocking.push(error.message);
+                    } else if !warnings.contains(&error.message) {
+                        warnings.push(error.message);
+                    }
+                }
                 
                 self.execute_confirm_dialog.show(
                     stats.total_operations,
@@ -337,6 +1241,30 @@ impl OrderlyApp {
                     stats.target_directories,
                     warnings,
                 );
+                // 目标卷空间不足时必须处理
+                let dry_run = self.dry_run_result.as_ref();
+                blocking.extend(dry_run.and_then(|r| r.space_shortage()));
+                self.execute_confirm_dialog.space = match dry_run {
+                    Some(r) if r.required_bytes > 0 => match r.available_bytes {
+                        Some(available) => format!(
+                            "需要 {}，可用 {}",
+                            format_bytes(r.required_bytes),
+                            format_bytes(available)
+                        ),
+                        None => format!("需要 {}（无法查询可用空间）", format_bytes(r.required_bytes)),
+                    },
+                    _ => String::new(),
+                };
+                self.execute_confirm_dialog.blocking_errors = blocking;
+                self.execute_confirm_dialog.mode = plan.mode;
+                let throughput = self.config.copy_throughput.unwrap_or(DEFAULT_COPY_THROUGHPUT);
+                self.execute_confirm_dialog.estimate =
+                    PlanStats::format_duration(stats.estimate_duration(throughput));
+                self.execute_confirm_dialog.diff = self
+                    .dry_run_result
+                    .as_ref()
+                    .map(|r| r.to_diff())
+                    .unwrap_or_default();
             }
         }
     }
@@ -347,26 +1275,80 @@ impl OrderlyApp {
             Some(p) => p,
             None => return,
         };
-        let executor = match self.executor.take() {
+        // 先处理上次未完成的批次，否则新批次的断点会覆盖它
+        if let Some(other) = self.executor.as_ref().and_then(|e| e.conflicting_pending_batch(&plan)) {
+            self.current_plan = Some(plan);
+            self.status_message = format!("存在未完成的批次 {}，请先继续或回滚", other);
+            self.check_pending_batch();
+            return;
+        }
+        let mut executor = match self.executor.take() {
             Some(e) => e,
             None => {
                 self.current_plan = Some(plan);
                 return;
             }
         };
+        executor.set_write_folder_readme(self.config.write_folder_readme);
+        executor.set_conflict_strategy(self.config.conflict_strategy);
+        executor.set_use_trash_on_conflict(self.config.use_trash_on_conflict);
+        executor.set_trash_duplicate_sources(self.config.trash_duplicate_sources);
+
+        let planned_ids: std::collections::HashSet<&str> =
+            plan.operations.iter().map(|op| op.file_id.as_str()).collect();
+        self.executing_files = self
+            .files
+            .iter()
+            .filter(|f| planned_ids.contains(f.id.as_str()))
+            .cloned()
+            .collect();
+
+        let sizes: std::collections::HashMap<String, u64> = self
+            .executing_files
+            .iter()
+            .map(|f| (f.id.clone(), f.size))
+            .collect();
 
         self.state = AppState::Executing;
-        self.status_message = "正在执行移动...".to_string();
+        self.status_message = format!("正在执行{}...", plan.mode.label());
+        self.last_receipt = None;
 
+        self.cancel_execution.store(false, Ordering::Relaxed);
+        let cancel = Arc::clone(&self.cancel_execution);
+        let transactional = self.config.transactional_execution;
         let tx = self.bg_tx.clone();
         thread::spawn(move || {
             let batch_id = plan.batch_id.clone();
             let mut exec = executor;
-            let result = exec.execute(&mut plan);
+            let started = std::time::Instant::now();
+            let result = if transactional {
+                match exec.execute_transactional_cancellable(&mut plan, &cancel) {
+                    TransactionOutcome::Committed(result) => result,
+                    TransactionOutcome::RolledBack {
+                        failed_index,
+                        error,
+                        rollback,
+                        ..
+                    } => {
+                        let _ = tx.send(BackgroundEvent::TransactionRolledBack {
+                            executor: exec,
+                            batch_id,
+                            failed_index,
+                            error,
+                            result: rollback,
+                        });
+                        return;
+                    }
+                }
+            } else {
+                exec.execute_cancellable(&mut plan, &cancel)
+            };
+            let receipt = OperationReceipt::from_execution(&plan, &result, &sizes, started.elapsed());
             let _ = tx.send(BackgroundEvent::ExecuteFinished {
                 executor: exec,
                 batch_id,
                 result,
+                receipt,
             });
         });
     }
@@ -377,14 +1359,43 @@ impl OrderlyApp {
             None => return,
         };
         self.state = AppState::Executing;
-        self.status_message = format!("正在回滚批次: {}", batch_id);
+        self.status_message = format!("正在回滚批次: {}", batch_id);
+        if self.last_receipt.as_ref().is_some_and(|r| r.batch_id == batch_id) {
+            self.last_receipt = None;
+        }
+        let tx = self.bg_tx.clone();
+        thread::spawn(move || {
+            let mut exec = executor;
+            let result = exec.rollback(&batch_id);
+            let _ = tx.send(BackgroundEvent::RollbackFinished {
+                executor: exec,
+                batch_id,
+                result,
+            });
+        });
+    }
+
+    /// 回滚上次未完成批次中已完成的操作
+    fn rollback_pending_batch(&mut self) {
+        let mut executor = match self.executor.take() {
+            Some(e) => e,
+            None => return,
+        };
+        let plan = match executor.pending_batch() {
+            Some(p) => p,
+            None => {
+                self.executor = Some(executor);
+                return;
+            }
+        };
+        self.state = AppState::Executing;
+        self.status_message = format!("正在回滚未完成批次: {}", plan.batch_id);
         let tx = self.bg_tx.clone();
         thread::spawn(move || {
-            let mut exec = executor;
-            let result = exec.rollback(&batch_id);
+            let result = executor.rollback_pending(&plan);
             let _ = tx.send(BackgroundEvent::RollbackFinished {
-                executor: exec,
-                batch_id,
+                executor,
+                batch_id: plan.batch_id,
                 result,
             });
         });
@@ -393,23 +1404,63 @@ impl OrderlyApp {
     fn pump_background_events(&mut self) {
         while let Ok(ev) = self.bg_rx.try_recv() {
             match ev {
-                BackgroundEvent::ScanFinished(result) => {
+                BackgroundEvent::ScanFinished { result, engine } => {
+                    self.rule_engine = Some(engine);
+                    self.apply_deferred_rules();
                     match result {
-                        Ok(files) => {
-                            self.files = files;
-
-                            // 初始化规则引擎/Planner
-                            let output_base = if self.output_path.is_empty() {
-                                PathBuf::from(&self.scan_path)
-                            } else {
-                                PathBuf::from(&self.output_path)
-                            };
+                        Ok(scan) => {
+                            if !scan.os_cruft.is_empty() {
+                                self.cruft_cleanup_dialog.show(scan.os_cruft.clone());
+                            }
+                            self.files = scan.files;
+                            if let Some(ref database) = self.database {
+                                if let Err(e) = database.attach_notes(&mut self.files) {
+                                    tracing::warn!("加载文件备注失败: {}", e);
+                                }
+                            }
+                            self.apply_memory();
+                            self.scan_warnings = scan.warnings.iter().map(|w| w.to_string()).collect();
+                            self.scan_warnings.extend(
+                                scan.skipped_links
+                                    .iter()
+                                    .map(|p| format!("{}: 链接/联接点，未遍历", p.display())),
+                            );
+                            if !self.config.move_cloud_placeholders {
+                                self.scan_warnings.extend(
+                                    self.files
+                                        .iter()
+                                        .filter(|f| f.is_cloud_placeholder)
+                                        .map(|f| format!("{}: 云端占位文件已跳过", f.full_path.display())),
+                                );
+                            }
+                            self.scan_notices = self
+                                .files
+                                .iter()
+                                .filter(|f| {
+                                    f.suggested_action.as_ref().is_some_and(|s| {
+                                        s.matched_rule_id.as_deref() == Some(INSTALLERS_RULE_ID)
+                                    })
+                                })
+                                .map(|f| format!(
+                                    "{}: 独立可执行文件，建议移入 Installers/ 隔离，请确认来源可信后再运行",
+                                    f.full_path.display()
+                                ))
+                                .collect();
+                            if scan.cancelled {
+                                self.scan_warnings.insert(
+                                    0,
+                                    format!("扫描已取消，仅包含取消前扫描到的 {} 个条目", self.files.len()),
+                                );
+                            }
+                            self.scan_warnings_expanded = false;
+                            self.scan_notices_expanded = false;
 
-                            self.rule_engine = Some(RuleEngine::new(output_base.clone()));
-                            self.planner = Some(Planner::new(output_base, self.config.confidence_threshold));
+                            // 初始化 Planner
+                            let output_base = self.effective_output_base();
+                            self.planner = Some(Planner::new(output_base, self.config.current_profile().confidence_threshold));
 
-                            // 进入分析
-                            self.start_analysis_async();
+                            // 规则已在扫描流水线中匹配，直接进入语义分析
+                            self.start_semantic_analysis();
                         }
                         Err(e) => {
                             self.status_message = format!("扫描失败: {}", e);
@@ -417,12 +1468,18 @@ impl OrderlyApp {
                         }
                     }
                 }
+                BackgroundEvent::ScanProgress { scanned, current_path } => {
+                    if self.state == AppState::Scanning && !self.cancel_scan.load(Ordering::Relaxed) {
+                        self.status_message =
+                            format!("已扫描 {} 个文件... {}", scanned, current_path.display());
+                    }
+                }
                 BackgroundEvent::AnalysisProgress { done, total } => {
                     self.analysis_done = done;
                     self.analysis_total = total;
                     self.status_message = format!("正在分析文件... {}/{}", done, total);
                 }
-                BackgroundEvent::AnalysisFinished(results) => {
+                BackgroundEvent::AnalysisFinished { results, mut ai_targets } => {
                     // 回填语义
                     for (id, semantic) in results {
                         if let Some(file) = self.files.iter_mut().find(|f| f.id == id) {
@@ -430,15 +1487,32 @@ impl OrderlyApp {
                         }
                     }
 
-                    // 对仍无建议的文件，再做一次规则匹配（让基于 semantic_tags 的规则生效）
-                    if let Some(ref mut engine) = self.rule_engine {
-                        for file in self.files.iter_mut() {
-                            if file.suggested_action.is_none() && !file.atomic && !file.is_directory {
-                                if let Some(suggestion) = engine.match_file(file) {
-                                    file.suggested_action = Some(suggestion);
-                                }
-                            }
-                        }
+                    // 有了主标签后再查一次记忆
+                    self.apply_memory();
+
+                    // 融合规则建议和模型给出的路径；仍无规则建议的文件再匹配一次
+                    // （让基于 semantic_tags 的规则生效），都没有时按标签离线推断
+                    if let Some(ref mut planner) = self.planner {
+                        planner.set_fusion_weights(self.config.fusion_rule_weight, self.config.fusion_ai_weight);
+                        planner.set_layout_preset(self.config.layout_preset);
+                        let mut engine = self.rule_engine.as_mut();
+                        let fused = planner.fuse_file_suggestions(
+                            &mut self.files,
+                            |f| engine.as_mut().and_then(|e| e.match_file(f)),
+                            |f| ai_targets.remove(&f.id),
+                        );
+                        tracing::info!("规则与AI建议融合 {} 个文件", fused);
+                    }
+
+                    // 保存用户规则的命中统计（次数和最后命中时间）
+                    if let Some(ref engine) = self.rule_engine {
+                        let hit_ids: Vec<String> = engine
+                            .get_rules()
+                            .iter()
+                            .filter(|r| r.origin == RuleOrigin::UserConfirmed && r.last_hit.is_some())
+                            .map(|r| r.id.clone())
+                            .collect();
+                        persist_rules(self.database.as_ref(), engine, &hit_ids);
                     }
 
                     self.preview_table.sort_files(&mut self.files);
@@ -448,18 +1522,67 @@ impl OrderlyApp {
                         "分析完成: {} 个文件, {} 个有建议, {} 个原子目录",
                         stats.total_files, stats.with_suggestion, stats.atomic_files
                     );
+
+                    // 置信度在阈值附近的相似文件按簇逐个询问一次
+                    self.pending_clusters = cluster_borderline(
+                        &self.files,
+                        self.config.current_profile().confidence_threshold,
+                        BORDERLINE_MARGIN,
+                        2,
+                    )
+                    .into();
+                    self.show_next_cluster();
                 }
                 BackgroundEvent::ExecuteFinished {
                     executor,
                     batch_id,
                     result,
+                    receipt,
                 } => {
-                    self.executor = Some(executor);
                     self.status_message = format!("执行完成(批次 {}): {}", batch_id, result.summary());
+                    self.last_receipt = Some(receipt);
+
+                    // 用本次实测的跨设备复制速度校准下一次的耗时估计
+                    if let Some(throughput) = result.copy_throughput() {
+                        self.config.copy_throughput = Some(throughput);
+                        if let Err(e) = self.config_manager.save(&self.config) {
+                            tracing::warn!("保存复制速度失败: {}", e);
+                        }
+                    }
                     self.current_plan = None;
                     self.dry_run_result = None;
 
+                    // 把本批次中反复出现的AI分类模式归纳为候选规则
+                    let executed_files = std::mem::take(&mut self.executing_files);
+                    if let Some(entry) = executor.get_history().iter().find(|h| h.batch_id == batch_id) {
+                        // 记住本批次成功移动的同类文件去向
+                        if let Some(ref database) = self.database {
+                            let output_base = self.effective_output_base();
+                            for (hash, dir) in learned_destinations(&executed_files, &entry.operations, &output_base) {
+                                if let Err(e) = database.save_memory(&hash, &dir) {
+                                    tracing::warn!("保存整理记忆失败: {}", e);
+                                }
+                            }
+                        }
+
+                        let proposals = propose_rules(
+                            &executed_files,
+                            &entry.operations,
+                            &self.effective_output_base(),
+                            DEFAULT_MIN_SUPPORT,
+                        );
+                        if !proposals.is_empty() {
+                            self.rule_mining_dialog.show(proposals);
+                        }
+                    }
+                    self.executor = Some(executor);
+
+                    if result.cancelled {
+                        self.check_pending_batch();
+                    }
+
                     // 执行完成后异步重新扫描
+                    self.state = AppState::Initial;
                     self.start_scan();
                 }
                 BackgroundEvent::RollbackFinished {
@@ -469,8 +1592,62 @@ impl OrderlyApp {
                 } => {
                     self.executor = Some(executor);
                     self.status_message = format!("回滚完成(批次 {}): {}", batch_id, result.summary());
+                    self.state = AppState::Initial;
+                    self.start_scan();
+                }
+                BackgroundEvent::TransactionRolledBack {
+                    executor,
+                    batch_id,
+                    failed_index,
+                    error,
+                    result,
+                } => {
+                    self.executor = Some(executor);
+                    self.executing_files.clear();
+                    self.current_plan = None;
+                    self.dry_run_result = None;
+                    self.status_message = format!(
+                        "第 {} 个操作未完成（{}），整批已撤销(批次 {}): {}",
+                        failed_index + 1,
+                        error,
+                        batch_id,
+                        result.summary()
+                    );
+                    self.state = AppState::Initial;
                     self.start_scan();
                 }
+                BackgroundEvent::CleanupFinished(result) => {
+                    self.status_message = if result.errors.is_empty() {
+                        format!("已将 {} 个系统缓存文件移到回收站", result.trashed)
+                    } else {
+                        for e in &result.errors {
+                            tracing::warn!("清理失败: {}", e);
+                        }
+                        format!(
+                            "已将 {} 个系统缓存文件移到回收站，{} 个失败",
+                            result.trashed,
+                            result.errors.len()
+                        )
+                    };
+                }
+                BackgroundEvent::ModelCompareFinished(report) => {
+                    self.status_message = report.summary();
+                    self.model_compare_dialog.show(report);
+                }
+                BackgroundEvent::PlanGenerated { plan, dedup } => {
+                    // 生成期间离开了预览（重新扫描等）则丢弃
+                    if self.state == AppState::Preview {
+                        self.finish_plan(plan, dedup);
+                    } else {
+                        self.generating_plan = false;
+                    }
+                }
+                BackgroundEvent::AiTestFinished(result) => {
+                    self.status_message = match result {
+                        Ok(reply) => format!("AI连接成功: {}", reply),
+                        Err(e) => format!("AI连接失败: {}", e),
+                    };
+                }
             }
         }
     }
@@ -537,35 +1714,241 @@ impl OrderlyApp {
     fn save_pending_rule(&mut self) {
         if let Some(rule) = self.pending_rule.take() {
             if let Some(ref mut engine) = self.rule_engine {
-                engine.add_rule(rule);
+                let id = engine.add_rule(rule);
+                persist_rules(self.database.as_ref(), engine, &[id]);
                 self.status_message = "规则已保存".to_string();
             }
         }
     }
+
+    /// 创建规则引擎并载入数据库中的用户规则
+    fn load_rule_engine(database: Option<&Database>) -> RuleEngine {
+        let mut engine = RuleEngine::new();
+        let Some(database) = database else {
+            return engine;
+        };
+        let rules = match database.load_user_rules() {
+            Ok(rules) => rules,
+            Err(e) => {
+                tracing::warn!("加载用户规则失败: {}", e);
+                return engine;
+            }
+        };
+        for rule in rules {
+            let stored_id = rule.id.clone();
+            let id = engine.add_rule(rule);
+            // 与内置规则同ID的旧记录改存为新ID，以后各次启动保持一致
+            if id != stored_id {
+                if let Err(e) = database.delete_rule(&stored_id) {
+                    tracing::warn!("更新规则ID失败: {}", e);
+                }
+                persist_rules(Some(database), &engine, &[id]);
+            }
+        }
+        engine
+    }
+}
+
+/// 创建边界分析器：配置目录下有 boundary.json 时合并其中的用户边界规则
+fn boundary_analyzer() -> BoundaryAnalyzer {
+    let path = ConfigManager::default_path().with_file_name(BOUNDARY_CONFIG_FILE);
+    if !path.exists() {
+        return BoundaryAnalyzer::new();
+    }
+    BoundaryAnalyzer::with_config(&path).unwrap_or_else(|e| {
+        tracing::warn!("加载边界规则失败，使用内置规则: {}", e);
+        BoundaryAnalyzer::new()
+    })
+}
+
+/// 拖放内容对应的扫描目录：目录取自身，文件取所在目录；涉及多个目录时为 None
+fn dropped_scan_root(paths: &[PathBuf]) -> Option<PathBuf> {
+    let mut roots = paths.iter().map(|p| {
+        if p.is_dir() {
+            p.clone()
+        } else {
+            p.parent().map(Path::to_path_buf).unwrap_or_else(|| p.clone())
+        }
+    });
+    let first = roots.next()?;
+    roots.all(|r| r == first).then_some(first)
+}
+
+/// 启动时从配置文件恢复的设置与路径
+struct StartupSettings {
+    config: AppConfig,
+    /// 上次使用的扫描目录，没有或已不存在时为当前配置档案的默认扫描路径
+    scan_path: String,
+    /// 上次使用的输出目录，没有或已不存在时为当前配置档案的默认输出路径
+    output_path: String,
+    /// 已载入配置的设置对话框
+    settings_dialog: SettingsDialog,
+}
+
+impl StartupSettings {
+    /// 读取配置文件，失败时使用默认配置
+    fn load(config_manager: &ConfigManager) -> Self {
+        let config = config_manager.load().unwrap_or_else(|e| {
+            tracing::warn!("加载配置失败，使用默认配置: {}", e);
+            AppConfig::default()
+        });
+
+        let profile = config.current_profile();
+        // 上次使用的目录已被删除或移走时不再恢复
+        let restore = |last: &Option<PathBuf>, default: &Option<PathBuf>| {
+            last.as_ref()
+                .filter(|p| p.is_dir())
+                .or(default.as_ref())
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_default()
+        };
+        let mut settings_dialog = SettingsDialog::default();
+        settings_dialog.load_from_config(&config);
+
+        Self {
+            scan_path: restore(&config.last_scan_path, &profile.default_scan_path),
+            output_path: restore(&config.last_output_path, &profile.default_output_base),
+            settings_dialog,
+            config,
+        }
+    }
+}
+
+/// 创建执行器：历史记录保存在数据库中（首次使用时导入旧的 history.json），
+/// 数据库无法打开时退回 JSON 文件
+fn history_executor(data_dir: PathBuf, database_path: &Path) -> Executor {
+    let database = Database::open(&database_path.to_path_buf()).and_then(|db| {
+        let imported = db.import_history_json(&data_dir.join(HISTORY_FILE_NAME))?;
+        if imported > 0 {
+            tracing::info!("已把 {} 个历史批次导入数据库", imported);
+        }
+        Ok(db)
+    });
+    match database {
+        Ok(db) => Executor::with_history_store(data_dir, Box::new(db)),
+        Err(e) => {
+            tracing::warn!("历史记录改用 JSON 文件保存: {}", e);
+            Executor::new(data_dir)
+        }
+    }
+}
+
+/// 输出基础路径：优先用户设置的输出目录；原地整理时用实际扫描的根目录，
+/// 避免扫描根是程序目录、只扫描其子目录时把分类目录建到程序目录里
+fn output_base_for(output_path: &str, scanned_root: Option<&Path>, scan_path: &str) -> PathBuf {
+    if !output_path.is_empty() {
+        PathBuf::from(output_path)
+    } else if let Some(root) = scanned_root {
+        root.to_path_buf()
+    } else {
+        PathBuf::from(scan_path)
+    }
+}
+
+/// 渲染一条可展开查看、可关闭的消息横幅；关闭时清空消息
+fn render_message_banner(
+    ui: &mut egui::Ui,
+    id_salt: &str,
+    title: String,
+    color: egui::Color32,
+    messages: &mut Vec<String>,
+    expanded: &mut bool,
+) {
+    if messages.is_empty() {
+        return;
+    }
+
+    let mut dismissed = false;
+    egui::Frame::none()
+        .fill(color.linear_multiply(0.15))
+        .inner_margin(6.0)
+        .rounding(4.0)
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                let text = RichText::new(title).color(color);
+                if ui.add(egui::Label::new(text).sense(egui::Sense::click())).clicked() {
+                    *expanded = !*expanded;
+                }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.small_button("✕").clicked() {
+                        dismissed = true;
+                    }
+                });
+            });
+
+            if *expanded {
+                egui::ScrollArea::vertical()
+                    .id_salt(id_salt)
+                    .max_height(150.0)
+                    .show(ui, |ui| {
+                        for message in messages.iter() {
+                            ui.label(RichText::new(message).small());
+                        }
+                    });
+            }
+        });
+
+    if dismissed {
+        messages.clear();
+    }
+}
+
+/// 把指定规则的变更写入数据库（内置规则不保存）
+fn persist_rules(database: Option<&Database>, engine: &RuleEngine, rule_ids: &[String]) {
+    if let Some(database) = database {
+        if let Err(e) = database.sync_rules(engine.get_rules(), rule_ids) {
+            tracing::warn!("保存规则失败: {}", e);
+        }
+    }
 }
 
 impl eframe::App for OrderlyApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         self.pump_background_events();
 
+        if ctx.input(|i| i.viewport().close_requested()) {
+            self.save_window_state(ctx);
+        } else if self.last_session_save.elapsed() >= SESSION_SAVE_INTERVAL {
+            // 定期保存，意外退出时也不丢失窗口状态与最近使用的目录
+            self.last_session_save = std::time::Instant::now();
+            if self.capture_session_state(ctx) {
+                if let Err(e) = self.config_manager.save(&self.config) {
+                    tracing::warn!("保存窗口状态失败: {}", e);
+                }
+            }
+        }
+
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::P)) {
+            self.command_palette.toggle();
+        }
+        self.handle_shortcuts(ctx);
+        self.handle_dropped_files(ctx);
+
         // 顶部菜单栏
         egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 ui.menu_button("文件", |ui| {
-                    if ui.button("📂 打开目录...").clicked() {
-                        if let Some(path) = rfd::FileDialog::new().pick_folder() {
-                            self.scan_path = path.to_string_lossy().to_string();
-                        }
+                    if ui.button("📂 打开目录... (Ctrl+O)").clicked() {
+                        self.pick_scan_folder();
                         ui.close_menu();
                     }
                     if ui.button("⚙️ 设置").clicked() {
-                        self.settings_dialog.load_from_config(&self.config);
-                        self.settings_dialog.visible = true;
+                        self.open_settings();
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.button("📤 导出规则").clicked() {
+                        self.export_rules();
+                        ui.close_menu();
+                    }
+                    if ui.button("📥 导入规则").clicked() {
+                        self.pick_rules_to_import();
                         ui.close_menu();
                     }
                     ui.separator();
                     if ui.button("❌ 退出").clicked() {
-                        std::process::exit(0);
+                        // 走正常关闭流程，以便保存窗口状态
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
                     }
                 });
 
@@ -576,6 +1959,11 @@ impl eframe::App for OrderlyApp {
                     if ui.checkbox(&mut self.show_history_panel, "历史记录").clicked() {
                         ui.close_menu();
                     }
+                    ui.separator();
+                    if ui.button("🔎 命令面板 (Ctrl+P)").clicked() {
+                        self.command_palette.toggle();
+                        ui.close_menu();
+                    }
                 });
 
                 ui.menu_button("帮助", |ui| {
@@ -583,6 +1971,37 @@ impl eframe::App for OrderlyApp {
                         ui.close_menu();
                     }
                 });
+
+                ui.separator();
+                let profile_names = self.config.profile_names();
+                if profile_names.len() > 1 {
+                    let current = self.config.active_profile.clone();
+                    let mut selected = current.clone();
+                    egui::ComboBox::from_id_salt("config_profile")
+                        .selected_text(format!("📁 {}", current))
+                        .show_ui(ui, |ui| {
+                            for name in profile_names {
+                                let label = name.clone();
+                                ui.selectable_value(&mut selected, name, label);
+                            }
+                        })
+                        .response
+                        .on_hover_text("配置档案：各自的扫描/输出目录与置信度阈值");
+                    if selected != current {
+                        self.switch_profile(selected);
+                    }
+                    ui.separator();
+                }
+
+                let can_undo = matches!(self.state, AppState::Initial | AppState::Preview)
+                    && self.last_undoable_batch().is_some();
+                if ui
+                    .add_enabled(can_undo, egui::Button::new("↩️ 撤销上次整理"))
+                    .on_hover_text("回滚最近一次尚未回滚的整理批次，完成后重新扫描（Ctrl+Z）")
+                    .clicked()
+                {
+                    self.undo_last_batch();
+                }
             });
         });
 
@@ -612,7 +2031,14 @@ impl eframe::App for OrderlyApp {
                 .default_width(300.0)
                 .show(ctx, |ui| {
                     if let Some(ref mut engine) = self.rule_engine {
-                        let action = self.rule_panel.render(ui, engine.get_rules_mut());
+                        // 重叠检查是两两比较，只在规则变化时重新计算
+                        let fingerprint = live_preview::rules_fingerprint(engine.get_rules());
+                        let (_, warnings, overlaps) = match self.rule_checks.take() {
+                            Some(checks) if checks.0 == fingerprint => checks,
+                            _ => (fingerprint, engine.lint(), engine.detect_overlaps()),
+                        };
+                        let action = self.rule_panel.render(ui, engine.get_rules_mut(), &warnings, &overlaps);
+                        self.rule_checks = Some((fingerprint, warnings, overlaps));
                         
                         match action {
                             RulePanelAction::CreateNew => {
@@ -624,6 +2050,7 @@ impl eframe::App for OrderlyApp {
                             }
                             RulePanelAction::Delete(id) => {
                                 engine.remove_rule(&id);
+                                persist_rules(self.database.as_ref(), engine, &[id]);
                                 self.rule_panel.reset_selection();
                             }
                             RulePanelAction::SaveEdit(id) => {
@@ -633,9 +2060,49 @@ impl eframe::App for OrderlyApp {
                                     rule.action.move_to = data.target;
                                     rule.condition.file_extensions = data.extensions;
                                     rule.condition.filename_keywords = data.keywords;
+                                    rule.condition.filename_regex = data.filename_regex;
+                                    rule.condition.match_mode = data.match_mode;
+                                    rule.condition.modified_after = data.modified_after;
+                                    rule.condition.modified_before = data.modified_before;
                                     rule.condition.semantic_tags = data.tags;
+                                    rule.condition.scope_roots = data.scope_roots;
+                                    rule.condition.directory_markers = data.directory_markers;
                                     rule.priority = data.priority;
+                                    rule.updated_at = chrono::Utc::now();
                                 }
+                                persist_rules(self.database.as_ref(), engine, &[id]);
+                            }
+                            RulePanelAction::BulkSetEnabled(ids, enabled) => {
+                                let changed = engine.set_rules_enabled(&ids, enabled);
+                                persist_rules(self.database.as_ref(), engine, &ids);
+                                self.status_message = format!(
+                                    "已{} {} 条规则",
+                                    if enabled { "启用" } else { "禁用" },
+                                    changed
+                                );
+                            }
+                            RulePanelAction::BulkDelete(ids) => {
+                                let removed = engine.remove_user_rules(&ids);
+                                persist_rules(self.database.as_ref(), engine, &ids);
+                                self.rule_panel.reset_selection();
+                                self.rule_panel.clear_checked();
+                                self.status_message = format!("已删除 {} 条用户规则", removed);
+                            }
+                            RulePanelAction::BulkExport(ids) => {
+                                self.status_message = match engine.export_rules_to_json(&ids) {
+                                    Ok(json) => match rfd::FileDialog::new()
+                                        .set_file_name("orderly_rules.json")
+                                        .add_filter("JSON", &["json"])
+                                        .save_file()
+                                    {
+                                        Some(path) => match std::fs::write(&path, json) {
+                                            Ok(_) => format!("已导出所选规则到 {}", path.display()),
+                                            Err(e) => format!("导出规则失败: {}", e),
+                                        },
+                                        None => return,
+                                    },
+                                    Err(e) => format!("导出规则失败: {}", e),
+                                };
                             }
                             RulePanelAction::None => {}
                         }
@@ -648,57 +2115,17 @@ impl eframe::App for OrderlyApp {
             egui::SidePanel::right("history_panel")
                 .default_width(340.0)
                 .show(ctx, |ui| {
-                    ui.heading("历史记录");
-                    ui.separator();
-
-                    let history_items: Vec<(String, chrono::DateTime<chrono::Utc>, usize, bool)> = self
+                    let history = self
                         .executor
                         .as_ref()
-                        .map(|executor| {
-                            executor
-                                .get_recent_history(30)
-                                .into_iter()
-                                .map(|entry| (entry.batch_id.clone(), entry.executed_at, entry.operations.len(), entry.rolled_back))
-                                .collect()
-                        })
+                        .map(|executor| executor.get_recent_history(HISTORY_PANEL_ENTRIES))
                         .unwrap_or_default();
-
-                    if history_items.is_empty() {
-                        ui.label("暂无历史记录");
-                        return;
+                    let busy = self.state == AppState::Executing;
+                    match self.history_panel.render(ui, &history, busy) {
+                        HistoryPanelAction::Rollback(batch_id) => self.request_undo(&batch_id),
+                        HistoryPanelAction::ExportCsv => self.export_history_csv(),
+                        HistoryPanelAction::None => {}
                     }
-
-                    egui::ScrollArea::vertical().max_height(600.0).show(ui, |ui| {
-                        for (batch_id, executed_at, op_len, rolled_back) in history_items {
-                            let selected = self
-                                .selected_batch_id
-                                .as_ref()
-                                .map(|s| s == &batch_id)
-                                .unwrap_or(false);
-
-                            ui.group(|ui| {
-                                ui.horizontal(|ui| {
-                                    let short_id = batch_id.get(0..8).unwrap_or(&batch_id);
-                                    if ui.selectable_label(selected, format!("批次 {}", short_id)).clicked() {
-                                        self.selected_batch_id = Some(batch_id.clone());
-                                    }
-                                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                                        ui.label(if rolled_back { "已回滚" } else { "已执行" });
-                                    });
-                                });
-
-                                ui.label(format!("时间: {}", executed_at));
-                                ui.label(format!("操作数: {}", op_len));
-
-                                if !rolled_back {
-                                    if ui.button("↩️ 回滚此批次").clicked() {
-                                        self.rollback_batch(batch_id.clone());
-                                    }
-                                }
-                            });
-                            ui.add_space(6.0);
-                        }
-                    });
                 });
         }
 
@@ -722,6 +2149,14 @@ impl eframe::App for OrderlyApp {
 
         // 渲染对话框
         self.render_dialogs(ctx);
+
+        // 执行结果卡片
+        self.render_receipt(ctx);
+
+        // 命令面板
+        if let Some(action) = self.command_palette.render(ctx) {
+            action(self);
+        }
     }
 }
 
@@ -764,6 +2199,26 @@ impl OrderlyApp {
                         .small()
                         .color(egui::Color32::GRAY)
                 );
+
+                ui.horizontal(|ui| {
+                    ui.label("目录结构:");
+                    let current = self.config.layout_preset;
+                    let mut selected = current;
+                    egui::ComboBox::from_id_salt("layout_preset")
+                        .selected_text(current.map(|p| p.label()).unwrap_or("内置默认"))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut selected, None, "内置默认");
+                            for preset in LayoutPreset::ALL {
+                                ui.selectable_value(&mut selected, Some(preset), preset.label());
+                            }
+                        });
+                    if selected != current {
+                        self.config.layout_preset = selected;
+                        if let Err(e) = self.config_manager.save(&self.config) {
+                            tracing::warn!("保存目录结构预设失败: {}", e);
+                        }
+                    }
+                });
             });
 
             ui.add_space(20.0);
@@ -782,11 +2237,48 @@ impl OrderlyApp {
             ui.spinner();
             ui.add_space(20.0);
             ui.label(&self.status_message);
+
+            if self.state == AppState::Scanning {
+                ui.add_space(20.0);
+                let cancelling = self.cancel_scan.load(Ordering::Relaxed);
+                if ui
+                    .add_enabled(!cancelling, egui::Button::new("⏹ 取消扫描"))
+                    .on_hover_text("停止扫描，保留已扫描的文件")
+                    .clicked()
+                {
+                    self.cancel_scan.store(true, Ordering::Relaxed);
+                    self.status_message = "正在取消扫描...".to_string();
+                }
+            }
         });
     }
 
+    /// 渲染扫描警告与提示横幅（可展开查看、可关闭）
+    fn render_scan_warnings_banner(&mut self, ui: &mut egui::Ui) {
+        let title = format!("⚠️ 扫描时 {} 个条目出错（点击查看）", self.scan_warnings.len());
+        render_message_banner(
+            ui,
+            "scan_warnings",
+            title,
+            self.theme.warning,
+            &mut self.scan_warnings,
+            &mut self.scan_warnings_expanded,
+        );
+        let title = format!("ℹ️ {} 条提示（点击查看）", self.scan_notices.len());
+        render_message_banner(
+            ui,
+            "scan_notices",
+            title,
+            self.theme.primary,
+            &mut self.scan_notices,
+            &mut self.scan_notices_expanded,
+        );
+    }
+
     /// 渲染预览视图
     fn render_preview_view(&mut self, ui: &mut egui::Ui) {
+        self.render_scan_warnings_banner(ui);
+
         // 工具栏
         ui.horizontal(|ui| {
             if ui.button("📂 重新扫描").clicked() {
@@ -806,14 +2298,32 @@ impl OrderlyApp {
             ui.separator();
             
             let selected_count = self.files.iter().filter(|f| f.selected).count();
-            let can_execute = selected_count > 0;
+            let can_execute = selected_count > 0 && !self.generating_plan;
             
-            if ui.add_enabled(can_execute, egui::Button::new("▶️ 预览执行")).clicked() {
+            if ui
+                .add_enabled(can_execute, egui::Button::new("▶️ 预览执行"))
+                .on_hover_text("Ctrl+Enter")
+                .clicked()
+            {
                 self.generate_plan();
-                self.show_execute_confirm();
+            }
+
+            ui.separator();
+
+            if ui
+                .checkbox(&mut self.live_preview.enabled, "⚡ 实时预览")
+                .on_hover_text("勾选、阈值或规则变化时自动刷新计划统计")
+                .changed()
+            {
+                self.config.live_preview = self.live_preview.enabled;
+                if let Err(e) = self.config_manager.save(&self.config) {
+                    tracing::warn!("保存配置失败: {}", e);
+                }
             }
         });
 
+        self.update_live_preview(ui);
+
         ui.separator();
 
         // 预览表格工具栏
@@ -823,6 +2333,66 @@ impl OrderlyApp {
 
         // 预览表格
         self.preview_table.render(ui, &mut self.files);
+        if let Some(file_id) = self.preview_table.take_diagnose_request() {
+            self.diagnose_file(&file_id);
+        }
+        if let Some((action, path)) = self.preview_table.take_file_action() {
+            self.run_file_action(action, &path);
+        }
+        for (file_id, note) in self.preview_table.take_note_edits() {
+            if let Some(ref database) = self.database {
+                if let Err(e) = database.save_note(&file_id, note.as_deref()) {
+                    tracing::warn!("保存备注失败: {}", e);
+                }
+            }
+        }
+        if let Some(widths) = self.preview_table.take_changed_widths(ui.ctx()) {
+            self.config.preview_column_widths = widths;
+            if let Err(e) = self.config_manager.save(&self.config) {
+                tracing::warn!("保存列宽失败: {}", e);
+            }
+        }
+    }
+
+    /// 实时预览：状态变化并经过防抖后重新生成计划，显示一行统计
+    fn update_live_preview(&mut self, ui: &mut egui::Ui) {
+        if !self.live_preview.enabled {
+            return;
+        }
+        if LivePreview::too_many_files(self.files.len()) {
+            ui.label(
+                RichText::new(format!("文件超过 {} 个，实时预览已停用", LIVE_PREVIEW_MAX_FILES))
+                    .small()
+                    .color(egui::Color32::GRAY),
+            );
+            return;
+        }
+        let output_base = self.effective_output_base();
+        let (Some(engine), Some(planner), Some(executor)) =
+            (self.rule_engine.as_ref(), self.planner.as_mut(), self.executor.as_ref())
+        else {
+            return;
+        };
+
+        let now = std::time::Instant::now();
+        let threshold = self.config.current_profile().confidence_threshold;
+        self.live_preview
+            .observe(live_preview::fingerprint(&self.files, threshold, engine.get_rules()), now);
+        if self.live_preview.is_due(now) {
+            planner.set_output_base(output_base);
+            planner.set_confidence_threshold(threshold);
+            planner.set_naming_policy(self.config.naming_policy);
+            planner.set_skip_cloud_placeholders(!self.config.move_cloud_placeholders);
+            self.live_preview
+                .refresh(&mut self.files, engine, &self.session_rules, planner, executor);
+        } else {
+            // 防抖期间无输入也要按时刷新
+            ui.ctx().request_repaint_after(LIVE_PREVIEW_DEBOUNCE);
+        }
+
+        if let Some(summary) = self.live_preview.summary() {
+            ui.label(RichText::new(format!("⚡ {}", summary)).small());
+        }
     }
 
     /// 渲染执行视图
@@ -831,10 +2401,49 @@ impl OrderlyApp {
             ui.add_space(200.0);
             ui.spinner();
             ui.add_space(20.0);
-            ui.label("正在执行文件移动...");
+            ui.label(&self.status_message);
+            ui.add_space(10.0);
+            let cancelling = self.cancel_execution.load(Ordering::Relaxed);
+            if ui.add_enabled(!cancelling, egui::Button::new("⏹ 取消")).clicked() {
+                self.cancel_execution.store(true, Ordering::Relaxed);
+                self.status_message = "正在取消，当前文件完成后停止...".to_string();
+            }
         });
     }
 
+    /// 渲染最近一次执行的结果卡片（右下角，可撤销、可关闭）
+    fn render_receipt(&mut self, ctx: &egui::Context) {
+        let Some(receipt) = self.last_receipt.clone() else {
+            return;
+        };
+
+        let mut undo = false;
+        let mut dismiss = false;
+        egui::Window::new("operation_receipt")
+            .title_bar(false)
+            .resizable(false)
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-16.0, -40.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let icon = if receipt.failed > 0 || receipt.cancelled { "⚠" } else { "✅" };
+                    ui.label(RichText::new(format!("{} {}", icon, receipt.headline())).strong());
+                    if ui.small_button("✕").on_hover_text("关闭").clicked() {
+                        dismiss = true;
+                    }
+                });
+                ui.label(RichText::new(receipt.detail()).small().color(egui::Color32::GRAY));
+                if receipt.can_undo() && ui.button("↩ 撤销本次整理").clicked() {
+                    undo = true;
+                }
+            });
+
+        if undo {
+            self.request_undo(&receipt.batch_id);
+        } else if dismiss {
+            self.last_receipt = None;
+        }
+    }
+
     /// 渲染对话框
     fn render_dialogs(&mut self, ctx: &egui::Context) {
         // 提示词对话框
@@ -872,9 +2481,65 @@ impl OrderlyApp {
                 self.current_plan = None;
                 self.dry_run_result = None;
             }
+            ExecuteConfirmResult::ExportDryRun => {
+                self.export_dry_run();
+            }
             ExecuteConfirmResult::None => {}
         }
 
+        // 诊断对话框
+        self.diagnostic_dialog.render(ctx);
+        self.model_compare_dialog.render(ctx);
+
+        // 拿不准文件簇对话框
+        match self.borderline_dialog.render(ctx) {
+            BorderlineClusterResult::Apply(move_to) => {
+                if let Some(cluster) = self.pending_clusters.pop_front() {
+                    self.apply_cluster_target(&cluster, &move_to);
+                }
+                self.show_next_cluster();
+            }
+            BorderlineClusterResult::Skip => {
+                self.pending_clusters.pop_front();
+                self.show_next_cluster();
+            }
+            BorderlineClusterResult::None => {}
+        }
+
+        // 系统缓存文件清理对话框
+        if let CruftCleanupResult::Trash(files) = self.cruft_cleanup_dialog.render(ctx) {
+            let tx = self.bg_tx.clone();
+            thread::spawn(move || {
+                let _ = tx.send(BackgroundEvent::CleanupFinished(move_to_trash(&files)));
+            });
+        }
+
+        // 原子扫描根对话框
+        match self.atomic_root_dialog.render(ctx) {
+            AtomicRootResult::ScanSubdirs(subdirs) => {
+                // 原地整理会在扫描根下创建分类目录，而扫描根是要保护的程序/项目目录
+                if self.output_path.is_empty() && subdirs.len() > 1 {
+                    self.status_message =
+                        "所选目录是程序/项目目录，同时扫描多个子目录时请先设置输出目录".to_string();
+                } else {
+                    self.start_scan_roots(subdirs);
+                }
+            }
+            AtomicRootResult::Cancel => {
+                self.status_message = "已取消扫描".to_string();
+            }
+            AtomicRootResult::None => {}
+        }
+
+        // 导入规则对话框
+        match self.rule_import_dialog.render(ctx) {
+            RuleImportResult::Import(path, strategy) => self.import_rules(&path, strategy),
+            RuleImportResult::Cancel => {
+                self.status_message = "已取消导入规则".to_string();
+            }
+            RuleImportResult::None => {}
+        }
+
         // 错误聚类对话框
         match self.error_cluster_dialog.render(ctx) {
             ErrorClusterResult::WritePrompt => {
@@ -888,22 +2553,53 @@ impl OrderlyApp {
             ErrorClusterResult::None => {}
         }
 
+        // 未完成批次对话框
+        match self.pending_batch_dialog.render(ctx) {
+            PendingBatchResult::Resume => {
+                self.current_plan = self.executor.as_ref().and_then(|e| e.pending_batch());
+                self.execute_move();
+            }
+            PendingBatchResult::Rollback => {
+                self.rollback_pending_batch();
+            }
+            PendingBatchResult::Later => {}
+            PendingBatchResult::None => {}
+        }
+
+        // 撤销确认对话框
+        match self.undo_confirm_dialog.render(ctx) {
+            UndoConfirmResult::Confirm(batch_id) => {
+                if matches!(self.state, AppState::Initial | AppState::Preview) {
+                    self.rollback_batch(batch_id);
+                }
+            }
+            UndoConfirmResult::Cancel => {
+                self.status_message = "已取消撤销".to_string();
+            }
+            UndoConfirmResult::None => {}
+        }
+
+        // 规则归纳对话框
+        match self.rule_mining_dialog.render(ctx) {
+            RuleMiningResult::Accept(rules) => {
+                let count = rules.len();
+                if self.add_accepted_rules(rules) {
+                    self.status_message = format!("已从AI结果生成 {} 条规则", count);
+                } else {
+                    self.status_message = format!("正在扫描，{} 条规则将在扫描完成后添加", count);
+                }
+            }
+            RuleMiningResult::Cancel => {}
+            RuleMiningResult::None => {}
+        }
+
         // 设置对话框
         match self.settings_dialog.render(ctx) {
             SettingsResult::Save => {
-                // 保存设置
-                self.config.ai_config.api_endpoint = self.settings_dialog.effective_endpoint();
-                self.config.ai_config.api_key = self.settings_dialog.ai_key.clone();
-                self.config.ai_config.model_name = self.settings_dialog.model_name.clone();
-                self.config.confidence_threshold = self.settings_dialog.confidence_threshold;
-                self.config.ai_enabled = self.settings_dialog.ai_enabled;
-                
-                if !self.settings_dialog.default_scan_path.is_empty() {
-                    self.config.default_scan_path = Some(PathBuf::from(&self.settings_dialog.default_scan_path));
-                }
-                if !self.settings_dialog.default_output_path.is_empty() {
-                    self.config.default_output_base = Some(PathBuf::from(&self.settings_dialog.default_output_path));
-                }
+                // 保存设置，并让对话框显示规范化后的值（端点拆分、最近使用列表）
+                self.settings_dialog.apply_to_config(&mut self.config);
+                self.settings_dialog.load_from_config(&self.config);
+                self.preview_table.set_naming_policy(self.config.naming_policy);
 
                 match self.config_manager.save(&self.config) {
                     Ok(_) => self.status_message = "设置已保存".to_string(),
@@ -915,3 +2611,75 @@ impl OrderlyApp {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_startup_settings_restore_saved_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::new(dir.path().join("config.json"));
+        let mut config = AppConfig {
+            default_scan_path: Some(PathBuf::from("/home/user/Downloads")),
+            default_output_base: Some(PathBuf::from("/home/user/Sorted")),
+            confidence_threshold: 0.55,
+            write_folder_readme: true,
+            ..Default::default()
+        };
+        config.ai_config.model_name = "llama3".to_string();
+        manager.save(&config).unwrap();
+
+        let startup = StartupSettings::load(&manager);
+        assert_eq!(startup.scan_path, "/home/user/Downloads");
+        assert_eq!(startup.output_path, "/home/user/Sorted");
+        assert_eq!(startup.settings_dialog.model_name, "llama3");
+        assert_eq!(startup.settings_dialog.confidence_threshold, 0.55);
+        assert!(startup.settings_dialog.write_folder_readme);
+        assert_eq!(startup.settings_dialog.default_scan_path, "/home/user/Downloads");
+    }
+
+    #[test]
+    fn test_in_place_output_base_uses_scanned_subdir() {
+        let subdir = Path::new("/apps/tool/Screenshots");
+        assert_eq!(output_base_for("", Some(subdir), "/apps/tool"), subdir);
+        assert_eq!(output_base_for("", None, "/inbox"), Path::new("/inbox"));
+        assert_eq!(output_base_for("/out", Some(subdir), "/apps/tool"), Path::new("/out"));
+    }
+
+    #[test]
+    fn test_dropped_scan_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b) = (dir.path().join("a"), dir.path().join("b"));
+        std::fs::create_dir_all(&a).unwrap();
+        std::fs::create_dir_all(&b).unwrap();
+        std::fs::write(a.join("x.txt"), "x").unwrap();
+        std::fs::write(a.join("y.txt"), "y").unwrap();
+
+        assert_eq!(dropped_scan_root(std::slice::from_ref(&a)), Some(a.clone()));
+        // 文件取所在目录，同一目录下的多个文件仍视为一个目录
+        assert_eq!(dropped_scan_root(&[a.join("x.txt"), a.join("y.txt")]), Some(a.clone()));
+        assert_eq!(dropped_scan_root(&[a.clone(), b]), None);
+        assert_eq!(dropped_scan_root(&[]), None);
+    }
+
+    #[test]
+    fn test_startup_restores_last_paths_that_still_exist() {
+        let dir = tempfile::tempdir().unwrap();
+        let last_scan = dir.path().join("inbox");
+        std::fs::create_dir_all(&last_scan).unwrap();
+        let manager = ConfigManager::new(dir.path().join("config.json"));
+        manager
+            .save(&AppConfig {
+                last_scan_path: Some(last_scan.clone()),
+                last_output_path: Some(dir.path().join("removed")),
+                ..Default::default()
+            })
+            .unwrap();
+
+        // 扫描目录仍在则恢复；输出目录已不存在且档案没有默认路径时为空
+        let startup = StartupSettings::load(&manager);
+        assert_eq!(startup.scan_path, last_scan.to_string_lossy());
+        assert_eq!(startup.output_path, "");
+    }
+}
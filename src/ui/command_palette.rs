@@ -0,0 +1,235 @@
+//! 命令面板
+//!
+//! Ctrl+P 唤出，列出所有可执行的操作，支持模糊搜索，回车执行。
+
+use eframe::egui::{self, RichText};
+use std::rc::Rc;
+
+/// 命令回调
+pub type CommandAction<T> = Rc<dyn Fn(&mut T)>;
+
+/// 命令面板中的一条命令
+pub struct PaletteCommand<T> {
+    /// 显示名称（同时用于搜索）
+    pub name: String,
+    /// 执行的操作
+    action: CommandAction<T>,
+}
+
+/// 命令面板
+pub struct CommandPalette<T> {
+    /// 是否显示
+    pub visible: bool,
+    /// 搜索输入
+    query: String,
+    /// 当前高亮的结果序号
+    selected: usize,
+    /// 已注册的命令
+    commands: Vec<PaletteCommand<T>>,
+}
+
+impl<T> Default for CommandPalette<T> {
+    fn default() -> Self {
+        Self {
+            visible: false,
+            query: String::new(),
+            selected: 0,
+            commands: Vec::new(),
+        }
+    }
+}
+
+impl<T> CommandPalette<T> {
+    /// 创建空的命令面板
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册命令
+    pub fn register(&mut self, name: &str, action: impl Fn(&mut T) + 'static) {
+        self.commands.push(PaletteCommand {
+            name: name.to_string(),
+            action: Rc::new(action),
+        });
+    }
+
+    /// 打开/关闭面板
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    /// 按当前输入排序后的命令序号（不匹配的命令不出现）
+    pub fn ranked(&self) -> Vec<usize> {
+        rank_by_fuzzy(&self.query, self.commands.iter().map(|c| c.name.as_str()))
+    }
+
+    /// 渲染面板，返回用户选中要执行的命令
+    pub fn render(&mut self, ctx: &egui::Context) -> Option<CommandAction<T>> {
+        if !self.visible {
+            return None;
+        }
+
+        let mut chosen = None;
+        let mut close = false;
+
+        egui::Window::new("command_palette")
+            .title_bar(false)
+            .resizable(false)
+            .fixed_size([420.0, 0.0])
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 60.0])
+            .show(ctx, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.query)
+                        .hint_text("输入命令名称...")
+                        .desired_width(f32::INFINITY),
+                );
+                response.request_focus();
+                if response.changed() {
+                    self.selected = 0;
+                }
+
+                let ranked = self.ranked();
+                let (up, down, enter, escape) = ui.input(|i| {
+                    (
+                        i.key_pressed(egui::Key::ArrowUp),
+                        i.key_pressed(egui::Key::ArrowDown),
+                        i.key_pressed(egui::Key::Enter),
+                        i.key_pressed(egui::Key::Escape),
+                    )
+                });
+                if down && self.selected + 1 < ranked.len() {
+                    self.selected += 1;
+                }
+                if up && self.selected > 0 {
+                    self.selected -= 1;
+                }
+                if escape {
+                    close = true;
+                }
+
+                ui.separator();
+
+                if ranked.is_empty() {
+                    ui.label(RichText::new("没有匹配的命令").color(egui::Color32::GRAY));
+                }
+                for (pos, &idx) in ranked.iter().enumerate() {
+                    let is_selected = pos == self.selected;
+                    if ui.selectable_label(is_selected, &self.commands[idx].name).clicked()
+                        || (enter && is_selected)
+                    {
+                        chosen = Some(Rc::clone(&self.commands[idx].action));
+                        close = true;
+                    }
+                }
+            });
+
+        if close {
+            self.toggle();
+        }
+        chosen
+    }
+}
+
+/// 对候选项按模糊匹配得分排序，返回匹配项的序号（同分保持原顺序）
+pub fn rank_by_fuzzy<'a>(query: &str, candidates: impl Iterator<Item = &'a str>) -> Vec<usize> {
+    let mut scored: Vec<(usize, i32)> = candidates
+        .enumerate()
+        .filter_map(|(i, name)| fuzzy_score(query, name).map(|s| (i, s)))
+        .collect();
+    scored.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
+    scored.into_iter().map(|(i, _)| i).collect()
+}
+
+/// 模糊匹配得分：查询字符需按顺序出现在候选中（忽略大小写）
+///
+/// 连续命中、从开头或单词边界命中加分，跳过的字符扣分；不匹配返回 None。
+/// 首字符的每个出现位置都会尝试一次，取最高分。
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i32> {
+    let query: Vec<char> = query
+        .to_lowercase()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+
+    let chars: Vec<char> = candidate.to_lowercase().chars().collect();
+    let best = (0..chars.len())
+        .filter(|&start| chars[start] == query[0])
+        .filter_map(|start| score_from(&query, &chars, start))
+        .max()?;
+
+    // 候选越短越靠前
+    Some(best - (chars.len() / 4) as i32)
+}
+
+/// 从指定位置开始贪心匹配并计分
+fn score_from(query: &[char], chars: &[char], start: usize) -> Option<i32> {
+    let mut score = 0i32;
+    let mut matched = 0usize;
+    let mut prev: Option<usize> = None;
+
+    for (i, c) in chars.iter().enumerate().skip(start) {
+        if matched == query.len() {
+            break;
+        }
+        if *c != query[matched] {
+            continue;
+        }
+
+        score += 10;
+        match prev {
+            Some(p) if p + 1 == i => score += 15,
+            Some(p) => score -= (i - p - 1) as i32,
+            None if i == 0 => score += 20,
+            None => score -= i as i32,
+        }
+        if i > 0 && !chars[i - 1].is_alphanumeric() {
+            score += 10;
+        }
+
+        prev = Some(i);
+        matched += 1;
+    }
+
+    (matched == query.len()).then_some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ACTIONS: [&str; 6] = [
+        "Open folder",
+        "Open settings",
+        "Export rules",
+        "Toggle rule panel",
+        "Undo last batch",
+        "Start scan",
+    ];
+
+    fn ranked_names(query: &str) -> Vec<&'static str> {
+        rank_by_fuzzy(query, ACTIONS.iter().copied())
+            .into_iter()
+            .map(|i| ACTIONS[i])
+            .collect()
+    }
+
+    #[test]
+    fn test_fuzzy_ranking() {
+        // 空查询保持注册顺序
+        assert_eq!(ranked_names(""), ACTIONS.to_vec());
+
+        // 只保留按顺序包含查询字符的命令
+        assert_eq!(ranked_names("rule"), vec!["Export rules", "Toggle rule panel"]);
+
+        // 开头和单词边界命中优先
+        assert_eq!(ranked_names("ss")[0], "Start scan");
+        assert_eq!(ranked_names("os")[0], "Open settings");
+
+        assert!(ranked_names("xyz").is_empty());
+    }
+}
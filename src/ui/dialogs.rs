@@ -1,6 +1,15 @@
 //! 对话框组件
 
+use crate::core::model_compare::ModelComparison;
+use crate::core::models::{
+    ConfidenceCalibration, ConflictStrategy, DuplicateHandling, ExecutionMode, RecentEndpoint,
+    RuleDefinition,
+};
+use crate::core::naming::NamingPolicy;
+use crate::core::rule_engine::ImportStrategy;
+use crate::core::rule_miner::ProposedRule;
 use eframe::egui::{self, RichText};
+use std::path::PathBuf;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ApiInterfaceKind {
@@ -135,6 +144,7 @@ pub enum PromptDialogResult {
 }
 
 /// 规则确认对话框
+#[derive(Default)]
 pub struct RuleConfirmDialog {
     /// 是否显示
     pub visible: bool,
@@ -148,18 +158,6 @@ pub struct RuleConfirmDialog {
     pub affected_count: usize,
 }
 
-impl Default for RuleConfirmDialog {
-    fn default() -> Self {
-        Self {
-            visible: false,
-            rule_name: String::new(),
-            condition_desc: String::new(),
-            target_path: String::new(),
-            affected_count: 0,
-        }
-    }
-}
-
 impl RuleConfirmDialog {
     /// 显示对话框
     pub fn show(&mut self, name: &str, condition: &str, target: &str, count: usize) {
@@ -245,7 +243,254 @@ pub enum RuleConfirmResult {
     Cancel,
 }
 
+/// 规则归纳确认对话框（批量确认AI归纳出的候选规则）
+#[derive(Default)]
+pub struct RuleMiningDialog {
+    /// 是否显示
+    pub visible: bool,
+    /// 候选规则
+    pub proposals: Vec<ProposedRule>,
+    /// 每条候选是否勾选
+    pub accepted: Vec<bool>,
+}
+
+impl RuleMiningDialog {
+    /// 显示对话框（默认全部勾选）
+    pub fn show(&mut self, proposals: Vec<ProposedRule>) {
+        self.visible = true;
+        self.accepted = vec![true; proposals.len()];
+        self.proposals = proposals;
+    }
+
+    /// 渲染对话框
+    pub fn render(&mut self, ctx: &egui::Context) -> RuleMiningResult {
+        let mut result = RuleMiningResult::None;
+
+        if !self.visible {
+            return result;
+        }
+
+        egui::Window::new("从AI结果生成规则")
+            .collapsible(false)
+            .resizable(true)
+            .default_width(480.0)
+            .show(ctx, |ui| {
+                ui.label("本次整理中以下分类模式反复出现，可保存为规则，以后无需再调用AI：");
+                ui.separator();
+
+                egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    for (proposal, accepted) in self.proposals.iter().zip(self.accepted.iter_mut()) {
+                        ui.group(|ui| {
+                            ui.checkbox(accepted, RichText::new(&proposal.rule.name).strong());
+                            ui.horizontal(|ui| {
+                                ui.label("目标路径:");
+                                ui.label(&proposal.rule.action.move_to);
+                            });
+                            ui.label(
+                                RichText::new(format!(
+                                    "{} 个文件，如: {}",
+                                    proposal.support,
+                                    proposal.examples.join(", ")
+                                ))
+                                .small()
+                                .color(egui::Color32::GRAY),
+                            );
+                        });
+                    }
+                });
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    let count = self.accepted.iter().filter(|a| **a).count();
+                    if ui
+                        .add_enabled(count > 0, egui::Button::new(format!("✓ 保存选中的 {} 条规则", count)))
+                        .clicked()
+                    {
+                        let rules = self
+                            .proposals
+                            .iter()
+                            .zip(&self.accepted)
+                            .filter(|(_, accepted)| **accepted)
+                            .map(|(p, _)| p.rule.clone())
+                            .collect();
+                        result = RuleMiningResult::Accept(rules);
+                        self.visible = false;
+                    }
+                    if ui.button("✗ 忽略").clicked() {
+                        result = RuleMiningResult::Cancel;
+                        self.visible = false;
+                    }
+                });
+            });
+
+        result
+    }
+}
+
+/// 规则归纳对话框结果
+#[derive(Debug)]
+pub enum RuleMiningResult {
+    None,
+    Accept(Vec<RuleDefinition>),
+    Cancel,
+}
+
+/// 未完成批次对话框（启动时或取消执行后提示继续/回滚）
+#[derive(Default)]
+pub struct PendingBatchDialog {
+    /// 是否显示
+    pub visible: bool,
+    /// 批次ID
+    pub batch_id: String,
+    /// 已完成操作数
+    pub completed: usize,
+    /// 剩余操作数
+    pub remaining: usize,
+}
+
+impl PendingBatchDialog {
+    /// 显示对话框
+    pub fn show(&mut self, batch_id: &str, completed: usize, remaining: usize) {
+        self.visible = true;
+        self.batch_id = batch_id.to_string();
+        self.completed = completed;
+        self.remaining = remaining;
+    }
+
+    /// 渲染对话框
+    pub fn render(&mut self, ctx: &egui::Context) -> PendingBatchResult {
+        let mut result = PendingBatchResult::None;
+
+        if !self.visible {
+            return result;
+        }
+
+        egui::Window::new("发现未完成的整理")
+            .collapsible(false)
+            .resizable(false)
+            .default_width(400.0)
+            .show(ctx, |ui| {
+                let short_id = self.batch_id.get(0..8).unwrap_or(&self.batch_id);
+                ui.label(format!("批次 {} 上次没有执行完：", short_id));
+
+                ui.group(|ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("已完成:");
+                        ui.label(format!("{} 个", self.completed));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("剩余:");
+                        ui.label(
+                            RichText::new(format!("{} 个", self.remaining))
+                                .color(egui::Color32::YELLOW)
+                        );
+                    });
+                });
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    if ui.button("▶️ 继续执行").clicked() {
+                        result = PendingBatchResult::Resume;
+                        self.visible = false;
+                    }
+                    if ui.button("↩️ 回滚已完成部分").clicked() {
+                        result = PendingBatchResult::Rollback;
+                        self.visible = false;
+                    }
+                    if ui.button("⏱️ 稍后处理").clicked() {
+                        result = PendingBatchResult::Later;
+                        self.visible = false;
+                    }
+                });
+            });
+
+        result
+    }
+}
+
+/// 未完成批次对话框结果
+#[derive(Debug)]
+pub enum PendingBatchResult {
+    None,
+    Resume,
+    Rollback,
+    Later,
+}
+
+/// 撤销确认对话框：回滚整理批次前确认
+#[derive(Default)]
+pub struct UndoConfirmDialog {
+    /// 是否显示
+    pub visible: bool,
+    /// 要回滚的批次ID
+    pub batch_id: String,
+    /// 批次中的操作数
+    pub operations: usize,
+    /// 执行时间（本地时间，已格式化）
+    pub executed_at: String,
+}
+
+impl UndoConfirmDialog {
+    /// 显示对话框
+    pub fn show(&mut self, batch_id: &str, operations: usize, executed_at: chrono::DateTime<chrono::Utc>) {
+        self.visible = true;
+        self.batch_id = batch_id.to_string();
+        self.operations = operations;
+        self.executed_at = executed_at
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
+    }
+
+    /// 渲染对话框
+    pub fn render(&mut self, ctx: &egui::Context) -> UndoConfirmResult {
+        let mut result = UndoConfirmResult::None;
+
+        if !self.visible {
+            return result;
+        }
+
+        egui::Window::new("撤销整理")
+            .collapsible(false)
+            .resizable(false)
+            .default_width(400.0)
+            .show(ctx, |ui| {
+                let short_id = self.batch_id.get(0..8).unwrap_or(&self.batch_id);
+                ui.label(format!(
+                    "将回滚批次 {}（{}，{} 个操作），文件会移回原位置。",
+                    short_id, self.executed_at, self.operations
+                ));
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    if ui.button("↩️ 撤销").clicked() {
+                        result = UndoConfirmResult::Confirm(self.batch_id.clone());
+                        self.visible = false;
+                    }
+                    if ui.button("✗ 取消").clicked() {
+                        result = UndoConfirmResult::Cancel;
+                        self.visible = false;
+                    }
+                });
+            });
+
+        result
+    }
+}
+
+/// 撤销确认对话框结果
+#[derive(Debug)]
+pub enum UndoConfirmResult {
+    None,
+    Confirm(String),
+    Cancel,
+}
+
 /// 执行确认对话框
+#[derive(Default)]
 pub struct ExecuteConfirmDialog {
     /// 是否显示
     pub visible: bool,
@@ -257,87 +502,404 @@ pub struct ExecuteConfirmDialog {
     pub target_dirs: usize,
     /// 潜在问题
     pub warnings: Vec<String>,
+    /// 需要处理的问题（目标冲突、循环路径等），存在时需额外确认才能执行
+    pub blocking_errors: Vec<String>,
+    /// 用户已确认忽略需要处理的问题
+    pub acknowledge_blocking: bool,
+    /// 差异文本（用于复制分享）
+    pub diff: String,
+    /// 预计耗时
+    pub estimate: String,
+    /// 目标卷空间（如 "需要 1.2 GB，可用 30 GB"），无需占用空间时为空
+    pub space: String,
+    /// 移动还是复制
+    pub mode: ExecutionMode,
 }
 
-impl Default for ExecuteConfirmDialog {
-    fn default() -> Self {
-        Self {
-            visible: false,
-            operation_count: 0,
-            total_size: String::new(),
-            target_dirs: 0,
-            warnings: Vec::new(),
+impl ExecuteConfirmDialog {
+    /// 显示对话框
+    pub fn show(&mut self, ops: usize, size: String, dirs: usize, warnings: Vec<String>) {
+        self.visible = true;
+        self.operation_count = ops;
+        self.total_size = size;
+        self.target_dirs = dirs;
+        self.warnings = warnings;
+        self.blocking_errors.clear();
+        self.acknowledge_blocking = false;
+    }
+
+    /// 是否允许执行（有需要处理的问题时须先勾选确认）
+    pub fn can_execute(&self) -> bool {
+        self.blocking_errors.is_empty() || self.acknowledge_blocking
+    }
+
+    /// 渲染对话框
+    pub fn render(&mut self, ctx: &egui::Context) -> ExecuteConfirmResult {
+        let mut result = ExecuteConfirmResult::None;
+
+        if !self.visible {
+            return result;
+        }
+
+        egui::Window::new("确认执行")
+            .collapsible(false)
+            .resizable(false)
+            .default_width(400.0)
+            .show(ctx, |ui| {
+                ui.heading("即将执行以下操作");
+                ui.label(match self.mode {
+                    ExecutionMode::Move => RichText::new("模式: 移动（文件将离开原位置）"),
+                    ExecutionMode::Copy => RichText::new("模式: 复制（原文件保留在原处）")
+                        .color(egui::Color32::LIGHT_BLUE),
+                });
+                
+                ui.separator();
+
+                ui.group(|ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{}文件数:", self.mode.label()));
+                        ui.label(
+                            RichText::new(format!("{}", self.operation_count))
+                                .strong()
+                        );
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("总大小:");
+                        ui.label(&self.total_size);
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("目标目录:");
+                        ui.label(format!("{} 个", self.target_dirs));
+                    });
+
+                    if !self.estimate.is_empty() {
+                        ui.horizontal(|ui| {
+                            ui.label("预计耗时:");
+                            ui.label(&self.estimate);
+                        });
+                    }
+
+                    if !self.space.is_empty() {
+                        ui.horizontal(|ui| {
+                            ui.label("目标卷空间:");
+                            ui.label(&self.space);
+                        });
+                    }
+                });
+
+                if !self.blocking_errors.is_empty() {
+                    ui.separator();
+                    ui.label(
+                        RichText::new(format!("⛔ 需要处理的问题（{}）", self.blocking_errors.len()))
+                            .color(egui::Color32::RED)
+                            .strong(),
+                    );
+                    let (shown, hidden) = collapse_list(&self.blocking_errors, MAX_VISIBLE_WARNINGS);
+                    egui::ScrollArea::vertical()
+                        .id_salt("execute_confirm_blocking")
+                        .max_height(150.0)
+                        .show(ui, |ui| {
+                            for error in shown {
+                                ui.label(RichText::new(format!("• {}", error)).color(egui::Color32::RED));
+                            }
+                            if hidden > 0 {
+                                ui.label(
+                                    RichText::new(format!("还有 {} 条…", hidden))
+                                        .color(egui::Color32::GRAY),
+                                );
+                            }
+                        });
+                    ui.checkbox(&mut self.acknowledge_blocking, "我了解上述问题，仍然执行");
+                }
+
+                if !self.warnings.is_empty() {
+                    ui.separator();
+                    ui.label(
+                        RichText::new("⚠️ 警告")
+                            .color(egui::Color32::YELLOW)
+                    );
+                    let (shown, hidden) = collapse_list(&self.warnings, MAX_VISIBLE_WARNINGS);
+                    egui::ScrollArea::vertical()
+                        .id_salt("execute_confirm_warnings")
+                        .max_height(200.0)
+                        .show(ui, |ui| {
+                            for warning in shown {
+                                ui.label(format!("• {}", warning));
+                            }
+                            if hidden > 0 {
+                                ui.label(
+                                    RichText::new(format!("还有 {} 条…", hidden))
+                                        .color(egui::Color32::GRAY),
+                                );
+                            }
+                        });
+                }
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    let execute = egui::Button::new(format!("✓ 执行{}", self.mode.label()));
+                    if ui.add_enabled(self.can_execute(), execute).clicked() {
+                        result = ExecuteConfirmResult::Execute;
+                        self.visible = false;
+                    }
+                    if !self.diff.is_empty() && ui.button("📋 复制差异").clicked() {
+                        ui.ctx().copy_text(self.diff.clone());
+                    }
+                    if ui.button("💾 导出预览").clicked() {
+                        result = ExecuteConfirmResult::ExportDryRun;
+                    }
+                    if ui.button("✗ 取消").clicked() {
+                        result = ExecuteConfirmResult::Cancel;
+                        self.visible = false;
+                    }
+                });
+            });
+
+        result
+    }
+}
+
+/// 确认对话框中最多逐条显示的警告数
+pub const MAX_VISIBLE_WARNINGS: usize = 50;
+
+/// 截取列表前 max 项用于显示，返回显示部分和被折叠的条数
+pub fn collapse_list<T>(items: &[T], max: usize) -> (&[T], usize) {
+    let shown = items.len().min(max);
+    (&items[..shown], items.len() - shown)
+}
+
+/// 执行确认结果
+#[derive(Debug)]
+pub enum ExecuteConfirmResult {
+    None,
+    Execute,
+    Cancel,
+    /// 把预览导出为 JSON/CSV 文件（对话框保持打开）
+    ExportDryRun,
+}
+
+/// 扫描根为原子目录时的提示对话框
+#[derive(Default)]
+pub struct AtomicRootDialog {
+    /// 是否显示
+    pub visible: bool,
+    /// 扫描根目录
+    pub root: PathBuf,
+    /// 可单独整理的子目录及是否勾选
+    pub subdirs: Vec<(PathBuf, bool)>,
+}
+
+impl AtomicRootDialog {
+    /// 显示对话框
+    pub fn show(&mut self, root: PathBuf, subdirs: Vec<PathBuf>) {
+        self.visible = true;
+        self.root = root;
+        self.subdirs = subdirs.into_iter().map(|d| (d, true)).collect();
+    }
+
+    /// 渲染对话框
+    pub fn render(&mut self, ctx: &egui::Context) -> AtomicRootResult {
+        let mut result = AtomicRootResult::None;
+
+        if !self.visible {
+            return result;
         }
+
+        egui::Window::new("扫描根目录是原子目录")
+            .collapsible(false)
+            .resizable(true)
+            .default_width(450.0)
+            .show(ctx, |ui| {
+                ui.label(
+                    RichText::new("⚠️ 所选目录看起来是程序或项目目录，整理其内部结构可能导致程序无法运行")
+                        .color(egui::Color32::YELLOW),
+                );
+                ui.label(self.root.display().to_string());
+
+                ui.separator();
+
+                if self.subdirs.is_empty() {
+                    ui.label("没有可以单独整理的子目录。");
+                } else {
+                    ui.label("可以只整理以下非原子子目录：");
+                    egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                        for (dir, checked) in &mut self.subdirs {
+                            let name = dir
+                                .file_name()
+                                .map(|n| n.to_string_lossy().to_string())
+                                .unwrap_or_default();
+                            ui.checkbox(checked, name);
+                        }
+                    });
+                }
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    let chosen: Vec<PathBuf> = self
+                        .subdirs
+                        .iter()
+                        .filter(|(_, checked)| *checked)
+                        .map(|(dir, _)| dir.clone())
+                        .collect();
+                    if ui
+                        .add_enabled(!chosen.is_empty(), egui::Button::new("📂 只整理所选子目录"))
+                        .clicked()
+                    {
+                        result = AtomicRootResult::ScanSubdirs(chosen);
+                        self.visible = false;
+                    }
+                    if ui.button("✗ 取消").clicked() {
+                        result = AtomicRootResult::Cancel;
+                        self.visible = false;
+                    }
+                });
+            });
+
+        result
     }
 }
 
-impl ExecuteConfirmDialog {
+/// 原子扫描根对话框结果
+#[derive(Debug)]
+pub enum AtomicRootResult {
+    None,
+    ScanSubdirs(Vec<PathBuf>),
+    Cancel,
+}
+
+/// 导入规则对话框：选择与已有规则同ID时的处理方式
+#[derive(Default)]
+pub struct RuleImportDialog {
+    /// 是否显示
+    pub visible: bool,
+    /// 要导入的规则文件
+    pub path: PathBuf,
+    /// 同ID规则的处理方式
+    pub strategy: ImportStrategy,
+}
+
+impl RuleImportDialog {
+    /// 显示对话框
+    pub fn show(&mut self, path: PathBuf) {
+        self.visible = true;
+        self.path = path;
+    }
+
+    /// 渲染对话框
+    pub fn render(&mut self, ctx: &egui::Context) -> RuleImportResult {
+        let mut result = RuleImportResult::None;
+
+        if !self.visible {
+            return result;
+        }
+
+        egui::Window::new("导入规则")
+            .collapsible(false)
+            .resizable(false)
+            .default_width(400.0)
+            .show(ctx, |ui| {
+                ui.label(self.path.display().to_string());
+                ui.separator();
+
+                ui.label("已有同ID的规则时：");
+                for strategy in ImportStrategy::ALL {
+                    ui.radio_value(&mut self.strategy, strategy, strategy.label());
+                }
+                ui.label(
+                    RichText::new("内置规则不会被导入的规则覆盖")
+                        .small()
+                        .color(egui::Color32::GRAY),
+                );
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    if ui.button("📥 导入").clicked() {
+                        result = RuleImportResult::Import(self.path.clone(), self.strategy);
+                        self.visible = false;
+                    }
+                    if ui.button("✗ 取消").clicked() {
+                        result = RuleImportResult::Cancel;
+                        self.visible = false;
+                    }
+                });
+            });
+
+        result
+    }
+}
+
+/// 导入规则对话框结果
+#[derive(Debug)]
+pub enum RuleImportResult {
+    None,
+    Import(PathBuf, ImportStrategy),
+    Cancel,
+}
+
+/// 系统缓存文件清理确认对话框
+#[derive(Default)]
+pub struct CruftCleanupDialog {
+    /// 是否显示
+    pub visible: bool,
+    /// 待清理的文件
+    pub files: Vec<PathBuf>,
+}
+
+impl CruftCleanupDialog {
     /// 显示对话框
-    pub fn show(&mut self, ops: usize, size: String, dirs: usize, warnings: Vec<String>) {
+    pub fn show(&mut self, files: Vec<PathBuf>) {
         self.visible = true;
-        self.operation_count = ops;
-        self.total_size = size;
-        self.target_dirs = dirs;
-        self.warnings = warnings;
+        self.files = files;
     }
 
     /// 渲染对话框
-    pub fn render(&mut self, ctx: &egui::Context) -> ExecuteConfirmResult {
-        let mut result = ExecuteConfirmResult::None;
+    pub fn render(&mut self, ctx: &egui::Context) -> CruftCleanupResult {
+        let mut result = CruftCleanupResult::None;
 
         if !self.visible {
             return result;
         }
 
-        egui::Window::new("确认执行")
+        egui::Window::new("清理系统缓存文件")
             .collapsible(false)
-            .resizable(false)
-            .default_width(400.0)
+            .resizable(true)
+            .default_width(450.0)
             .show(ctx, |ui| {
-                ui.heading("即将执行以下操作");
-                
-                ui.separator();
-
-                ui.group(|ui| {
-                    ui.horizontal(|ui| {
-                        ui.label("移动文件数:");
-                        ui.label(
-                            RichText::new(format!("{}", self.operation_count))
-                                .strong()
-                        );
-                    });
-
-                    ui.horizontal(|ui| {
-                        ui.label("总大小:");
-                        ui.label(&self.total_size);
-                    });
+                ui.label(format!(
+                    "🧹 发现 {} 个系统自动生成的缓存文件 (.DS_Store / Thumbs.db / desktop.ini)",
+                    self.files.len()
+                ));
+                ui.label(
+                    RichText::new("清理与整理相互独立；文件会移到回收站，可随时恢复。")
+                        .color(egui::Color32::GRAY),
+                );
 
-                    ui.horizontal(|ui| {
-                        ui.label("目标目录:");
-                        ui.label(format!("{} 个", self.target_dirs));
-                    });
-                });
+                ui.separator();
 
-                if !self.warnings.is_empty() {
-                    ui.separator();
-                    ui.label(
-                        RichText::new("⚠️ 警告")
-                            .color(egui::Color32::YELLOW)
-                    );
-                    for warning in &self.warnings {
-                        ui.label(format!("• {}", warning));
+                let (shown, hidden) = collapse_list(&self.files, MAX_VISIBLE_WARNINGS);
+                egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                    for path in shown {
+                        ui.label(format!("• {}", path.display()));
                     }
-                }
+                    if hidden > 0 {
+                        ui.label(RichText::new(format!("还有 {} 条…", hidden)).color(egui::Color32::GRAY));
+                    }
+                });
 
                 ui.separator();
 
                 ui.horizontal(|ui| {
-                    if ui.button("✓ 执行").clicked() {
-                        result = ExecuteConfirmResult::Execute;
+                    if ui.button(format!("🗑 移到回收站 ({})", self.files.len())).clicked() {
+                        result = CruftCleanupResult::Trash(std::mem::take(&mut self.files));
                         self.visible = false;
                     }
-                    if ui.button("✗ 取消").clicked() {
-                        result = ExecuteConfirmResult::Cancel;
+                    if ui.button("跳过").clicked() {
+                        result = CruftCleanupResult::Skip;
                         self.visible = false;
                     }
                 });
@@ -347,15 +909,130 @@ impl ExecuteConfirmDialog {
     }
 }
 
-/// 执行确认结果
+/// 系统缓存文件清理结果
 #[derive(Debug)]
-pub enum ExecuteConfirmResult {
+pub enum CruftCleanupResult {
     None,
-    Execute,
-    Cancel,
+    Trash(Vec<PathBuf>),
+    Skip,
+}
+
+/// “为何没有建议”诊断对话框
+#[derive(Default)]
+pub struct DiagnosticDialog {
+    /// 是否显示
+    pub visible: bool,
+    /// 被诊断的文件名
+    pub file_name: String,
+    /// 诊断结果（逐行显示）
+    pub lines: Vec<String>,
+}
+
+impl DiagnosticDialog {
+    /// 显示对话框
+    pub fn show(&mut self, file_name: &str, lines: Vec<String>) {
+        self.visible = true;
+        self.file_name = file_name.to_string();
+        self.lines = lines;
+    }
+
+    /// 渲染对话框
+    pub fn render(&mut self, ctx: &egui::Context) {
+        if !self.visible {
+            return;
+        }
+
+        let mut open = true;
+        egui::Window::new("为何没有建议")
+            .collapsible(false)
+            .resizable(true)
+            .default_width(450.0)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label(RichText::new(format!("🔍 {}", self.file_name)).strong());
+                ui.separator();
+                for line in &self.lines {
+                    ui.label(format!("• {}", line));
+                }
+                ui.separator();
+                if ui.button("关闭").clicked() {
+                    self.visible = false;
+                }
+            });
+        if !open {
+            self.visible = false;
+        }
+    }
+}
+
+/// 模型对比结果对话框
+#[derive(Default)]
+pub struct ModelCompareDialog {
+    /// 是否显示
+    pub visible: bool,
+    /// 对比报告
+    pub report: ModelComparison,
+}
+
+impl ModelCompareDialog {
+    /// 显示对话框
+    pub fn show(&mut self, report: ModelComparison) {
+        self.visible = true;
+        self.report = report;
+    }
+
+    /// 渲染对话框
+    pub fn render(&mut self, ctx: &egui::Context) {
+        if !self.visible {
+            return;
+        }
+
+        let mut open = true;
+        egui::Window::new("模型对比")
+            .collapsible(false)
+            .resizable(true)
+            .default_width(560.0)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label(RichText::new(self.report.summary()).strong());
+                ui.separator();
+                if self.report.disagreements.is_empty() {
+                    ui.label("两个模型的建议完全一致");
+                } else {
+                    let show = |target: &Option<PathBuf>| {
+                        target
+                            .as_ref()
+                            .map(|p| p.display().to_string())
+                            .unwrap_or_else(|| "（请求失败）".to_string())
+                    };
+                    egui::ScrollArea::vertical().max_height(360.0).show(ui, |ui| {
+                        egui::Grid::new("model_compare_grid").striped(true).show(ui, |ui| {
+                            ui.label(RichText::new("文件").strong());
+                            ui.label(RichText::new(&self.report.left_model).strong());
+                            ui.label(RichText::new(&self.report.right_model).strong());
+                            ui.end_row();
+                            for d in &self.report.disagreements {
+                                ui.label(&d.file_name);
+                                ui.label(show(&d.left));
+                                ui.label(show(&d.right));
+                                ui.end_row();
+                            }
+                        });
+                    });
+                }
+                ui.separator();
+                if ui.button("关闭").clicked() {
+                    self.visible = false;
+                }
+            });
+        if !open {
+            self.visible = false;
+        }
+    }
 }
 
 /// 错误聚类提示对话框
+#[derive(Default)]
 pub struct ErrorClusterDialog {
     /// 是否显示
     pub visible: bool,
@@ -365,16 +1042,6 @@ pub struct ErrorClusterDialog {
     pub related_files: Vec<String>,
 }
 
-impl Default for ErrorClusterDialog {
-    fn default() -> Self {
-        Self {
-            visible: false,
-            description: String::new(),
-            related_files: Vec::new(),
-        }
-    }
-}
-
 impl ErrorClusterDialog {
     /// 显示对话框
     pub fn show(&mut self, desc: &str, files: Vec<String>) {
@@ -452,6 +1119,105 @@ pub enum ErrorClusterResult {
     Ignore,
 }
 
+/// 拿不准文件簇对话框：分析途中为一整簇相似文件指定一次目标
+#[derive(Default)]
+pub struct BorderlineClusterDialog {
+    /// 是否显示
+    pub visible: bool,
+    /// 簇的共同标签
+    pub tag: String,
+    /// 簇内文件数
+    pub count: usize,
+    /// 示例文件名
+    pub examples: Vec<String>,
+    /// 目标路径模板输入
+    pub target: String,
+    /// 后面还排着几个簇
+    pub remaining: usize,
+}
+
+impl BorderlineClusterDialog {
+    /// 显示对话框
+    pub fn show(&mut self, tag: &str, count: usize, examples: Vec<String>, remaining: usize) {
+        self.visible = true;
+        self.tag = tag.to_string();
+        self.count = count;
+        self.examples = examples;
+        self.remaining = remaining;
+        self.target.clear();
+    }
+
+    /// 渲染对话框
+    pub fn render(&mut self, ctx: &egui::Context) -> BorderlineClusterResult {
+        let mut result = BorderlineClusterResult::None;
+
+        if !self.visible {
+            return result;
+        }
+
+        egui::Window::new("这些文件该放哪里？")
+            .collapsible(false)
+            .resizable(false)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "有 {} 个标签为「{}」的文件置信度不高，暂时没有建议：",
+                    self.count, self.tag
+                ));
+                for name in &self.examples {
+                    ui.label(format!("• {}", name));
+                }
+                if self.count > self.examples.len() {
+                    ui.label(
+                        RichText::new(format!("还有 {} 个…", self.count - self.examples.len()))
+                            .color(egui::Color32::GRAY),
+                    );
+                }
+
+                ui.separator();
+
+                ui.label("为整组指定目标（仅本次运行生效，可用 {year}、{extension} 等变量）：");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.target)
+                        .hint_text("例如 Bills/{year}")
+                        .desired_width(f32::INFINITY),
+                );
+
+                if self.remaining > 0 {
+                    ui.label(
+                        RichText::new(format!("之后还有 {} 组待确认", self.remaining))
+                            .small()
+                            .color(egui::Color32::GRAY),
+                    );
+                }
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    let can_apply = !self.target.trim().is_empty();
+                    if ui.add_enabled(can_apply, egui::Button::new("✅ 应用到整组")).clicked() {
+                        result = BorderlineClusterResult::Apply(self.target.trim().to_string());
+                        self.visible = false;
+                    }
+                    if ui.button("跳过").clicked() {
+                        result = BorderlineClusterResult::Skip;
+                        self.visible = false;
+                    }
+                });
+            });
+
+        result
+    }
+}
+
+/// 拿不准文件簇对话框结果
+#[derive(Debug)]
+pub enum BorderlineClusterResult {
+    None,
+    Apply(String),
+    Skip,
+}
+
 /// 设置对话框
 pub struct SettingsDialog {
     /// 是否显示
@@ -468,14 +1234,66 @@ pub struct SettingsDialog {
     pub ai_key: String,
     /// 模型名称
     pub model_name: String,
+    /// 自定义系统提示词
+    pub system_prompt: String,
+    /// 内容摘要读取上限（KB）
+    pub content_summary_kb: usize,
+    /// AI请求失败重试次数
+    pub ai_max_retries: u32,
+    /// AI请求超时（秒）
+    pub ai_request_timeout_secs: u64,
+    /// 模型对比时的另一个模型
+    pub ai_comparison_model: String,
+    /// 对比模型的端点（为空时与当前端点相同）
+    pub ai_comparison_endpoint: String,
+    /// 对比模型端点的 API 密钥
+    pub ai_comparison_key: String,
     /// 置信度阈值
     pub confidence_threshold: f32,
+    /// 置信度校准 gamma（1.0 表示不校准）
+    pub confidence_gamma: f32,
+    /// 配置文件中使用了分段映射校准（界面只读）
+    pub piecewise_calibration: bool,
     /// 是否启用AI
     pub ai_enabled: bool,
+    /// 规则已匹配的文件也请模型给出路径并融合
+    pub fuse_rule_matches_with_ai: bool,
     /// 默认扫描路径
     pub default_scan_path: String,
     /// 默认输出路径
     pub default_output_path: String,
+    /// 最近使用的端点与模型
+    pub recent_endpoints: Vec<RecentEndpoint>,
+    /// 新建分类目录时写入说明文件
+    pub write_folder_readme: bool,
+    /// 隔离独立可执行文件
+    pub route_loose_executables: bool,
+    /// 票据按机构归档
+    pub organize_by_entity: bool,
+    /// 检测并提供清理系统缓存文件
+    pub cleanup_os_cruft: bool,
+    /// 执行前校验源文件内容
+    pub verify_source_hash: bool,
+    /// 拒绝输出目录之外的目标
+    pub strict_output_containment: bool,
+    /// 也移动云端占位文件
+    pub move_cloud_placeholders: bool,
+    /// 目标已存在时的处理策略
+    pub conflict_strategy: ConflictStrategy,
+    /// 移动还是复制文件
+    pub execution_mode: ExecutionMode,
+    /// 冲突时先把原有文件移到回收站
+    pub use_trash_on_conflict: bool,
+    /// 按内容去重跳过时把源文件移到回收站
+    pub trash_duplicate_sources: bool,
+    /// 任一操作失败时自动回滚整批
+    pub transactional_execution: bool,
+    /// 生成计划时对内容相同的文件去重
+    pub dedupe_identical: bool,
+    /// 重复文件的处理方式
+    pub duplicate_handling: DuplicateHandling,
+    /// 文件名规范化策略
+    pub naming_policy: NamingPolicy,
 }
 
 impl Default for SettingsDialog {
@@ -488,10 +1306,36 @@ impl Default for SettingsDialog {
             custom_suffix: "/api/generate".to_string(),
             ai_key: String::new(),
             model_name: "qwen3:30b-a3b".to_string(),
+            system_prompt: String::new(),
+            content_summary_kb: 1,
+            ai_max_retries: 2,
+            ai_request_timeout_secs: 60,
+            ai_comparison_model: String::new(),
+            ai_comparison_endpoint: String::new(),
+            ai_comparison_key: String::new(),
             confidence_threshold: 0.7,
+            confidence_gamma: 1.0,
+            piecewise_calibration: false,
             ai_enabled: true,
+            fuse_rule_matches_with_ai: false,
             default_scan_path: String::new(),
             default_output_path: String::new(),
+            recent_endpoints: Vec::new(),
+            write_folder_readme: false,
+            route_loose_executables: false,
+            organize_by_entity: false,
+            cleanup_os_cruft: false,
+            verify_source_hash: false,
+            strict_output_containment: true,
+            move_cloud_placeholders: false,
+            conflict_strategy: ConflictStrategy::default(),
+            execution_mode: ExecutionMode::default(),
+            use_trash_on_conflict: false,
+            trash_duplicate_sources: false,
+            transactional_execution: false,
+            dedupe_identical: false,
+            duplicate_handling: DuplicateHandling::default(),
+            naming_policy: NamingPolicy::default(),
         }
     }
 }
@@ -499,24 +1343,134 @@ impl Default for SettingsDialog {
 impl SettingsDialog {
     pub fn load_from_config(&mut self, config: &crate::core::models::AppConfig) {
         self.ai_enabled = config.ai_enabled;
+        self.fuse_rule_matches_with_ai = config.fuse_rule_matches_with_ai;
         self.ai_key = config.ai_config.api_key.clone();
         self.model_name = config.ai_config.model_name.clone();
-        self.confidence_threshold = config.confidence_threshold;
+        self.system_prompt = config.ai_config.system_prompt.clone().unwrap_or_default();
+        self.content_summary_kb = config.ai_config.content_summary_kb;
+        self.ai_max_retries = config.ai_config.max_retries;
+        self.ai_request_timeout_secs = config.ai_config.request_timeout_secs;
+        self.ai_comparison_model = config.ai_config.comparison_model.clone();
+        self.ai_comparison_endpoint = config.ai_config.comparison_endpoint.clone();
+        self.ai_comparison_key = config.ai_config.comparison_api_key.clone();
+        let profile = config.current_profile();
+        self.confidence_threshold = profile.confidence_threshold;
+        self.piecewise_calibration = false;
+        self.confidence_gamma = match config.ai_config.confidence_calibration {
+            ConfidenceCalibration::Gamma(gamma) => gamma,
+            ConfidenceCalibration::Piecewise(_) => {
+                self.piecewise_calibration = true;
+                1.0
+            }
+            ConfidenceCalibration::Identity => 1.0,
+        };
+
+        // 切换档案后重新打开设置时不保留上一个档案的路径
+        let path_text = |p: &Option<PathBuf>| p.as_ref().map(|p| p.to_string_lossy().to_string()).unwrap_or_default();
+        self.default_scan_path = path_text(&profile.default_scan_path);
+        self.default_output_path = path_text(&profile.default_output_base);
+
+        self.recent_endpoints = config.recent_endpoints.clone();
+        self.write_folder_readme = config.write_folder_readme;
+        self.route_loose_executables = config.route_loose_executables;
+        self.organize_by_entity = config.organize_by_entity;
+        self.cleanup_os_cruft = config.cleanup_os_cruft;
+        self.verify_source_hash = config.verify_source_hash;
+        self.strict_output_containment = config.strict_output_containment;
+        self.move_cloud_placeholders = config.move_cloud_placeholders;
+        self.conflict_strategy = config.conflict_strategy;
+        self.execution_mode = config.execution_mode;
+        self.use_trash_on_conflict = config.use_trash_on_conflict;
+        self.trash_duplicate_sources = config.trash_duplicate_sources;
+        self.transactional_execution = config.transactional_execution;
+        self.dedupe_identical = config.dedupe_identical;
+        self.duplicate_handling = config.duplicate_handling;
+        self.naming_policy = config.naming_policy;
+        self.set_endpoint(&config.ai_config.api_endpoint);
+    }
 
-        if let Some(ref p) = config.default_scan_path {
-            self.default_scan_path = p.to_string_lossy().to_string();
+    /// 把界面上的设置写入配置，并记入最近使用的端点与模型
+    ///
+    /// 阈值和默认路径写入当前配置档案；默认路径留空时保留原值。
+    pub fn apply_to_config(&self, config: &mut crate::core::models::AppConfig) {
+        config.ai_config.api_endpoint = self.effective_endpoint();
+        config.ai_config.api_key = self.ai_key.clone();
+        config.ai_config.model_name = self.model_name.clone();
+        config.ai_config.system_prompt = self.system_prompt();
+        config.ai_config.content_summary_kb = self.content_summary_kb;
+        config.ai_config.max_retries = self.ai_max_retries;
+        config.ai_config.request_timeout_secs = self.ai_request_timeout_secs;
+        config.ai_config.comparison_model = self.ai_comparison_model.trim().to_string();
+        config.ai_config.comparison_endpoint = self.ai_comparison_endpoint.trim().to_string();
+        config.ai_config.comparison_api_key = self.ai_comparison_key.clone();
+        config.ai_config.confidence_calibration = self.calibration(&config.ai_config.confidence_calibration);
+        config.ai_enabled = self.ai_enabled;
+        config.fuse_rule_matches_with_ai = self.fuse_rule_matches_with_ai;
+        config.write_folder_readme = self.write_folder_readme;
+        config.route_loose_executables = self.route_loose_executables;
+        config.organize_by_entity = self.organize_by_entity;
+        config.cleanup_os_cruft = self.cleanup_os_cruft;
+        config.verify_source_hash = self.verify_source_hash;
+        config.strict_output_containment = self.strict_output_containment;
+        config.move_cloud_placeholders = self.move_cloud_placeholders;
+        config.conflict_strategy = self.conflict_strategy;
+        config.execution_mode = self.execution_mode;
+        config.use_trash_on_conflict = self.use_trash_on_conflict;
+        config.trash_duplicate_sources = self.trash_duplicate_sources;
+        config.transactional_execution = self.transactional_execution;
+        config.dedupe_identical = self.dedupe_identical;
+        config.duplicate_handling = self.duplicate_handling;
+        config.naming_policy = self.naming_policy;
+        let endpoint = config.ai_config.api_endpoint.clone();
+        let model_name = config.ai_config.model_name.clone();
+        config.remember_endpoint(&endpoint, &model_name);
+
+        let mut profile = config.current_profile();
+        profile.confidence_threshold = self.confidence_threshold;
+        if !self.default_scan_path.is_empty() {
+            profile.default_scan_path = Some(PathBuf::from(&self.default_scan_path));
         }
-        if let Some(ref p) = config.default_output_base {
-            self.default_output_path = p.to_string_lossy().to_string();
+        if !self.default_output_path.is_empty() {
+            profile.default_output_base = Some(PathBuf::from(&self.default_output_path));
         }
+        let active = config.active_profile.clone();
+        config.set_profile(&active, profile);
+    }
 
-        let (kind, base, suffix_mode, custom_suffix) = Self::split_endpoint(&config.ai_config.api_endpoint);
+    /// 把完整端点拆分填入界面字段
+    fn set_endpoint(&mut self, endpoint: &str) {
+        let (kind, base, suffix_mode, custom_suffix) = Self::split_endpoint(endpoint);
         self.api_kind = kind;
         self.api_base_url = base;
         self.suffix_mode = suffix_mode;
         self.custom_suffix = custom_suffix;
     }
 
+    /// 自定义系统提示词（留空为 None）
+    pub fn system_prompt(&self) -> Option<String> {
+        let prompt = self.system_prompt.trim();
+        if prompt.is_empty() {
+            None
+        } else {
+            Some(prompt.to_string())
+        }
+    }
+
+    /// 根据界面输入得到置信度校准方式
+    ///
+    /// 配置文件中的分段映射在界面上不可编辑，gamma 保持 1.0 时原样保留。
+    pub fn calibration(&self, current: &ConfidenceCalibration) -> ConfidenceCalibration {
+        if (self.confidence_gamma - 1.0).abs() < f32::EPSILON {
+            if self.piecewise_calibration {
+                current.clone()
+            } else {
+                ConfidenceCalibration::Identity
+            }
+        } else {
+            ConfidenceCalibration::Gamma(self.confidence_gamma)
+        }
+    }
+
     pub fn effective_endpoint(&self) -> String {
         let base = self.api_base_url.trim().trim_end_matches('/');
         if base.is_empty() {
@@ -622,8 +1576,32 @@ impl SettingsDialog {
                 
                 ui.horizontal(|ui| {
                     ui.checkbox(&mut self.ai_enabled, "启用 AI 分类");
+                    ui.checkbox(&mut self.fuse_rule_matches_with_ai, "规则命中的文件也请模型校验")
+                        .on_hover_text("规则已匹配的文件也请模型给出路径并与规则融合，每个文件多一次请求");
                 });
 
+                if !self.recent_endpoints.is_empty() {
+                    let mut picked: Option<RecentEndpoint> = None;
+                    ui.horizontal(|ui| {
+                        ui.label("最近使用:");
+                        egui::ComboBox::from_id_salt("recent_endpoints")
+                            .selected_text("选择以填入...")
+                            .width(300.0)
+                            .show_ui(ui, |ui| {
+                                for recent in &self.recent_endpoints {
+                                    let label = format!("{}  ·  {}", recent.model_name, recent.endpoint);
+                                    if ui.selectable_label(false, label).clicked() {
+                                        picked = Some(recent.clone());
+                                    }
+                                }
+                            });
+                    });
+                    if let Some(recent) = picked {
+                        self.set_endpoint(&recent.endpoint);
+                        self.model_name = recent.model_name;
+                    }
+                }
+
                 ui.horizontal(|ui| {
                     ui.label("API 接口:");
                     egui::ComboBox::from_id_salt("api_kind")
@@ -684,11 +1662,72 @@ impl SettingsDialog {
                     ui.text_edit_singleline(&mut self.model_name);
                 });
 
+                ui.label("系统提示词（可选）:");
+                ui.add(
+                    egui::TextEdit::multiline(&mut self.system_prompt)
+                        .hint_text("例如：Use English tags. 我是摄影师，照片按拍摄项目归类。")
+                        .desired_rows(2)
+                        .desired_width(f32::INFINITY),
+                );
+
+                ui.horizontal(|ui| {
+                    ui.label("内容摘要读取上限:");
+                    ui.add(egui::DragValue::new(&mut self.content_summary_kb).range(1..=64).suffix(" KB"))
+                        .on_hover_text("发送给AI的文本文件开头内容，按字节截断");
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("失败重试次数:");
+                    ui.add(egui::DragValue::new(&mut self.ai_max_retries).range(0..=10))
+                        .on_hover_text("连接失败、限流（429）或服务端错误（5xx）时重试，等待时间逐次翻倍");
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("请求超时:");
+                    ui.add(egui::DragValue::new(&mut self.ai_request_timeout_secs).range(1..=600).suffix(" 秒"))
+                        .on_hover_text("超时后该文件改用离线分析");
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("对比模型:");
+                    ui.text_edit_singleline(&mut self.ai_comparison_model)
+                        .on_hover_text("命令面板「对比两个AI模型」用这个模型与当前模型比较建议");
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("对比端点:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.ai_comparison_endpoint)
+                            .hint_text("留空则使用当前端点"),
+                    )
+                    .on_hover_text("完整请求URL，如 https://api.openai.com/v1/chat/completions");
+                });
+
+                if !self.ai_comparison_endpoint.trim().is_empty() {
+                    ui.horizontal(|ui| {
+                        ui.label("对比端点密钥:");
+                        ui.add(egui::TextEdit::singleline(&mut self.ai_comparison_key).password(true));
+                    });
+                }
+
                 ui.horizontal(|ui| {
                     ui.label("置信度阈值:");
                     ui.add(egui::Slider::new(&mut self.confidence_threshold, 0.0..=1.0));
                 });
 
+                ui.horizontal(|ui| {
+                    ui.label("置信度校准(gamma):");
+                    ui.add(egui::Slider::new(&mut self.confidence_gamma, 0.25..=4.0).logarithmic(true))
+                        .on_hover_text("大于1压低AI置信度，小于1抬高，1为不校准");
+                });
+                if self.piecewise_calibration {
+                    ui.label(
+                        RichText::new("当前使用配置文件中的分段映射校准（gamma 为 1 时保持不变）")
+                            .small()
+                            .color(egui::Color32::GRAY),
+                    );
+                }
+
                 ui.separator();
                 ui.heading("默认路径");
 
@@ -712,6 +1751,91 @@ impl SettingsDialog {
                     }
                 });
 
+                ui.separator();
+                ui.heading("整理");
+
+                ui.checkbox(&mut self.write_folder_readme, "在新建的分类目录中写入说明文件 (_orderly.txt)");
+                ui.checkbox(
+                    &mut self.route_loose_executables,
+                    "把散落的可执行文件 (.exe/.msi/.scr) 集中到 Installers/",
+                );
+                ui.checkbox(
+                    &mut self.organize_by_entity,
+                    "发票/合同按识别出的机构归档到 Finance/{机构}/{年份}（需要AI）",
+                );
+                ui.checkbox(
+                    &mut self.cleanup_os_cruft,
+                    "扫描后提供清理系统缓存文件 (.DS_Store/Thumbs.db/desktop.ini)",
+                );
+                ui.checkbox(
+                    &mut self.verify_source_hash,
+                    "执行前校验源文件内容未被修改（生成计划时计算哈希，较慢）",
+                );
+                ui.checkbox(
+                    &mut self.strict_output_containment,
+                    "安全模式：拒绝把文件移到输出目录之外",
+                );
+                ui.checkbox(
+                    &mut self.move_cloud_placeholders,
+                    "也移动云端占位文件（OneDrive/Dropbox 仅在线文件，会触发下载）",
+                );
+
+                ui.horizontal(|ui| {
+                    ui.label("目标已存在时:");
+                    egui::ComboBox::from_id_salt("conflict_strategy")
+                        .selected_text(self.conflict_strategy.label())
+                        .show_ui(ui, |ui| {
+                            for strategy in ConflictStrategy::ALL {
+                                ui.selectable_value(&mut self.conflict_strategy, strategy, strategy.label());
+                            }
+                        });
+                });
+
+                ui.checkbox(
+                    &mut self.use_trash_on_conflict,
+                    "冲突时先把原有文件移到回收站（代替覆盖或报错）",
+                );
+                ui.add_enabled_ui(self.conflict_strategy == ConflictStrategy::DedupeByHash, |ui| {
+                    ui.checkbox(
+                        &mut self.trash_duplicate_sources,
+                        "内容相同而跳过时，把源文件移到回收站（仅移动模式）",
+                    );
+                });
+                ui.checkbox(
+                    &mut self.transactional_execution,
+                    "全部成功或全部撤销（任一操作失败时自动回滚整批）",
+                );
+
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.dedupe_identical, "内容相同的文件只移动一个，其余");
+                    ui.add_enabled_ui(self.dedupe_identical, |ui| {
+                        egui::ComboBox::from_id_salt("duplicate_handling")
+                            .selected_text(self.duplicate_handling.label())
+                            .show_ui(ui, |ui| {
+                                for handling in DuplicateHandling::ALL {
+                                    ui.selectable_value(&mut self.duplicate_handling, handling, handling.label());
+                                }
+                            });
+                    });
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("执行方式:");
+                    for mode in ExecutionMode::ALL {
+                        ui.radio_value(&mut self.execution_mode, mode, mode.label());
+                    }
+                    if self.execution_mode == ExecutionMode::Copy {
+                        ui.label(RichText::new("原文件保留在原处").small());
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("移动时重命名:");
+                    ui.checkbox(&mut self.naming_policy.lowercase, "小写");
+                    ui.checkbox(&mut self.naming_policy.replace_spaces, "空格改为 _");
+                    ui.checkbox(&mut self.naming_policy.prefix_year, "补年份前缀");
+                });
+
                 ui.separator();
 
                 ui.horizontal(|ui| {
@@ -737,3 +1861,68 @@ pub enum SettingsResult {
     Save,
     Cancel,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collapse_list() {
+        let warnings: Vec<String> = (0..500).map(|i| format!("目标已存在: {}", i)).collect();
+
+        let (shown, hidden) = collapse_list(&warnings, MAX_VISIBLE_WARNINGS);
+        assert_eq!(shown.len(), MAX_VISIBLE_WARNINGS);
+        assert_eq!(hidden, 450);
+        assert_eq!(shown[0], "目标已存在: 0");
+
+        let (shown, hidden) = collapse_list(&warnings[..3], MAX_VISIBLE_WARNINGS);
+        assert_eq!(shown.len(), 3);
+        assert_eq!(hidden, 0);
+    }
+
+    #[test]
+    fn test_settings_apply_to_config() {
+        use crate::core::models::AppConfig;
+
+        let mut dialog = SettingsDialog {
+            api_kind: ApiInterfaceKind::OpenAIChatCompletions,
+            api_base_url: "https://api.openai.com/".to_string(),
+            ai_key: "sk-test".to_string(),
+            model_name: "gpt-4o-mini".to_string(),
+            confidence_threshold: 0.85,
+            default_output_path: "/out".to_string(),
+            ..Default::default()
+        };
+        let mut config = AppConfig::default();
+        dialog.apply_to_config(&mut config);
+
+        assert_eq!(config.ai_config.api_endpoint, dialog.effective_endpoint());
+        assert_eq!(config.ai_config.api_endpoint, "https://api.openai.com/v1/chat/completions");
+        assert_eq!(config.ai_config.api_key, "sk-test");
+        assert_eq!(config.ai_config.model_name, "gpt-4o-mini");
+        assert_eq!(config.confidence_threshold, 0.85);
+        assert_eq!(config.default_output_base, Some(PathBuf::from("/out")));
+        assert_eq!(config.recent_endpoints[0].model_name, "gpt-4o-mini");
+
+        // 重新载入后界面显示保存的端点
+        dialog.load_from_config(&config);
+        assert_eq!(dialog.effective_endpoint(), config.ai_config.api_endpoint);
+        assert_eq!(dialog.api_kind, ApiInterfaceKind::OpenAIChatCompletions);
+    }
+
+    #[test]
+    fn test_blocking_errors_require_acknowledgement() {
+        let mut dialog = ExecuteConfirmDialog::default();
+        dialog.show(2, "1 KB".to_string(), 1, vec!["源文件不存在: /in/x".to_string()]);
+        assert!(dialog.can_execute(), "只有警告时可以直接执行");
+
+        dialog.blocking_errors = vec!["目标冲突: /in/a 和 /in/b 都要移动到 /out/a".to_string()];
+        assert!(!dialog.can_execute());
+        dialog.acknowledge_blocking = true;
+        assert!(dialog.can_execute());
+
+        // 重新打开时清空上次的问题和确认
+        dialog.show(1, "1 KB".to_string(), 1, Vec::new());
+        assert!(dialog.blocking_errors.is_empty() && !dialog.acknowledge_blocking);
+    }
+}
@@ -0,0 +1,167 @@
+//! 历史记录面板
+
+use crate::core::models::{HistoryEntry, OperationStatus};
+use eframe::egui::{self, RichText, Ui};
+
+/// 历史面板最多列出的批次数
+pub const HISTORY_PANEL_ENTRIES: usize = 30;
+
+/// 历史记录面板
+#[derive(Default)]
+pub struct HistoryPanel {
+    /// 当前选择的批次
+    selected_batch_id: Option<String>,
+}
+
+impl HistoryPanel {
+    /// 创建新的历史面板
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 渲染历史面板
+    ///
+    /// history 为最近的批次（新的在前）；执行中执行器被占用时传入空列表并设置 busy。
+    pub fn render(&mut self, ui: &mut Ui, history: &[&HistoryEntry], busy: bool) -> HistoryPanelAction {
+        let mut action = HistoryPanelAction::None;
+
+        ui.horizontal(|ui| {
+            ui.heading("历史记录");
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.add_enabled(!busy, egui::Button::new("📤 导出 CSV")).clicked() {
+                    action = HistoryPanelAction::ExportCsv;
+                }
+            });
+        });
+        ui.separator();
+
+        if busy {
+            ui.label("正在执行，完成后显示历史记录");
+            return action;
+        }
+        if history.is_empty() {
+            ui.label("暂无历史记录");
+            return action;
+        }
+
+        egui::ScrollArea::vertical().max_height(600.0).show(ui, |ui| {
+            for entry in history {
+                let batch_id = &entry.batch_id;
+                let selected = self.selected_batch_id.as_ref() == Some(batch_id);
+
+                ui.group(|ui| {
+                    ui.horizontal(|ui| {
+                        let short_id = batch_id.get(0..8).unwrap_or(batch_id);
+                        if ui.selectable_label(selected, format!("批次 {}", short_id)).clicked() {
+                            self.selected_batch_id = Some(batch_id.clone());
+                        }
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            ui.label(if entry.rolled_back { "已回滚" } else { "已执行" });
+                        });
+                    });
+
+                    ui.label(format!(
+                        "时间: {}",
+                        entry.executed_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S")
+                    ));
+                    ui.label(format!("{}: {}", entry.mode.label(), status_counts(entry)));
+
+                    egui::CollapsingHeader::new("操作明细")
+                        .id_salt(("history_ops", batch_id))
+                        .show(ui, |ui| {
+                            for op in &entry.operations {
+                                ui.label(
+                                    RichText::new(format!(
+                                        "[{}] {} → {}",
+                                        status_label(op.status),
+                                        op.from.display(),
+                                        op.to.display()
+                                    ))
+                                    .small(),
+                                );
+                                if let Some(ref error) = op.error {
+                                    ui.label(RichText::new(format!("    {}", error)).small().weak());
+                                }
+                            }
+                        });
+
+                    if !entry.rolled_back && ui.button("↩️ 回滚").clicked() {
+                        action = HistoryPanelAction::Rollback(batch_id.clone());
+                    }
+                });
+                ui.add_space(6.0);
+            }
+        });
+
+        action
+    }
+}
+
+/// 历史面板操作
+#[derive(Debug, PartialEq)]
+pub enum HistoryPanelAction {
+    None,
+    /// 回滚指定批次
+    Rollback(String),
+    /// 导出历史为 CSV
+    ExportCsv,
+}
+
+/// 操作状态的显示名称
+fn status_label(status: OperationStatus) -> &'static str {
+    match status {
+        OperationStatus::Pending => "待执行",
+        OperationStatus::InProgress => "执行中",
+        OperationStatus::Completed => "已完成",
+        OperationStatus::Failed => "失败",
+        OperationStatus::RolledBack => "已回滚",
+        OperationStatus::Skipped => "已跳过",
+    }
+}
+
+/// 批次中各状态的操作数，如 "3 个操作（已完成 2，失败 1）"
+fn status_counts(entry: &HistoryEntry) -> String {
+    let mut counts: Vec<(&'static str, usize)> = Vec::new();
+    for op in &entry.operations {
+        let label = status_label(op.status);
+        match counts.iter_mut().find(|(l, _)| *l == label) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((label, 1)),
+        }
+    }
+    let detail: Vec<String> = counts.iter().map(|(l, n)| format!("{} {}", l, n)).collect();
+    format!("{} 个操作（{}）", entry.operations.len(), detail.join("，"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::models::{ExecutionMode, MoveOperation};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_status_counts_in_first_seen_order() {
+        let op = |status| MoveOperation {
+            from: PathBuf::from("/in/a.txt"),
+            to: PathBuf::from("/out/a.txt"),
+            file_id: "a".to_string(),
+            status,
+            error: None,
+            source_hash: None,
+            trashed_target: false,
+        };
+        let entry = HistoryEntry {
+            batch_id: "batch".to_string(),
+            executed_at: chrono::Utc::now(),
+            operations: vec![
+                op(OperationStatus::Completed),
+                op(OperationStatus::Failed),
+                op(OperationStatus::Completed),
+            ],
+            rolled_back: false,
+            mode: ExecutionMode::Move,
+        };
+
+        assert_eq!(status_counts(&entry), "3 个操作（已完成 2，失败 1）");
+    }
+}
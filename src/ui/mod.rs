@@ -1,7 +1,10 @@
 //! UI模块 - 包含所有图形界面相关代码
 
 pub mod app;
+pub mod command_palette;
 pub mod preview_table;
 pub mod rule_panel;
+pub mod history_panel;
 pub mod dialogs;
 pub mod styles;
+pub mod thumbnail;
@@ -5,12 +5,32 @@
 //! - 显示当前路径和建议路径
 //! - 置信度颜色
 //! - 原子目录高亮
+//! - 键盘导航（↑/↓ 移动焦点，Shift+↑/↓ 连续选择，空格切换勾选）
+//! - 列宽可拖动调整，并保存到配置
+//! - 按来源目录分组，查看每个目录的文件被分散到哪些目标
+//! - 为文件添加备注（🗒 弹出编辑框）
+//! - 图片缩略图列（只为可见行生成，LRU 缓存）
 
-use crate::core::models::{FileDescriptor, SuggestionSource};
+use crate::core::models::{
+    sanitize_relative_path, ColumnWidths, FileDescriptor, MoveSuggestion, RuleOrigin, SuggestionSource,
+};
+use crate::core::naming::NamingPolicy;
 use crate::ui::styles::Theme;
+use crate::ui::thumbnail::{supports_thumbnail, ThumbnailCache, THUMBNAIL_SIZE};
 use eframe::egui::{self, RichText, Ui};
+use egui_extras::{Column, TableBuilder, TableRow};
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 
+/// 表格行高
+const ROW_HEIGHT: f32 = 24.0;
+
+/// 显示缩略图时的行高
+const THUMBNAIL_ROW_HEIGHT: f32 = THUMBNAIL_SIZE as f32 + 4.0;
+
+/// 一个来源目录的文件被分散到这么多个目标目录及以上时提示
+const SCATTER_WARN_DESTINATIONS: usize = 3;
+
 /// 预览表格
 pub struct PreviewTable {
     /// 主题
@@ -25,6 +45,67 @@ pub struct PreviewTable {
     show_only_with_suggestion: bool,
     /// 是否隐藏原子目录内的文件
     hide_atomic_children: bool,
+    /// 键盘焦点所在行的文件ID
+    focused_id: Option<String>,
+    /// Shift 连续选择的起点文件ID
+    anchor_id: Option<String>,
+    /// 焦点刚移动，需要滚动到可见位置
+    scroll_to_focus: bool,
+    /// 列宽（可拖动调整，保存到配置）
+    column_widths: ColumnWidths,
+    /// 列宽被调整但尚未保存
+    widths_dirty: bool,
+    /// 文件名规范化策略（建议路径列显示改名后的文件名）
+    naming_policy: NamingPolicy,
+    /// 用户请求诊断“为何无建议”的文件ID
+    diagnose_request: Option<String>,
+    /// 是否按来源目录分组显示
+    group_by_source: bool,
+    /// 是否按目标目录分组显示
+    group_by_target: bool,
+    /// 本帧被编辑过的备注（文件ID -> 新备注），等待保存
+    note_edits: HashMap<String, Option<String>>,
+    /// 正在编辑建议路径的文件ID与输入内容
+    editing_target: Option<(String, String)>,
+    /// 手动输入的目标被拒绝的原因，显示在输入框旁
+    editing_target_error: Option<&'static str>,
+    /// 右键菜单中请求的文件操作，等待调用方处理
+    file_action: Option<(FileAction, PathBuf)>,
+    /// 是否显示图片缩略图列
+    show_thumbnails: bool,
+    /// 缩略图缓存
+    thumbnails: ThumbnailCache,
+}
+
+/// 预览表格右键菜单中的文件操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileAction {
+    /// 在文件管理器中显示
+    Reveal,
+    /// 用默认程序打开
+    Open,
+}
+
+/// 双击打开即会运行的文件类型，不提供“打开文件”
+const LAUNCHABLE_EXTENSIONS: &[&str] = &[
+    ".exe", ".msi", ".bat", ".cmd", ".com", ".scr", ".ps1", ".vbs", ".js", ".jar", ".sh", ".app", ".lnk",
+];
+
+/// 文件能否从预览中用默认程序打开，不能时给出原因
+///
+/// 程序目录内的文件、可执行文件和隐藏文件只能在文件管理器中查看；目录请用“在文件管理器中显示”。
+pub fn open_blocked_reason(file: &FileDescriptor) -> Option<&'static str> {
+    if file.is_directory {
+        Some("目录请在文件管理器中查看")
+    } else if file.atomic {
+        Some("程序目录内的文件不在此打开")
+    } else if LAUNCHABLE_EXTENSIONS.contains(&file.extension.to_lowercase().as_str()) {
+        Some("可执行文件不在此打开")
+    } else if file.name.starts_with('.') {
+        Some("隐藏文件不在此打开")
+    } else {
+        None
+    }
 }
 
 /// 排序列
@@ -37,6 +118,45 @@ pub enum SortColumn {
     Source,
 }
 
+/// 来源列的显示文字：规则建议再按命中规则的来源区分内置规则和用户规则
+pub fn source_label(suggestion: &MoveSuggestion) -> &'static str {
+    match (suggestion.source, suggestion.rule_origin) {
+        (SuggestionSource::AI, _) => "🤖 AI",
+        (SuggestionSource::Rule, Some(RuleOrigin::BuiltIn)) => "📋 内置规则",
+        (SuggestionSource::Rule, Some(RuleOrigin::UserConfirmed)) => "📋 我的规则",
+        (SuggestionSource::Rule, None) => "📋 规则",
+        (SuggestionSource::Memory, _) => "💾 记忆",
+        (SuggestionSource::Manual, _) => "✏️ 手动",
+        (SuggestionSource::Offline, _) => "🧭 离线推断",
+    }
+}
+
+/// 手动输入的目标会离开输出目录时返回拒绝原因：绝对路径、带盘符的路径或含 ".." 段
+pub fn manual_target_error(input: &str) -> Option<&'static str> {
+    let input = input.trim();
+    let escapes = input.starts_with(['/', '\\'])
+        || input.get(1..2) == Some(":")
+        || input.split(['/', '\\']).any(|segment| segment.trim() == "..");
+    escapes.then_some("请输入输出目录下的相对路径（不能是绝对路径或包含 ..）")
+}
+
+/// 把手动输入的目标写入文件的建议并勾选；输入为空、会离开输出目录或文件不能单独移动时不修改
+///
+/// 输入可以是目录，也可以带文件名，生成计划时会统一取目录；各段按规则目标的方式清理。
+pub fn apply_manual_target(file: &mut FileDescriptor, input: &str) -> bool {
+    let input = input.trim();
+    if input.is_empty() || manual_target_error(input).is_some() || !PreviewTable::is_selectable(file) {
+        return false;
+    }
+    let target = sanitize_relative_path(input);
+    if target.as_os_str().is_empty() {
+        return false;
+    }
+    file.suggested_action = Some(MoveSuggestion::manual(target));
+    file.selected = true;
+    true
+}
+
 fn effective_target_path(file: &FileDescriptor, suggested: &Path) -> PathBuf {
     // 与执行层保持一致：只做“分类移动”，最终目标必须使用原文件名。
     // 如果 suggested 看起来已经包含文件名（等于原名 / 以扩展名结尾），则取其 parent 作为目录。
@@ -68,8 +188,125 @@ impl Default for PreviewTable {
             filter_text: String::new(),
             show_only_with_suggestion: false,
             hide_atomic_children: true,
+            focused_id: None,
+            anchor_id: None,
+            scroll_to_focus: false,
+            column_widths: ColumnWidths::default(),
+            widths_dirty: false,
+            naming_policy: NamingPolicy::default(),
+            diagnose_request: None,
+            group_by_source: false,
+            group_by_target: false,
+            note_edits: HashMap::new(),
+            editing_target: None,
+            editing_target_error: None,
+            file_action: None,
+            show_thumbnails: false,
+            thumbnails: ThumbnailCache::default(),
+        }
+    }
+}
+
+/// 按来源目录分组的一组文件
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceGroup {
+    /// 来源目录
+    pub source: PathBuf,
+    /// 组内文件数
+    pub file_count: usize,
+    /// 目标目录及去往该目录的文件数（按数量从多到少）
+    pub destinations: Vec<(PathBuf, usize)>,
+    /// 没有建议的文件数
+    pub unassigned: usize,
+}
+
+impl SourceGroup {
+    /// 文件是否被分散到较多目标目录
+    pub fn is_scattered(&self) -> bool {
+        self.destinations.len() >= SCATTER_WARN_DESTINATIONS
+    }
+}
+
+/// 全选（原子目录内的文件不参与，只能随目录整体移动）
+pub fn select_all(files: &mut [FileDescriptor]) {
+    for file in files.iter_mut() {
+        if !file.atomic || file.is_directory {
+            file.selected = true;
+        }
+    }
+}
+
+/// 全不选
+pub fn deselect_all(files: &mut [FileDescriptor]) {
+    for file in files.iter_mut() {
+        file.selected = false;
+    }
+}
+
+/// 把文件按所在目录分组，统计每组文件去往的目标目录（按来源目录排序）
+pub fn group_by_source<'a>(files: impl IntoIterator<Item = &'a FileDescriptor>) -> Vec<SourceGroup> {
+    let mut groups: BTreeMap<PathBuf, (usize, BTreeMap<PathBuf, usize>, usize)> = BTreeMap::new();
+    for file in files {
+        let (count, destinations, unassigned) = groups.entry(file.parent_dir.clone()).or_default();
+        *count += 1;
+        match &file.suggested_action {
+            Some(suggestion) => {
+                let target = effective_target_path(file, &suggestion.target_path);
+                let dir = target.parent().map(Path::to_path_buf).unwrap_or_default();
+                *destinations.entry(dir).or_default() += 1;
+            }
+            None => *unassigned += 1,
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(source, (file_count, destinations, unassigned))| {
+            let mut destinations: Vec<(PathBuf, usize)> = destinations.into_iter().collect();
+            destinations.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            SourceGroup {
+                source,
+                file_count,
+                destinations,
+                unassigned,
+            }
+        })
+        .collect()
+}
+
+/// 按目标目录分组的一组文件
+#[derive(Debug, Clone, PartialEq)]
+pub struct TargetGroup {
+    /// 目标目录（None 表示没有建议的“未分类”组）
+    pub target: Option<PathBuf>,
+    /// 组内文件在文件列表中的下标（保持列表原有顺序）
+    pub indices: Vec<usize>,
+}
+
+/// 把指定下标的文件按最终目标目录分组（按目标目录排序，“未分类”在最后）
+pub fn group_by_target(files: &[FileDescriptor], indices: &[usize]) -> Vec<TargetGroup> {
+    let mut groups: BTreeMap<PathBuf, Vec<usize>> = BTreeMap::new();
+    let mut unassigned = Vec::new();
+    for &i in indices {
+        let file = &files[i];
+        match &file.suggested_action {
+            Some(suggestion) => {
+                let target = effective_target_path(file, &suggestion.target_path);
+                let dir = target.parent().map(Path::to_path_buf).unwrap_or_default();
+                groups.entry(dir).or_default().push(i);
+            }
+            None => unassigned.push(i),
         }
     }
+
+    let mut result: Vec<TargetGroup> = groups
+        .into_iter()
+        .map(|(target, indices)| TargetGroup { target: Some(target), indices })
+        .collect();
+    if !unassigned.is_empty() {
+        result.push(TargetGroup { target: None, indices: unassigned });
+    }
+    result
 }
 
 impl PreviewTable {
@@ -94,21 +331,34 @@ impl PreviewTable {
             // 过滤选项
             ui.checkbox(&mut self.show_only_with_suggestion, "只显示有建议的");
             ui.checkbox(&mut self.hide_atomic_children, "隐藏程序目录内文件");
+            // 两种分组视图互斥
+            if ui
+                .checkbox(&mut self.group_by_source, "按来源目录分组")
+                .on_hover_text("查看每个目录的文件去往哪些目标，发现被拆散的目录")
+                .changed()
+                && self.group_by_source
+            {
+                self.group_by_target = false;
+            }
+            if ui
+                .checkbox(&mut self.group_by_target, "按目标目录分组")
+                .on_hover_text("按整理后所在的目录逐组核对，可整组勾选")
+                .changed()
+                && self.group_by_target
+            {
+                self.group_by_source = false;
+            }
+            ui.checkbox(&mut self.show_thumbnails, "显示缩略图")
+                .on_hover_text("为图片文件显示小图，便于整理照片");
 
             ui.separator();
 
             // 批量操作
-            if ui.button("✓ 全选").clicked() {
-                for file in files.iter_mut() {
-                    if !file.atomic || file.is_directory {
-                        file.selected = true;
-                    }
-                }
+            if ui.button("✓ 全选").on_hover_text("Ctrl+A").clicked() {
+                select_all(files);
             }
-            if ui.button("✗ 全不选").clicked() {
-                for file in files.iter_mut() {
-                    file.selected = false;
-                }
+            if ui.button("✗ 全不选").on_hover_text("Ctrl+Shift+A").clicked() {
+                deselect_all(files);
             }
             if ui.button("↔ 反选").clicked() {
                 for file in files.iter_mut() {
@@ -120,85 +370,329 @@ impl PreviewTable {
         });
     }
 
-    /// 渲染表格
-    pub fn render(&mut self, ui: &mut Ui, files: &mut [FileDescriptor]) {
-        // 表头
-        ui.horizontal(|ui| {
-            ui.set_min_height(30.0);
-            
-            // 选择列
-            ui.allocate_ui_with_layout(
-                egui::vec2(30.0, 20.0),
-                egui::Layout::centered_and_justified(egui::Direction::LeftToRight),
-                |ui| { ui.label(""); }
-            );
+    /// 设置列宽（通常来自配置）
+    pub fn set_column_widths(&mut self, widths: ColumnWidths) {
+        self.column_widths = widths;
+    }
 
-            // 文件名列
-            if ui.selectable_label(
-                self.sort_column == SortColumn::Name,
-                format!("文件名 {}", self.sort_indicator(SortColumn::Name))
-            ).clicked() {
-                self.toggle_sort(SortColumn::Name);
-            }
+    /// 设置文件名规范化策略
+    pub fn set_naming_policy(&mut self, policy: NamingPolicy) {
+        self.naming_policy = policy;
+    }
 
-            ui.separator();
+    /// 取出用户请求诊断的文件ID（点击“无建议”）
+    pub fn take_diagnose_request(&mut self) -> Option<String> {
+        self.diagnose_request.take()
+    }
 
-            // 当前路径列
-            if ui.selectable_label(
-                self.sort_column == SortColumn::Path,
-                format!("当前路径 {}", self.sort_indicator(SortColumn::Path))
-            ).clicked() {
-                self.toggle_sort(SortColumn::Path);
-            }
+    /// 取出右键菜单中请求的文件操作（只返回一次）
+    pub fn take_file_action(&mut self) -> Option<(FileAction, PathBuf)> {
+        self.file_action.take()
+    }
 
-            ui.separator();
+    /// 取出被编辑过的备注，由调用方保存
+    pub fn take_note_edits(&mut self) -> HashMap<String, Option<String>> {
+        std::mem::take(&mut self.note_edits)
+    }
 
-            // 建议路径列
-            if ui.selectable_label(
-                self.sort_column == SortColumn::Target,
-                format!("建议路径 {}", self.sort_indicator(SortColumn::Target))
-            ).clicked() {
-                self.toggle_sort(SortColumn::Target);
-            }
+    /// 用户拖动调整过列宽且已松开鼠标时，返回新的列宽（只返回一次）
+    pub fn take_changed_widths(&mut self, ctx: &egui::Context) -> Option<ColumnWidths> {
+        if self.widths_dirty && !ctx.input(|i| i.pointer.any_down()) {
+            self.widths_dirty = false;
+            Some(self.column_widths)
+        } else {
+            None
+        }
+    }
 
-            ui.separator();
+    /// 渲染表格
+    pub fn render(&mut self, ui: &mut Ui, files: &mut [FileDescriptor]) {
+        if self.group_by_source {
+            self.render_source_groups(ui, files);
+            return;
+        }
+        if self.group_by_target {
+            self.render_target_groups(ui, files);
+            return;
+        }
 
-            // 置信度列
-            if ui.selectable_label(
-                self.sort_column == SortColumn::Confidence,
-                format!("置信度 {}", self.sort_indicator(SortColumn::Confidence))
-            ).clicked() {
-                self.toggle_sort(SortColumn::Confidence);
-            }
+        self.handle_keyboard(ui, files);
 
-            ui.separator();
+        let visible = self.visible_indices(files);
+        let focus_row = if self.scroll_to_focus {
+            self.scroll_to_focus = false;
+            self.focused_id
+                .as_ref()
+                .and_then(|id| visible.iter().position(|&i| &files[i].id == id))
+        } else {
+            None
+        };
 
-            // 来源列
-            if ui.selectable_label(
-                self.sort_column == SortColumn::Source,
-                format!("来源 {}", self.sort_indicator(SortColumn::Source))
-            ).clicked() {
-                self.toggle_sort(SortColumn::Source);
-            }
-        });
+        let widths = self.column_widths.to_array();
+        if self.show_thumbnails {
+            self.thumbnails.begin_frame(ui.ctx());
+        }
+        let mut builder = TableBuilder::new(ui)
+            .id_salt("preview_table")
+            .striped(false)
+            .resizable(true)
+            .auto_shrink([false; 2])
+            .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+            .sense(egui::Sense::click())
+            .column(Column::exact(24.0));
+        // 缩略图列固定宽度，不参与列宽保存
+        let fixed_columns = if self.show_thumbnails {
+            builder = builder.column(Column::exact(THUMBNAIL_ROW_HEIGHT));
+            2
+        } else {
+            1
+        };
+        let row_height = if self.show_thumbnails { THUMBNAIL_ROW_HEIGHT } else { ROW_HEIGHT };
+        for width in widths {
+            builder = builder.column(Column::initial(width).at_least(40.0).clip(true));
+        }
+        if let Some(row) = focus_row {
+            builder = builder.scroll_to_row(row, None);
+        }
 
-        ui.separator();
+        builder
+            .header(26.0, |mut header| {
+                header.col(|_| {});
+                if self.show_thumbnails {
+                    header.col(|_| {});
+                }
+                for (column, title) in [
+                    (SortColumn::Name, "文件名"),
+                    (SortColumn::Path, "当前路径"),
+                    (SortColumn::Target, "建议路径"),
+                    (SortColumn::Confidence, "置信度"),
+                    (SortColumn::Source, "来源"),
+                ] {
+                    header.col(|ui| {
+                        if ui
+                            .selectable_label(
+                                self.sort_column == column,
+                                format!("{} {}", title, self.sort_indicator(column)),
+                            )
+                            .clicked()
+                        {
+                            self.toggle_sort(column);
+                        }
+                    });
+                }
+            })
+            .body(|body| {
+                // 前面是固定宽度的勾选框（和缩略图）列，其后才是可调整的列
+                let measured = body.widths();
+                if measured.len() == widths.len() + fixed_columns {
+                    let mut current = [0.0; 5];
+                    current.copy_from_slice(&measured[fixed_columns..]);
+                    if current.iter().zip(widths.iter()).any(|(a, b)| (a - b).abs() > 0.5) {
+                        self.column_widths = ColumnWidths::from_array(current);
+                        self.widths_dirty = true;
+                    }
+                }
+
+                // 表格只为可见行调用，缩略图因此也只为可见行生成
+                body.rows(row_height, visible.len(), |row| {
+                    let file = &mut files[visible[row.index()]];
+                    let focused = self.focused_id.as_deref() == Some(file.id.as_str());
+                    self.render_row(row, file, focused);
+                });
+            });
+    }
+
+    /// 渲染按来源目录分组的视图：每组列出目标目录及文件数
+    fn render_source_groups(&self, ui: &mut Ui, files: &[FileDescriptor]) {
+        let groups = group_by_source(files.iter().filter(|f| self.should_show_file(f)));
 
-        // 表格内容
         egui::ScrollArea::vertical()
+            .id_salt("preview_source_groups")
             .auto_shrink([false; 2])
             .show(ui, |ui| {
-                for file in files.iter_mut() {
-                    // 过滤
-                    if !self.should_show_file(file) {
-                        continue;
+                for group in &groups {
+                    let mut title = format!(
+                        "📁 {}  ({} 个文件 → {} 个目标)",
+                        group.source.display(),
+                        group.file_count,
+                        group.destinations.len()
+                    );
+                    if group.is_scattered() {
+                        title = format!("⚠ {}", title);
                     }
+                    let header = if group.is_scattered() {
+                        RichText::new(title).color(self.theme.warning)
+                    } else {
+                        RichText::new(title)
+                    };
 
-                    self.render_row(ui, file);
+                    egui::CollapsingHeader::new(header)
+                        .id_salt(&group.source)
+                        .show(ui, |ui| {
+                            for (destination, count) in &group.destinations {
+                                ui.label(format!("→ {}  ({})", destination.display(), count));
+                            }
+                            if group.unassigned > 0 {
+                                ui.label(
+                                    RichText::new(format!("无建议  ({})", group.unassigned))
+                                        .color(self.theme.secondary),
+                                );
+                            }
+                        });
                 }
             });
     }
 
+    /// 渲染按目标目录分组的视图：可折叠的组标题带文件数和整组勾选框
+    fn render_target_groups(&mut self, ui: &mut Ui, files: &mut [FileDescriptor]) {
+        let visible = self.visible_indices(files);
+        let groups = group_by_target(files, &visible);
+
+        egui::ScrollArea::vertical()
+            .id_salt("preview_target_groups")
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                for group in &groups {
+                    let selectable: Vec<usize> =
+                        group.indices.iter().copied().filter(|&i| Self::is_selectable(&files[i])).collect();
+                    let checked = selectable.iter().filter(|&&i| files[i].selected).count();
+                    let title = match &group.target {
+                        Some(target) => format!("📁 {}  ({})", target.display(), group.indices.len()),
+                        None => format!("❓ 未分类  ({})", group.indices.len()),
+                    };
+
+                    let id = ui.make_persistent_id(("target_group", &group.target));
+                    egui::collapsing_header::CollapsingState::load_with_default_open(ui.ctx(), id, false)
+                        .show_header(ui, |ui| {
+                            let mut all = !selectable.is_empty() && checked == selectable.len();
+                            let checkbox = egui::Checkbox::new(&mut all, title)
+                                .indeterminate(checked > 0 && checked < selectable.len());
+                            if ui.add_enabled(!selectable.is_empty(), checkbox).changed() {
+                                for &i in &selectable {
+                                    files[i].selected = all;
+                                }
+                            }
+                        })
+                        .body(|ui| {
+                            for &i in &group.indices {
+                                let file = &mut files[i];
+                                ui.horizontal(|ui| {
+                                    ui.add_enabled(
+                                        Self::is_selectable(file),
+                                        egui::Checkbox::without_text(&mut file.selected),
+                                    );
+                                    ui.label(&file.name);
+                                    ui.label(
+                                        RichText::new(format!("← {}", file.parent_dir.display()))
+                                            .small()
+                                            .color(self.theme.secondary),
+                                    );
+                                    if let Some(suggestion) = &file.suggested_action {
+                                        ui.label(RichText::new(source_label(suggestion)).small());
+                                    }
+                                });
+                            }
+                        });
+                }
+            });
+    }
+
+    /// 处理键盘导航（文本框获得焦点时不处理）
+    fn handle_keyboard(&mut self, ui: &Ui, files: &mut [FileDescriptor]) {
+        if ui.ctx().memory(|m| m.focused().is_some()) {
+            return;
+        }
+
+        let (up, down, space, shift) = ui.input(|i| {
+            (
+                i.key_pressed(egui::Key::ArrowUp),
+                i.key_pressed(egui::Key::ArrowDown),
+                i.key_pressed(egui::Key::Space),
+                i.modifiers.shift,
+            )
+        });
+
+        if down {
+            self.move_focus(files, 1, shift);
+        }
+        if up {
+            self.move_focus(files, -1, shift);
+        }
+        if space {
+            self.toggle_focused(files);
+        }
+    }
+
+    /// 当前过滤条件下可见行在 files 中的下标
+    fn visible_indices(&self, files: &[FileDescriptor]) -> Vec<usize> {
+        files
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| self.should_show_file(f))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// 在可见行中移动焦点；extend 为 true 时把起点到新焦点之间的行全部勾选
+    pub fn move_focus(&mut self, files: &mut [FileDescriptor], delta: isize, extend: bool) {
+        let visible = self.visible_indices(files);
+        if visible.is_empty() {
+            return;
+        }
+
+        let current = self
+            .focused_id
+            .as_ref()
+            .and_then(|id| visible.iter().position(|&i| &files[i].id == id));
+        let next = match current {
+            Some(pos) => (pos as isize + delta).clamp(0, visible.len() as isize - 1) as usize,
+            // 尚无焦点时，向下从第一行开始，向上从最后一行开始
+            None if delta >= 0 => 0,
+            None => visible.len() - 1,
+        };
+
+        if extend {
+            let anchor = self
+                .anchor_id
+                .as_ref()
+                .and_then(|id| visible.iter().position(|&i| &files[i].id == id))
+                .or(current)
+                .unwrap_or(next);
+            self.anchor_id = Some(files[visible[anchor]].id.clone());
+
+            let (start, end) = if anchor <= next { (anchor, next) } else { (next, anchor) };
+            for &i in &visible[start..=end] {
+                if Self::is_selectable(&files[i]) {
+                    files[i].selected = true;
+                }
+            }
+        } else {
+            self.anchor_id = None;
+        }
+
+        self.focused_id = Some(files[visible[next]].id.clone());
+        self.scroll_to_focus = true;
+    }
+
+    /// 切换焦点行的勾选状态
+    pub fn toggle_focused(&mut self, files: &mut [FileDescriptor]) {
+        let id = match self.focused_id.as_ref() {
+            Some(id) => id,
+            None => return,
+        };
+        if let Some(file) = files.iter_mut().find(|f| &f.id == id) {
+            if Self::is_selectable(file) {
+                file.selected = !file.selected;
+            }
+        }
+        self.anchor_id = self.focused_id.clone();
+    }
+
+    /// 是否允许单独勾选（原子目录内的文件不行）
+    fn is_selectable(file: &FileDescriptor) -> bool {
+        !file.atomic || file.is_directory
+    }
+
     /// 判断是否应该显示此文件
     fn should_show_file(&self, file: &FileDescriptor) -> bool {
         // 搜索过滤
@@ -225,87 +719,199 @@ impl PreviewTable {
     }
 
     /// 渲染单行
-    fn render_row(&mut self, ui: &mut Ui, file: &mut FileDescriptor) {
+    fn render_row(&mut self, mut row: TableRow<'_, '_>, file: &mut FileDescriptor, focused: bool) {
         let is_atomic = file.atomic;
         let is_directory = file.is_directory;
 
-        // 行背景色
-        let bg_color = if is_atomic {
+        // 行背景色（焦点行优先）
+        let bg_color = if focused {
+            self.theme.primary.gamma_multiply(0.3)
+        } else if is_atomic {
             self.theme.atomic_highlight.gamma_multiply(0.2)
         } else if file.selected {
             self.theme.selected_bg
         } else {
             self.theme.unselected_bg
         };
+        let fill = |ui: &mut Ui| {
+            ui.painter().rect_filled(ui.max_rect().expand(2.0), 0.0, bg_color);
+        };
 
-        egui::Frame::none()
-            .fill(bg_color)
-            .inner_margin(egui::Margin::symmetric(4.0, 2.0))
-            .show(ui, |ui| {
-                ui.horizontal(|ui| {
-                    // 选择框
-                    let checkbox_enabled = !is_atomic || is_directory;
-                    ui.add_enabled(
-                        checkbox_enabled,
-                        egui::Checkbox::without_text(&mut file.selected)
-                    );
-
-                    // 文件图标和名称
-                    let icon = if is_directory {
-                        if is_atomic { "🔒" } else { "📁" }
-                    } else {
-                        self.get_file_icon(&file.extension)
-                    };
+        // 选择框
+        row.col(|ui| {
+            fill(ui);
+            ui.add_enabled(
+                Self::is_selectable(file),
+                egui::Checkbox::without_text(&mut file.selected),
+            );
+        });
 
-                    ui.label(format!("{} {}", icon, file.name));
-
-                    ui.separator();
-
-                    // 当前路径（截断显示）
-                    let current_path = file.parent_dir.to_string_lossy();
-                    let truncated_path = Self::truncate_path(&current_path, 40);
-                    ui.label(&truncated_path).on_hover_text(&*current_path);
-
-                    ui.separator();
-
-                    // 建议路径
-                    if let Some(ref suggestion) = file.suggested_action {
-                        let target_path = effective_target_path(file, &suggestion.target_path);
-                        let target = target_path.to_string_lossy();
-                        let truncated_target = Self::truncate_path(&target, 40);
-                        ui.label(&truncated_target).on_hover_text(&*target);
-
-                        ui.separator();
-
-                        // 置信度
-                        let confidence_color = self.theme.confidence_color(suggestion.confidence);
-                        ui.label(
-                            RichText::new(format!("{:.0}%", suggestion.confidence * 100.0))
-                                .color(confidence_color)
-                        );
-
-                        ui.separator();
-
-                        // 来源
-                        let source_text = match suggestion.source {
-                            SuggestionSource::AI => "🤖 AI",
-                            SuggestionSource::Rule => "📋 规则",
-                            SuggestionSource::Memory => "💾 记忆",
-                        };
-                        ui.label(source_text);
-                    } else if is_atomic {
-                        ui.label(
-                            RichText::new("🔒 原子目录")
-                                .color(self.theme.atomic_highlight)
-                        );
-                    } else {
-                        ui.label(
-                            RichText::new("无建议")
-                                .color(self.theme.secondary)
-                        );
+        // 缩略图（非图片行留空，名称列仍显示图标）
+        if self.show_thumbnails {
+            row.col(|ui| {
+                fill(ui);
+                if !is_directory && supports_thumbnail(&file.extension) {
+                    if let Some(texture) = self.thumbnails.get_or_load(ui.ctx(), file) {
+                        ui.add(egui::Image::new(&texture).max_size(egui::vec2(
+                            THUMBNAIL_SIZE as f32,
+                            THUMBNAIL_SIZE as f32,
+                        )));
                     }
-                });
+                }
             });
+        }
+
+        // 文件图标和名称
+        row.col(|ui| {
+            fill(ui);
+            let icon = if is_directory {
+                if is_atomic { "🔒" } else { "📁" }
+            } else {
+                self.get_file_icon(&file.extension)
+            };
+            self.render_note_button(ui, file);
+            ui.label(format!("{} {}", icon, file.name)).on_hover_text(&file.name);
+        });
+
+        // 当前路径
+        row.col(|ui| {
+            fill(ui);
+            let current_path = file.parent_dir.to_string_lossy();
+            ui.label(&*current_path).on_hover_text(&*current_path);
+        });
+
+        let suggestion = file.suggested_action.as_ref();
+
+        // 建议路径（点击后可手动修改）
+        let mut start_editing = None;
+        let mut manual_target = None;
+        row.col(|ui| {
+            fill(ui);
+            if let Some((_, text)) = self.editing_target.as_mut().filter(|(id, _)| *id == file.id) {
+                let response = ui.add(egui::TextEdit::singleline(text).hint_text("目标目录"));
+                if !response.has_focus() && !response.lost_focus() {
+                    response.request_focus();
+                }
+                if let Some(error) = self.editing_target_error {
+                    response.show_tooltip_text(RichText::new(error).color(self.theme.error));
+                }
+                if response.lost_focus() {
+                    // Esc 放弃修改，回车或点到别处时保存
+                    let cancelled = ui.input(|i| i.key_pressed(egui::Key::Escape));
+                    manual_target = Some((!cancelled).then(|| text.clone()));
+                }
+            } else if let Some(suggestion) = suggestion {
+                let target_path = effective_target_path(file, &suggestion.target_path);
+                let new_name = self.naming_policy.apply(file);
+                let (text, hover) = if new_name != file.name {
+                    let target = target_path.with_file_name(&new_name).to_string_lossy().to_string();
+                    (RichText::new(&target).color(self.theme.primary), format!("{}\n原名: {}", target, file.name))
+                } else {
+                    let target = target_path.to_string_lossy().to_string();
+                    (RichText::new(&target), target)
+                };
+                let label = egui::Label::new(text).sense(egui::Sense::click());
+                if ui.add(label).on_hover_text(format!("{}\n点击修改目标", hover)).clicked() && !is_atomic {
+                    start_editing = Some(suggestion.target_path.to_string_lossy().to_string());
+                }
+            } else if is_atomic {
+                ui.label(RichText::new("🔒 原子目录").color(self.theme.atomic_highlight));
+            } else {
+                let label = egui::Label::new(RichText::new("无建议 ❓").color(self.theme.secondary))
+                    .sense(egui::Sense::click());
+                if ui.add(label).on_hover_text("点击查看为何没有建议，右键手动指定目标").clicked() {
+                    self.diagnose_request = Some(file.id.clone());
+                }
+            }
+        });
+        if let Some(text) = start_editing {
+            self.editing_target = Some((file.id.clone(), text));
+        }
+        if let Some(input) = manual_target {
+            self.editing_target = None;
+            self.editing_target_error = None;
+            if let Some(input) = input {
+                // 会离开输出目录的输入不保存，保留输入框并提示原因
+                if let Some(error) = manual_target_error(&input) {
+                    self.editing_target = Some((file.id.clone(), input));
+                    self.editing_target_error = Some(error);
+                } else {
+                    apply_manual_target(file, &input);
+                }
+            }
+        }
+        let suggestion = file.suggested_action.as_ref();
+
+        // 置信度
+        row.col(|ui| {
+            fill(ui);
+            if let Some(suggestion) = suggestion {
+                let confidence_color = self.theme.confidence_color(suggestion.confidence);
+                ui.label(
+                    RichText::new(format!("{:.0}%", suggestion.confidence * 100.0))
+                        .color(confidence_color),
+                );
+            }
+        });
+
+        // 来源
+        row.col(|ui| {
+            fill(ui);
+            if let Some(suggestion) = suggestion {
+                let response = ui.label(source_label(suggestion));
+                if let Some(rule_id) = &suggestion.matched_rule_id {
+                    response.on_hover_text(format!("{}\n规则ID: {}", suggestion.reason, rule_id));
+                }
+            }
+        });
+
+        // 右键菜单
+        row.response().context_menu(|ui| {
+            if Self::is_selectable(file) && ui.button("✏️ 修改目标").clicked() {
+                let current = file.suggested_action.as_ref().map(|s| s.target_path.to_string_lossy().to_string());
+                self.editing_target = Some((file.id.clone(), current.unwrap_or_default()));
+                ui.close_menu();
+            }
+            if ui.button("📂 在文件管理器中显示").clicked() {
+                self.file_action = Some((FileAction::Reveal, file.full_path.clone()));
+                ui.close_menu();
+            }
+            let blocked = open_blocked_reason(file);
+            let open = ui.add_enabled(blocked.is_none(), egui::Button::new("📄 打开文件"));
+            if let Some(reason) = blocked {
+                open.on_disabled_hover_text(reason);
+            } else if open.clicked() {
+                self.file_action = Some((FileAction::Open, file.full_path.clone()));
+                ui.close_menu();
+            }
+        });
+    }
+
+    /// 备注按钮：有备注时显示 🗒 并悬停显示内容，点击弹出编辑框
+    fn render_note_button(&mut self, ui: &mut Ui, file: &mut FileDescriptor) {
+        let icon = if file.user_note.is_some() {
+            RichText::new("🗒")
+        } else {
+            RichText::new("🗒").weak()
+        };
+        let response = ui.menu_button(icon, |ui| {
+            ui.label("备注");
+            let mut text = file.user_note.clone().unwrap_or_default();
+            let edited = ui
+                .add(egui::TextEdit::multiline(&mut text).desired_rows(3).hint_text("例如：问问 Bob"))
+                .changed();
+            let cleared = ui.button("清除").clicked();
+            if edited || cleared {
+                file.user_note = (!cleared && !text.trim().is_empty()).then_some(text);
+                self.note_edits.insert(file.id.clone(), file.user_note.clone());
+            }
+            if cleared {
+                ui.close_menu();
+            }
+        });
+        if let Some(note) = &file.user_note {
+            response.response.on_hover_text(note);
+        }
     }
 
     /// 获取文件图标
@@ -328,15 +934,6 @@ impl PreviewTable {
         }
     }
 
-    /// 截断路径显示
-    fn truncate_path(path: &str, max_len: usize) -> String {
-        if path.len() <= max_len {
-            path.to_string()
-        } else {
-            format!("...{}", &path[path.len() - max_len + 3..])
-        }
-    }
-
     /// 获取排序指示器
     fn sort_indicator(&self, column: SortColumn) -> &'static str {
         if self.sort_column == column {
@@ -410,3 +1007,222 @@ impl TableStats {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_file(name: &str, atomic: bool) -> FileDescriptor {
+        let mut file = FileDescriptor::new(
+            PathBuf::from("/data").join(name),
+            name.to_string(),
+            ".txt".to_string(),
+            1,
+            chrono::Utc::now(),
+            false,
+        );
+        file.atomic = atomic;
+        file.selected = false;
+        file
+    }
+
+    #[test]
+    fn test_manual_target_overrides_suggestion() {
+        let mut file = make_file("report.txt", false);
+        assert!(!apply_manual_target(&mut file, "   "));
+        assert!(file.suggested_action.is_none());
+
+        assert!(apply_manual_target(&mut file, " Work/Reports "));
+        let suggestion = file.suggested_action.as_ref().unwrap();
+        assert_eq!(suggestion.source, SuggestionSource::Manual);
+        assert_eq!(suggestion.target_path, PathBuf::from("Work/Reports"));
+        assert_eq!(source_label(suggestion), "✏️ 手动");
+        assert!(file.selected);
+
+        // 带文件名的输入按目录处理
+        apply_manual_target(&mut file, "Work/Reports/report.txt");
+        let target = &file.suggested_action.as_ref().unwrap().target_path;
+        assert_eq!(effective_target_path(&file, target), PathBuf::from("Work/Reports/report.txt"));
+
+        // 会离开输出目录的输入被拒绝，其余按规则目标的方式清理
+        for escaping in ["/data/x", "C:\\x", "D:/x", "../..", "Work/../../x", "\\\\server\\share"] {
+            assert!(manual_target_error(escaping).is_some(), "{}", escaping);
+            assert!(!apply_manual_target(&mut file, escaping), "{}", escaping);
+        }
+        assert!(apply_manual_target(&mut file, "./Work/ Notes: 2024 /"));
+        assert_eq!(
+            file.suggested_action.as_ref().unwrap().target_path,
+            PathBuf::from("Work/Notes_ 2024")
+        );
+
+        // 原子目录内的文件不能单独指定
+        let mut locked = make_file("lib.dll", true);
+        assert!(!apply_manual_target(&mut locked, "Elsewhere"));
+    }
+
+    #[test]
+    fn test_open_blocked_for_programs_and_hidden_files() {
+        assert_eq!(open_blocked_reason(&make_file("notes.txt", false)), None);
+        assert!(open_blocked_reason(&make_file("notes.txt", true)).is_some());
+        assert!(open_blocked_reason(&make_file(".env", false)).is_some());
+
+        let mut setup = make_file("setup.EXE", false);
+        setup.extension = ".EXE".to_string();
+        assert_eq!(open_blocked_reason(&setup), Some("可执行文件不在此打开"));
+    }
+
+    #[test]
+    fn test_select_all_skips_files_inside_atomic_dirs() {
+        let mut files = vec![make_file("a.txt", false), make_file("b.txt", true)];
+        select_all(&mut files);
+        assert!(files[0].selected);
+        assert!(!files[1].selected);
+
+        deselect_all(&mut files);
+        assert!(files.iter().all(|f| !f.selected));
+    }
+
+    #[test]
+    fn test_keyboard_focus_and_range_select() {
+        let mut files = vec![
+            make_file("a.txt", false),
+            make_file("b.txt", true), // 原子目录内文件，默认被过滤
+            make_file("c.txt", false),
+            make_file("d.txt", false),
+            make_file("e.txt", false),
+        ];
+        let mut table = PreviewTable::new();
+        let focused = |t: &PreviewTable, files: &[FileDescriptor]| {
+            files.iter().find(|f| Some(&f.id) == t.focused_id.as_ref()).map(|f| f.name.clone())
+        };
+
+        // 焦点只在可见行中移动，并在边界处停住
+        table.move_focus(&mut files, 1, false);
+        assert_eq!(focused(&table, &files).as_deref(), Some("a.txt"));
+        table.move_focus(&mut files, 1, false);
+        assert_eq!(focused(&table, &files).as_deref(), Some("c.txt"));
+        table.move_focus(&mut files, -5, false);
+        assert_eq!(focused(&table, &files).as_deref(), Some("a.txt"));
+
+        // 空格切换当前行
+        table.toggle_focused(&mut files);
+        assert!(files[0].selected);
+        table.toggle_focused(&mut files);
+        assert!(!files[0].selected);
+
+        // 从 c 开始 Shift+↓ 两次：c、d、e 被勾选
+        table.move_focus(&mut files, 1, false);
+        table.move_focus(&mut files, 1, true);
+        table.move_focus(&mut files, 1, true);
+        let selected: Vec<_> = files.iter().filter(|f| f.selected).map(|f| f.name.as_str()).collect();
+        assert_eq!(selected, vec!["c.txt", "d.txt", "e.txt"]);
+
+        // 过滤后焦点只落在匹配行上
+        table.filter_text = "d.t".to_string();
+        table.move_focus(&mut files, -1, false);
+        assert_eq!(focused(&table, &files).as_deref(), Some("d.txt"));
+    }
+
+    #[test]
+    fn test_source_label_distinguishes_rule_origin() {
+        use crate::core::models::{RuleAction, RuleCondition, RuleDefinition};
+        use crate::core::rule_engine::RuleEngine;
+
+        let mut engine = RuleEngine::new();
+        engine.add_rule(RuleDefinition::new(
+            "我的笔记".to_string(),
+            RuleCondition {
+                filename_keywords: vec!["notes".to_string()],
+                ..Default::default()
+            },
+            RuleAction { move_to: "Notes".to_string() },
+        ));
+
+        let user = engine.match_file(&make_file("notes.txt", false)).unwrap();
+        let mut image = make_file("photo.jpg", false);
+        image.extension = ".jpg".to_string();
+        let builtin = engine.match_file(&image).unwrap();
+
+        assert_eq!(user.rule_origin, Some(RuleOrigin::UserConfirmed));
+        assert_eq!(builtin.rule_origin, Some(RuleOrigin::BuiltIn));
+        assert_eq!(source_label(&user), "📋 我的规则");
+        assert_eq!(source_label(&builtin), "📋 内置规则");
+        assert_ne!(user.matched_rule_id, builtin.matched_rule_id);
+    }
+
+    #[test]
+    fn test_group_by_target_with_unassigned_last() {
+        let mut files = vec![
+            make_file("a.txt", false),
+            make_file("b.txt", false),
+            make_file("c.txt", false),
+            make_file("d.txt", false),
+        ];
+        apply_manual_target(&mut files[0], "Work");
+        apply_manual_target(&mut files[2], "Archive");
+        // 带文件名的建议与同目录的建议归为一组
+        apply_manual_target(&mut files[3], "Work/d.txt");
+
+        let groups = group_by_target(&files, &[0, 1, 2, 3]);
+        let summary: Vec<(Option<PathBuf>, Vec<usize>)> =
+            groups.into_iter().map(|g| (g.target, g.indices)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Some(PathBuf::from("Archive")), vec![2]),
+                (Some(PathBuf::from("Work")), vec![0, 3]),
+                (None, vec![1]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_group_by_source_buckets() {
+        let file = |dir: &str, name: &str, target: Option<&str>| {
+            let mut file = FileDescriptor::new(
+                PathBuf::from(dir).join(name),
+                name.to_string(),
+                ".txt".to_string(),
+                1,
+                chrono::Utc::now(),
+                false,
+            );
+            file.suggested_action = target.map(|t| MoveSuggestion {
+                target_path: PathBuf::from(t),
+                reason: String::new(),
+                source: SuggestionSource::Rule,
+                confidence: 0.9,
+                matched_rule_id: None,
+                rule_origin: None,
+                fused: false,
+            });
+            file
+        };
+        let files = vec![
+            file("/in/b", "x.txt", None),
+            file("/in/a", "1.txt", Some("Pictures")),
+            file("/in/a", "2.txt", Some("Documents")),
+            file("/in/a", "3.txt", Some("Pictures")),
+            file("/in/a", "4.txt", Some("Music")),
+        ];
+
+        let groups = group_by_source(&files);
+        assert_eq!(groups.len(), 2);
+
+        assert_eq!(groups[0].source, PathBuf::from("/in/a"));
+        assert_eq!(groups[0].file_count, 4);
+        assert_eq!(
+            groups[0].destinations,
+            vec![
+                (PathBuf::from("Pictures"), 2),
+                (PathBuf::from("Documents"), 1),
+                (PathBuf::from("Music"), 1),
+            ]
+        );
+        assert!(groups[0].is_scattered());
+
+        assert_eq!(groups[1].source, PathBuf::from("/in/b"));
+        assert_eq!((groups[1].file_count, groups[1].unassigned), (1, 1));
+        assert!(groups[1].destinations.is_empty());
+    }
+}
@@ -1,7 +1,13 @@
 //! 规则管理面板
 
-use crate::core::models::RuleDefinition;
+use crate::core::models::{canonical_extensions, ConditionMatchMode, RuleDefinition};
+use crate::core::rule_engine::{RuleLintWarning, RuleOverlap};
+use chrono::{DateTime, NaiveDate, Utc};
 use eframe::egui::{self, RichText, Ui};
+use std::collections::HashSet;
+
+/// 命中次数超过此值的规则，删除前需要再次确认
+pub const DELETE_CONFIRM_HITS: u64 = 10;
 
 /// 规则面板
 pub struct RulePanel {
@@ -9,6 +15,12 @@ pub struct RulePanel {
     show_builtin: bool,
     /// 选中的规则ID
     selected_rule_id: Option<String>,
+    /// 勾选用于批量操作的规则ID
+    checked: HashSet<String>,
+    /// 等待确认删除的规则（规则ID, 命中次数）
+    pending_delete: Option<(String, u64)>,
+    /// 等待确认的批量删除（勾选的规则ID, 其中命中次数多的规则数）
+    pending_bulk_delete: Option<(Vec<String>, usize)>,
     /// 是否处于编辑模式
     editing: bool,
     /// 编辑中的规则名称
@@ -19,8 +31,20 @@ pub struct RulePanel {
     edit_extensions: String,
     /// 编辑中的关键词（逗号分隔）
     edit_keywords: String,
+    /// 编辑中的文件名正则
+    edit_regex: String,
+    /// 编辑中的条件组合方式
+    edit_match_mode: ConditionMatchMode,
+    /// 编辑中的修改时间起（YYYY-MM-DD）
+    edit_modified_after: String,
+    /// 编辑中的修改时间止（YYYY-MM-DD）
+    edit_modified_before: String,
     /// 编辑中的标签（逗号分隔）
     edit_tags: String,
+    /// 编辑中的作用范围（一级目录，逗号分隔）
+    edit_scope_roots: String,
+    /// 编辑中的目录标记（逗号分隔，设置后规则整体移动原子目录）
+    edit_directory_markers: String,
     /// 编辑中的优先级
     edit_priority: u8,
 }
@@ -30,12 +54,21 @@ impl Default for RulePanel {
         Self {
             show_builtin: true,
             selected_rule_id: None,
+            checked: HashSet::new(),
+            pending_delete: None,
+            pending_bulk_delete: None,
             editing: false,
             edit_name: String::new(),
             edit_target: String::new(),
             edit_extensions: String::new(),
             edit_keywords: String::new(),
+            edit_regex: String::new(),
+            edit_match_mode: ConditionMatchMode::AllOf,
+            edit_modified_after: String::new(),
+            edit_modified_before: String::new(),
             edit_tags: String::new(),
+            edit_scope_roots: String::new(),
+            edit_directory_markers: String::new(),
             edit_priority: 50,
         }
     }
@@ -48,7 +81,16 @@ impl RulePanel {
     }
 
     /// 渲染规则面板
-    pub fn render(&mut self, ui: &mut Ui, rules: &mut Vec<RuleDefinition>) -> RulePanelAction {
+    ///
+    /// warnings 为规则检查结果，被遮蔽的规则旁显示 ⚠ 提示；
+    /// overlaps 为规则重叠检测结果，部分重叠的规则旁显示 ⇅ 并在悬停时说明由哪条规则优先。
+    pub fn render(
+        &mut self,
+        ui: &mut Ui,
+        rules: &mut [RuleDefinition],
+        warnings: &[RuleLintWarning],
+        overlaps: &[RuleOverlap],
+    ) -> RulePanelAction {
         let mut action = RulePanelAction::None;
 
         ui.horizontal(|ui| {
@@ -63,6 +105,54 @@ impl RulePanel {
 
         ui.separator();
 
+        // 已删除的规则不再保留勾选
+        self.checked.retain(|id| rules.iter().any(|r| &r.id == id));
+        if let Some((_, popular)) = self.pending_bulk_delete.clone() {
+            ui.horizontal(|ui| {
+                ui.label(
+                    RichText::new(format!(
+                        "其中 {} 条规则命中超过 {} 次，确认删除？",
+                        popular, DELETE_CONFIRM_HITS
+                    ))
+                    .color(egui::Color32::YELLOW),
+                );
+                if ui.button("🗑️ 确认删除").clicked() {
+                    action = self.confirm_bulk_delete();
+                }
+                if ui.button("取消").clicked() {
+                    self.pending_bulk_delete = None;
+                }
+            });
+        } else if !self.checked.is_empty() {
+            ui.horizontal(|ui| {
+                ui.label(format!("已选 {} 条:", self.checked.len()));
+                if ui.button("启用").clicked() {
+                    action = RulePanelAction::BulkSetEnabled(self.checked_ids(), true);
+                }
+                if ui.button("禁用").clicked() {
+                    action = RulePanelAction::BulkSetEnabled(self.checked_ids(), false);
+                }
+                if ui.button("🗑️ 删除").on_hover_text("只删除用户规则，内置规则不受影响").clicked() {
+                    action = self.request_bulk_delete(rules);
+                }
+                if ui.button("📤 导出").clicked() {
+                    action = RulePanelAction::BulkExport(self.checked_ids());
+                }
+                if ui.button("取消选择").clicked() {
+                    self.checked.clear();
+                }
+            });
+            ui.separator();
+        }
+
+        if !warnings.is_empty() {
+            ui.label(
+                RichText::new(format!("⚠ {} 条规则被更高优先级的规则覆盖，永远不会生效", warnings.len()))
+                    .color(egui::Color32::YELLOW),
+            );
+            ui.separator();
+        }
+
         // 规则列表
         egui::ScrollArea::vertical()
             .max_height(300.0)
@@ -84,8 +174,18 @@ impl RulePanel {
                         .inner_margin(egui::Margin::symmetric(8.0, 4.0))
                         .show(ui, |ui| {
                             ui.horizontal(|ui| {
+                                // 批量操作勾选
+                                let mut checked = self.checked.contains(&rule.id);
+                                if ui.checkbox(&mut checked, "").on_hover_text("勾选以批量操作").changed() {
+                                    if checked {
+                                        self.checked.insert(rule.id.clone());
+                                    } else {
+                                        self.checked.remove(&rule.id);
+                                    }
+                                }
+
                                 // 启用开关
-                                ui.checkbox(&mut rule.enabled, "");
+                                ui.add(egui::Checkbox::without_text(&mut rule.enabled)).on_hover_text("启用");
 
                                 // 规则名称
                                 let name_color = if rule.enabled {
@@ -101,6 +201,21 @@ impl RulePanel {
                                     self.load_rule_for_edit(rule);
                                 }
 
+                                if let Some(warning) = warnings.iter().find(|w| w.rule_id == rule.id) {
+                                    ui.label(RichText::new("⚠").color(egui::Color32::YELLOW))
+                                        .on_hover_text(warning.message());
+                                } else {
+                                    let partial: Vec<String> = overlaps
+                                        .iter()
+                                        .filter(|o| o.loser_id == rule.id && !o.shadowed)
+                                        .map(|o| o.message())
+                                        .collect();
+                                    if !partial.is_empty() {
+                                        ui.label(RichText::new("⇅").color(egui::Color32::GRAY))
+                                            .on_hover_text(partial.join("\n"));
+                                    }
+                                }
+
                                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                                     // 优先级
                                     ui.label(
@@ -109,12 +224,27 @@ impl RulePanel {
                                             .color(egui::Color32::GRAY)
                                     );
 
-                                    // 命中次数
-                                    ui.label(
+                                    // 命中次数；从未命中的用户规则提示可以清理
+                                    let hits = ui.label(
                                         RichText::new(format!("×{}", rule.hit_count))
                                             .small()
                                             .color(egui::Color32::GRAY)
                                     );
+                                    if let Some(last_hit) = rule.last_hit {
+                                        hits.on_hover_text(format!(
+                                            "最后命中: {}",
+                                            last_hit.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")
+                                        ));
+                                    }
+                                    if rule.hit_count == 0
+                                        && rule.origin == crate::core::models::RuleOrigin::UserConfirmed
+                                    {
+                                        ui.label(
+                                            RichText::new("从未命中")
+                                                .small()
+                                                .color(egui::Color32::from_rgb(251, 188, 4))
+                                        );
+                                    }
 
                                     // 来源标签
                                     let origin_text = match rule.origin {
@@ -176,6 +306,54 @@ impl RulePanel {
                         }
                     });
 
+                    ui.horizontal(|ui| {
+                        ui.label("文件名正则:");
+                        if self.editing {
+                            ui.text_edit_singleline(&mut self.edit_regex)
+                                .on_hover_text("例如 ^\\d{4}-\\d{2}\\.pdf$，留空表示不限");
+                            if let Err(e) = regex::Regex::new(self.edit_regex.trim()) {
+                                ui.label(RichText::new("⚠ 无效").color(egui::Color32::YELLOW))
+                                    .on_hover_text(e.to_string());
+                            }
+                        } else {
+                            ui.label(rule.condition.filename_regex.as_deref().unwrap_or(""));
+                        }
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("条件组合:");
+                        if self.editing {
+                            for mode in [ConditionMatchMode::AllOf, ConditionMatchMode::AnyOf] {
+                                ui.radio_value(&mut self.edit_match_mode, mode, mode.label());
+                            }
+                        } else {
+                            ui.label(rule.condition.match_mode.label());
+                        }
+                    })
+                    .response
+                    .on_hover_text("任一满足时，排除目录和作用范围仍必须满足");
+
+                    ui.horizontal(|ui| {
+                        ui.label("修改时间:");
+                        if self.editing {
+                            for (input, hint) in [
+                                (&mut self.edit_modified_after, "起 YYYY-MM-DD"),
+                                (&mut self.edit_modified_before, "止 YYYY-MM-DD"),
+                            ] {
+                                ui.add(egui::TextEdit::singleline(input).hint_text(hint).desired_width(90.0));
+                                if !input.trim().is_empty() && parse_date(input).is_none() {
+                                    ui.label(RichText::new("⚠").color(egui::Color32::YELLOW))
+                                        .on_hover_text("日期格式应为 YYYY-MM-DD");
+                                }
+                            }
+                        } else {
+                            ui.label(format_date_range(
+                                rule.condition.modified_after,
+                                rule.condition.modified_before,
+                            ));
+                        }
+                    });
+
                     ui.horizontal(|ui| {
                         ui.label("语义标签:");
                         if self.editing {
@@ -185,6 +363,30 @@ impl RulePanel {
                         }
                     });
 
+                    ui.horizontal(|ui| {
+                        ui.label("作用范围:");
+                        if self.editing {
+                            ui.text_edit_singleline(&mut self.edit_scope_roots)
+                                .on_hover_text("仅对这些一级目录下的文件生效，留空表示全部");
+                        } else if rule.condition.scope_roots.is_empty() {
+                            ui.label("全部");
+                        } else {
+                            ui.label(rule.condition.scope_roots.join(", "));
+                        }
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("目录标记:");
+                        if self.editing {
+                            ui.text_edit_singleline(&mut self.edit_directory_markers)
+                                .on_hover_text("填写后规则只匹配包含这些文件/子目录的原子目录，并整体移动");
+                        } else if rule.condition.directory_markers.is_empty() {
+                            ui.label("无（匹配文件）");
+                        } else {
+                            ui.label(rule.condition.directory_markers.join(", "));
+                        }
+                    });
+
                     ui.horizontal(|ui| {
                         ui.label("优先级:");
                         if self.editing {
@@ -205,14 +407,25 @@ impl RulePanel {
                             if ui.button("❌ 取消").clicked() {
                                 self.editing = false;
                             }
-                        } else {
-                            if rule.origin == crate::core::models::RuleOrigin::UserConfirmed {
-                                if ui.button("✏️ 编辑").clicked() {
-                                    self.editing = true;
-                                }
-                                if ui.button("🗑️ 删除").clicked() {
-                                    action = RulePanelAction::Delete(rule_id.clone());
-                                }
+                        } else if let Some((_, hits)) =
+                            self.pending_delete.clone().filter(|(id, _)| id == rule_id)
+                        {
+                            ui.label(
+                                RichText::new(format!("该规则已命中 {} 次，确认删除？", hits))
+                                    .color(egui::Color32::YELLOW),
+                            );
+                            if ui.button("🗑️ 确认删除").clicked() {
+                                action = self.confirm_delete();
+                            }
+                            if ui.button("取消").clicked() {
+                                self.pending_delete = None;
+                            }
+                        } else if rule.origin == crate::core::models::RuleOrigin::UserConfirmed {
+                            if ui.button("✏️ 编辑").clicked() {
+                                self.editing = true;
+                            }
+                            if ui.button("🗑️ 删除").clicked() {
+                                action = self.request_delete(rule);
                             }
                         }
                     });
@@ -229,7 +442,13 @@ impl RulePanel {
         self.edit_target = rule.action.move_to.clone();
         self.edit_extensions = rule.condition.file_extensions.join(", ");
         self.edit_keywords = rule.condition.filename_keywords.join(", ");
+        self.edit_regex = rule.condition.filename_regex.clone().unwrap_or_default();
+        self.edit_match_mode = rule.condition.match_mode;
+        self.edit_modified_after = format_date(rule.condition.modified_after);
+        self.edit_modified_before = format_date(rule.condition.modified_before);
         self.edit_tags = rule.condition.semantic_tags.join(", ");
+        self.edit_scope_roots = rule.condition.scope_roots.join(", ");
+        self.edit_directory_markers = rule.condition.directory_markers.join(", ");
         self.edit_priority = rule.priority;
     }
 
@@ -238,29 +457,82 @@ impl RulePanel {
         EditedRuleData {
             name: self.edit_name.clone(),
             target: self.edit_target.clone(),
-            extensions: self.edit_extensions
-                .split(',')
-                .map(|s| s.trim().to_string())
-                .filter(|s| !s.is_empty())
-                .collect(),
-            keywords: self.edit_keywords
-                .split(',')
-                .map(|s| s.trim().to_string())
-                .filter(|s| !s.is_empty())
-                .collect(),
-            tags: self.edit_tags
-                .split(',')
-                .map(|s| s.trim().to_string())
-                .filter(|s| !s.is_empty())
-                .collect(),
+            extensions: canonical_extensions(&split_list(&self.edit_extensions)),
+            keywords: split_list(&self.edit_keywords),
+            filename_regex: Some(self.edit_regex.trim().to_string()).filter(|r| !r.is_empty()),
+            match_mode: self.edit_match_mode,
+            modified_after: parse_date(&self.edit_modified_after),
+            modified_before: parse_date(&self.edit_modified_before),
+            tags: split_list(&self.edit_tags),
+            scope_roots: split_list(&self.edit_scope_roots),
+            directory_markers: split_list(&self.edit_directory_markers),
             priority: self.edit_priority,
         }
     }
 
+    /// 勾选的规则ID（排序后，保证操作顺序稳定）
+    fn checked_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.checked.iter().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    /// 请求删除规则：命中次数多的规则先进入待确认状态，其余直接删除
+    pub fn request_delete(&mut self, rule: &RuleDefinition) -> RulePanelAction {
+        if rule.hit_count > DELETE_CONFIRM_HITS {
+            self.pending_delete = Some((rule.id.clone(), rule.hit_count));
+            RulePanelAction::None
+        } else {
+            RulePanelAction::Delete(rule.id.clone())
+        }
+    }
+
+    /// 确认删除待确认的规则
+    pub fn confirm_delete(&mut self) -> RulePanelAction {
+        match self.pending_delete.take() {
+            Some((id, _)) => RulePanelAction::Delete(id),
+            None => RulePanelAction::None,
+        }
+    }
+
+    /// 请求批量删除勾选的规则：与单条删除相同，包含命中次数多的用户规则时先进入待确认状态
+    pub fn request_bulk_delete(&mut self, rules: &[RuleDefinition]) -> RulePanelAction {
+        let ids = self.checked_ids();
+        let popular = rules
+            .iter()
+            .filter(|r| {
+                r.origin == crate::core::models::RuleOrigin::UserConfirmed
+                    && r.hit_count > DELETE_CONFIRM_HITS
+                    && ids.contains(&r.id)
+            })
+            .count();
+        if popular > 0 {
+            self.pending_bulk_delete = Some((ids, popular));
+            RulePanelAction::None
+        } else {
+            RulePanelAction::BulkDelete(ids)
+        }
+    }
+
+    /// 确认待确认的批量删除
+    pub fn confirm_bulk_delete(&mut self) -> RulePanelAction {
+        match self.pending_bulk_delete.take() {
+            Some((ids, _)) => RulePanelAction::BulkDelete(ids),
+            None => RulePanelAction::None,
+        }
+    }
+
+    /// 清空批量勾选
+    pub fn clear_checked(&mut self) {
+        self.checked.clear();
+    }
+
     /// 重置选择
     pub fn reset_selection(&mut self) {
         self.selected_rule_id = None;
         self.editing = false;
+        self.pending_delete = None;
+        self.pending_bulk_delete = None;
     }
 }
 
@@ -271,6 +543,12 @@ pub enum RulePanelAction {
     CreateNew,
     SaveEdit(String),
     Delete(String),
+    /// 批量启用/禁用
+    BulkSetEnabled(Vec<String>, bool),
+    /// 批量删除（仅用户规则）
+    BulkDelete(Vec<String>),
+    /// 批量导出到文件
+    BulkExport(Vec<String>),
 }
 
 /// 编辑后的规则数据
@@ -279,6 +557,92 @@ pub struct EditedRuleData {
     pub target: String,
     pub extensions: Vec<String>,
     pub keywords: Vec<String>,
+    pub filename_regex: Option<String>,
+    pub match_mode: ConditionMatchMode,
+    pub modified_after: Option<DateTime<Utc>>,
+    pub modified_before: Option<DateTime<Utc>>,
     pub tags: Vec<String>,
+    pub scope_roots: Vec<String>,
+    pub directory_markers: Vec<String>,
     pub priority: u8,
 }
+
+/// 解析 YYYY-MM-DD 为当天零点（UTC），空白或格式错误返回 None
+fn parse_date(input: &str) -> Option<DateTime<Utc>> {
+    NaiveDate::parse_from_str(input.trim(), "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|dt| dt.and_utc())
+}
+
+/// 日期显示为 YYYY-MM-DD，未设置为空
+fn format_date(date: Option<DateTime<Utc>>) -> String {
+    date.map(|d| d.format("%Y-%m-%d").to_string()).unwrap_or_default()
+}
+
+/// 修改时间范围的显示文字
+fn format_date_range(after: Option<DateTime<Utc>>, before: Option<DateTime<Utc>>) -> String {
+    match (after, before) {
+        (None, None) => "不限".to_string(),
+        (Some(a), None) => format!("{} 起", format_date(Some(a))),
+        (None, Some(b)) => format!("{} 之前", format_date(Some(b))),
+        (Some(a), Some(b)) => format!("{} 至 {} 之前", format_date(Some(a)), format_date(Some(b))),
+    }
+}
+
+/// 拆分逗号分隔的输入（同时接受中文逗号）
+fn split_list(input: &str) -> Vec<String> {
+    input
+        .split([',', '，'])
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::models::{RuleAction, RuleCondition};
+
+    fn rule_with_hits(hit_count: u64) -> RuleDefinition {
+        let mut rule = RuleDefinition::new(
+            "发票".to_string(),
+            RuleCondition::default(),
+            RuleAction { move_to: "Finance".to_string() },
+        );
+        rule.hit_count = hit_count;
+        rule
+    }
+
+    #[test]
+    fn test_delete_high_hit_rule_requires_confirmation() {
+        let mut panel = RulePanel::new();
+
+        let unused = rule_with_hits(0);
+        assert!(matches!(panel.request_delete(&unused), RulePanelAction::Delete(id) if id == unused.id));
+
+        let popular = rule_with_hits(DELETE_CONFIRM_HITS + 1);
+        assert!(matches!(panel.request_delete(&popular), RulePanelAction::None));
+        assert_eq!(panel.pending_delete, Some((popular.id.clone(), DELETE_CONFIRM_HITS + 1)));
+        assert!(matches!(panel.confirm_delete(), RulePanelAction::Delete(id) if id == popular.id));
+        assert!(panel.pending_delete.is_none());
+        assert!(matches!(panel.confirm_delete(), RulePanelAction::None));
+    }
+
+    #[test]
+    fn test_bulk_delete_with_high_hit_rule_requires_confirmation() {
+        let mut panel = RulePanel::new();
+        let unused = rule_with_hits(0);
+        let popular = rule_with_hits(DELETE_CONFIRM_HITS + 1);
+        let rules = vec![unused.clone(), popular.clone()];
+
+        panel.checked.insert(unused.id.clone());
+        assert!(matches!(panel.request_bulk_delete(&rules), RulePanelAction::BulkDelete(ids) if ids == [unused.id.clone()]));
+
+        panel.checked.insert(popular.id.clone());
+        assert!(matches!(panel.request_bulk_delete(&rules), RulePanelAction::None));
+        assert_eq!(panel.pending_bulk_delete.as_ref().map(|(_, n)| *n), Some(1));
+        assert!(matches!(panel.confirm_bulk_delete(), RulePanelAction::BulkDelete(ids) if ids.len() == 2));
+        assert!(matches!(panel.confirm_bulk_delete(), RulePanelAction::None));
+    }
+}
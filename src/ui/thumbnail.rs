@@ -0,0 +1,249 @@
+//! 图片缩略图缓存
+//!
+//! 预览表格只为可见行生成缩略图：在后台线程解码并缩小到 THUMBNAIL_SIZE，
+//! 界面线程只上传解码好的小图为纹理；按文件ID做 LRU 缓存，避免每帧重新读取图片。
+
+use crate::core::models::FileDescriptor;
+use eframe::egui::{self, ColorImage, TextureHandle, TextureOptions};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+
+/// 缩略图最长边（像素）
+pub const THUMBNAIL_SIZE: u32 = 48;
+
+/// 最多缓存的缩略图数
+pub const THUMBNAIL_CACHE_CAPACITY: usize = 256;
+
+/// 同时排队等待后台解码的图片数上限，快速滚动时不会为已滚出视野的行排起长队
+const MAX_PENDING_LOADS: usize = 8;
+
+/// 扩展名是否为可生成缩略图的图片格式（取决于启用的解码器）
+pub fn supports_thumbnail(extension: &str) -> bool {
+    image::ImageFormat::from_extension(extension.trim_start_matches('.'))
+        .is_some_and(|format| format.reading_enabled())
+}
+
+/// 容量固定的 LRU 缓存，超出容量时淘汰最久未使用的项
+pub struct LruCache<V> {
+    capacity: usize,
+    entries: HashMap<String, V>,
+    /// 使用顺序（最近使用的在后）
+    order: VecDeque<String>,
+}
+
+impl<V> LruCache<V> {
+    /// 创建指定容量的缓存（容量至少为 1）
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// 读取并标记为最近使用
+    pub fn get(&mut self, key: &str) -> Option<&V> {
+        if self.entries.contains_key(key) {
+            self.touch(key);
+        }
+        self.entries.get(key)
+    }
+
+    /// 插入，必要时淘汰最久未使用的项
+    pub fn insert(&mut self, key: String, value: V) {
+        if self.entries.insert(key.clone(), value).is_some() {
+            self.touch(&key);
+            return;
+        }
+        self.order.push_back(key);
+        while self.entries.len() > self.capacity {
+            match self.order.pop_front() {
+                Some(oldest) => {
+                    self.entries.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// 缓存项数
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 缓存是否为空
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn touch(&mut self, key: &str) {
+        if let Some(pos) = self.order.iter().position(|k| k == key) {
+            if let Some(k) = self.order.remove(pos) {
+                self.order.push_back(k);
+            }
+        }
+    }
+}
+
+/// 后台解码线程：按请求顺序解码，完成后通知界面重绘
+struct ThumbnailWorker {
+    requests: mpsc::Sender<(String, PathBuf)>,
+    results: mpsc::Receiver<(String, PathBuf, anyhow::Result<ColorImage>)>,
+}
+
+impl ThumbnailWorker {
+    /// 启动解码线程，缓存被丢弃（请求通道关闭）后线程退出
+    fn spawn(ctx: egui::Context) -> Self {
+        let (requests, request_rx) = mpsc::channel::<(String, PathBuf)>();
+        let (result_tx, results) = mpsc::channel();
+        thread::spawn(move || {
+            for (id, path) in request_rx {
+                let image = load_thumbnail(&path);
+                if result_tx.send((id, path, image)).is_err() {
+                    break;
+                }
+                ctx.request_repaint();
+            }
+        });
+        Self { requests, results }
+    }
+}
+
+/// 缩略图缓存（解码失败的文件也记录下来，不再重试）
+pub struct ThumbnailCache {
+    cache: LruCache<Option<TextureHandle>>,
+    /// 后台解码线程，第一次需要缩略图时启动
+    worker: Option<ThumbnailWorker>,
+    /// 已提交解码、尚未取回结果的文件ID
+    pending: HashSet<String>,
+}
+
+impl Default for ThumbnailCache {
+    fn default() -> Self {
+        Self {
+            cache: LruCache::new(THUMBNAIL_CACHE_CAPACITY),
+            worker: None,
+            pending: HashSet::new(),
+        }
+    }
+}
+
+impl ThumbnailCache {
+    /// 每帧开始时取回后台解码完成的图片，上传为纹理
+    pub fn begin_frame(&mut self, ctx: &egui::Context) {
+        let Some(worker) = &self.worker else {
+            return;
+        };
+        while let Ok((id, path, image)) = worker.results.try_recv() {
+            self.pending.remove(&id);
+            let texture = match image {
+                Ok(image) => Some(ctx.load_texture(format!("thumbnail-{}", id), image, TextureOptions::LINEAR)),
+                Err(e) => {
+                    tracing::debug!("无法生成缩略图 {}: {}", path.display(), e);
+                    None
+                }
+            };
+            self.cache.insert(id, texture);
+        }
+    }
+
+    /// 获取文件的缩略图；未缓存时交给后台线程解码，完成前返回 None
+    pub fn get_or_load(&mut self, ctx: &egui::Context, file: &FileDescriptor) -> Option<TextureHandle> {
+        if let Some(entry) = self.cache.get(&file.id) {
+            return entry.clone();
+        }
+        if self.pending.contains(&file.id) {
+            return None;
+        }
+        if self.pending.len() >= MAX_PENDING_LOADS {
+            // 队列已满，等已提交的解码完成后再提交
+            ctx.request_repaint();
+            return None;
+        }
+        let worker = self
+            .worker
+            .get_or_insert_with(|| ThumbnailWorker::spawn(ctx.clone()));
+        if worker.requests.send((file.id.clone(), file.full_path.clone())).is_ok() {
+            self.pending.insert(file.id.clone());
+        }
+        None
+    }
+}
+
+/// 读取图片并缩小到 THUMBNAIL_SIZE 以内
+pub fn load_thumbnail(path: &Path) -> anyhow::Result<ColorImage> {
+    let image = image::ImageReader::open(path)?
+        .with_guessed_format()?
+        .decode()?
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .to_rgba8();
+    let size = [image.width() as usize, image.height() as usize];
+    Ok(ColorImage::from_rgba_unmultiplied(size, image.as_raw()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_evicts_least_recently_used() {
+        let mut cache = LruCache::new(2);
+        cache.insert("a".to_string(), 1);
+        cache.insert("b".to_string(), 2);
+        // 访问 a 后，b 成为最久未使用的项
+        assert_eq!(cache.get("a"), Some(&1));
+        cache.insert("c".to_string(), 3);
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("a"), Some(&1));
+        assert_eq!(cache.get("c"), Some(&3));
+    }
+
+    #[test]
+    fn test_thumbnail_is_downscaled() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("photo.png");
+        image::RgbaImage::new(200, 100).save(&path).unwrap();
+
+        let thumbnail = load_thumbnail(&path).unwrap();
+        assert_eq!(thumbnail.size, [48, 24]);
+        assert!(supports_thumbnail(".PNG"));
+        assert!(supports_thumbnail(".jpg"));
+        assert!(supports_thumbnail(".webp"));
+        assert!(!supports_thumbnail(".pdf"));
+    }
+
+    #[test]
+    fn test_thumbnail_decoded_in_background() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("photo.jpg");
+        image::RgbImage::new(120, 60).save(&path).unwrap();
+        let file = FileDescriptor::new(
+            path,
+            "photo.jpg".to_string(),
+            ".jpg".to_string(),
+            1,
+            chrono::Utc::now(),
+            false,
+        );
+
+        let ctx = egui::Context::default();
+        let mut cache = ThumbnailCache::default();
+        // 第一次只提交解码，不阻塞界面线程
+        assert!(cache.get_or_load(&ctx, &file).is_none());
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        let texture = loop {
+            cache.begin_frame(&ctx);
+            if let Some(texture) = cache.get_or_load(&ctx, &file) {
+                break texture;
+            }
+            assert!(std::time::Instant::now() < deadline, "后台解码超时");
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        };
+        assert_eq!(texture.size(), [48, 24]);
+    }
+}